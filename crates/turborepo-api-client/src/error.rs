@@ -3,7 +3,7 @@ use std::backtrace::Backtrace;
 use reqwest::header::ToStrError;
 use thiserror::Error;
 
-use crate::CachingStatus;
+use crate::{retry, CachingStatus};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -38,4 +38,14 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Whether the request that failed with this error is worth sending again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ReqwestError(err) => retry::should_retry_request(err),
+            _ => false,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
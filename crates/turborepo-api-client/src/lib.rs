@@ -7,14 +7,14 @@ use std::{backtrace::Backtrace, env};
 use async_trait::async_trait;
 use lazy_static::lazy_static;
 use regex::Regex;
-pub use reqwest::Response;
+pub use reqwest::{Body, Response};
 use reqwest::{Method, RequestBuilder, StatusCode};
 use serde::Deserialize;
 use turborepo_ci::{is_ci, Vendor};
 use turborepo_vercel_api::{
-    APIError, CachingStatus, CachingStatusResponse, PreflightResponse, SpacesResponse, Team,
-    TeamsResponse, TokenMetadata, TokenMetadataResponse, UserResponse, VerificationResponse,
-    VerifiedSsoUser,
+    APIError, ArtifactsQueryRequest, ArtifactsQueryResponse, CachingStatus, CachingStatusResponse,
+    PreflightResponse, SpacesResponse, Team, TeamsResponse, TokenMetadata, TokenMetadataResponse,
+    UserResponse, VerificationResponse, VerifiedSsoUser,
 };
 use url::Url;
 
@@ -22,7 +22,7 @@ pub use crate::error::{Error, Result};
 
 pub mod analytics;
 mod error;
-mod retry;
+pub mod retry;
 pub mod spaces;
 pub mod telemetry;
 
@@ -51,7 +51,7 @@ pub trait Client {
     async fn put_artifact(
         &self,
         hash: &str,
-        artifact_body: &[u8],
+        artifact_body: Body,
         duration: u64,
         tag: Option<&str>,
         token: &str,
@@ -73,6 +73,13 @@ pub trait Client {
         team_id: Option<&str>,
         team_slug: Option<&str>,
     ) -> Result<Option<Response>>;
+    async fn query_artifacts(
+        &self,
+        hashes: &[String],
+        token: &str,
+        team_id: Option<&str>,
+        team_slug: Option<&str>,
+    ) -> Result<ArtifactsQueryResponse>;
     async fn get_artifact(
         &self,
         hash: &str,
@@ -245,7 +252,7 @@ impl Client for APIClient {
     async fn put_artifact(
         &self,
         hash: &str,
-        artifact_body: &[u8],
+        artifact_body: Body,
         duration: u64,
         tag: Option<&str>,
         token: &str,
@@ -275,7 +282,7 @@ impl Client for APIClient {
             .header("Content-Type", "application/octet-stream")
             .header("x-artifact-duration", duration.to_string())
             .header("User-Agent", self.user_agent.clone())
-            .body(artifact_body.to_vec());
+            .body(artifact_body);
 
         if allow_auth {
             request_builder = request_builder.header("Authorization", format!("Bearer {}", token));
@@ -368,6 +375,55 @@ impl Client for APIClient {
             .await
     }
 
+    async fn query_artifacts(
+        &self,
+        hashes: &[String],
+        token: &str,
+        team_id: Option<&str>,
+        team_slug: Option<&str>,
+    ) -> Result<ArtifactsQueryResponse> {
+        let mut request_url = self.make_url("/v8/artifacts");
+        let mut allow_auth = true;
+
+        if self.use_preflight {
+            let preflight_response = self
+                .do_preflight(
+                    token,
+                    &request_url,
+                    "POST",
+                    "Authorization, Content-Type, User-Agent",
+                )
+                .await?;
+
+            allow_auth = preflight_response.allow_authorization_header;
+            request_url = preflight_response.location.to_string();
+        }
+
+        let mut request_builder = self
+            .client
+            .post(&request_url)
+            .header("User-Agent", self.user_agent.clone())
+            .json(&ArtifactsQueryRequest {
+                hashes: hashes.to_vec(),
+            });
+
+        if allow_auth {
+            request_builder = request_builder.header("Authorization", format!("Bearer {}", token));
+        }
+
+        request_builder = Self::add_team_params(request_builder, team_id, team_slug);
+
+        request_builder = Self::add_ci_header(request_builder);
+
+        let response = retry::make_retryable_request(request_builder).await?;
+
+        if response.status() == StatusCode::FORBIDDEN {
+            return Err(Self::handle_403(response).await);
+        }
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
     async fn get_artifact(
         &self,
         hash: &str,
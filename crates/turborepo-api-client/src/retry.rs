@@ -1,3 +1,5 @@
+use std::{future::Future, time::Duration};
+
 use reqwest::{RequestBuilder, Response, StatusCode};
 use tokio::time::sleep;
 
@@ -14,12 +16,18 @@ const RETRY_MAX: u32 = 2;
 /// # Arguments
 ///
 /// * `request_builder`: The request builder with everything, i.e. headers and
-///   body already set. NOTE: This must be cloneable, so no streams are allowed.
+///   body already set. A request with a streamed body can't be cloned, so it's
+///   only sent once. Use `retry_future` to retry those by building a new body
+///   for each attempt.
 ///
 /// returns: Result<Response, Error>
 pub(crate) async fn make_retryable_request(
     request_builder: RequestBuilder,
 ) -> Result<Response, Error> {
+    if request_builder.try_clone().is_none() {
+        return Ok(request_builder.send().await?);
+    }
+
     let mut last_error = None;
     for retry_count in 0..RETRY_MAX {
         let builder = request_builder.try_clone().expect("cannot clone request");
@@ -33,16 +41,47 @@ pub(crate) async fn make_retryable_request(
             }
         }
 
-        let sleep_period = (2_u64)
-            .pow(retry_count)
-            .clamp(MIN_SLEEP_TIME_SECS, MAX_SLEEP_TIME_SECS);
-        sleep(std::time::Duration::from_secs(sleep_period)).await;
+        sleep(sleep_period(retry_count)).await;
     }
 
     Err(Error::TooManyFailures(Box::new(last_error.unwrap())))
 }
 
-fn should_retry_request(error: &reqwest::Error) -> bool {
+/// Retries `attempt` until `RETRY_MAX` is reached, `should_retry` returns false
+/// for its error, or it succeeds. Uses the same backoff as
+/// `make_retryable_request`.
+///
+/// This is for requests that can't be cloned, such as uploads with a streamed
+/// body, where each attempt has to build its own request.
+pub async fn retry_future<T, E, F, Fut>(
+    mut attempt: F,
+    should_retry: impl Fn(&E) -> bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut retry_count = 0;
+    loop {
+        match attempt().await {
+            Err(err) if retry_count + 1 < RETRY_MAX && should_retry(&err) => {
+                sleep(sleep_period(retry_count)).await;
+                retry_count += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn sleep_period(retry_count: u32) -> Duration {
+    Duration::from_secs(
+        (2_u64)
+            .pow(retry_count)
+            .clamp(MIN_SLEEP_TIME_SECS, MAX_SLEEP_TIME_SECS),
+    )
+}
+
+pub(crate) fn should_retry_request(error: &reqwest::Error) -> bool {
     if let Some(status) = error.status() {
         if status == StatusCode::TOO_MANY_REQUESTS {
             return true;
@@ -55,3 +94,47 @@ fn should_retry_request(error: &reqwest::Error) -> bool {
 
     false
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_future_retries_until_success() {
+        let attempts = Cell::new(0);
+        let result = retry_future(
+            || {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    match attempt {
+                        1 => Err("unavailable"),
+                        _ => Ok(attempt),
+                    }
+                }
+            },
+            |_| true,
+        )
+        .await;
+
+        assert_eq!(result, Ok(2));
+    }
+
+    #[tokio::test]
+    async fn test_retry_future_stops_on_fatal_error() {
+        let attempts = Cell::new(0);
+        let result: Result<(), _> = retry_future(
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err("forbidden") }
+            },
+            |err| *err != "forbidden",
+        )
+        .await;
+
+        assert_eq!(result, Err("forbidden"));
+        assert_eq!(attempts.get(), 1);
+    }
+}
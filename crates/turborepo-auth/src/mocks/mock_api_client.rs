@@ -4,8 +4,8 @@ use async_trait::async_trait;
 use reqwest::{Method, RequestBuilder, Response};
 use turborepo_api_client::Client;
 use turborepo_vercel_api::{
-    CachingStatusResponse, Membership, PreflightResponse, Role, Space, SpacesResponse, Team,
-    TeamsResponse, TokenMetadata, User, UserResponse, VerifiedSsoUser,
+    ArtifactsQueryResponse, CachingStatusResponse, Membership, PreflightResponse, Role, Space,
+    SpacesResponse, Team, TeamsResponse, TokenMetadata, User, UserResponse, VerifiedSsoUser,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -133,7 +133,7 @@ impl Client for MockApiClient {
     async fn put_artifact(
         &self,
         _hash: &str,
-        _artifact_body: &[u8],
+        _artifact_body: turborepo_api_client::Body,
         _duration: u64,
         _tag: Option<&str>,
         _token: &str,
@@ -163,6 +163,15 @@ impl Client for MockApiClient {
     ) -> turborepo_api_client::Result<Option<Response>> {
         unimplemented!("artifact_exists")
     }
+    async fn query_artifacts(
+        &self,
+        _hashes: &[String],
+        _token: &str,
+        _team_id: Option<&str>,
+        _team_slug: Option<&str>,
+    ) -> turborepo_api_client::Result<ArtifactsQueryResponse> {
+        unimplemented!("query_artifacts")
+    }
     async fn get_artifact(
         &self,
         _hash: &str,
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use turborepo_cache::CacheLimits;
+
+use crate::Error;
+
+/// The config file the server is started with
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Where artifacts are stored. Relative paths are resolved from the
+    /// directory of the config file.
+    pub storage_dir: PathBuf,
+    // Limits for the stored artifacts, in bytes, seconds and artifacts. The
+    // least recently used artifacts are evicted once any of them are exceeded.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+    pub teams: Vec<TeamConfig>,
+}
+
+/// A team that can use the cache. Artifacts are only shared within a team.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TeamConfig {
+    pub id: String,
+    pub slug: String,
+    /// The bearer tokens that have access to the team
+    pub tokens: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|source| Error::ReadConfig {
+            path: path.to_owned(),
+            source,
+        })?;
+        let mut config: ServerConfig =
+            serde_json::from_str(&contents).map_err(|source| Error::ParseConfig {
+                path: path.to_owned(),
+                source,
+            })?;
+
+        if config.storage_dir.is_relative() {
+            let config_dir = path.parent().unwrap_or(Path::new(""));
+            config.storage_dir = config_dir.join(&config.storage_dir);
+        }
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let mut team_ids = HashSet::new();
+        for team in &self.teams {
+            // Team ids are used as directory names
+            if !is_valid_path_component(&team.id) {
+                return Err(Error::InvalidTeamId(team.id.clone()));
+            }
+            if !team_ids.insert(team.id.as_str()) {
+                return Err(Error::DuplicateTeam(team.id.clone()));
+            }
+            if team.tokens.iter().any(|token| token.is_empty()) {
+                return Err(Error::EmptyToken(team.id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        CacheLimits {
+            max_size: self.max_size,
+            max_age: self.max_age.map(Duration::from_secs),
+            max_entries: self.max_entries,
+        }
+    }
+
+    /// Finds the team a request is for, if its token has access to it. As with
+    /// the Vercel API, the team is chosen with either its id or slug, and can
+    /// be left out if the token only has access to one team.
+    pub fn authorize(
+        &self,
+        token: &str,
+        team_id: Option<&str>,
+        team_slug: Option<&str>,
+    ) -> Option<&TeamConfig> {
+        let mut teams = self.teams.iter().filter(|team| {
+            team.tokens
+                .iter()
+                .any(|team_token| constant_time_eq(team_token.as_bytes(), token.as_bytes()))
+        });
+
+        match (team_id, team_slug) {
+            (Some(team_id), _) => teams.find(|team| team.id == team_id),
+            (None, Some(team_slug)) => teams.find(|team| team.slug == team_slug),
+            (None, None) => {
+                let team = teams.next()?;
+                teams.next().is_none().then_some(team)
+            }
+        }
+    }
+}
+
+/// Whether `name` can be used as a file name without escaping the directory
+/// it's joined to
+pub(crate) fn is_valid_path_component(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Compares tokens without returning early, so their contents can't be
+// guessed from how long a comparison takes
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> ServerConfig {
+        serde_json::from_str(
+            r#"{
+                "storageDir": "artifacts",
+                "teams": [
+                    { "id": "team_web", "slug": "web", "tokens": ["web-token", "shared-token"] },
+                    { "id": "team_docs", "slug": "docs", "tokens": ["shared-token"] }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authorize() {
+        let config = config();
+        let team_id = |team: Option<&TeamConfig>| team.map(|team| team.id.as_str());
+
+        assert_eq!(
+            team_id(config.authorize("web-token", None, None)),
+            Some("team_web")
+        );
+        assert_eq!(
+            team_id(config.authorize("web-token", Some("team_web"), None)),
+            Some("team_web")
+        );
+        assert_eq!(
+            team_id(config.authorize("web-token", None, Some("docs"))),
+            None
+        );
+        assert_eq!(team_id(config.authorize("wrong-token", None, None)), None);
+
+        // A token with access to several teams has to pick one
+        assert_eq!(team_id(config.authorize("shared-token", None, None)), None);
+        assert_eq!(
+            team_id(config.authorize("shared-token", None, Some("docs"))),
+            Some("team_docs")
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache-server.json");
+        std::fs::write(
+            &path,
+            r#"{ "storageDir": "artifacts", "maxAge": 60, "teams": [] }"#,
+        )
+        .unwrap();
+
+        let config = ServerConfig::load(&path).unwrap();
+        assert_eq!(config.storage_dir, dir.path().join("artifacts"));
+        assert_eq!(config.limits().max_age, Some(Duration::from_secs(60)));
+        assert_eq!(config.limits().max_size, None);
+
+        std::fs::write(
+            &path,
+            r#"{ "storageDir": "artifacts", "teams": [{ "id": "../team", "slug": "team", "tokens": [] }] }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ServerConfig::load(&path),
+            Err(Error::InvalidTeamId(team_id)) if team_id == "../team"
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! A self-hostable remote cache server. It implements the `/v8/artifacts`
+//! routes of the Vercel API that `turbo` uses, storing artifacts on local disk
+//! and checking bearer tokens against a config file of teams.
+#![deny(clippy::all)]
+
+mod config;
+mod routes;
+mod storage;
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+pub use crate::{
+    config::{ServerConfig, TeamConfig},
+    storage::{ArtifactMetadata, Storage, Usage},
+};
+
+// How often artifacts are evicted to keep the storage within its limits
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unable to read config file {path}: {source}")]
+    ReadConfig {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid config file {path}: {source}")]
+    ParseConfig {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid team id {0}: team ids can only contain letters, numbers, '-' and '_'")]
+    InvalidTeamId(String),
+    #[error("team {0} is configured more than once")]
+    DuplicateTeam(String),
+    #[error("team {0} has an empty token")]
+    EmptyToken(String),
+    #[error("invalid artifact hash: {0}")]
+    InvalidHash(String),
+    #[error("invalid artifact metadata: {0}")]
+    Metadata(#[source] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("eviction task failed: {0}")]
+    Eviction(#[from] tokio::task::JoinError),
+}
+
+pub(crate) struct AppState {
+    config: ServerConfig,
+    storage: Storage,
+    // Counts of the analytics events teams have sent
+    hits: AtomicU64,
+    misses: AtomicU64,
+    // Updated by the eviction task, so reporting it doesn't scan the storage
+    usage: Mutex<Usage>,
+}
+
+/// Starts the server, evicting artifacts in the background if the config sets
+/// any limits
+pub async fn serve(config: ServerConfig, addr: SocketAddr) -> Result<(), Error> {
+    let storage = Storage::new(&config.storage_dir)?;
+    let limits = config.limits();
+    let state = Arc::new(AppState {
+        config,
+        storage: storage.clone(),
+        hits: AtomicU64::new(0),
+        misses: AtomicU64::new(0),
+        usage: Mutex::new(Usage::default()),
+    });
+
+    let eviction_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let storage = storage.clone();
+            match tokio::task::spawn_blocking(move || storage.evict(&limits)).await {
+                Ok(Ok((evicted, usage))) => {
+                    if !evicted.is_empty() {
+                        info!("evicted {} artifacts", evicted.len());
+                    }
+                    *eviction_state.usage.lock().expect("lock poisoned") = usage;
+                }
+                Ok(Err(e)) => warn!("failed to evict artifacts: {}", e),
+                Err(e) => warn!("failed to evict artifacts: {}", Error::from(e)),
+            }
+        }
+    });
+
+    info!("listening on {}", addr);
+    axum_server::bind(addr)
+        .serve(routes::router(state).into_make_service())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use tokio::net::TcpStream;
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+    use turborepo_api_client::{APIAuth, APIClient};
+    use turborepo_cache::{http::HTTPCache, CacheOpts, RemoteCacheOpts};
+
+    use super::*;
+
+    async fn start_server(storage_dir: &std::path::Path) -> Result<u16> {
+        let port = port_scanner::request_open_port().unwrap();
+        let config = ServerConfig {
+            storage_dir: storage_dir.to_owned(),
+            max_size: None,
+            max_age: None,
+            max_entries: None,
+            teams: vec![TeamConfig {
+                id: "team_web".to_string(),
+                slug: "web".to_string(),
+                tokens: vec!["web-token".to_string()],
+            }],
+        };
+        tokio::spawn(serve(config, SocketAddr::from(([127, 0, 0, 1], port))));
+
+        // Only error responses are retried, not refused connections, so wait
+        // for the server to start accepting connections
+        for _ in 0..50 {
+            if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        Ok(port)
+    }
+
+    fn http_cache(port: u16, repo_root: &AbsoluteSystemPathBuf, token: &str) -> Result<HTTPCache> {
+        let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
+        let opts = CacheOpts {
+            remote_cache_opts: Some(RemoteCacheOpts::new("team_web".to_string(), false)),
+            ..CacheOpts::default()
+        };
+        let api_auth = APIAuth {
+            team_id: Some("team_web".to_string()),
+            token: token.to_string(),
+            team_slug: None,
+        };
+
+        Ok(HTTPCache::new(
+            api_client,
+            &opts,
+            repo_root.clone(),
+            api_auth,
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_http_cache_round_trip() -> Result<()> {
+        let storage_dir = tempfile::tempdir()?;
+        let port = start_server(storage_dir.path()).await?;
+
+        let repo_root = tempfile::tempdir()?;
+        let repo_root = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+        repo_root
+            .join_component("output.txt")
+            .create_with_contents("built")?;
+        let files = vec![AnchoredSystemPathBuf::from_raw("output.txt")?];
+        http_cache(port, &repo_root, "web-token")?
+            .put(&repo_root, "abc123", &files, 1234)
+            .await?;
+
+        let restore_root = tempfile::tempdir()?;
+        let restore_root = AbsoluteSystemPathBuf::try_from(restore_root.path())?;
+        let cache = http_cache(port, &restore_root, "web-token")?;
+        assert_eq!(
+            cache.exists("abc123").await?.map(|hit| hit.time_saved),
+            Some(1234)
+        );
+        let (hit, restored) = cache.fetch("abc123").await?.expect("artifact exists");
+        assert_eq!(hit.time_saved, 1234);
+        assert_eq!(restored, files);
+        assert_eq!(
+            restore_root.join_component("output.txt").read_to_string()?,
+            "built"
+        );
+        assert!(cache.fetch("def456").await?.is_none());
+
+        // Tokens that don't have access to the team can't read its artifacts
+        let cache = http_cache(port, &restore_root, "wrong-token")?;
+        assert!(cache.fetch("abc123").await.is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    process,
+};
+
+use clap::Parser;
+use turborepo_cache_server::{serve, ServerConfig};
+
+/// A self-hosted remote cache for Turborepo
+#[derive(Parser)]
+#[command(name = "turbo-cache-server", version)]
+struct Args {
+    /// Path to the JSON config file with the storage directory, eviction
+    /// limits and teams
+    #[arg(long, default_value = "cache-server.json")]
+    config: PathBuf,
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    host: IpAddr,
+    /// Port to listen on
+    #[arg(long, default_value_t = 3000)]
+    port: u16,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    let result = match ServerConfig::load(&args.config) {
+        Ok(config) => serve(config, SocketAddr::new(args.host, args.port)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("turbo-cache-server: {}", e);
+        process::exit(1);
+    }
+}
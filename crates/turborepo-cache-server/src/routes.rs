@@ -0,0 +1,480 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use axum::{
+    async_trait,
+    body::StreamBody,
+    extract::{BodyStream, FromRequestParts, Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        request::Parts,
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio_util::io::ReaderStream;
+use tracing::error;
+use turborepo_vercel_api::{
+    AnalyticsEvent, ArtifactInfo, ArtifactQueryResult, ArtifactsQueryRequest,
+    ArtifactsQueryResponse, CacheEvent, CachingStatus, CachingStatusResponse,
+};
+
+use crate::{
+    storage::{ArtifactMetadata, Usage},
+    AppState, Error,
+};
+
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, User-Agent, x-artifact-duration, \
+                               x-artifact-tag, x-artifact-client-ci";
+
+pub(crate) fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/status", get(server_status))
+        .route("/v8/artifacts/status", get(caching_status))
+        .route("/v8/artifacts/events", post(record_events))
+        .route("/v8/artifacts", post(query_artifacts).options(preflight))
+        .route(
+            "/v8/artifacts/:hash",
+            get(get_artifact)
+                .head(artifact_exists)
+                .put(put_artifact)
+                .options(preflight),
+        )
+        .with_state(state)
+}
+
+/// An error response in the format of the Vercel API
+pub(crate) struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidHash(_) => Self::bad_request(error.to_string()),
+            error => {
+                error!("{}", error);
+                Self::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_server_error",
+                    "an unexpected error occurred",
+                )
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({ "error": { "code": self.code, "message": self.message } })),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct TeamParams {
+    #[serde(rename = "teamId")]
+    team_id: Option<String>,
+    slug: Option<String>,
+}
+
+/// The id of the team a request is authorized for
+pub(crate) struct Team(String);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Team {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "unauthorized",
+                    "missing bearer token",
+                )
+            })?;
+        let Query(params) = Query::<TeamParams>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+        state
+            .config
+            .authorize(token, params.team_id.as_deref(), params.slug.as_deref())
+            .map(|team| Team(team.id.clone()))
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::FORBIDDEN,
+                    "forbidden",
+                    "the token doesn't have access to this team",
+                )
+            })
+    }
+}
+
+fn artifact_headers(metadata: &ArtifactMetadata) -> Result<HeaderMap, ApiError> {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-artifact-duration", HeaderValue::from(metadata.duration));
+    if let Some(tag) = &metadata.tag {
+        headers.insert(
+            "x-artifact-tag",
+            HeaderValue::from_str(tag).map_err(|_| {
+                ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_server_error",
+                    "the artifact's tag is not a valid header",
+                )
+            })?,
+        );
+    }
+
+    Ok(headers)
+}
+
+async fn put_artifact(
+    State(state): State<Arc<AppState>>,
+    Team(team_id): Team,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+    body: BodyStream,
+) -> Result<StatusCode, ApiError> {
+    let duration = headers
+        .get("x-artifact-duration")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|duration| duration.parse().ok())
+        .ok_or_else(|| ApiError::bad_request("x-artifact-duration header is missing or invalid"))?;
+    let tag = headers
+        .get("x-artifact-tag")
+        .map(|header| header.to_str().map(|tag| tag.to_string()))
+        .transpose()
+        .map_err(|_| ApiError::bad_request("x-artifact-tag header is invalid"))?;
+
+    state
+        .storage
+        .put(&team_id, &hash, &ArtifactMetadata { duration, tag }, body)
+        .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn get_artifact(
+    State(state): State<Arc<AppState>>,
+    Team(team_id): Team,
+    Path(hash): Path<String>,
+) -> Result<Response, ApiError> {
+    let Some((metadata, file)) = state.storage.get(&team_id, &hash).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let mut headers = artifact_headers(&metadata)?;
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/octet-stream"),
+    );
+
+    // Artifacts are streamed from disk rather than read into memory
+    Ok((headers, StreamBody::new(ReaderStream::new(file))).into_response())
+}
+
+async fn artifact_exists(
+    State(state): State<Arc<AppState>>,
+    Team(team_id): Team,
+    Path(hash): Path<String>,
+) -> Result<Response, ApiError> {
+    match state.storage.exists(&team_id, &hash).await? {
+        Some((metadata, _)) => Ok((artifact_headers(&metadata)?, StatusCode::OK).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+async fn query_artifacts(
+    State(state): State<Arc<AppState>>,
+    Team(team_id): Team,
+    Json(request): Json<ArtifactsQueryRequest>,
+) -> Result<Json<ArtifactsQueryResponse>, ApiError> {
+    let mut response = ArtifactsQueryResponse::new();
+    for hash in request.hashes {
+        let artifact = state
+            .storage
+            .exists(&team_id, &hash)
+            .await?
+            .map(|(metadata, size)| {
+                ArtifactQueryResult::Found(ArtifactInfo {
+                    size,
+                    task_duration_ms: metadata.duration,
+                    tag: metadata.tag,
+                })
+            });
+        response.insert(hash, artifact);
+    }
+
+    Ok(Json(response))
+}
+
+async fn record_events(
+    State(state): State<Arc<AppState>>,
+    Team(team_id): Team,
+    Json(events): Json<Vec<AnalyticsEvent>>,
+) -> Result<StatusCode, ApiError> {
+    for event in &events {
+        let counter = match event.event {
+            CacheEvent::Hit => &state.hits,
+            CacheEvent::Miss => &state.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    state.storage.record_events(&team_id, &events).await?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn caching_status(_: Team) -> Json<CachingStatusResponse> {
+    Json(CachingStatusResponse {
+        status: CachingStatus::Enabled,
+    })
+}
+
+// `turbo` checks whether it can send the Authorization header before
+// requests when preflight is enabled
+async fn preflight() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Access-Control-Allow-Methods",
+        HeaderValue::from_static("GET, HEAD, POST, PUT, OPTIONS"),
+    );
+    headers.insert(
+        "Access-Control-Allow-Headers",
+        HeaderValue::from_static(ALLOWED_HEADERS),
+    );
+
+    headers
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerStatus {
+    #[serde(flatten)]
+    usage: Usage,
+    hits: u64,
+    misses: u64,
+}
+
+/// Reports how much was stored at the last eviction and how the cache has
+/// been used, without requiring a token so it can be used as a health check
+async fn server_status(State(state): State<Arc<AppState>>) -> Json<ServerStatus> {
+    Json(ServerStatus {
+        usage: *state.usage.lock().expect("lock poisoned"),
+        hits: state.hits.load(Ordering::Relaxed),
+        misses: state.misses.load(Ordering::Relaxed),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{atomic::AtomicU64, Mutex},
+        time::{Duration, SystemTime},
+    };
+
+    use axum::{
+        body::{Body, HttpBody},
+        http::{Method, Request},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{ServerConfig, Storage, TeamConfig};
+
+    fn state(storage_dir: &std::path::Path) -> Arc<AppState> {
+        let team = |id: &str, token: &str| TeamConfig {
+            id: id.to_string(),
+            slug: id.trim_start_matches("team_").to_string(),
+            tokens: vec![token.to_string()],
+        };
+        let config = ServerConfig {
+            storage_dir: storage_dir.to_owned(),
+            max_size: None,
+            max_age: None,
+            max_entries: None,
+            teams: vec![
+                team("team_web", "web-token"),
+                team("team_docs", "docs-token"),
+            ],
+        };
+
+        Arc::new(AppState {
+            storage: Storage::new(storage_dir).unwrap(),
+            config,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            usage: Mutex::new(Usage::default()),
+        })
+    }
+
+    fn app(storage_dir: &std::path::Path) -> Router {
+        router(state(storage_dir))
+    }
+
+    fn request(method: Method, uri: &str, token: Option<&str>, body: Body) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        request.body(body).unwrap()
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let response = app(dir.path())
+            .oneshot(request(
+                Method::GET,
+                "/v8/artifacts/abc123",
+                None,
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(json_body(response).await["error"]["code"], "unauthorized");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_team() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = app(dir.path());
+
+        for uri in [
+            "/v8/artifacts/abc123?teamId=team_docs",
+            "/v8/artifacts/abc123?slug=docs",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(request(Method::GET, uri, Some("web-token"), Body::empty()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+        }
+
+        let response = app
+            .oneshot(request(
+                Method::GET,
+                "/v8/artifacts/abc123?teamId=team_web",
+                Some("web-token"),
+                Body::empty(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_events_are_counted_in_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = app(dir.path());
+        let events = json!([
+            { "source": "REMOTE", "event": "HIT", "hash": "abc123", "duration": 100 },
+            { "source": "REMOTE", "event": "MISS", "hash": "def456", "duration": 0 },
+            { "source": "LOCAL", "event": "HIT", "hash": "ghi789", "duration": 50 },
+        ]);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::POST,
+                "/v8/artifacts/events",
+                Some("web-token"),
+                Body::from(events.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let event_log = std::fs::read_to_string(dir.path().join("events/team_web.jsonl")).unwrap();
+        assert_eq!(event_log.lines().count(), 3);
+
+        // The status doesn't need a token
+        let response = app
+            .oneshot(request(Method::GET, "/status", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json_body(response).await,
+            json!({ "artifacts": 0, "size": 0, "hits": 2, "misses": 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_does_not_touch_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(dir.path());
+        *state.usage.lock().unwrap() = Usage {
+            artifacts: 3,
+            size: 1024,
+        };
+        // A temporary file left behind by an interrupted upload, which only
+        // the eviction task should clean up
+        let team_dir = dir.path().join("artifacts/team_web");
+        std::fs::create_dir_all(&team_dir).unwrap();
+        let stale_upload = team_dir.join(".upload.tmp");
+        std::fs::File::create(&stale_upload)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(2 * 60 * 60))
+            .unwrap();
+
+        let response = router(state)
+            .oneshot(request(Method::GET, "/status", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json_body(response).await,
+            json!({ "artifacts": 3, "size": 1024, "hits": 0, "misses": 0 })
+        );
+        assert!(stale_upload.exists());
+    }
+}
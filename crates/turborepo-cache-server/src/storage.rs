@@ -0,0 +1,415 @@
+//! Artifacts are stored as `<storage dir>/artifacts/<team id>/<hash>`, next to
+//! a `<hash>.json` file with the headers they were uploaded with. Both are
+//! written to temporary files first and the metadata is moved into place last,
+//! so an artifact only exists once it has been fully uploaded. The metadata
+//! file's modification time is when the artifact was last downloaded.
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use axum::body::Bytes;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use turborepo_cache::CacheLimits;
+use turborepo_vercel_api::AnalyticsEvent;
+
+use crate::{config::is_valid_path_component, Error};
+
+const METADATA_SUFFIX: &str = ".json";
+const TEMP_SUFFIX: &str = ".tmp";
+// Uploads that were interrupted by the server stopping leave temporary files
+// behind, anything this old is no longer being written to
+const STALE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// The headers an artifact was uploaded with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactMetadata {
+    pub duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredArtifact {
+    pub team_id: String,
+    pub hash: String,
+    // Combined size of the archive and metadata in bytes
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// The number and combined size of the stored artifacts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub artifacts: usize,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    pub fn new(storage_dir: &Path) -> Result<Self, Error> {
+        let storage = Self {
+            root: storage_dir.to_owned(),
+        };
+        fs::create_dir_all(storage.artifacts_dir())?;
+        fs::create_dir_all(storage.events_dir())?;
+
+        Ok(storage)
+    }
+
+    fn artifacts_dir(&self) -> PathBuf {
+        self.root.join("artifacts")
+    }
+
+    fn events_dir(&self) -> PathBuf {
+        self.root.join("events")
+    }
+
+    // The archive and metadata paths of an artifact
+    fn paths(&self, team_id: &str, hash: &str) -> Result<(PathBuf, PathBuf), Error> {
+        if !is_valid_path_component(hash) {
+            return Err(Error::InvalidHash(hash.to_string()));
+        }
+        let team_dir = self.artifacts_dir().join(team_id);
+
+        Ok((
+            team_dir.join(hash),
+            team_dir.join(format!("{hash}{METADATA_SUFFIX}")),
+        ))
+    }
+
+    pub async fn put<E>(
+        &self,
+        team_id: &str,
+        hash: &str,
+        metadata: &ArtifactMetadata,
+        body: impl Stream<Item = Result<Bytes, E>>,
+    ) -> Result<(), Error>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let (archive_path, metadata_path) = self.paths(team_id, hash)?;
+        let team_dir = archive_path
+            .parent()
+            .expect("artifact is in a team directory");
+        tokio::fs::create_dir_all(team_dir).await?;
+
+        // The temporary files are removed if the upload fails
+        let archive = temp_file(team_dir)?;
+        let (file, archive) = archive.into_parts();
+        let mut file = tokio::fs::File::from_std(file);
+        futures::pin_mut!(body);
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        file.sync_all().await?;
+        drop(file);
+
+        let mut metadata_file = temp_file(team_dir)?;
+        serde_json::to_writer(&mut metadata_file, metadata).map_err(Error::Metadata)?;
+
+        archive.persist(&archive_path).map_err(|e| e.error)?;
+        metadata_file.persist(&metadata_path).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    /// Opens an artifact for downloading, if it exists
+    pub async fn get(
+        &self,
+        team_id: &str,
+        hash: &str,
+    ) -> Result<Option<(ArtifactMetadata, tokio::fs::File)>, Error> {
+        let (archive_path, metadata_path) = self.paths(team_id, hash)?;
+        let Some(metadata) = Self::read_metadata(&metadata_path).await? else {
+            return Ok(None);
+        };
+        // The artifact could have been evicted since we read its metadata
+        let file = match tokio::fs::File::open(&archive_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Marks the artifact as used, so it's evicted after less recently used ones
+        let touched = tokio::task::spawn_blocking(move || {
+            fs::File::options()
+                .write(true)
+                .open(&metadata_path)?
+                .set_modified(SystemTime::now())
+        })
+        .await
+        .map_err(io::Error::from);
+        if let Err(e) = touched.and_then(|touched| touched) {
+            tracing::debug!("unable to update last use of {}: {}", hash, e);
+        }
+
+        Ok(Some((metadata, file)))
+    }
+
+    /// Reads the metadata and size of an artifact, if it exists
+    pub async fn exists(
+        &self,
+        team_id: &str,
+        hash: &str,
+    ) -> Result<Option<(ArtifactMetadata, u64)>, Error> {
+        let (archive_path, metadata_path) = self.paths(team_id, hash)?;
+        let Some(metadata) = Self::read_metadata(&metadata_path).await? else {
+            return Ok(None);
+        };
+
+        match tokio::fs::metadata(&archive_path).await {
+            Ok(archive) => Ok(Some((metadata, archive.len()))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn read_metadata(path: &Path) -> Result<Option<ArtifactMetadata>, Error> {
+        match tokio::fs::read(path).await {
+            Ok(contents) => Ok(Some(
+                serde_json::from_slice(&contents).map_err(Error::Metadata)?,
+            )),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Appends analytics events to the team's event log, one JSON event per
+    /// line
+    pub async fn record_events(
+        &self,
+        team_id: &str,
+        events: &[AnalyticsEvent],
+    ) -> Result<(), Error> {
+        let mut lines = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut lines, event).map_err(Error::Metadata)?;
+            lines.push(b'\n');
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.events_dir().join(format!("{team_id}.jsonl")))
+            .await?;
+        file.write_all(&lines).await?;
+
+        Ok(())
+    }
+
+    /// Lists the stored artifacts of every team, removing temporary files left
+    /// behind by interrupted uploads
+    pub fn artifacts(&self) -> Result<Vec<StoredArtifact>, Error> {
+        let now = SystemTime::now();
+        let mut artifacts = Vec::new();
+
+        for team_dir in fs::read_dir(self.artifacts_dir())? {
+            let team_dir = team_dir?;
+            if !team_dir.file_type()?.is_dir() {
+                continue;
+            }
+            let team_id = team_dir.file_name().to_string_lossy().into_owned();
+
+            for entry in fs::read_dir(team_dir.path())? {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                // Artifacts can be removed while we list them
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let is_stale = now
+                    .duration_since(metadata.modified()?)
+                    .map_or(false, |age| age > STALE_AGE);
+
+                if let Some(hash) = file_name.strip_suffix(METADATA_SUFFIX) {
+                    let archive_size = match fs::metadata(team_dir.path().join(hash)) {
+                        Ok(archive) => archive.len(),
+                        Err(e) if e.kind() == ErrorKind::NotFound => 0,
+                        Err(e) => return Err(e.into()),
+                    };
+                    artifacts.push(StoredArtifact {
+                        team_id: team_id.clone(),
+                        hash: hash.to_string(),
+                        size: metadata.len() + archive_size,
+                        last_used: metadata.modified()?,
+                    });
+                } else if file_name.ends_with(TEMP_SUFFIX) && is_stale {
+                    remove_file(&entry.path())?;
+                } else if is_stale
+                    && !team_dir
+                        .path()
+                        .join(format!("{file_name}{METADATA_SUFFIX}"))
+                        .exists()
+                {
+                    // An archive whose metadata was never written
+                    remove_file(&entry.path())?;
+                }
+            }
+        }
+
+        Ok(artifacts)
+    }
+
+    pub fn remove(&self, artifact: &StoredArtifact) -> Result<(), Error> {
+        let (archive_path, metadata_path) = self.paths(&artifact.team_id, &artifact.hash)?;
+        // The metadata is removed first, so the artifact stops existing before
+        // its archive is gone
+        remove_file(&metadata_path)?;
+        remove_file(&archive_path)?;
+
+        Ok(())
+    }
+
+    /// Removes the least recently used artifacts across all teams until the
+    /// storage is within `limits`, returning the artifacts that were removed
+    /// and the usage of the ones that are left.
+    pub fn evict(&self, limits: &CacheLimits) -> Result<(Vec<StoredArtifact>, Usage), Error> {
+        let mut artifacts = self.artifacts()?;
+        if limits.is_unlimited() {
+            let usage = Usage {
+                artifacts: artifacts.len(),
+                size: artifacts.iter().map(|artifact| artifact.size).sum(),
+            };
+            return Ok((Vec::new(), usage));
+        }
+        // Most recently used first, so we can pop artifacts off the end
+        artifacts.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+
+        let now = SystemTime::now();
+        let mut total_size: u64 = artifacts.iter().map(|artifact| artifact.size).sum();
+        let mut evicted = Vec::new();
+
+        while let Some(artifact) = artifacts.last() {
+            let is_expired = limits.max_age.map_or(false, |max_age| {
+                now.duration_since(artifact.last_used)
+                    .map_or(false, |age| age > max_age)
+            });
+            let has_too_many = limits
+                .max_entries
+                .map_or(false, |max_entries| artifacts.len() > max_entries);
+            let is_too_large = limits
+                .max_size
+                .map_or(false, |max_size| total_size > max_size);
+
+            if !is_expired && !has_too_many && !is_too_large {
+                break;
+            }
+
+            let artifact = artifacts.pop().expect("artifacts is not empty");
+            self.remove(&artifact)?;
+            total_size -= artifact.size;
+            evicted.push(artifact);
+        }
+
+        let usage = Usage {
+            artifacts: artifacts.len(),
+            size: total_size,
+        };
+        Ok((evicted, usage))
+    }
+}
+
+fn temp_file(dir: &Path) -> Result<tempfile::NamedTempFile, Error> {
+    Ok(tempfile::Builder::new()
+        .prefix(".")
+        .suffix(TEMP_SUFFIX)
+        .tempfile_in(dir)?)
+}
+
+fn remove_file(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    async fn put(storage: &Storage, team_id: &str, hash: &str, body: &'static [u8]) {
+        let metadata = ArtifactMetadata {
+            duration: 100,
+            tag: None,
+        };
+        let body = futures::stream::iter([Ok::<_, Infallible>(Bytes::from_static(body))]);
+        storage.put(team_id, hash, &metadata, body).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_teams_are_isolated() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path()).unwrap();
+        put(&storage, "team_web", "abc123", b"artifact").await;
+
+        let (metadata, size) = storage.exists("team_web", "abc123").await.unwrap().unwrap();
+        assert_eq!(metadata.duration, 100);
+        assert_eq!(size, 8);
+        assert!(storage
+            .exists("team_docs", "abc123")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            storage.exists("team_web", "../team_docs").await,
+            Err(Error::InvalidHash(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path()).unwrap();
+        put(&storage, "team_web", "oldest", b"artifact").await;
+        put(&storage, "team_docs", "older", b"artifact").await;
+        put(&storage, "team_web", "newest", b"artifact").await;
+
+        // Modification times aren't precise enough to order artifacts that were
+        // just written, so we set them explicitly
+        let now = SystemTime::now();
+        for (team_id, hash, age) in [
+            ("team_web", "oldest", 30),
+            ("team_docs", "older", 20),
+            ("team_web", "newest", 10),
+        ] {
+            let (_, metadata_path) = storage.paths(team_id, hash).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(metadata_path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+        // Downloading an artifact makes it the most recently used
+        storage.get("team_web", "oldest").await.unwrap().unwrap();
+
+        let (evicted, usage) = storage
+            .evict(&CacheLimits {
+                max_entries: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        let evicted = evicted
+            .iter()
+            .map(|artifact| artifact.hash.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(evicted, vec!["older", "newest"]);
+        assert_eq!(usage.artifacts, 1);
+        assert!(storage.get("team_web", "oldest").await.unwrap().is_some());
+    }
+}
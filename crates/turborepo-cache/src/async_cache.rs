@@ -131,6 +131,12 @@ impl AsyncCache {
         }
     }
 
+    /// Checks the existence of many artifacts in the remote cache at once,
+    /// ahead of the calls to `exists` or `fetch` for them.
+    pub async fn prefetch_exists(&self, keys: &[String]) {
+        self.real_cache.prefetch_exists(keys).await
+    }
+
     pub async fn exists(&self, key: &str) -> Result<Option<CacheHitMetadata>, CacheError> {
         self.real_cache.exists(key).await
     }
@@ -174,7 +180,7 @@ mod tests {
 
     use crate::{
         test_cases::{get_test_cases, TestCase},
-        AsyncCache, CacheHitMetadata, CacheOpts, CacheSource, RemoteCacheOpts,
+        AsyncCache, CacheHitMetadata, CacheLayout, CacheOpts, CacheSource, RemoteCacheOpts,
     };
 
     #[tokio::test]
@@ -202,6 +208,7 @@ mod tests {
 
         let opts = CacheOpts {
             override_dir: None,
+            shared_dir: None,
             remote_cache_read_only: false,
             skip_remote: false,
             skip_filesystem: true,
@@ -209,7 +216,9 @@ mod tests {
             remote_cache_opts: Some(RemoteCacheOpts {
                 team_id: "my-team".to_string(),
                 signature: false,
+                ..Default::default()
             }),
+            layout: CacheLayout::Archive,
         };
 
         let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
@@ -276,6 +285,7 @@ mod tests {
 
         let opts = CacheOpts {
             override_dir: None,
+            shared_dir: None,
             remote_cache_read_only: false,
             skip_remote: true,
             skip_filesystem: false,
@@ -283,7 +293,9 @@ mod tests {
             remote_cache_opts: Some(RemoteCacheOpts {
                 team_id: "my-team".to_string(),
                 signature: false,
+                ..Default::default()
             }),
+            layout: CacheLayout::Archive,
         };
 
         // Initialize client with invalid API url to ensure that we don't hit the
@@ -360,6 +372,7 @@ mod tests {
 
         let opts = CacheOpts {
             override_dir: None,
+            shared_dir: None,
             remote_cache_read_only: false,
             skip_remote: false,
             skip_filesystem: false,
@@ -367,7 +380,9 @@ mod tests {
             remote_cache_opts: Some(RemoteCacheOpts {
                 team_id: "my-team".to_string(),
                 signature: false,
+                ..Default::default()
             }),
+            layout: CacheLayout::Archive,
         };
 
         let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
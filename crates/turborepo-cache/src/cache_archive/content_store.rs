@@ -0,0 +1,464 @@
+//! A content-addressed layout for cache artifacts. Regular files are stored
+//! once as blobs keyed by their SHA-256 digest and permissions, and an
+//! artifact is a manifest of the entries its archive would have. Restoring a
+//! file reflinks its blob where the filesystem supports it and copies it
+//! otherwise, unless hardlinks are enabled.
+use std::{
+    backtrace::Backtrace,
+    fs::{self, FileTimes},
+    io::{self, ErrorKind, Read, Write},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Entry, EntryType};
+use turbopath::{
+    AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPath, AnchoredSystemPathBuf, IntoUnix,
+    RelativeUnixPath,
+};
+
+use crate::{
+    cache_archive::{restore_directory::CachedDirTree, CacheReader, CacheWriter},
+    CacheError,
+};
+
+// Blobs can be hardlinked into the repository, so a task that later writes to
+// one of its outputs in place also writes to the blob. Blobs are given a fixed
+// modification time, so a blob that was written to can be detected and treated
+// as a cache miss.
+const BLOB_MTIME: Duration = Duration::from_secs(946_684_800);
+
+/// The entries of an artifact, in the order they would be in its archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The path of the entry in the archive
+    pub path: String,
+    pub mode: u32,
+    #[serde(flatten)]
+    pub kind: ManifestEntryKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ManifestEntryKind {
+    File { digest: String, size: u64 },
+    Directory,
+    Symlink { target: String },
+}
+
+pub struct ContentStore {
+    blob_directory: AbsoluteSystemPathBuf,
+    hardlinks: bool,
+}
+
+impl Manifest {
+    /// The blobs the manifest refers to, with their sizes
+    pub fn blobs<'a>(
+        &'a self,
+        store: &'a ContentStore,
+    ) -> impl Iterator<Item = (AbsoluteSystemPathBuf, u64)> + 'a {
+        self.entries.iter().filter_map(|entry| match &entry.kind {
+            ManifestEntryKind::File { digest, size } => {
+                Some((store.blob_path(digest, entry.mode), *size))
+            }
+            _ => None,
+        })
+    }
+}
+
+impl ContentStore {
+    pub fn new(blob_directory: AbsoluteSystemPathBuf) -> Self {
+        Self {
+            blob_directory,
+            hardlinks: false,
+        }
+    }
+
+    /// Restores files by hardlinking their blobs when they can't be reflinked.
+    /// Hardlinked files share their contents with the cache, so a task that
+    /// writes to one of its outputs in place changes every artifact with that
+    /// file, and those artifacts become cache misses.
+    pub fn with_hardlinks(mut self, hardlinks: bool) -> Self {
+        self.hardlinks = hardlinks;
+        self
+    }
+
+    // Hardlinks share their permissions, so files with the same contents but
+    // different permissions are stored as different blobs
+    pub(crate) fn blob_path(&self, digest: &str, mode: u32) -> AbsoluteSystemPathBuf {
+        self.blob_directory.join_components(&[
+            &digest[..2.min(digest.len())],
+            &format!("{}-{:o}", digest, mode & 0o7777),
+        ])
+    }
+
+    /// Lists the blobs in the store with their sizes
+    pub(crate) fn blobs(&self) -> Result<Vec<(AbsoluteSystemPathBuf, u64)>, CacheError> {
+        let mut blobs = Vec::new();
+        let prefixes = match fs::read_dir(&self.blob_directory) {
+            Ok(prefixes) => prefixes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(blobs),
+            Err(e) => return Err(e.into()),
+        };
+
+        for prefix in prefixes {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for blob in fs::read_dir(prefix.path())? {
+                let blob = blob?;
+                let metadata = blob.metadata()?;
+                if metadata.is_file() {
+                    blobs.push((
+                        AbsoluteSystemPathBuf::try_from(blob.path().as_path())?,
+                        metadata.len(),
+                    ));
+                }
+            }
+        }
+
+        Ok(blobs)
+    }
+
+    // Stores the contents of a file, returning its digest and size
+    fn add_blob(&self, mut body: impl Read, mode: u32) -> Result<(String, u64), CacheError> {
+        self.blob_directory.create_dir_all()?;
+        let mut file = tempfile::NamedTempFile::new_in(&self.blob_directory)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buffer = [0; 8192];
+        loop {
+            let n = body.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            file.write_all(&buffer[..n])?;
+            size += n as u64;
+        }
+
+        let digest = hex::encode(hasher.finalize());
+        let blob_path = self.blob_path(&digest, mode);
+        // The temporary file is removed if we already have the blob
+        if self.check_blob(&blob_path, size).is_ok() {
+            return Ok((digest, size));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.as_file()
+                .set_permissions(fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+        file.as_file()
+            .set_times(FileTimes::new().set_modified(SystemTime::UNIX_EPOCH + BLOB_MTIME))?;
+        blob_path.ensure_dir()?;
+        file.persist(&blob_path).map_err(|e| e.error)?;
+
+        Ok((digest, size))
+    }
+
+    // Checks that a blob exists and hasn't been written to since it was stored
+    fn check_blob(&self, blob_path: &AbsoluteSystemPath, size: u64) -> Result<(), CacheError> {
+        let is_valid = blob_path.symlink_metadata().map_or(false, |metadata| {
+            metadata.is_file()
+                && metadata.len() == size
+                && metadata.modified().map_or(false, |modified| {
+                    modified == SystemTime::UNIX_EPOCH + BLOB_MTIME
+                })
+        });
+        if !is_valid {
+            return Err(CacheError::InvalidBlob(
+                blob_path.to_string(),
+                Backtrace::capture(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Opens the blob of a file entry, checking that it hasn't been modified
+    pub(crate) fn open_blob(
+        &self,
+        digest: &str,
+        mode: u32,
+        size: u64,
+    ) -> Result<fs::File, CacheError> {
+        let blob_path = self.blob_path(digest, mode);
+        self.check_blob(&blob_path, size)?;
+
+        Ok(blob_path.open()?)
+    }
+
+    /// Stores a file from the repository, returning its manifest entry
+    pub fn add_file(
+        &self,
+        anchor: &AbsoluteSystemPath,
+        file_path: &AnchoredSystemPath,
+    ) -> Result<ManifestEntry, CacheError> {
+        let source_path = anchor.resolve(file_path);
+        let file_info = source_path.symlink_metadata()?;
+
+        // Paths and modes are normalized the same way as in archives, so
+        // converting between the layouts is lossless
+        let mut path = file_path.to_unix();
+        path.make_canonical_for_tar(file_info.is_dir());
+        let header = CacheWriter::create_header(&file_info)?;
+        let mode = header.mode()?;
+
+        let kind = match header.entry_type() {
+            EntryType::Regular => {
+                let (digest, size) = self.add_blob(source_path.open()?, mode)?;
+                ManifestEntryKind::File { digest, size }
+            }
+            EntryType::Symlink => ManifestEntryKind::Symlink {
+                target: source_path.read_link()?.into_unix().into_string(),
+            },
+            _ => ManifestEntryKind::Directory,
+        };
+
+        Ok(ManifestEntry {
+            path: path.into_inner(),
+            mode,
+            kind,
+        })
+    }
+
+    /// Stores an entry from an archive, returning its manifest entry
+    pub(crate) fn add_entry(
+        &self,
+        mut entry: Entry<'_, impl Read>,
+    ) -> Result<ManifestEntry, CacheError> {
+        let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let mode = entry.header().mode()?;
+
+        let kind = match entry.header().entry_type() {
+            EntryType::Regular => {
+                let (digest, size) = self.add_blob(&mut entry, mode)?;
+                ManifestEntryKind::File { digest, size }
+            }
+            EntryType::Directory => ManifestEntryKind::Directory,
+            EntryType::Symlink => ManifestEntryKind::Symlink {
+                target: String::from_utf8_lossy(
+                    &entry
+                        .link_name_bytes()
+                        .ok_or_else(|| CacheError::LinkTargetNotOnHeader(Backtrace::capture()))?,
+                )
+                .into_owned(),
+            },
+            ty => {
+                return Err(CacheError::RestoreUnsupportedFileType(
+                    ty,
+                    Backtrace::capture(),
+                ))
+            }
+        };
+
+        Ok(ManifestEntry { path, mode, kind })
+    }
+
+    /// Restores an artifact into `anchor`, returning the restored paths. Every
+    /// blob is checked first, so a modified blob fails the restore before
+    /// anything is written.
+    pub fn restore(
+        &self,
+        manifest: &Manifest,
+        anchor: &AbsoluteSystemPath,
+    ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
+        let (files, others): (Vec<_>, Vec<_>) = manifest
+            .entries
+            .iter()
+            .partition(|entry| matches!(entry.kind, ManifestEntryKind::File { .. }));
+        for entry in &files {
+            if let ManifestEntryKind::File { digest, size } = &entry.kind {
+                self.check_blob(&self.blob_path(digest, entry.mode), *size)?;
+            }
+        }
+
+        // Directories and symlinks are restored from an archive, which takes
+        // care of symlinks whose targets are restored after them
+        let mut archive = Vec::new();
+        let mut writer = CacheWriter::from_writer(&mut archive, false)?;
+        for entry in others {
+            writer.add_manifest_entry(self, entry)?;
+        }
+        writer.finish()?;
+        let mut restored = CacheReader::from_reader(archive.as_slice(), false)?.restore(anchor)?;
+
+        let mut dir_cache = CachedDirTree::new(anchor.to_owned());
+        for entry in files {
+            let ManifestEntryKind::File { digest, .. } = &entry.kind else {
+                continue;
+            };
+            let path = RelativeUnixPath::new(&entry.path)?.to_anchored_system_path_buf();
+            dir_cache.safe_mkdir_file(anchor, &path)?;
+            restore_blob(
+                &self.blob_path(digest, entry.mode),
+                &anchor.resolve(&path),
+                entry.mode,
+                self.hardlinks,
+            )?;
+            restored.push(path);
+        }
+
+        Ok(restored)
+    }
+}
+
+// Reflinks are copy-on-write, so they're preferred over hardlinks, which share
+// their contents with the blob
+fn restore_blob(
+    blob: &AbsoluteSystemPath,
+    path: &AbsoluteSystemPath,
+    mode: u32,
+    hardlinks: bool,
+) -> io::Result<()> {
+    // Never write through a hardlink left by an earlier restore
+    match path.remove_file() {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    if reflink_copy::reflink(blob, path).is_err() {
+        // Hardlinks already have the blob's permissions
+        if hardlinks && fs::hard_link(blob, path).is_ok() {
+            return Ok(());
+        }
+        fs::copy(blob, path)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))?;
+    }
+    #[cfg(windows)]
+    let _ = mode;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn setup() -> Result<(tempfile::TempDir, AbsoluteSystemPathBuf, ContentStore)> {
+        let dir = tempdir()?;
+        let root = AbsoluteSystemPathBuf::try_from(dir.path())?;
+        let store = ContentStore::new(root.join_components(&["cache", "blobs"]));
+        Ok((dir, root, store))
+    }
+
+    #[test]
+    fn test_identical_files_are_stored_once() -> Result<()> {
+        let (_dir, root, store) = setup()?;
+        let repo = root.join_component("repo");
+        for name in ["a.js", "b.js"] {
+            repo.join_components(&["dist", name]).ensure_dir()?;
+            repo.join_components(&["dist", name])
+                .create_with_contents("console.log('hello')")?;
+        }
+
+        let files = ["dist", "dist/a.js", "dist/b.js"]
+            .iter()
+            .map(|path| AnchoredSystemPathBuf::from_raw(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let manifest = Manifest {
+            entries: files
+                .iter()
+                .map(|file| store.add_file(&repo, file))
+                .collect::<Result<_, _>>()?,
+        };
+
+        assert_eq!(manifest.entries[0].path, "dist/");
+        assert_eq!(manifest.entries[0].kind, ManifestEntryKind::Directory);
+        assert_eq!(manifest.entries[1].kind, manifest.entries[2].kind);
+        assert_eq!(store.blobs()?.len(), 1);
+
+        let restore_root = root.join_component("restored");
+        let mut restored = store.restore(&manifest, &restore_root)?;
+        restored.sort();
+        assert_eq!(restored, files);
+        assert_eq!(
+            restore_root
+                .join_components(&["dist", "b.js"])
+                .read_to_string()?,
+            "console.log('hello')"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_round_trip() -> Result<()> {
+        let (_dir, root, store) = setup()?;
+        let repo = root.join_component("repo");
+        repo.join_components(&["dist", "index.js"]).ensure_dir()?;
+        repo.join_components(&["dist", "index.js"])
+            .create_with_contents("export {}")?;
+        repo.join_component("empty.txt").create_with_contents("")?;
+
+        let mut archive = Vec::new();
+        let mut writer = CacheWriter::from_writer(&mut archive, true)?;
+        for file in ["dist", "dist/index.js", "empty.txt"] {
+            writer.add_file(&repo, &AnchoredSystemPathBuf::from_raw(file)?)?;
+        }
+        writer.finish()?;
+
+        // Converting an archive to a manifest and back gives the same archive
+        let manifest = CacheReader::from_reader(archive.as_slice(), true)?.import(&store)?;
+        let mut converted = Vec::new();
+        let mut writer = CacheWriter::from_writer(&mut converted, true)?;
+        for entry in &manifest.entries {
+            writer.add_manifest_entry(&store, entry)?;
+        }
+        writer.finish()?;
+        assert_eq!(
+            CacheReader::from_reader(archive.as_slice(), true)?.get_sha()?,
+            CacheReader::from_reader(converted.as_slice(), true)?.get_sha()?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_blob_is_invalid() -> Result<()> {
+        let (_dir, root, store) = setup()?;
+        let store = store.with_hardlinks(true);
+        let repo = root.join_component("repo");
+        repo.join_component("out.txt")
+            .create_with_contents("output")?;
+        let manifest = Manifest {
+            entries: vec![store.add_file(&repo, &AnchoredSystemPathBuf::from_raw("out.txt")?)?],
+        };
+
+        let restore_root = root.join_component("restored");
+        store.restore(&manifest, &restore_root)?;
+        // Restoring again replaces the restored file rather than writing
+        // through it
+        store.restore(&manifest, &restore_root)?;
+
+        // Simulates a task writing to a hardlinked output in place
+        let (blob_path, _) = manifest.blobs(&store).next().unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&blob_path)?
+            .write_all(b" changed")?;
+
+        assert!(matches!(
+            store.restore(&manifest, &root.join_component("other")),
+            Err(CacheError::InvalidBlob(..))
+        ));
+        Ok(())
+    }
+}
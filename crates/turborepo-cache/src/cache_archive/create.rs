@@ -9,7 +9,10 @@ use std::{
 use tar::{EntryType, Header};
 use turbopath::{AbsoluteSystemPath, AnchoredSystemPath, IntoUnix};
 
-use crate::CacheError;
+use crate::{
+    cache_archive::{ContentStore, ManifestEntry, ManifestEntryKind},
+    CacheError,
+};
 
 pub struct CacheWriter<'a> {
     builder: tar::Builder<Box<dyn Write + 'a>>,
@@ -112,7 +115,40 @@ impl<'a> CacheWriter<'a> {
         Ok(())
     }
 
-    fn create_header(file_info: &fs::Metadata) -> Result<Header, CacheError> {
+    // Adds an entry of a content-addressed artifact to the tar, producing the
+    // same entry `add_file` would have for the original file
+    pub(crate) fn add_manifest_entry(
+        &mut self,
+        store: &ContentStore,
+        entry: &ManifestEntry,
+    ) -> Result<(), CacheError> {
+        let mut header = Header::new_gnu();
+        header.set_mode(entry.mode);
+
+        match &entry.kind {
+            ManifestEntryKind::File { digest, size } => {
+                header.set_entry_type(EntryType::Regular);
+                header.set_size(*size);
+                Self::clear_metadata(&mut header);
+                let blob = store.open_blob(digest, entry.mode, *size)?;
+                self.append_data(&mut header, &entry.path, blob)
+            }
+            ManifestEntryKind::Directory => {
+                header.set_entry_type(EntryType::Directory);
+                header.set_size(0);
+                Self::clear_metadata(&mut header);
+                self.append_data(&mut header, &entry.path, &mut std::io::empty())
+            }
+            ManifestEntryKind::Symlink { target } => {
+                header.set_entry_type(EntryType::Symlink);
+                header.set_size(0);
+                Self::clear_metadata(&mut header);
+                self.append_link(&mut header, &entry.path, target)
+            }
+        }
+    }
+
+    pub(super) fn create_header(file_info: &fs::Metadata) -> Result<Header, CacheError> {
         let mut header = Header::new_gnu();
 
         let mode: u32;
@@ -146,14 +182,18 @@ impl<'a> CacheWriter<'a> {
             return Err(CacheError::CreateUnsupportedFileType(Backtrace::capture()));
         }
 
-        // Consistent creation
+        Self::clear_metadata(&mut header);
+
+        Ok(header)
+    }
+
+    // Consistent creation
+    fn clear_metadata(header: &mut Header) {
         header.set_uid(0);
         header.set_gid(0);
         header.as_gnu_mut().unwrap().set_atime(0);
         header.set_mtime(0);
         header.as_gnu_mut().unwrap().set_ctime(0);
-
-        Ok(header)
     }
 }
 
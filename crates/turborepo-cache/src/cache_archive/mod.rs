@@ -1,9 +1,11 @@
 #![allow(dead_code)]
+mod content_store;
 mod create;
 mod restore;
 mod restore_directory;
 mod restore_regular;
 mod restore_symlink;
 
+pub use content_store::{ContentStore, Manifest, ManifestEntry, ManifestEntryKind};
 pub use create::CacheWriter;
-pub use restore::CacheReader;
+pub use restore::{CacheEntry, CacheEntryKind, CacheReader};
@@ -12,6 +12,7 @@ use crate::{
         restore_symlink::{
             canonicalize_linkname, restore_symlink, restore_symlink_allow_missing_target,
         },
+        ContentStore, Manifest,
     },
     CacheError,
 };
@@ -20,6 +21,22 @@ pub struct CacheReader<'a> {
     reader: Box<dyn Read + 'a>,
 }
 
+/// The kind of an entry in a cache archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheEntryKind {
+    File,
+    Directory,
+    Symlink { target: String },
+}
+
+/// An entry in a cache archive, as listed by `CacheReader::entries`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: AnchoredSystemPathBuf,
+    pub kind: CacheEntryKind,
+    pub size: u64,
+}
+
 impl<'a> CacheReader<'a> {
     pub fn from_reader(reader: impl Read + 'a, is_compressed: bool) -> Result<Self, CacheError> {
         let reader: Box<dyn Read> = if is_compressed {
@@ -58,6 +75,54 @@ impl<'a> CacheReader<'a> {
         Ok(hasher.finalize().to_vec())
     }
 
+    /// Lists the entries of the archive without restoring them
+    pub fn entries(&mut self) -> Result<Vec<CacheEntry>, CacheError> {
+        let mut tr = tar::Archive::new(&mut self.reader);
+
+        tr.entries()?
+            .map(|entry| {
+                let entry = entry?;
+                let header = entry.header();
+                let path = AnchoredSystemPathBuf::from_system_path(&header.path()?)?;
+                let kind = match header.entry_type() {
+                    tar::EntryType::Directory => CacheEntryKind::Directory,
+                    tar::EntryType::Regular => CacheEntryKind::File,
+                    tar::EntryType::Symlink => CacheEntryKind::Symlink {
+                        target: header
+                            .link_name()?
+                            .ok_or_else(|| CacheError::LinkTargetNotOnHeader(Backtrace::capture()))?
+                            .to_string_lossy()
+                            .into_owned(),
+                    },
+                    ty => {
+                        return Err(CacheError::RestoreUnsupportedFileType(
+                            ty,
+                            Backtrace::capture(),
+                        ))
+                    }
+                };
+
+                Ok(CacheEntry {
+                    path,
+                    kind,
+                    size: header.size()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Moves the files of the archive into a content store, returning the
+    /// manifest of the artifact
+    pub fn import(&mut self, store: &ContentStore) -> Result<Manifest, CacheError> {
+        let mut tr = tar::Archive::new(&mut self.reader);
+        let entries = tr
+            .entries()?
+            .map(|entry| store.add_entry(entry?))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Manifest { entries })
+    }
+
     pub fn restore(
         &mut self,
         anchor: &AbsoluteSystemPath,
@@ -193,7 +258,10 @@ mod tests {
     use tracing::debug;
     use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 
-    use crate::cache_archive::{restore::CacheReader, restore_symlink::canonicalize_linkname};
+    use crate::cache_archive::{
+        restore::{CacheEntry, CacheEntryKind, CacheReader},
+        restore_symlink::canonicalize_linkname,
+    };
 
     // Expected output of the cache
     #[derive(Debug)]
@@ -914,6 +982,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_entries() -> Result<()> {
+        let input_files = vec![
+            TarFile::Directory {
+                path: AnchoredSystemPathBuf::from_raw("dist").unwrap(),
+            },
+            TarFile::File {
+                body: b"contents".to_vec(),
+                path: AnchoredSystemPathBuf::from_raw("dist/index.js").unwrap(),
+            },
+            TarFile::Symlink {
+                link_path: AnchoredSystemPathBuf::from_raw("link").unwrap(),
+                link_target: AnchoredSystemPathBuf::from_raw("dist").unwrap(),
+            },
+        ];
+        let expected = vec![
+            CacheEntry {
+                path: AnchoredSystemPathBuf::from_raw("dist").unwrap(),
+                kind: CacheEntryKind::Directory,
+                size: 0,
+            },
+            CacheEntry {
+                path: AnchoredSystemPathBuf::from_raw(
+                    ["dist", "index.js"].join(std::path::MAIN_SEPARATOR_STR),
+                )
+                .unwrap(),
+                kind: CacheEntryKind::File,
+                size: 8,
+            },
+            CacheEntry {
+                path: AnchoredSystemPathBuf::from_raw("link").unwrap(),
+                kind: CacheEntryKind::Symlink {
+                    target: "dist".to_string(),
+                },
+                size: 0,
+            },
+        ];
+
+        for compressed in [false, true] {
+            let input_dir = tempdir()?;
+            let archive_path = generate_tar(&input_dir, &input_files)?;
+            let archive_path = if compressed {
+                compress_tar(&archive_path)?
+            } else {
+                archive_path
+            };
+
+            let mut cache_reader = CacheReader::open(&archive_path)?;
+            assert_eq!(cache_reader.entries()?, expected);
+        }
+
+        Ok(())
+    }
+
     #[test_case(Path::new("source").try_into()?, Path::new("target"), "/Users/test/target", "C:\\Users\\test\\target" ; "hello world")]
     #[test_case(Path::new("child/source").try_into()?, Path::new("../sibling/target"), "/Users/test/sibling/target", "C:\\Users\\test\\sibling\\target" ; "Unix path subdirectory traversal")]
     #[test_case(Path::new("child/source").try_into()?, Path::new("..\\sibling\\target"), "/Users/test/child/..\\sibling\\target", "C:\\Users\\test\\sibling\\target" ; "Windows path subdirectory traversal")]
@@ -1,34 +1,93 @@
-use std::{backtrace::Backtrace, fs::OpenOptions};
+use std::{
+    backtrace::Backtrace,
+    collections::{HashMap, HashSet},
+    fs::{FileTimes, OpenOptions},
+    io::{ErrorKind, Seek},
+    time::SystemTime,
+};
 
 use camino::Utf8Path;
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 use turborepo_analytics::AnalyticsSender;
 use turborepo_api_client::{analytics, analytics::AnalyticsEvent};
 
 use crate::{
-    cache_archive::{CacheReader, CacheWriter},
-    CacheError, CacheHitMetadata, CacheSource,
+    cache_archive::{CacheReader, CacheWriter, ContentStore, Manifest},
+    CacheError, CacheHitMetadata, CacheLayout, CacheLimits, CacheSource,
 };
 
+// Suffixes of the files that make up a single artifact in the cache directory.
+// An artifact has either an archive or, in the content-addressed layout, a
+// manifest of files stored in the `blobs` directory.
+const ARCHIVE_SUFFIXES: [&str; 3] = [".tar.zst", ".tar", MANIFEST_SUFFIX];
+const MANIFEST_SUFFIX: &str = "-manifest.json";
+const METADATA_SUFFIX: &str = "-meta.json";
+
 pub struct FSCache {
     cache_directory: AbsoluteSystemPathBuf,
     analytics_recorder: Option<AnalyticsSender>,
+    layout: CacheLayout,
+    content_store: ContentStore,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-struct CacheMetadata {
-    hash: String,
-    duration: u64,
+pub struct CacheMetadata {
+    pub hash: String,
+    /// How long the task took to run in milliseconds
+    pub duration: u64,
 }
 
 impl CacheMetadata {
-    fn read(path: &AbsoluteSystemPath) -> Result<CacheMetadata, CacheError> {
+    pub(crate) fn read(path: &AbsoluteSystemPath) -> Result<CacheMetadata, CacheError> {
         serde_json::from_str(&path.read_to_string()?)
             .map_err(|e| CacheError::InvalidMetadata(e, Backtrace::capture()))
     }
 }
 
+/// An artifact stored in the filesystem cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FSCacheArtifact {
+    pub hash: String,
+    /// Combined size in bytes of the archive and its metadata. For
+    /// content-addressed artifacts this includes the files the manifest
+    /// refers to, which can be shared with other artifacts.
+    pub size: u64,
+    /// The last time the artifact was written or restored
+    pub last_used: SystemTime,
+    // Archives come before the metadata file, so removing the files in order
+    // never leaves an archive without its metadata
+    paths: Vec<AbsoluteSystemPathBuf>,
+    // The blobs the artifact's manifest refers to, with their sizes
+    blobs: Vec<(AbsoluteSystemPathBuf, u64)>,
+}
+
+impl FSCacheArtifact {
+    fn blob_size(&self) -> u64 {
+        self.blobs.iter().map(|(_, size)| size).sum()
+    }
+
+    fn has_metadata(&self) -> bool {
+        self.paths
+            .last()
+            .map_or(false, |path| path.as_str().ends_with(METADATA_SUFFIX))
+    }
+
+    fn remove(&self) -> Result<(), CacheError> {
+        for path in &self.paths {
+            match path.remove_file() {
+                Ok(()) => {}
+                // Another process might have evicted it first
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl FSCache {
     fn resolve_cache_dir(
         repo_root: &AbsoluteSystemPath,
@@ -50,11 +109,96 @@ impl FSCache {
         cache_directory.create_dir_all()?;
 
         Ok(FSCache {
+            content_store: ContentStore::new(cache_directory.join_component("blobs")),
             cache_directory,
             analytics_recorder,
+            layout: CacheLayout::default(),
         })
     }
 
+    pub fn with_layout(mut self, layout: CacheLayout) -> Self {
+        self.layout = layout;
+        self.content_store = self
+            .content_store
+            .with_hardlinks(layout == CacheLayout::Hardlinked);
+        self
+    }
+
+    fn manifest_path(&self, hash: &str) -> AbsoluteSystemPathBuf {
+        self.cache_directory
+            .join_component(&format!("{}{}", hash, MANIFEST_SUFFIX))
+    }
+
+    // Finds the archive of an artifact stored in the archive layout
+    fn archive_path(&self, hash: &str) -> Option<AbsoluteSystemPathBuf> {
+        [".tar", ".tar.zst"]
+            .iter()
+            .map(|suffix| {
+                self.cache_directory
+                    .join_component(&format!("{}{}", hash, suffix))
+            })
+            .find(|path| path.exists())
+    }
+
+    fn read_manifest(&self, hash: &str) -> Result<Manifest, CacheError> {
+        serde_json::from_str(&self.manifest_path(hash).read_to_string()?)
+            .map_err(|e| CacheError::InvalidMetadata(e, Backtrace::capture()))
+    }
+
+    // The manifest is renamed into place so that it's never read before all of
+    // its blobs are written
+    fn write_manifest(&self, hash: &str, manifest: &Manifest) -> Result<(), CacheError> {
+        let file = tempfile::NamedTempFile::new_in(&self.cache_directory)?;
+        serde_json::to_writer(file.as_file(), manifest)
+            .map_err(|e| CacheError::MetadataWriteFailure(e, Backtrace::capture()))?;
+        file.persist(self.manifest_path(hash))
+            .map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    // Restores an artifact in either layout. Archives are converted to
+    // manifests the first time they're restored with the content-addressed
+    // layout enabled.
+    fn restore(
+        &self,
+        anchor: &AbsoluteSystemPath,
+        hash: &str,
+    ) -> Result<Option<Vec<AnchoredSystemPathBuf>>, CacheError> {
+        if self.manifest_path(hash).exists() {
+            let manifest = self.read_manifest(hash)?;
+            return self.content_store.restore(&manifest, anchor).map(Some);
+        }
+
+        let Some(archive_path) = self.archive_path(hash) else {
+            return Ok(None);
+        };
+        let mut cache_reader = CacheReader::open(&archive_path)?;
+        match self.layout {
+            CacheLayout::Archive => cache_reader.restore(anchor).map(Some),
+            CacheLayout::ContentAddressed | CacheLayout::Hardlinked => {
+                let manifest = cache_reader.import(&self.content_store)?;
+                self.write_manifest(hash, &manifest)?;
+                archive_path.remove_file()?;
+                self.content_store.restore(&manifest, anchor).map(Some)
+            }
+        }
+    }
+
+    // Marks an artifact as used by bumping the access time of its metadata file.
+    // We do this by hand since many filesystems are mounted with `noatime` or
+    // `relatime`. This is only used for eviction, so errors are ignored.
+    fn touch(&self, hash: &str) {
+        let metadata_path = self
+            .cache_directory
+            .join_component(&format!("{}{}", hash, METADATA_SUFFIX));
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if let Ok(file) = metadata_path.open_with_options(options) {
+            let _ = file.set_times(FileTimes::new().set_accessed(SystemTime::now()));
+        }
+    }
+
     fn log_fetch(&self, event: analytics::CacheEvent, hash: &str, duration: u64) {
         // If analytics fails to record, it's not worth failing the cache
         if let Some(analytics_recorder) = &self.analytics_recorder {
@@ -75,26 +219,23 @@ impl FSCache {
         anchor: &AbsoluteSystemPath,
         hash: &str,
     ) -> Result<Option<(CacheHitMetadata, Vec<AnchoredSystemPathBuf>)>, CacheError> {
-        let uncompressed_cache_path = self
-            .cache_directory
-            .join_component(&format!("{}.tar", hash));
-        let compressed_cache_path = self
-            .cache_directory
-            .join_component(&format!("{}.tar.zst", hash));
-
-        let cache_path = if uncompressed_cache_path.exists() {
-            uncompressed_cache_path
-        } else if compressed_cache_path.exists() {
-            compressed_cache_path
-        } else {
-            self.log_fetch(analytics::CacheEvent::Miss, hash, 0);
-            return Ok(None);
+        let restored_files = match self.restore(anchor, hash) {
+            Ok(Some(restored_files)) => restored_files,
+            Ok(None) => {
+                self.log_fetch(analytics::CacheEvent::Miss, hash, 0);
+                return Ok(None);
+            }
+            // A blob was removed or written to through a hardlink, so the
+            // artifact can no longer be restored
+            Err(CacheError::InvalidBlob(blob, _)) => {
+                debug!("removing artifact {} with invalid blob {}", hash, blob);
+                self.remove(hash)?;
+                self.log_fetch(analytics::CacheEvent::Miss, hash, 0);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
         };
 
-        let mut cache_reader = CacheReader::open(&cache_path)?;
-
-        let restored_files = cache_reader.restore(anchor)?;
-
         let meta = CacheMetadata::read(
             &self
                 .cache_directory
@@ -102,6 +243,7 @@ impl FSCache {
         )?;
 
         self.log_fetch(analytics::CacheEvent::Hit, hash, meta.duration);
+        self.touch(hash);
 
         Ok(Some((
             CacheHitMetadata {
@@ -113,14 +255,7 @@ impl FSCache {
     }
 
     pub(crate) fn exists(&self, hash: &str) -> Result<Option<CacheHitMetadata>, CacheError> {
-        let uncompressed_cache_path = self
-            .cache_directory
-            .join_component(&format!("{}.tar", hash));
-        let compressed_cache_path = self
-            .cache_directory
-            .join_component(&format!("{}.tar.zst", hash));
-
-        if !uncompressed_cache_path.exists() && !compressed_cache_path.exists() {
+        if !self.manifest_path(hash).exists() && self.archive_path(hash).is_none() {
             return Ok(None);
         }
 
@@ -132,6 +267,8 @@ impl FSCache {
         .map(|meta| meta.duration)
         .unwrap_or(0);
 
+        self.touch(hash);
+
         Ok(Some(CacheHitMetadata {
             time_saved: duration,
             source: CacheSource::Local,
@@ -145,14 +282,25 @@ impl FSCache {
         files: &[AnchoredSystemPathBuf],
         duration: u64,
     ) -> Result<(), CacheError> {
-        let cache_path = self
-            .cache_directory
-            .join_component(&format!("{}.tar.zst", hash));
+        match self.layout {
+            CacheLayout::Archive => {
+                let cache_path = self
+                    .cache_directory
+                    .join_component(&format!("{}.tar.zst", hash));
 
-        let mut cache_item = CacheWriter::create(&cache_path)?;
+                let mut cache_item = CacheWriter::create(&cache_path)?;
 
-        for file in files {
-            cache_item.add_file(anchor, file)?;
+                for file in files {
+                    cache_item.add_file(anchor, file)?;
+                }
+            }
+            CacheLayout::ContentAddressed | CacheLayout::Hardlinked => {
+                let entries = files
+                    .iter()
+                    .map(|file| self.content_store.add_file(anchor, file))
+                    .collect::<Result<_, _>>()?;
+                self.write_manifest(hash, &Manifest { entries })?;
+            }
         }
 
         let metadata_path = self
@@ -174,13 +322,230 @@ impl FSCache {
 
         Ok(())
     }
+
+    /// Lists all artifacts in the cache directory
+    pub fn artifacts(&self) -> Result<Vec<FSCacheArtifact>, CacheError> {
+        let mut artifacts: HashMap<String, FSCacheArtifact> = HashMap::new();
+
+        for entry in self.cache_directory.as_std_path().read_dir()? {
+            let entry = entry?;
+            let Some(file_name) = entry.file_name().to_str().map(|name| name.to_owned()) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let (hash, suffix) = if let Some(hash) = file_name.strip_suffix(METADATA_SUFFIX) {
+                (hash, METADATA_SUFFIX)
+            } else if let Some((hash, suffix)) = ARCHIVE_SUFFIXES
+                .iter()
+                .find_map(|suffix| file_name.strip_suffix(suffix).map(|hash| (hash, *suffix)))
+            {
+                (hash, suffix)
+            } else {
+                continue;
+            };
+            let is_metadata = suffix == METADATA_SUFFIX;
+
+            // The metadata file is the source of truth for when the artifact was
+            // last used, but fall back to the archive in case it's missing.
+            let last_used = if is_metadata {
+                metadata.accessed()
+            } else {
+                metadata.modified()
+            }?;
+            let path = self.cache_directory.join_component(&file_name);
+
+            let artifact = artifacts
+                .entry(hash.to_owned())
+                .or_insert_with(|| FSCacheArtifact {
+                    hash: hash.to_owned(),
+                    size: 0,
+                    last_used,
+                    paths: Vec::new(),
+                    blobs: Vec::new(),
+                });
+            artifact.size += metadata.len();
+            if suffix == MANIFEST_SUFFIX {
+                // An unreadable manifest is still listed so that it can be
+                // removed
+                if let Ok(manifest) = self.read_manifest(hash) {
+                    let mut blobs: Vec<_> = manifest.blobs(&self.content_store).collect();
+                    // Identical files in the same artifact share a blob
+                    blobs.sort();
+                    blobs.dedup();
+                    artifact.size += blobs.iter().map(|(_, size)| size).sum::<u64>();
+                    artifact.blobs = blobs;
+                }
+            }
+            if is_metadata {
+                artifact.last_used = last_used;
+                artifact.paths.push(path);
+            } else {
+                if !artifact.has_metadata() {
+                    artifact.last_used = artifact.last_used.max(last_used);
+                }
+                artifact.paths.insert(0, path);
+            }
+        }
+
+        Ok(artifacts.into_values().collect())
+    }
+
+    /// Reads the metadata of an artifact
+    pub fn metadata(&self, hash: &str) -> Result<CacheMetadata, CacheError> {
+        CacheMetadata::read(
+            &self
+                .cache_directory
+                .join_component(&format!("{}{}", hash, METADATA_SUFFIX)),
+        )
+    }
+
+    /// Opens the archive of an artifact without restoring it, if the artifact
+    /// exists. Content-addressed artifacts are converted to an archive.
+    pub fn open(&self, hash: &str) -> Result<Option<CacheReader<'static>>, CacheError> {
+        if !self.manifest_path(hash).exists() {
+            return self
+                .archive_path(hash)
+                .map(|path| CacheReader::open(&path))
+                .transpose();
+        }
+
+        let manifest = self.read_manifest(hash)?;
+        let mut archive = tempfile::tempfile()?;
+        let mut cache_writer = CacheWriter::from_writer(&mut archive, false)?;
+        for entry in &manifest.entries {
+            cache_writer.add_manifest_entry(&self.content_store, entry)?;
+        }
+        cache_writer.finish()?;
+        archive.rewind()?;
+
+        CacheReader::from_reader(archive, false).map(Some)
+    }
+
+    // Removes blobs that no artifact refers to anymore
+    fn remove_unused_blobs(&self) -> Result<(), CacheError> {
+        let used_blobs: HashSet<_> = self
+            .artifacts()?
+            .into_iter()
+            .flat_map(|artifact| artifact.blobs)
+            .map(|(path, _)| path)
+            .collect();
+        for (path, _) in self.content_store.blobs()? {
+            if !used_blobs.contains(&path) {
+                remove_blob(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes an artifact, returning it if it existed
+    pub fn remove(&self, hash: &str) -> Result<Option<FSCacheArtifact>, CacheError> {
+        let artifact = self
+            .artifacts()?
+            .into_iter()
+            .find(|artifact| artifact.hash == hash);
+        if let Some(artifact) = &artifact {
+            artifact.remove()?;
+            self.remove_unused_blobs()?;
+        }
+
+        Ok(artifact)
+    }
+
+    /// Removes every artifact, returning the removed artifacts
+    pub fn clear(&self) -> Result<Vec<FSCacheArtifact>, CacheError> {
+        let artifacts = self.artifacts()?;
+        for artifact in &artifacts {
+            artifact.remove()?;
+        }
+        self.remove_unused_blobs()?;
+
+        Ok(artifacts)
+    }
+
+    /// Removes the least recently used artifacts until the cache is within
+    /// `limits`, returning the artifacts that were removed.
+    pub fn evict(&self, limits: &CacheLimits) -> Result<Vec<FSCacheArtifact>, CacheError> {
+        if limits.is_unlimited() {
+            return Ok(Vec::new());
+        }
+
+        let mut artifacts = self.artifacts()?;
+        // Most recently used first, so we can pop artifacts off the end
+        artifacts.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+
+        // Blobs are shared between artifacts, so they're only counted once and
+        // removed along with the last artifact that refers to them
+        let mut blobs: HashMap<AbsoluteSystemPathBuf, (u64, usize)> = HashMap::new();
+        for (path, size) in artifacts.iter().flat_map(|artifact| &artifact.blobs) {
+            blobs.entry(path.clone()).or_insert((*size, 0)).1 += 1;
+        }
+
+        let now = SystemTime::now();
+        let mut total_size: u64 = artifacts
+            .iter()
+            .map(|artifact| artifact.size - artifact.blob_size())
+            .sum::<u64>()
+            + blobs.values().map(|(size, _)| size).sum::<u64>();
+        let mut evicted = Vec::new();
+
+        while let Some(artifact) = artifacts.last() {
+            let is_expired = limits.max_age.map_or(false, |max_age| {
+                now.duration_since(artifact.last_used)
+                    .map_or(false, |age| age > max_age)
+            });
+            let has_too_many = limits
+                .max_entries
+                .map_or(false, |max_entries| artifacts.len() > max_entries);
+            let is_too_large = limits
+                .max_size
+                .map_or(false, |max_size| total_size > max_size);
+
+            if !is_expired && !has_too_many && !is_too_large {
+                break;
+            }
+
+            let artifact = artifacts.pop().expect("artifacts is not empty");
+            artifact.remove()?;
+            total_size -= artifact.size - artifact.blob_size();
+            for (path, _) in &artifact.blobs {
+                let Some((size, references)) = blobs.get_mut(path) else {
+                    continue;
+                };
+                *references -= 1;
+                if *references == 0 {
+                    remove_blob(path)?;
+                    total_size -= *size;
+                }
+            }
+            evicted.push(artifact);
+        }
+
+        Ok(evicted)
+    }
+}
+
+fn remove_blob(path: &AbsoluteSystemPath) -> Result<(), CacheError> {
+    match path.remove_file() {
+        Ok(()) => Ok(()),
+        // Another process might have removed it first
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::{io::Write, time::Duration};
+
     use anyhow::Result;
     use futures::future::try_join_all;
     use tempfile::tempdir;
+    use test_case::test_case;
     use turbopath::AnchoredSystemPath;
     use turborepo_analytics::start_analytics;
     use turborepo_api_client::{APIAuth, APIClient};
@@ -258,4 +623,188 @@ mod test {
         analytics_handle.close_with_timeout().await;
         Ok(())
     }
+
+    // Writes an artifact for each hash, each one used an hour after the previous
+    fn eviction_test_cache(repo_root: &AbsoluteSystemPath, hashes: &[&str]) -> Result<FSCache> {
+        let cache = FSCache::new(None, repo_root, None)?;
+        let file = AnchoredSystemPathBuf::from_raw("out.txt")?;
+        repo_root.resolve(&file).create_with_contents("output")?;
+
+        let now = SystemTime::now();
+        for (i, hash) in hashes.iter().enumerate() {
+            cache.put(repo_root, hash, &[file.clone()], 0)?;
+
+            let hours_ago = (hashes.len() - i) as u64;
+            let mut options = OpenOptions::new();
+            options.write(true);
+            cache
+                .cache_directory
+                .join_component(&format!("{}-meta.json", hash))
+                .open_with_options(options)?
+                .set_times(
+                    FileTimes::new().set_accessed(now - Duration::from_secs(hours_ago * 3600)),
+                )?;
+        }
+
+        Ok(cache)
+    }
+
+    #[test_case(CacheLimits::default(), &[] ; "unlimited")]
+    #[test_case(
+        CacheLimits { max_entries: Some(1), ..Default::default() },
+        &["a", "b"] ;
+        "max entries"
+    )]
+    #[test_case(
+        CacheLimits { max_age: Some(Duration::from_secs(90 * 60)), ..Default::default() },
+        &["a", "b"] ;
+        "max age"
+    )]
+    #[test_case(
+        CacheLimits { max_age: Some(Duration::from_secs(150 * 60)), max_entries: Some(1), ..Default::default() },
+        &["a", "b"] ;
+        "combined limits"
+    )]
+    fn test_evict(limits: CacheLimits, expected: &[&str]) -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        let cache = eviction_test_cache(repo_root_path, &["a", "b", "c"])?;
+
+        let evicted = cache.evict(&limits)?;
+        let evicted: Vec<_> = evicted
+            .iter()
+            .map(|artifact| artifact.hash.as_str())
+            .collect();
+        assert_eq!(evicted, expected);
+
+        let remaining: Vec<_> = cache
+            .artifacts()?
+            .into_iter()
+            .map(|artifact| artifact.hash)
+            .collect();
+        assert_eq!(remaining.len(), 3 - expected.len());
+        assert!(remaining
+            .iter()
+            .all(|hash| !expected.contains(&hash.as_str())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        let cache = eviction_test_cache(repo_root_path, &["a", "b", "c"])?;
+
+        // Restoring the oldest artifact should make it the most recently used
+        cache.fetch(repo_root_path, "a")?.unwrap();
+
+        let artifact_size = cache.artifacts()?[0].size;
+        let evicted = cache.evict(&CacheLimits {
+            max_size: Some(artifact_size * 2),
+            ..Default::default()
+        })?;
+        let evicted: Vec<_> = evicted
+            .iter()
+            .map(|artifact| artifact.hash.as_str())
+            .collect();
+        assert_eq!(evicted, &["b"]);
+        assert!(cache.exists("a")?.is_some());
+        assert!(cache.exists("b")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_addressed_layout() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        let file = AnchoredSystemPathBuf::from_raw("out.txt")?;
+        repo_root_path
+            .resolve(&file)
+            .create_with_contents("output")?;
+
+        // Artifacts written with the archive layout are converted when restored
+        FSCache::new(None, repo_root_path, None)?.put(repo_root_path, "a", &[file.clone()], 0)?;
+        let cache =
+            FSCache::new(None, repo_root_path, None)?.with_layout(CacheLayout::ContentAddressed);
+        cache.put(repo_root_path, "b", &[file.clone()], 0)?;
+        let (_, restored) = cache.fetch(repo_root_path, "a")?.unwrap();
+        assert_eq!(restored, &[file.clone()]);
+        assert!(cache.archive_path("a").is_none());
+
+        // Both artifacts share the blob of the identical file
+        assert_eq!(cache.content_store.blobs()?.len(), 1);
+        let entries = cache.open("b")?.unwrap().entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, file);
+
+        // The blob is only removed along with the last artifact using it
+        cache.remove("a")?;
+        assert_eq!(cache.content_store.blobs()?.len(), 1);
+        let evicted = cache.evict(&CacheLimits {
+            max_entries: Some(0),
+            ..Default::default()
+        })?;
+        assert_eq!(evicted.len(), 1);
+        assert!(cache.content_store.blobs()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restored_outputs_are_copies() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        let file = AnchoredSystemPathBuf::from_raw("out.txt")?;
+        repo_root_path
+            .resolve(&file)
+            .create_with_contents("output")?;
+
+        // Both artifacts share the blob of the identical file
+        let cache =
+            FSCache::new(None, repo_root_path, None)?.with_layout(CacheLayout::ContentAddressed);
+        cache.put(repo_root_path, "a", &[file.clone()], 0)?;
+        cache.put(repo_root_path, "b", &[file.clone()], 0)?;
+        let restore_a = repo_root_path.join_component("a");
+        let restore_b = repo_root_path.join_component("b");
+        cache.fetch(&restore_a, "a")?.unwrap();
+        cache.fetch(&restore_b, "b")?.unwrap();
+
+        // A task writing to its output in place doesn't change other outputs
+        // or the cache
+        OpenOptions::new()
+            .append(true)
+            .open(restore_a.resolve(&file))?
+            .write_all(b" changed")?;
+        assert_eq!(restore_b.resolve(&file).read_to_string()?, "output");
+
+        let restore_c = repo_root_path.join_component("c");
+        assert!(cache.fetch(&restore_c, "b")?.is_some());
+        assert_eq!(restore_c.resolve(&file).read_to_string()?, "output");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_blob_is_a_miss() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        let file = AnchoredSystemPathBuf::from_raw("out.txt")?;
+        let file_path = repo_root_path.resolve(&file);
+        file_path.create_with_contents("output")?;
+
+        let cache = FSCache::new(None, repo_root_path, None)?.with_layout(CacheLayout::Hardlinked);
+        cache.put(repo_root_path, "a", &[file.clone()], 0)?;
+        cache.fetch(repo_root_path, "a")?.unwrap();
+
+        // Writing to a hardlinked output in place also changes the blob
+        let (blob_path, _) = cache.content_store.blobs()?.pop().unwrap();
+        blob_path.create_with_contents("changed")?;
+
+        assert!(cache.fetch(repo_root_path, "a")?.is_none());
+        assert!(cache.exists("a")?.is_none());
+
+        Ok(())
+    }
 }
@@ -1,24 +1,50 @@
-use std::{backtrace::Backtrace, io::Write};
+use std::{
+    backtrace::Backtrace,
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, Write},
+    sync::Mutex,
+};
 
+use tokio::{io::AsyncWriteExt, task::spawn_blocking};
 use tracing::debug;
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 use turborepo_analytics::AnalyticsSender;
 use turborepo_api_client::{
-    analytics, analytics::AnalyticsEvent, APIAuth, APIClient, Client, Response,
+    analytics, analytics::AnalyticsEvent, retry::retry_future, APIAuth, APIClient, Client, Response,
 };
+use turborepo_vercel_api::ArtifactQueryResult;
 
 use crate::{
     cache_archive::{CacheReader, CacheWriter},
-    signature_authentication::ArtifactSignatureAuthenticator,
+    signature_authentication::{
+        ArtifactSignatureAuthenticator, ArtifactTag, SignatureAlgorithm, SignatureError,
+    },
+    stream::{body_channel, BodyReader, TagWriter},
     CacheError, CacheHitMetadata, CacheOpts, CacheSource,
 };
 
+/// The result of checking the signature of a remote artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactVerification {
+    /// The artifact's tag matches its contents
+    Valid,
+    /// The artifact's tag doesn't match its contents
+    Invalid,
+    /// The artifact wasn't signed when it was uploaded
+    MissingTag,
+    /// The artifact was signed with a key that isn't trusted
+    UnknownKey,
+}
+
 pub struct HTTPCache {
     client: APIClient,
     signer_verifier: Option<ArtifactSignatureAuthenticator>,
     repo_root: AbsoluteSystemPathBuf,
     api_auth: APIAuth,
     analytics_recorder: Option<AnalyticsSender>,
+    // Results of `prefetch_exists`, keyed by hash. A `None` is a known miss.
+    prefetched: Mutex<HashMap<String, Option<CacheHitMetadata>>>,
 }
 
 impl HTTPCache {
@@ -29,23 +55,26 @@ impl HTTPCache {
         api_auth: APIAuth,
         analytics_recorder: Option<AnalyticsSender>,
     ) -> HTTPCache {
-        let signer_verifier = if opts
+        let signer_verifier = opts
             .remote_cache_opts
             .as_ref()
-            .map_or(false, |remote_cache_opts| remote_cache_opts.signature)
-        {
-            Some(ArtifactSignatureAuthenticator {
-                team_id: api_auth
-                    .team_id
-                    .as_deref()
-                    .unwrap_or_default()
-                    .as_bytes()
-                    .to_vec(),
-                secret_key_override: None,
-            })
-        } else {
-            None
-        };
+            .filter(|remote_cache_opts| remote_cache_opts.signature)
+            .map(|remote_cache_opts| {
+                let signer_verifier = ArtifactSignatureAuthenticator::new(
+                    api_auth
+                        .team_id
+                        .as_deref()
+                        .unwrap_or_default()
+                        .as_bytes()
+                        .to_vec(),
+                    None,
+                );
+                match remote_cache_opts.signature_algorithm {
+                    SignatureAlgorithm::HmacSha256 => signer_verifier,
+                    SignatureAlgorithm::Ed25519 => signer_verifier
+                        .with_public_keys(remote_cache_opts.signature_public_keys.clone()),
+                }
+            });
 
         HTTPCache {
             client,
@@ -53,9 +82,26 @@ impl HTTPCache {
             repo_root,
             api_auth,
             analytics_recorder,
+            prefetched: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Whether artifacts can be downloaded but not uploaded, because they're
+    /// signed with Ed25519 and there's no private key to sign them with
+    pub fn is_verify_only(&self) -> bool {
+        self.signer_verifier
+            .as_ref()
+            .map_or(false, |signer_verifier| signer_verifier.is_verify_only())
+    }
+
+    fn prefetched(&self, hash: &str) -> Option<Option<CacheHitMetadata>> {
+        self.prefetched
+            .lock()
+            .expect("prefetched lock poisoned")
+            .get(hash)
+            .copied()
+    }
+
     pub async fn put(
         &self,
         anchor: &AbsoluteSystemPath,
@@ -63,32 +109,75 @@ impl HTTPCache {
         files: &[AnchoredSystemPathBuf],
         duration: u64,
     ) -> Result<(), CacheError> {
-        let mut artifact_body = Vec::new();
-        self.write(&mut artifact_body, anchor, files).await?;
-
+        let anchor = anchor.to_owned();
+        let files = files.to_vec();
         let tag = self
             .signer_verifier
             .as_ref()
-            .map(|signer| signer.generate_tag(hash.as_bytes(), &artifact_body))
+            .map(|signer| signer.tag(hash.as_bytes()))
             .transpose()?;
 
-        self.client
-            .put_artifact(
-                hash,
-                &artifact_body,
-                duration,
-                tag.as_deref(),
-                &self.api_auth.token,
-                self.api_auth.team_id.as_deref(),
-                self.api_auth.team_slug.as_deref(),
-            )
-            .await?;
+        // The archive is written to a temporary file first, so a signed
+        // artifact's tag is known before the body is sent and a failed upload
+        // can be retried with the same archive
+        let (file, tag) =
+            spawn_blocking(move || Self::write_to_file(tag, &anchor, &files)).await??;
+        retry_future(
+            || self.upload(hash, &file, duration, tag.as_deref()),
+            |err: &CacheError| matches!(err, CacheError::ApiClientError(err, _) if err.is_retryable()),
+        )
+        .await?;
+
+        self.prefetched
+            .lock()
+            .expect("prefetched lock poisoned")
+            .insert(
+                hash.to_string(),
+                Some(CacheHitMetadata {
+                    source: CacheSource::Remote,
+                    time_saved: duration,
+                }),
+            );
 
         Ok(())
     }
 
-    async fn write(
+    async fn upload(
         &self,
+        hash: &str,
+        file: &File,
+        duration: u64,
+        tag: Option<&str>,
+    ) -> Result<(), CacheError> {
+        let mut file = file.try_clone()?;
+        file.rewind()?;
+        let (mut writer, body) = body_channel();
+
+        // The archive is copied into the body on a blocking thread while the
+        // request is sent, so only a few chunks of it are in memory at a time
+        let archive = spawn_blocking(move || {
+            io::copy(&mut file, &mut writer)?;
+            Ok::<_, CacheError>(writer.finish()?)
+        });
+        let upload = self.client.put_artifact(
+            hash,
+            body,
+            duration,
+            tag,
+            &self.api_auth.token,
+            self.api_auth.team_id.as_deref(),
+            self.api_auth.team_slug.as_deref(),
+        );
+        let (upload, archive) = tokio::join!(upload, archive);
+        // A failed upload stops the copy, so the upload error is the one that
+        // explains what happened
+        upload?;
+        archive??;
+
+        Ok(())
+    }
+
+    fn write(
         writer: impl Write,
         anchor: &AbsoluteSystemPath,
         files: &[AnchoredSystemPathBuf],
@@ -98,10 +187,58 @@ impl HTTPCache {
             cache_archive.add_file(anchor, file)?;
         }
 
-        Ok(())
+        cache_archive.finish()
+    }
+
+    // Returns the archive along with its tag if it's signed. The tag is sent
+    // in a header before the body, so it's computed while writing the archive.
+    fn write_to_file(
+        tag: Option<ArtifactTag>,
+        anchor: &AbsoluteSystemPath,
+        files: &[AnchoredSystemPathBuf],
+    ) -> Result<(File, Option<String>), CacheError> {
+        let file = tempfile::tempfile()?;
+        match tag {
+            Some(tag) => {
+                let mut writer = TagWriter::new(file, tag);
+                Self::write(&mut writer, anchor, files)?;
+                let (file, tag) = writer.into_parts();
+                Ok((file, Some(tag.finish()?)))
+            }
+            None => {
+                Self::write(&file, anchor, files)?;
+                Ok((file, None))
+            }
+        }
+    }
+
+    // Downloads a response body to a temporary file, adding it to `tag` as it
+    // arrives
+    async fn download(mut response: Response, tag: &mut ArtifactTag) -> Result<File, CacheError> {
+        let mut file = tokio::fs::File::from_std(tempfile::tempfile()?);
+        while let Some(chunk) = response.chunk().await.map_err(Self::body_error)? {
+            tag.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        let mut file = file.into_std().await;
+        file.rewind()?;
+        Ok(file)
+    }
+
+    fn body_error(error: reqwest::Error) -> CacheError {
+        CacheError::ApiClientError(
+            Box::new(turborepo_api_client::Error::ReqwestError(error)),
+            Backtrace::capture(),
+        )
     }
 
     pub async fn exists(&self, hash: &str) -> Result<Option<CacheHitMetadata>, CacheError> {
+        if let Some(cache_hit) = self.prefetched(hash) {
+            return Ok(cache_hit);
+        }
+
         let Some(response) = self
             .client
             .artifact_exists(
@@ -123,6 +260,58 @@ impl HTTPCache {
         }))
     }
 
+    /// Checks whether each of `hashes` exists with a single request. Hashes
+    /// the server couldn't answer for are left out of the result.
+    pub async fn exists_batch(
+        &self,
+        hashes: &[String],
+    ) -> Result<HashMap<String, Option<CacheHitMetadata>>, CacheError> {
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let response = self
+            .client
+            .query_artifacts(
+                hashes,
+                &self.api_auth.token,
+                self.api_auth.team_id.as_deref(),
+                self.api_auth.team_slug.as_deref(),
+            )
+            .await?;
+
+        Ok(response
+            .into_iter()
+            .filter_map(|(hash, result)| match result {
+                Some(ArtifactQueryResult::Found(info)) => {
+                    let cache_hit = CacheHitMetadata {
+                        source: CacheSource::Remote,
+                        time_saved: info.task_duration_ms,
+                    };
+                    Some((hash, Some(cache_hit)))
+                }
+                Some(ArtifactQueryResult::Error { error }) => {
+                    debug!("failed to query artifact {}: {}", hash, error.message);
+                    None
+                }
+                None => Some((hash, None)),
+            })
+            .collect())
+    }
+
+    /// Queries the existence of all `hashes` up front, so that later calls to
+    /// `exists` and `fetch` can skip the request for artifacts that are known
+    /// to be missing.
+    pub async fn prefetch_exists(&self, hashes: &[String]) -> Result<(), CacheError> {
+        let results = self.exists_batch(hashes).await?;
+        self.prefetched
+            .lock()
+            .expect("prefetched lock poisoned")
+            .extend(results);
+
+        Ok(())
+    }
+
     fn get_duration_from_response(response: &Response) -> Result<u64, CacheError> {
         if let Some(duration_value) = response.headers().get("x-artifact-duration") {
             let duration = duration_value
@@ -156,6 +345,14 @@ impl HTTPCache {
         &self,
         hash: &str,
     ) -> Result<Option<(CacheHitMetadata, Vec<AnchoredSystemPathBuf>)>, CacheError> {
+        // An artifact that was missing when we prefetched could have been
+        // uploaded by another machine since, but we accept missing that hit in
+        // exchange for not making a request for every task.
+        if let Some(None) = self.prefetched(hash) {
+            self.log_fetch(analytics::CacheEvent::Miss, hash, 0);
+            return Ok(None);
+        }
+
         let Some(response) = self
             .client
             .fetch_artifact(
@@ -171,8 +368,9 @@ impl HTTPCache {
         };
 
         let duration = Self::get_duration_from_response(&response)?;
+        let repo_root = self.repo_root.clone();
 
-        let body = if let Some(signer_verifier) = &self.signer_verifier {
+        let files = if let Some(signer_verifier) = &self.signer_verifier {
             let expected_tag = response
                 .headers()
                 .get("x-artifact-tag")
@@ -183,30 +381,32 @@ impl HTTPCache {
                 .map_err(|_| CacheError::InvalidTag(Backtrace::capture()))?
                 .to_string();
 
-            let body = response.bytes().await.map_err(|e| {
-                CacheError::ApiClientError(
-                    Box::new(turborepo_api_client::Error::ReqwestError(e)),
-                    Backtrace::capture(),
-                )
-            })?;
-            let is_valid = signer_verifier.validate(hash.as_bytes(), &body, &expected_tag)?;
-
-            if !is_valid {
-                return Err(CacheError::InvalidTag(Backtrace::capture()));
+            // Nothing is restored until the tag is checked, so the artifact is
+            // downloaded to a temporary file first
+            let mut tag = signer_verifier.tag(hash.as_bytes())?;
+            let file = Self::download(response, &mut tag).await?;
+            match tag.verify(&expected_tag) {
+                Ok(true) => {}
+                Ok(false)
+                | Err(SignatureError::MalformedTag | SignatureError::Base64EncodingError(_)) => {
+                    return Err(CacheError::InvalidTag(Backtrace::capture()));
+                }
+                Err(SignatureError::UnknownKey(key_id)) => {
+                    return Err(CacheError::UnknownSignatureKey(
+                        key_id,
+                        Backtrace::capture(),
+                    ));
+                }
+                Err(e) => return Err(e.into()),
             }
 
-            body
+            spawn_blocking(move || Self::restore_tar(&repo_root, file)).await??
         } else {
-            response.bytes().await.map_err(|e| {
-                CacheError::ApiClientError(
-                    Box::new(turborepo_api_client::Error::ReqwestError(e)),
-                    Backtrace::capture(),
-                )
-            })?
+            // Restores the artifact while it's still being downloaded
+            let body = BodyReader::new(response);
+            spawn_blocking(move || Self::restore_tar(&repo_root, body)).await??
         };
 
-        let files = Self::restore_tar(&self.repo_root, &body)?;
-
         self.log_fetch(analytics::CacheEvent::Hit, hash, duration);
         Ok(Some((
             CacheHitMetadata {
@@ -217,9 +417,56 @@ impl HTTPCache {
         )))
     }
 
+    /// Downloads an artifact and checks its tag against its contents, without
+    /// restoring it. Returns `None` if the artifact doesn't exist.
+    pub async fn verify(&self, hash: &str) -> Result<Option<ArtifactVerification>, CacheError> {
+        let Some(signer_verifier) = &self.signer_verifier else {
+            return Err(CacheError::SignatureVerificationDisabled(
+                Backtrace::capture(),
+            ));
+        };
+
+        let Some(mut response) = self
+            .client
+            .fetch_artifact(
+                hash,
+                &self.api_auth.token,
+                self.api_auth.team_id.as_deref(),
+                self.api_auth.team_slug.as_deref(),
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let Some(expected_tag) = response.headers().get("x-artifact-tag") else {
+            return Ok(Some(ArtifactVerification::MissingTag));
+        };
+        let expected_tag = expected_tag
+            .to_str()
+            .map_err(|_| CacheError::InvalidTag(Backtrace::capture()))?
+            .to_string();
+
+        let mut tag = signer_verifier.tag(hash.as_bytes())?;
+        while let Some(chunk) = response.chunk().await.map_err(Self::body_error)? {
+            tag.update(&chunk);
+        }
+
+        match tag.verify(&expected_tag) {
+            Ok(true) => Ok(Some(ArtifactVerification::Valid)),
+            // A tag that isn't valid base64 can't match either
+            Ok(false)
+            | Err(SignatureError::MalformedTag | SignatureError::Base64EncodingError(_)) => {
+                Ok(Some(ArtifactVerification::Invalid))
+            }
+            Err(SignatureError::UnknownKey(_)) => Ok(Some(ArtifactVerification::UnknownKey)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub(crate) fn restore_tar(
         root: &AbsoluteSystemPath,
-        body: &[u8],
+        body: impl Read,
     ) -> Result<Vec<AnchoredSystemPathBuf>, CacheError> {
         let mut cache_reader = CacheReader::from_reader(body, true)?;
         cache_reader.restore(root)
@@ -228,18 +475,21 @@ impl HTTPCache {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use anyhow::Result;
     use futures::future::try_join_all;
     use tempfile::tempdir;
-    use turbopath::AbsoluteSystemPathBuf;
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
     use turborepo_analytics::start_analytics;
     use turborepo_api_client::{analytics, APIClient};
     use turborepo_vercel_api_mock::start_test_server;
 
     use crate::{
-        http::{APIAuth, HTTPCache},
+        http::{APIAuth, ArtifactVerification, HTTPCache},
+        signature_authentication::ArtifactSignatureAuthenticator,
         test_cases::{get_test_cases, validate_analytics, TestCase},
-        CacheOpts, CacheSource,
+        CacheHitMetadata, CacheOpts, CacheSource, RemoteCacheOpts,
     };
 
     #[tokio::test]
@@ -319,4 +569,188 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_signed_large_artifact() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+        // Incompressible contents, so the artifact spans many chunks
+        let mut state = 0x2545_f491_u32;
+        let contents = (0..4 * 1024 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        let output = AnchoredSystemPathBuf::from_raw("large.bin")?;
+        repo_root_path
+            .resolve(&output)
+            .create_with_contents(&contents)?;
+
+        let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
+        let api_auth = APIAuth {
+            team_id: Some("my-team".to_string()),
+            token: "my-token".to_string(),
+            team_slug: None,
+        };
+        let mut cache = HTTPCache::new(
+            api_client,
+            &CacheOpts::default(),
+            repo_root_path.to_owned(),
+            api_auth,
+            None,
+        );
+        cache.signer_verifier = Some(ArtifactSignatureAuthenticator::new(
+            b"my-team".to_vec(),
+            Some(b"my-secret-key".to_vec()),
+        ));
+
+        let hash = "signed-large-artifact";
+        cache
+            .put(&repo_root_path, hash, &[output.clone()], 10)
+            .await?;
+        assert_eq!(cache.verify(hash).await?, Some(ArtifactVerification::Valid));
+
+        std::fs::remove_file(repo_root_path.resolve(&output))?;
+        let (_, files) = cache.fetch(hash).await?.unwrap();
+        assert_eq!(files, vec![output.clone()]);
+        assert!(std::fs::read(repo_root_path.resolve(&output))? == contents);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_unsigned_artifact() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+        let test_case = &get_test_cases()[0];
+        test_case.initialize(&repo_root_path)?;
+        let hash = "unsigned-artifact";
+
+        let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
+        let api_auth = APIAuth {
+            team_id: Some("my-team".to_string()),
+            token: "my-token".to_string(),
+            team_slug: None,
+        };
+
+        let unsigned_cache = HTTPCache::new(
+            api_client.clone(),
+            &CacheOpts::default(),
+            repo_root_path.to_owned(),
+            api_auth.clone(),
+            None,
+        );
+        let signed_opts = CacheOpts {
+            remote_cache_opts: Some(RemoteCacheOpts::new("my-team".to_string(), true)),
+            ..CacheOpts::default()
+        };
+        let signed_cache = HTTPCache::new(
+            api_client,
+            &signed_opts,
+            repo_root_path.to_owned(),
+            api_auth,
+            None,
+        );
+
+        // Verification is opt-in
+        assert!(unsigned_cache.verify(hash).await.is_err());
+        assert_eq!(signed_cache.verify(hash).await?, None);
+
+        let files: Vec<_> = test_case
+            .files
+            .iter()
+            .map(|f| f.path().to_owned())
+            .collect();
+        unsigned_cache
+            .put(&repo_root_path, hash, &files, test_case.duration)
+            .await?;
+
+        assert_eq!(
+            signed_cache.verify(hash).await?,
+            Some(ArtifactVerification::MissingTag)
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_exists() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+        let test_case = &get_test_cases()[0];
+        test_case.initialize(&repo_root_path)?;
+        let files: Vec<_> = test_case
+            .files
+            .iter()
+            .map(|f| f.path().to_owned())
+            .collect();
+
+        let api_client = APIClient::new(format!("http://localhost:{}", port), 200, "2.0.0", true)?;
+        let api_auth = APIAuth {
+            team_id: Some("my-team".to_string()),
+            token: "my-token".to_string(),
+            team_slug: None,
+        };
+        let new_cache = || {
+            HTTPCache::new(
+                api_client.clone(),
+                &CacheOpts::default(),
+                repo_root_path.to_owned(),
+                api_auth.clone(),
+                None,
+            )
+        };
+
+        let uploader = new_cache();
+        uploader
+            .put(&repo_root_path, "prefetch-hit", &files, test_case.duration)
+            .await?;
+
+        let cache = new_cache();
+        let hashes = vec!["prefetch-hit".to_string(), "prefetch-miss".to_string()];
+        let expected_hit = CacheHitMetadata {
+            source: CacheSource::Remote,
+            time_saved: test_case.duration,
+        };
+        assert_eq!(
+            cache.exists_batch(&hashes).await?,
+            HashMap::from([
+                ("prefetch-hit".to_string(), Some(expected_hit)),
+                ("prefetch-miss".to_string(), None),
+            ])
+        );
+
+        cache.prefetch_exists(&hashes).await?;
+
+        // Once prefetched, a miss is remembered even if the artifact shows up later
+        uploader
+            .put(&repo_root_path, "prefetch-miss", &files, test_case.duration)
+            .await?;
+        assert_eq!(cache.exists("prefetch-miss").await?, None);
+        assert!(cache.fetch("prefetch-miss").await?.is_none());
+
+        // Our own uploads are remembered as hits
+        cache
+            .put(&repo_root_path, "prefetch-miss", &files, test_case.duration)
+            .await?;
+        assert_eq!(cache.exists("prefetch-miss").await?, Some(expected_hit));
+        assert_eq!(cache.exists("prefetch-hit").await?, Some(expected_hit));
+
+        handle.abort();
+        Ok(())
+    }
 }
@@ -14,19 +14,24 @@ pub mod http;
 /// A wrapper that allows reads and writes from the file system and remote
 /// cache.
 mod multiplexer;
+/// A cache in a directory shared between machines, e.g. an NFS mount
+pub mod shared;
 /// Cache signature authentication lets users provide a private key to sign
 /// their cache payloads.
 pub mod signature_authentication;
+/// Adapters that stream artifacts to and from HTTP bodies
+mod stream;
 #[cfg(test)]
 mod test_cases;
 
-use std::{backtrace, backtrace::Backtrace};
+use std::{backtrace, backtrace::Backtrace, collections::BTreeMap, time::Duration};
 
 pub use async_cache::AsyncCache;
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub use crate::signature_authentication::SignatureAlgorithm;
 use crate::signature_authentication::SignatureError;
 
 #[derive(Debug, Error)]
@@ -40,10 +45,17 @@ pub enum CacheError {
     ArtifactTagMissing(#[backtrace] Backtrace),
     #[error("invalid artifact verification tag")]
     InvalidTag(#[backtrace] Backtrace),
+    #[error(
+        "artifact verification failed: artifact was signed with key {0}, which isn't in \
+         signaturePublicKeys"
+    )]
+    UnknownSignatureKey(String, #[backtrace] Backtrace),
     #[error("cannot untar file to {0}")]
     InvalidFilePath(String, #[backtrace] Backtrace),
     #[error("artifact verification failed: {0}")]
     ApiClientError(Box<turborepo_api_client::Error>, #[backtrace] Backtrace),
+    #[error("artifact signature verification is not enabled")]
+    SignatureVerificationDisabled(#[backtrace] Backtrace),
     #[error("signing artifact failed: {0}")]
     SignatureError(#[from] SignatureError, #[backtrace] Backtrace),
     #[error("invalid duration")]
@@ -62,6 +74,8 @@ pub enum CacheError {
     // way to display it nicely.
     #[error("attempted to create unsupported file type")]
     CreateUnsupportedFileType(#[backtrace] Backtrace),
+    #[error("cached file {0} is missing or was modified")]
+    InvalidBlob(String, #[backtrace] Backtrace),
     #[error("tar file is malformed")]
     MalformedTar(#[backtrace] Backtrace),
     #[error("file name is not Windows-safe: {0}")]
@@ -74,6 +88,8 @@ pub enum CacheError {
     MetadataWriteFailure(serde_json::Error, #[backtrace] Backtrace),
     #[error("Unable to perform write as cache is shutting down")]
     CacheShuttingDown,
+    #[error("artifact stream failed: {0}")]
+    StreamTask(#[from] tokio::task::JoinError, #[backtrace] Backtrace),
 }
 
 impl From<turborepo_api_client::Error> for CacheError {
@@ -97,21 +113,78 @@ pub struct CacheHitMetadata {
 #[derive(Debug, Default)]
 pub struct CacheOpts<'a> {
     pub override_dir: Option<&'a Utf8Path>,
+    pub shared_dir: Option<Utf8PathBuf>,
     pub remote_cache_read_only: bool,
     pub skip_remote: bool,
     pub skip_filesystem: bool,
     pub workers: u32,
     pub remote_cache_opts: Option<RemoteCacheOpts>,
+    pub layout: CacheLayout,
+}
+
+/// How artifacts are stored in the filesystem cache
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheLayout {
+    /// Each artifact is a compressed tarball
+    #[default]
+    Archive,
+    /// Files are stored once by their contents and shared between artifacts,
+    /// and are reflinked into place when restored, or copied where the
+    /// filesystem doesn't support reflinks
+    ContentAddressed,
+    /// Like `ContentAddressed`, but files are hardlinked into place when they
+    /// can't be reflinked. A task that writes to one of its outputs in place
+    /// then also writes to the cache, which turns every artifact with that
+    /// file into a cache miss.
+    Hardlinked,
+}
+
+/// Limits on the size of the filesystem cache. Once any of them are exceeded,
+/// the least recently used artifacts are evicted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Maximum combined size of all artifacts in bytes
+    pub max_size: Option<u64>,
+    /// Maximum time since an artifact was last used
+    pub max_age: Option<Duration>,
+    /// Maximum number of artifacts
+    pub max_entries: Option<usize>,
+}
+
+impl CacheLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_size.is_none() && self.max_age.is_none() && self.max_entries.is_none()
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RemoteCacheOpts {
     team_id: String,
     signature: bool,
+    #[serde(default)]
+    signature_algorithm: SignatureAlgorithm,
+    // Trusted Ed25519 public keys by key id
+    #[serde(default)]
+    signature_public_keys: BTreeMap<String, String>,
 }
 
 impl RemoteCacheOpts {
     pub fn new(team_id: String, signature: bool) -> Self {
-        Self { team_id, signature }
+        Self {
+            team_id,
+            signature,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_signature_keys(
+        mut self,
+        algorithm: SignatureAlgorithm,
+        public_keys: BTreeMap<String, String>,
+    ) -> Self {
+        self.signature_algorithm = algorithm;
+        self.signature_public_keys = public_keys;
+        self
     }
 }
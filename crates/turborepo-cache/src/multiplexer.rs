@@ -5,7 +5,9 @@ use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf};
 use turborepo_analytics::AnalyticsSender;
 use turborepo_api_client::{APIAuth, APIClient};
 
-use crate::{fs::FSCache, http::HTTPCache, CacheError, CacheHitMetadata, CacheOpts};
+use crate::{
+    fs::FSCache, http::HTTPCache, shared::SharedFSCache, CacheError, CacheHitMetadata, CacheOpts,
+};
 
 pub struct CacheMultiplexer {
     // We use an `AtomicBool` instead of removing the cache because that would require
@@ -18,6 +20,7 @@ pub struct CacheMultiplexer {
     should_print_skipping_remote_put: AtomicBool,
     remote_cache_read_only: bool,
     fs: Option<FSCache>,
+    shared: Option<SharedFSCache>,
     http: Option<HTTPCache>,
 }
 
@@ -31,16 +34,26 @@ impl CacheMultiplexer {
     ) -> Result<Self, CacheError> {
         let use_fs_cache = !opts.skip_filesystem;
         let use_http_cache = !opts.skip_remote;
+        let use_shared_cache = opts.shared_dir.is_some();
 
-        // Since the above two flags are not mutually exclusive it is possible to
+        // Since the above flags are not mutually exclusive it is possible to
         // configure yourself out of having a cache. We should tell you about it
         // but we shouldn't fail your build for that reason.
-        if !use_fs_cache && !use_http_cache {
+        if !use_fs_cache && !use_http_cache && !use_shared_cache {
             warn!("no caches are enabled");
         }
 
         let fs_cache = use_fs_cache
-            .then(|| FSCache::new(opts.override_dir, repo_root, analytics_recorder.clone()))
+            .then(|| {
+                FSCache::new(opts.override_dir, repo_root, analytics_recorder.clone())
+                    .map(|fs_cache| fs_cache.with_layout(opts.layout))
+            })
+            .transpose()?;
+
+        let shared_cache = opts
+            .shared_dir
+            .as_deref()
+            .map(|shared_dir| SharedFSCache::new(shared_dir, repo_root))
             .transpose()?;
 
         let http_cache = use_http_cache
@@ -61,6 +74,7 @@ impl CacheMultiplexer {
             should_use_http_cache: AtomicBool::new(http_cache.is_some()),
             remote_cache_read_only: opts.remote_cache_read_only,
             fs: fs_cache,
+            shared: shared_cache,
             http: http_cache,
         })
     }
@@ -75,6 +89,26 @@ impl CacheMultiplexer {
         }
     }
 
+    // Returns whether we're allowed to write to the remote caches, warning
+    // about it the first time we aren't.
+    fn should_put_remote(&self) -> bool {
+        if !self.remote_cache_read_only {
+            return true;
+        }
+
+        if self
+            .should_print_skipping_remote_put
+            .load(Ordering::Relaxed)
+        {
+            // Warn once per build, not per task
+            warn!("Remote cache is read-only, skipping upload");
+            self.should_print_skipping_remote_put
+                .store(false, Ordering::Relaxed);
+        }
+
+        false
+    }
+
     pub async fn put(
         &self,
         anchor: &AbsoluteSystemPath,
@@ -87,30 +121,32 @@ impl CacheMultiplexer {
             .map(|fs| fs.put(anchor, key, files, duration))
             .transpose()?;
 
-        let http_result = match self.get_http_cache() {
-            Some(http) => {
-                if self.remote_cache_read_only {
-                    if self
-                        .should_print_skipping_remote_put
-                        .load(Ordering::Relaxed)
-                    {
-                        // Warn once per build, not per task
-                        warn!("Remote cache is read-only, skipping upload");
-                        self.should_print_skipping_remote_put
-                            .store(false, Ordering::Relaxed);
-                    }
-                    // Cache is functional but running in read-only mode, so we don't want to try to
-                    // write to it
-                    None
-                } else {
-                    let http_result = http.put(anchor, key, files, duration).await;
+        let has_remote_cache = self.shared.is_some() || self.get_http_cache().is_some();
+        // Caches are functional but running in read-only mode, so we don't want to try
+        // to write to them
+        if !has_remote_cache || !self.should_put_remote() {
+            return Ok(());
+        }
 
-                    Some(http_result)
-                }
+        // We still try the http cache if the shared cache fails, but report the
+        // shared cache's error afterwards.
+        let shared_result = self
+            .shared
+            .as_ref()
+            .map(|shared| shared.put(anchor, key, files, duration))
+            .transpose();
+
+        // Machines without an Ed25519 private key, such as developer machines,
+        // only read signed artifacts
+        let http_result = match self.get_http_cache() {
+            Some(http) if !http.is_verify_only() => {
+                Some(http.put(anchor, key, files, duration).await)
             }
             _ => None,
         };
 
+        shared_result?;
+
         match http_result {
             Some(Err(CacheError::ApiClientError(
                 box turborepo_api_client::Error::CacheDisabled { .. },
@@ -125,6 +161,27 @@ impl CacheMultiplexer {
         }
     }
 
+    /// Checks the existence of all `hashes` in the http cache with a single
+    /// request, so tasks don't need to check it individually.
+    pub async fn prefetch_exists(&self, hashes: &[String]) {
+        let Some(http) = self.get_http_cache() else {
+            return;
+        };
+
+        match http.prefetch_exists(hashes).await {
+            Ok(()) => {}
+            Err(CacheError::ApiClientError(
+                box turborepo_api_client::Error::CacheDisabled { .. },
+                ..,
+            )) => {
+                warn!("failed to query http cache: cache disabled");
+                self.should_use_http_cache.store(false, Ordering::Relaxed);
+            }
+            // Tasks will check the http cache individually instead
+            Err(err) => debug!("failed to prefetch http cache: {:?}", err),
+        }
+    }
+
     pub async fn fetch(
         &self,
         anchor: &AbsoluteSystemPath,
@@ -136,6 +193,18 @@ impl CacheMultiplexer {
             }
         }
 
+        if let Some(shared) = &self.shared {
+            if let Ok(Some((metadata, files))) = shared.fetch(anchor, key) {
+                // As with the http cache below, storing in the fs cache is only an
+                // optimization, so errors are ignored.
+                if let Some(fs) = &self.fs {
+                    let _ = fs.put(anchor, key, &files, metadata.time_saved);
+                }
+
+                return Ok(Some((metadata, files)));
+            }
+        }
+
         if let Some(http) = self.get_http_cache() {
             if let Ok(Some((CacheHitMetadata { source, time_saved }, files))) =
                 http.fetch(key).await
@@ -147,6 +216,11 @@ impl CacheMultiplexer {
                 if let Some(fs) = &self.fs {
                     let _ = fs.put(anchor, key, &files, time_saved);
                 }
+                if let Some(shared) = &self.shared {
+                    if !self.remote_cache_read_only {
+                        let _ = shared.put(anchor, key, &files, time_saved);
+                    }
+                }
 
                 return Ok(Some((CacheHitMetadata { source, time_saved }, files)));
             }
@@ -166,6 +240,16 @@ impl CacheMultiplexer {
             }
         }
 
+        if let Some(shared) = &self.shared {
+            match shared.exists(key) {
+                cache_hit @ Ok(Some(_)) => {
+                    return cache_hit;
+                }
+                Ok(None) => {}
+                Err(err) => debug!("failed to check shared cache: {:?}", err),
+            }
+        }
+
         if let Some(http) = self.get_http_cache() {
             match http.exists(key).await {
                 cache_hit @ Ok(Some(_)) => {
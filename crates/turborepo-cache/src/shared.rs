@@ -0,0 +1,228 @@
+use std::{
+    backtrace::Backtrace,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::Utf8Path;
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+use crate::{
+    cache_archive::{CacheReader, CacheWriter},
+    fs::CacheMetadata,
+    CacheError, CacheHitMetadata, CacheSource,
+};
+
+// Distinguishes temporary files written by the same process
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A cache stored in a directory shared between machines, e.g. an NFS mount.
+/// It uses the same `{hash}.tar.zst` and `{hash}-meta.json` layout as
+/// `FSCache`, but every file is written to a temporary file first and then
+/// renamed into place, so concurrent readers never observe a partially
+/// written artifact.
+pub struct SharedFSCache {
+    cache_directory: AbsoluteSystemPathBuf,
+}
+
+impl SharedFSCache {
+    pub fn new(cache_dir: &Utf8Path, repo_root: &AbsoluteSystemPath) -> Result<Self, CacheError> {
+        let cache_directory = AbsoluteSystemPathBuf::from_unknown(repo_root, cache_dir);
+        cache_directory.create_dir_all()?;
+
+        Ok(SharedFSCache { cache_directory })
+    }
+
+    fn archive_path(&self, hash: &str) -> AbsoluteSystemPathBuf {
+        self.cache_directory
+            .join_component(&format!("{}.tar.zst", hash))
+    }
+
+    fn metadata_path(&self, hash: &str) -> AbsoluteSystemPathBuf {
+        self.cache_directory
+            .join_component(&format!("{}-meta.json", hash))
+    }
+
+    // Temporary files live in the cache directory itself so that the final
+    // rename never crosses a filesystem boundary. The name keeps the original
+    // extension since `CacheWriter` uses it to decide whether to compress.
+    fn temp_path(&self, file_name: &str) -> AbsoluteSystemPathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let count = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        self.cache_directory.join_component(&format!(
+            ".tmp-{}-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            count,
+            file_name
+        ))
+    }
+
+    // Writes a file via `write` to a temporary path and then atomically moves it
+    // to `path`. The temporary file is removed if anything fails.
+    fn write_atomic(
+        &self,
+        path: &AbsoluteSystemPath,
+        write: impl FnOnce(&AbsoluteSystemPath) -> Result<(), CacheError>,
+    ) -> Result<(), CacheError> {
+        let file_name = path
+            .as_path()
+            .file_name()
+            .expect("cache paths always have a file name");
+        let temp_path = self.temp_path(file_name);
+
+        let result = write(&temp_path).and_then(|_| Ok(temp_path.rename(path)?));
+        if result.is_err() {
+            let _ = temp_path.remove_file();
+        }
+
+        result
+    }
+
+    pub fn fetch(
+        &self,
+        anchor: &AbsoluteSystemPath,
+        hash: &str,
+    ) -> Result<Option<(CacheHitMetadata, Vec<AnchoredSystemPathBuf>)>, CacheError> {
+        let cache_path = self.archive_path(hash);
+        if !cache_path.exists() {
+            return Ok(None);
+        }
+
+        let mut cache_reader = CacheReader::open(&cache_path)?;
+
+        let restored_files = cache_reader.restore(anchor)?;
+
+        let meta = CacheMetadata::read(&self.metadata_path(hash))?;
+
+        Ok(Some((
+            CacheHitMetadata {
+                time_saved: meta.duration,
+                source: CacheSource::Remote,
+            },
+            restored_files,
+        )))
+    }
+
+    pub(crate) fn exists(&self, hash: &str) -> Result<Option<CacheHitMetadata>, CacheError> {
+        if !self.archive_path(hash).exists() {
+            return Ok(None);
+        }
+
+        let duration = CacheMetadata::read(&self.metadata_path(hash))
+            .map(|meta| meta.duration)
+            .unwrap_or(0);
+
+        Ok(Some(CacheHitMetadata {
+            time_saved: duration,
+            source: CacheSource::Remote,
+        }))
+    }
+
+    pub fn put(
+        &self,
+        anchor: &AbsoluteSystemPath,
+        hash: &str,
+        files: &[AnchoredSystemPathBuf],
+        duration: u64,
+    ) -> Result<(), CacheError> {
+        // The metadata goes first, so that by the time the archive is visible to
+        // other machines its metadata is as well.
+        let meta = CacheMetadata {
+            hash: hash.to_string(),
+            duration,
+        };
+        self.write_atomic(&self.metadata_path(hash), |temp_path| {
+            let metadata_file = temp_path.create()?;
+            serde_json::to_writer(metadata_file, &meta)
+                .map_err(|e| CacheError::MetadataWriteFailure(e, Backtrace::capture()))
+        })?;
+
+        self.write_atomic(&self.archive_path(hash), |temp_path| {
+            let mut cache_item = CacheWriter::create(temp_path)?;
+
+            for file in files {
+                cache_item.add_file(anchor, file)?;
+            }
+
+            cache_item.finish()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use tempfile::tempdir;
+    use turbopath::AnchoredSystemPath;
+
+    use super::*;
+    use crate::test_cases::{get_test_cases, TestCase};
+
+    #[test]
+    fn test_shared_fs_cache() -> Result<()> {
+        let shared_dir = tempdir()?;
+        let shared_dir_path = AbsoluteSystemPath::from_std_path(shared_dir.path())?;
+
+        for test_case in get_test_cases() {
+            round_trip_test(&test_case, shared_dir_path)?;
+        }
+
+        // Only the final artifacts should be left in the shared directory
+        for entry in std::fs::read_dir(shared_dir.path())? {
+            let file_name = entry?.file_name();
+            let file_name = file_name.to_string_lossy();
+            assert!(!file_name.starts_with(".tmp-"), "found {file_name}");
+        }
+
+        Ok(())
+    }
+
+    fn round_trip_test(test_case: &TestCase, shared_dir: &AbsoluteSystemPath) -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPath::from_std_path(repo_root.path())?;
+        test_case.initialize(repo_root_path)?;
+
+        let cache = SharedFSCache::new(shared_dir.as_path(), repo_root_path)?;
+
+        let expected_miss = cache.fetch(repo_root_path, test_case.hash)?;
+        assert!(expected_miss.is_none());
+        assert!(cache.exists(test_case.hash)?.is_none());
+
+        let files: Vec<_> = test_case
+            .files
+            .iter()
+            .map(|f| f.path().to_owned())
+            .collect();
+        cache.put(repo_root_path, test_case.hash, &files, test_case.duration)?;
+
+        let expected_hit = CacheHitMetadata {
+            time_saved: test_case.duration,
+            source: CacheSource::Remote,
+        };
+        assert_eq!(cache.exists(test_case.hash)?, Some(expected_hit));
+
+        // Restore into a fresh checkout, as another machine would
+        let other_root = tempdir()?;
+        let other_root_path = AbsoluteSystemPath::from_std_path(other_root.path())?;
+        let (status, files) = cache.fetch(other_root_path, test_case.hash)?.unwrap();
+
+        assert_eq!(status, expected_hit);
+        assert_eq!(files.len(), test_case.files.len());
+        for (expected, actual) in test_case.files.iter().zip(files.iter()) {
+            let actual: &AnchoredSystemPath = actual;
+            assert_eq!(expected.path(), actual);
+            let actual_file = other_root_path.resolve(actual);
+            if let Some(contents) = expected.contents() {
+                assert_eq!(contents, actual_file.read_to_string()?);
+            } else {
+                assert!(actual_file.exists());
+            }
+        }
+
+        Ok(())
+    }
+}
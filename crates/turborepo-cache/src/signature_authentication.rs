@@ -1,13 +1,19 @@
-use std::env;
+use std::{collections::BTreeMap, env};
 
 use base64::{prelude::BASE64_STANDARD, Engine};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
 use hmac::{Hmac, Mac};
 use os_str_bytes::OsStringBytes;
-use sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 type HmacSha256 = Hmac<Sha256>;
 
+// Ed25519 tags look like `ed25519:<key id>:<base64 signature>`, which can't be
+// mistaken for a base64 HMAC tag
+const ED25519_TAG_PREFIX: &str = "ed25519:";
+
 #[derive(Debug, Error)]
 pub enum SignatureError {
     #[error(
@@ -21,6 +27,46 @@ pub enum SignatureError {
     Base64EncodingError(#[from] base64::DecodeError),
     #[error(transparent)]
     Hmac(#[from] hmac::digest::InvalidLength),
+    #[error("artifact was signed with key {0}, which isn't in signaturePublicKeys")]
+    UnknownKey(String),
+    #[error("artifact tag is not an Ed25519 signature")]
+    MalformedTag,
+    #[error("public key {0} in signaturePublicKeys is not a valid Ed25519 key")]
+    InvalidPublicKey(String),
+    #[error(
+        "TURBO_REMOTE_CACHE_SIGNATURE_KEY must be a base64 encoded 32 byte Ed25519 private key"
+    )]
+    InvalidPrivateKey,
+    #[error("the public key of TURBO_REMOTE_CACHE_SIGNATURE_KEY isn't in signaturePublicKeys")]
+    UntrustedPrivateKey,
+}
+
+/// The algorithm used to sign artifacts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureAlgorithm {
+    /// Signs and verifies with a secret shared by every machine
+    #[default]
+    HmacSha256,
+    /// Signs with a private key and verifies with a keyring of public keys
+    Ed25519,
+}
+
+/// A tag that is computed as an artifact is streamed, so the artifact never has
+/// to be in memory all at once
+pub struct ArtifactTag {
+    state: TagState,
+}
+
+enum TagState {
+    Hmac(HmacSha256),
+    // Ed25519 signs a digest of the artifact, so that it can be computed
+    // incrementally as well
+    Ed25519 {
+        digest: Sha256,
+        public_keys: BTreeMap<String, String>,
+        private_key: Option<Vec<u8>>,
+    },
 }
 
 #[derive(Debug)]
@@ -28,6 +74,9 @@ pub struct ArtifactSignatureAuthenticator {
     pub(crate) team_id: Vec<u8>,
     // An override for testing purposes (to avoid env var race conditions)
     pub(crate) secret_key_override: Option<Vec<u8>>,
+    // Trusted Ed25519 public keys by key id. Artifacts are signed with HMAC
+    // unless this is set.
+    pub(crate) public_keys: Option<BTreeMap<String, String>>,
 }
 
 impl ArtifactSignatureAuthenticator {
@@ -35,21 +84,40 @@ impl ArtifactSignatureAuthenticator {
         Self {
             team_id,
             secret_key_override,
+            public_keys: None,
         }
     }
 
+    /// Switches to Ed25519 signatures. Artifacts are verified with the public
+    /// key named in their tag, so keys can be rotated by adding the new key
+    /// before signing with it and removing the old key once its artifacts are
+    /// no longer needed.
+    pub fn with_public_keys(mut self, public_keys: BTreeMap<String, String>) -> Self {
+        self.public_keys = Some(public_keys);
+        self
+    }
+
+    /// Whether this machine can only verify artifacts, which is the case when
+    /// using Ed25519 without a private key
+    pub fn is_verify_only(&self) -> bool {
+        self.public_keys.is_some() && self.secret_key_var().is_none()
+    }
+
+    fn secret_key_var(&self) -> Option<Vec<u8>> {
+        if let Some(secret_key) = &self.secret_key_override {
+            return Some(secret_key.to_vec());
+        }
+
+        env::var_os("TURBO_REMOTE_CACHE_SIGNATURE_KEY").map(|key| key.into_raw_vec())
+    }
+
     // Gets secret key from either secret key override or environment variable.
     // HMAC_SHA256 has no key length limit, although it's generally recommended
     // to keep key length under 64 bytes since anything longer is hashed using
     // SHA-256.
     fn secret_key(&self) -> Result<Vec<u8>, SignatureError> {
-        if let Some(secret_key) = &self.secret_key_override {
-            return Ok(secret_key.to_vec());
-        }
-
-        Ok(env::var_os("TURBO_REMOTE_CACHE_SIGNATURE_KEY")
-            .ok_or(SignatureError::NoSignatureSecretKey)?
-            .into_raw_vec())
+        self.secret_key_var()
+            .ok_or(SignatureError::NoSignatureSecretKey)
     }
 
     fn construct_metadata(&self, hash: &[u8]) -> Result<Vec<u8>, SignatureError> {
@@ -68,6 +136,26 @@ impl ArtifactSignatureAuthenticator {
         Ok(mac)
     }
 
+    /// Starts a tag for the artifact with the given hash. The artifact's body
+    /// is added with `ArtifactTag::update`.
+    pub fn tag(&self, hash: &[u8]) -> Result<ArtifactTag, SignatureError> {
+        let state = match &self.public_keys {
+            None => TagState::Hmac(self.get_tag_generator(hash)?),
+            Some(public_keys) => {
+                let mut digest = Sha256::new();
+                digest.update(self.construct_metadata(hash)?);
+                TagState::Ed25519 {
+                    digest,
+                    public_keys: public_keys.clone(),
+                    // Only needed for signing, machines that verify don't have one
+                    private_key: self.secret_key_var(),
+                }
+            }
+        };
+
+        Ok(ArtifactTag { state })
+    }
+
     pub fn generate_tag_bytes(
         &self,
         hash: &[u8],
@@ -85,11 +173,9 @@ impl ArtifactSignatureAuthenticator {
         hash: &[u8],
         artifact_body: &[u8],
     ) -> Result<String, SignatureError> {
-        let mut hmac_ctx = self.get_tag_generator(hash)?;
-
-        hmac_ctx.update(artifact_body);
-        let hmac_output = hmac_ctx.finalize();
-        Ok(BASE64_STANDARD.encode(hmac_output.into_bytes()))
+        let mut tag = self.tag(hash)?;
+        tag.update(artifact_body);
+        tag.finish()
     }
 
     pub fn validate(
@@ -98,16 +184,102 @@ impl ArtifactSignatureAuthenticator {
         artifact_body: &[u8],
         expected_tag: &str,
     ) -> Result<bool, SignatureError> {
-        let mut mac = HmacSha256::new_from_slice(&self.secret_key()?)?;
-        let message = self.construct_metadata(hash)?;
-        mac.update(&message);
-        mac.update(artifact_body);
+        let mut tag = self.tag(hash)?;
+        tag.update(artifact_body);
+        tag.verify(expected_tag)
+    }
+}
+
+impl ArtifactTag {
+    pub fn update(&mut self, chunk: &[u8]) {
+        match &mut self.state {
+            TagState::Hmac(mac) => mac.update(chunk),
+            TagState::Ed25519 { digest, .. } => digest.update(chunk),
+        }
+    }
+
+    /// Returns the tag to send with the artifact
+    pub fn finish(self) -> Result<String, SignatureError> {
+        match self.state {
+            TagState::Hmac(mac) => Ok(BASE64_STANDARD.encode(mac.finalize().into_bytes())),
+            TagState::Ed25519 {
+                digest,
+                public_keys,
+                private_key,
+            } => {
+                let private_key = private_key.ok_or(SignatureError::NoSignatureSecretKey)?;
+                let signing_key = parse_private_key(&private_key)?;
+                let verifying_key = signing_key.verifying_key();
+                // The key id is looked up rather than configured, so it can't
+                // name a different key than the one that signed
+                let key_id = public_keys
+                    .iter()
+                    .find(|(key_id, public_key)| {
+                        parse_public_key(key_id, public_key).is_ok_and(|key| key == verifying_key)
+                    })
+                    .map(|(key_id, _)| key_id)
+                    .ok_or(SignatureError::UntrustedPrivateKey)?;
+
+                let signature = signing_key.sign(digest.finalize().as_slice());
+                Ok(format!(
+                    "{ED25519_TAG_PREFIX}{key_id}:{}",
+                    BASE64_STANDARD.encode(signature.to_bytes())
+                ))
+            }
+        }
+    }
 
-        let expected_bytes = BASE64_STANDARD.decode(expected_tag)?;
-        Ok(mac.verify_slice(&expected_bytes).is_ok())
+    /// Checks the tag an artifact was sent with. With Ed25519, an artifact
+    /// signed by a key that isn't trusted is an `UnknownKey` error rather than
+    /// an invalid tag.
+    pub fn verify(self, expected_tag: &str) -> Result<bool, SignatureError> {
+        match self.state {
+            TagState::Hmac(mac) => {
+                let expected_bytes = BASE64_STANDARD.decode(expected_tag)?;
+                Ok(mac.verify_slice(&expected_bytes).is_ok())
+            }
+            TagState::Ed25519 {
+                digest,
+                public_keys,
+                ..
+            } => {
+                let (key_id, signature) = expected_tag
+                    .strip_prefix(ED25519_TAG_PREFIX)
+                    .and_then(|tag| tag.rsplit_once(':'))
+                    .ok_or(SignatureError::MalformedTag)?;
+                let public_key = public_keys
+                    .get(key_id)
+                    .ok_or_else(|| SignatureError::UnknownKey(key_id.to_string()))?;
+                let public_key = parse_public_key(key_id, public_key)?;
+                let signature = Signature::from_slice(&BASE64_STANDARD.decode(signature)?)
+                    .map_err(|_| SignatureError::MalformedTag)?;
+
+                Ok(public_key
+                    .verify_strict(digest.finalize().as_slice(), &signature)
+                    .is_ok())
+            }
+        }
     }
 }
 
+fn parse_private_key(private_key: &[u8]) -> Result<SigningKey, SignatureError> {
+    let private_key = std::str::from_utf8(private_key)
+        .ok()
+        .and_then(|key| BASE64_STANDARD.decode(key.trim()).ok())
+        .and_then(|key| <[u8; 32]>::try_from(key).ok())
+        .ok_or(SignatureError::InvalidPrivateKey)?;
+    Ok(SigningKey::from_bytes(&private_key))
+}
+
+fn parse_public_key(key_id: &str, public_key: &str) -> Result<VerifyingKey, SignatureError> {
+    BASE64_STANDARD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|key| <[u8; 32]>::try_from(key).ok())
+        .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+        .ok_or_else(|| SignatureError::InvalidPublicKey(key_id.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -245,6 +417,7 @@ mod tests {
         let signature = ArtifactSignatureAuthenticator {
             team_id: test_case.team_id.to_vec(),
             secret_key_override: None,
+            public_keys: None,
         };
 
         let hash = test_case.artifact_hash;
@@ -270,4 +443,92 @@ mod tests {
         assert!(signature.validate(hash, artifact_body, &tag)?);
         Ok(())
     }
+
+    #[test]
+    fn test_incremental_tag() -> Result<()> {
+        let signature = ArtifactSignatureAuthenticator::new(
+            b"my-team".to_vec(),
+            Some(b"my-secret-key".to_vec()),
+        );
+        let artifact_body = b"an artifact that arrives in chunks";
+
+        let mut tag = signature.tag(b"hash")?;
+        for chunk in artifact_body.chunks(5) {
+            tag.update(chunk);
+        }
+        let tag = tag.finish()?;
+        assert_eq!(tag, signature.generate_tag(b"hash", artifact_body)?);
+
+        let mut other_body = signature.tag(b"hash")?;
+        other_body.update(b"another artifact");
+        assert!(!other_body.verify(&tag)?);
+        Ok(())
+    }
+
+    fn ed25519_key(seed: u8) -> (String, String) {
+        let signing_key = SigningKey::from_bytes(&[seed; 32]);
+        (
+            BASE64_STANDARD.encode(signing_key.to_bytes()),
+            BASE64_STANDARD.encode(signing_key.verifying_key().to_bytes()),
+        )
+    }
+
+    #[test]
+    fn test_ed25519_key_rotation() -> Result<()> {
+        let (old_private, old_public) = ed25519_key(1);
+        let (new_private, new_public) = ed25519_key(2);
+        let keyring = BTreeMap::from([
+            ("2024-01".to_string(), old_public),
+            ("2024-07".to_string(), new_public.clone()),
+        ]);
+        let signer = |private_key: &str| {
+            ArtifactSignatureAuthenticator::new(
+                b"my-team".to_vec(),
+                Some(private_key.as_bytes().to_vec()),
+            )
+            .with_public_keys(keyring.clone())
+        };
+        let body = b"artifact body";
+
+        let old_tag = signer(&old_private).generate_tag(b"hash", body)?;
+        let new_tag = signer(&new_private).generate_tag(b"hash", body)?;
+        assert!(old_tag.starts_with("ed25519:2024-01:"));
+        assert!(new_tag.starts_with("ed25519:2024-07:"));
+
+        // Machines that only verify don't need a private key
+        let verifier = ArtifactSignatureAuthenticator::new(b"my-team".to_vec(), None)
+            .with_public_keys(keyring.clone());
+        assert!(verifier.validate(b"hash", body, &old_tag)?);
+        assert!(verifier.validate(b"hash", body, &new_tag)?);
+        assert!(!verifier.validate(b"other-hash", body, &new_tag)?);
+        assert!(!verifier.validate(b"hash", b"tampered body", &new_tag)?);
+
+        // Once the old key is removed, its artifacts are from an unknown key
+        let verifier = ArtifactSignatureAuthenticator::new(b"my-team".to_vec(), None)
+            .with_public_keys(BTreeMap::from([("2024-07".to_string(), new_public)]));
+        assert!(matches!(
+            verifier.validate(b"hash", body, &old_tag),
+            Err(SignatureError::UnknownKey(key_id)) if key_id == "2024-01"
+        ));
+        assert!(matches!(
+            verifier.validate(b"hash", body, &BASE64_STANDARD.encode(b"hmac tag")),
+            Err(SignatureError::MalformedTag)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed25519_untrusted_private_key() {
+        let (private_key, _) = ed25519_key(3);
+        let (_, public_key) = ed25519_key(4);
+        let signer = ArtifactSignatureAuthenticator::new(
+            b"my-team".to_vec(),
+            Some(private_key.into_bytes()),
+        )
+        .with_public_keys(BTreeMap::from([("ci".to_string(), public_key)]));
+        assert!(matches!(
+            signer.generate_tag(b"hash", b"artifact body"),
+            Err(SignatureError::UntrustedPrivateKey)
+        ));
+    }
 }
@@ -0,0 +1,169 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use turborepo_api_client::{Body, Response};
+
+use crate::signature_authentication::ArtifactTag;
+
+// Size of the chunks that are passed between the archive and the request
+const CHUNK_SIZE: usize = 256 * 1024;
+// How many chunks can be waiting to be uploaded or restored. Along with
+// `CHUNK_SIZE` this bounds how much of an artifact is in memory at once.
+const MAX_PENDING_CHUNKS: usize = 8;
+
+/// Writes an artifact into a streamed request body. Writes block until the
+/// request has caught up, so it must be used from a blocking thread.
+pub(crate) struct BodyWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+    finished: Arc<AtomicBool>,
+}
+
+/// Reads a response body while it's still being downloaded. Reads block until
+/// the next chunk arrives, so it must be used from a blocking thread.
+pub(crate) struct BodyReader {
+    receiver: mpsc::Receiver<io::Result<Bytes>>,
+    chunk: Bytes,
+}
+
+/// Updates a tag with everything that is written through it
+pub(crate) struct TagWriter<W> {
+    writer: W,
+    tag: ArtifactTag,
+}
+
+/// Creates a request body along with the writer that fills it. The body fails
+/// unless `BodyWriter::finish` is called, so an archive that couldn't be
+/// written completely is never uploaded.
+pub(crate) fn body_channel() -> (BodyWriter, Body) {
+    let (sender, receiver) = mpsc::channel(MAX_PENDING_CHUNKS);
+    let finished = Arc::new(AtomicBool::new(false));
+    let writer = BodyWriter {
+        sender,
+        buffer: Vec::with_capacity(CHUNK_SIZE),
+        finished: finished.clone(),
+    };
+
+    let chunks = futures::stream::unfold(Some(receiver), move |receiver| {
+        let finished = finished.clone();
+        async move {
+            let mut receiver = receiver?;
+            match receiver.recv().await {
+                Some(chunk) => Some((chunk, Some(receiver))),
+                // The writer was dropped, which is only expected once it's done
+                None if finished.load(Ordering::SeqCst) => None,
+                None => Some((
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "artifact was not completely written",
+                    )),
+                    None,
+                )),
+            }
+        }
+    });
+
+    (writer, Body::wrap_stream(chunks))
+}
+
+impl BodyWriter {
+    pub fn finish(mut self) -> io::Result<()> {
+        self.send_buffer()?;
+        self.finished.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        self.sender
+            .blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "upload was closed"))
+    }
+}
+
+impl Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+impl BodyReader {
+    /// Starts downloading the body of `response` in the background
+    pub fn new(mut response: Response) -> Self {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_CHUNKS);
+        tokio::spawn(async move {
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => Ok(chunk),
+                    Ok(None) => break,
+                    Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+                };
+                let is_err = chunk.is_err();
+                // The reader stops early if the archive is malformed
+                if sender.send(chunk).await.is_err() || is_err {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            receiver,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = chunk?,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
+}
+
+impl<W: Write> TagWriter<W> {
+    pub fn new(writer: W, tag: ArtifactTag) -> Self {
+        Self { writer, tag }
+    }
+
+    pub fn into_parts(self) -> (W, ArtifactTag) {
+        (self.writer, self.tag)
+    }
+}
+
+impl<W: Write> Write for TagWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.tag.update(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
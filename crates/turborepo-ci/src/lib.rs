@@ -53,6 +53,32 @@ impl Vendor {
             .unwrap_or_default()
     }
 
+    /// Gets the ref that changes in this build should be compared against,
+    /// such as the branch a pull request targets. Only set for builds where
+    /// the vendor exposes it.
+    pub fn get_base_ref() -> Option<String> {
+        let base_ref = Self::get_env_var(|v| v.base_ref_env_var)?;
+        // Some vendors give the full ref of the target branch
+        Some(
+            base_ref
+                .strip_prefix("refs/heads/")
+                .map(ToOwned::to_owned)
+                .unwrap_or(base_ref),
+        )
+    }
+
+    /// Gets the commit that is being built
+    pub fn get_head_sha() -> Option<String> {
+        Self::get_env_var(|v| v.sha_env_var)
+    }
+
+    fn get_env_var(env_var: impl FnOnce(&Vendor) -> Option<&'static str>) -> Option<String> {
+        Vendor::infer()
+            .and_then(env_var)
+            .and_then(|v| env::var(v).ok())
+            .filter(|value| !value.is_empty())
+    }
+
     fn infer_inner() -> Option<&'static Vendor> {
         for env in get_vendors() {
             if let Some(eval_env) = &env.eval_env {
@@ -15,6 +15,8 @@ pub struct Vendor {
     pub(crate) eval_env: Option<HashMap<&'static str, &'static str>>,
     pub sha_env_var: Option<&'static str>,
     pub branch_env_var: Option<&'static str>,
+    // The commit or branch that a pull request will be merged into
+    pub base_ref_env_var: Option<&'static str>,
     pub username_env_var: Option<&'static str>,
 }
 
@@ -34,6 +36,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -46,6 +49,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -58,6 +62,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -68,8 +73,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("BUILD_SOURCEVERSION"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("SYSTEM_PULLREQUEST_TARGETBRANCH"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -82,6 +88,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -92,8 +99,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("BITBUCKET_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("BITBUCKET_PR_DESTINATION_BRANCH"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -104,8 +112,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("BITRISE_GIT_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("BITRISEIO_GIT_BRANCH_DEST"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -118,6 +127,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -128,8 +138,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("BUILDKITE_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("BUILDKITE_PULL_REQUEST_BASE_BRANCH"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -140,8 +151,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("CIRCLE_SHA1"),
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -152,8 +164,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("CIRRUS_CHANGE_IN_REPO"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CIRRUS_BASE_SHA"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -166,6 +179,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -176,8 +190,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("CM_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CM_PULL_REQUEST_DEST"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -194,6 +209,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     }),
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -204,8 +220,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("DRONE_COMMIT_SHA"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("DRONE_TARGET_BRANCH"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -218,6 +235,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -230,6 +248,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -242,6 +261,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: Some("GITHUB_SHA"),
                     branch_env_var: Some("GITHUB_REF_NAME"),
+                    base_ref_env_var: Some("GITHUB_BASE_REF"),
                     username_env_var: Some("GITHUB_ACTOR"),
                 },
                 Vendor {
@@ -252,8 +272,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("CI_COMMIT_SHA"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CI_MERGE_REQUEST_DIFF_BASE_SHA"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -266,6 +287,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -278,6 +300,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -290,6 +313,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -302,6 +326,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -314,6 +339,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -324,8 +350,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec!["JENKINS_URL", "BUILD_ID"],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("GIT_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CHANGE_TARGET"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -338,6 +365,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -348,8 +376,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("COMMIT_REF"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CACHED_COMMIT_REF"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -362,6 +391,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -374,6 +404,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -386,6 +417,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -398,6 +430,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -410,6 +443,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -422,6 +456,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -434,6 +469,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -446,6 +482,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -462,6 +499,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     }),
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -474,6 +512,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -486,6 +525,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -498,6 +538,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -508,8 +549,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         all: vec![],
                     },
                     eval_env: None,
-                    sha_env_var: None,
+                    sha_env_var: Some("TRAVIS_COMMIT"),
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -522,6 +564,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: Some("VERCEL_GIT_COMMIT_SHA"),
                     branch_env_var: Some("VERCEL_GIT_COMMIT_REF"),
+                    base_ref_env_var: Some("VERCEL_GIT_PREVIOUS_SHA"),
                     username_env_var: Some("VERCEL_GIT_COMMIT_AUTHOR_LOGIN"),
                 },
                 Vendor {
@@ -534,6 +577,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -548,8 +592,9 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                         map.insert("CI", "woodpecker");
                         map
                     }),
-                    sha_env_var: None,
+                    sha_env_var: Some("CI_COMMIT_SHA"),
                     branch_env_var: None,
+                    base_ref_env_var: Some("CI_COMMIT_TARGET_BRANCH"),
                     username_env_var: None,
                 },
                 Vendor {
@@ -562,6 +607,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
                 Vendor {
@@ -574,6 +620,7 @@ pub(crate) fn get_vendors() -> &'static [Vendor] {
                     eval_env: None,
                     sha_env_var: None,
                     branch_env_var: None,
+                    base_ref_env_var: None,
                     username_env_var: None,
                 },
             ]
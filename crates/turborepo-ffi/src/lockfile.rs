@@ -182,7 +182,7 @@ fn subgraph_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
         }
         proto::PackageManager::Yarn => turborepo_lockfiles::yarn_subgraph(&contents, &packages)?,
         proto::PackageManager::Bun => {
-            return Err(Error::UnsupportedPackageManager(proto::PackageManager::Bun))
+            turborepo_lockfiles::bun_subgraph(&contents, &workspaces, &packages)?
         }
     };
     Ok(contents)
@@ -0,0 +1,532 @@
+//! This module hosts the `HashWatcher` type, which keeps an in-memory index of
+//! the git-compatible hashes of package files. The index is populated the
+//! first time a package is requested and is then kept up to date from file
+//! system events, so that subsequent requests don't need to spawn git or
+//! rehash the whole package.
+
+use std::{collections::HashMap, future::IntoFuture, sync::Arc};
+
+use notify::{Event, EventKind};
+use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, trace, warn};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+use turborepo_scm::{
+    package_deps::{GitHashes, GitIgnore},
+    SCM,
+};
+use wax::{Any, Glob, Pattern};
+
+use crate::{
+    cookie_jar::{CookieError, CookieJar},
+    NotifyError,
+};
+
+/// Identifies a set of file hashes: the files of a package, optionally
+/// filtered by the `inputs` globs of a task
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashSpec {
+    pub package_path: AnchoredSystemPathBuf,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    CookieError(#[from] CookieError),
+    #[error("hash watcher has closed")]
+    Closed,
+}
+
+impl From<mpsc::error::SendError<Query>> for Error {
+    fn from(_: mpsc::error::SendError<Query>) -> Self {
+        Error::Closed
+    }
+}
+
+impl From<oneshot::error::RecvError> for Error {
+    fn from(_: oneshot::error::RecvError) -> Self {
+        Error::Closed
+    }
+}
+
+pub struct HashWatcher {
+    cookie_jar: CookieJar,
+    // _exit_ch exists to trigger a close on the receiver when an instance
+    // of this struct is dropped. The task that is receiving events will exit,
+    // dropping the other sender for the broadcast channel, causing all receivers
+    // to be notified of a close.
+    _exit_ch: oneshot::Sender<()>,
+    query_ch: mpsc::Sender<Query>,
+}
+
+#[derive(Debug)]
+pub enum Query {
+    GetFileHashes {
+        specs: Vec<HashSpec>,
+        resp: oneshot::Sender<Vec<Option<GitHashes>>>,
+    },
+}
+
+impl HashWatcher {
+    pub fn new(
+        repo_root: AbsoluteSystemPathBuf,
+        cookie_jar: CookieJar,
+        recv: broadcast::Receiver<Result<Event, NotifyError>>,
+    ) -> Self {
+        let (exit_ch, exit_signal) = oneshot::channel();
+        let (query_ch, query_recv) = mpsc::channel(256);
+        tokio::task::spawn(HashTracker::new(repo_root, exit_signal, recv, query_recv).watch());
+        Self {
+            cookie_jar,
+            _exit_ch: exit_ch,
+            query_ch,
+        }
+    }
+
+    /// Returns the hashes for each of `specs` that have already been indexed.
+    /// For those that haven't, hashing starts in the background and `None` is
+    /// returned so that the caller can hash the files itself in the meantime.
+    pub async fn get_file_hashes(
+        &self,
+        specs: Vec<HashSpec>,
+    ) -> Result<Vec<Option<GitHashes>>, Error> {
+        // Make sure that every change made before this request is reflected
+        // in the index
+        self.cookie_jar.wait_for_cookie().await?;
+        let (tx, rx) = oneshot::channel();
+        self.query_ch
+            .send(Query::GetFileHashes { specs, resp: tx })
+            .await?;
+        Ok(rx.await?)
+    }
+}
+
+struct Entry {
+    hashes: GitHashes,
+    // Matches package relative paths that could be picked up by the inputs
+    // globs, `None` if every file in the package is included
+    inputs: Option<Any<'static>>,
+}
+
+struct HashTracker {
+    repo_root: AbsoluteSystemPathBuf,
+    scm: Arc<SCM>,
+    git_ignore: Option<GitIgnore>,
+
+    entries: HashMap<HashSpec, Entry>,
+    // Specs that are currently being hashed along with the paths that changed
+    // since hashing started. `None` if we lost track of the changes.
+    pending: HashMap<HashSpec, Option<Vec<AbsoluteSystemPathBuf>>>,
+
+    exit_signal: oneshot::Receiver<()>,
+    recv: broadcast::Receiver<Result<Event, NotifyError>>,
+    query_recv: mpsc::Receiver<Query>,
+    hashed_tx: mpsc::Sender<(HashSpec, Result<GitHashes, turborepo_scm::Error>)>,
+    hashed_recv: mpsc::Receiver<(HashSpec, Result<GitHashes, turborepo_scm::Error>)>,
+}
+
+impl HashTracker {
+    fn new(
+        repo_root: AbsoluteSystemPathBuf,
+        exit_signal: oneshot::Receiver<()>,
+        recv: broadcast::Receiver<Result<Event, NotifyError>>,
+        query_recv: mpsc::Receiver<Query>,
+    ) -> Self {
+        let scm = SCM::new(&repo_root);
+        let git_ignore = scm.git_ignore();
+        let (hashed_tx, hashed_recv) = mpsc::channel(256);
+        Self {
+            repo_root,
+            scm: Arc::new(scm),
+            git_ignore,
+            entries: HashMap::new(),
+            pending: HashMap::new(),
+            exit_signal,
+            recv,
+            query_recv,
+            hashed_tx,
+            hashed_recv,
+        }
+    }
+
+    async fn watch(mut self) {
+        loop {
+            // File events are handled first so that a query that arrives after
+            // its cookie sees every change made before it
+            tokio::select! {
+                biased;
+                _ = &mut self.exit_signal => return,
+                file_event = self.recv.recv().into_future() => match file_event {
+                    Ok(Ok(event)) => self.handle_file_event(event),
+                    Ok(Err(error)) => self.on_error(&error),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                    Err(error @ broadcast::error::RecvError::Lagged(_)) => self.on_error(&error),
+                },
+                Some((spec, result)) = self.hashed_recv.recv() => self.handle_hashed(spec, result),
+                Some(query) = self.query_recv.recv() => self.handle_query(query),
+            }
+        }
+    }
+
+    fn handle_query(&mut self, query: Query) {
+        match query {
+            Query::GetFileHashes { specs, resp } => {
+                let hashes = specs
+                    .into_iter()
+                    .map(|spec| {
+                        if let Some(entry) = self.entries.get(&spec) {
+                            return Some(entry.hashes.clone());
+                        }
+                        if !self.pending.contains_key(&spec) {
+                            self.start_hashing(spec);
+                        }
+                        None
+                    })
+                    .collect();
+                let _ = resp.send(hashes);
+            }
+        }
+    }
+
+    fn start_hashing(&mut self, spec: HashSpec) {
+        trace!("hashing {:?}", spec);
+        self.pending.insert(spec.clone(), Some(Vec::new()));
+        let repo_root = self.repo_root.clone();
+        let scm = self.scm.clone();
+        let hashed_tx = self.hashed_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = scm.get_package_file_hashes(&repo_root, &spec.package_path, &spec.inputs);
+            // The tracker only goes away when it is shutting down
+            let _ = hashed_tx.blocking_send((spec, result));
+        });
+    }
+
+    fn handle_hashed(&mut self, spec: HashSpec, result: Result<GitHashes, turborepo_scm::Error>) {
+        let Some(changed_paths) = self.pending.remove(&spec) else {
+            return;
+        };
+        let hashes = match result {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                debug!("failed to hash {:?}: {}", spec, e);
+                return;
+            }
+        };
+        let Some(changed_paths) = changed_paths else {
+            // We missed events while hashing, these hashes might be stale
+            return;
+        };
+        let mut entry = Entry {
+            hashes,
+            inputs: compile_inputs(&spec.inputs),
+        };
+        // Files may have changed after they were hashed
+        for path in &changed_paths {
+            if !entry.update(
+                &self.repo_root,
+                &spec,
+                &self.scm,
+                self.git_ignore.as_ref(),
+                path,
+            ) {
+                return;
+            }
+        }
+        self.entries.insert(spec, entry);
+    }
+
+    fn handle_file_event(&mut self, event: Event) {
+        // Reading files while hashing them shouldn't trigger any work
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            let Ok(path) = AbsoluteSystemPathBuf::try_from(path) else {
+                continue;
+            };
+            self.handle_path_change(path);
+        }
+    }
+
+    fn handle_path_change(&mut self, path: AbsoluteSystemPathBuf) {
+        let Ok(repo_relative_path) = self.repo_root.anchor(&path) else {
+            // irrelevant filesystem update
+            return;
+        };
+        let mut components = repo_relative_path.components();
+        let first = components.next().map(|c| c.as_str());
+        let second = components.next().map(|c| c.as_str());
+        // Changes to git's internal state and to our own cookies don't change
+        // the contents of any package
+        if first == Some(".git") || (first == Some(".turbo") && second == Some("cookies")) {
+            return;
+        }
+        if path.as_path().file_name() == Some(".gitignore") {
+            debug!("{} changed, clearing file hashes", repo_relative_path);
+            self.clear();
+            return;
+        }
+
+        for changed_paths in self.pending.values_mut().flatten() {
+            changed_paths.push(path.clone());
+        }
+        let Self {
+            repo_root,
+            scm,
+            git_ignore,
+            entries,
+            ..
+        } = self;
+        entries
+            .retain(|spec, entry| entry.update(repo_root, spec, scm, git_ignore.as_ref(), &path));
+    }
+
+    /// on_error takes the conservative approach of considering everything
+    /// changed in the event of any error related to filewatching
+    fn on_error(&mut self, err: &dyn std::error::Error) {
+        warn!(
+            "encountered filewatching error, flushing all file hashes: {}",
+            err
+        );
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        for changed_paths in self.pending.values_mut() {
+            *changed_paths = None;
+        }
+    }
+}
+
+impl Entry {
+    /// Updates the hashes after `path` changed. Returns false if the change
+    /// can't be applied and the entry needs to be rebuilt.
+    fn update(
+        &mut self,
+        repo_root: &AbsoluteSystemPath,
+        spec: &HashSpec,
+        scm: &SCM,
+        git_ignore: Option<&GitIgnore>,
+        path: &AbsoluteSystemPath,
+    ) -> bool {
+        let package_path = repo_root.resolve(&spec.package_path);
+        let Ok(relative_path) = package_path.anchor(path) else {
+            // Not part of this package
+            return true;
+        };
+        let relative_path = relative_path.to_unix();
+
+        // Rehash the files we know about, this handles modifications and
+        // deletions of both files and directories
+        let is_known_file = self.hashes.contains_key(&relative_path);
+        let known_files = if is_known_file {
+            vec![relative_path.clone()]
+        } else if path.as_std_path().is_file() {
+            Vec::new()
+        } else {
+            self.hashes
+                .keys()
+                .filter(|file| file.strip_prefix(&relative_path).is_ok())
+                .cloned()
+                .collect()
+        };
+        for file in &known_files {
+            let mut hashes = match scm.hash_existing_of(
+                &package_path,
+                std::iter::once(file.to_anchored_system_path_buf()),
+            ) {
+                Ok(hashes) => hashes,
+                Err(e) => {
+                    debug!("failed to rehash {}: {}", file, e);
+                    return false;
+                }
+            };
+            match hashes.remove(file) {
+                Some(hash) => self.hashes.insert(file.clone(), hash),
+                None => self.hashes.remove(file),
+            };
+        }
+        if is_known_file || !path.exists() {
+            return true;
+        }
+
+        // Something we weren't tracking appeared, check if it could be one of
+        // the package's files
+        let is_candidate = match &self.inputs {
+            None => git_ignore.map_or(true, |git_ignore| !git_ignore.is_ignored(path)),
+            // We can't tell what a new directory contains, so we assume that it
+            // contains inputs
+            Some(inputs) => path.as_std_path().is_dir() || inputs.is_match(relative_path.as_str()),
+        };
+        !is_candidate
+    }
+}
+
+/// Compiles the globs of files that can be picked up by `inputs`. Exclusions
+/// are ignored as matching too many files only costs us a rehash.
+fn compile_inputs(inputs: &[String]) -> Option<Any<'static>> {
+    if inputs.is_empty() {
+        return None;
+    }
+    let globs = inputs
+        .iter()
+        .filter(|input| !input.starts_with('!'))
+        .map(|input| input.trim_start_matches('/'))
+        // Both of these are always included by `get_package_file_hashes`
+        .chain(["package.json", "turbo.json"])
+        .map(|input| Glob::new(input).map(|glob| glob.into_owned()))
+        .collect::<Result<Vec<_>, _>>();
+    match globs.and_then(wax::any) {
+        Ok(inputs) => Some(inputs),
+        Err(e) => {
+            // Fall back to treating every new file as a possible input
+            debug!("failed to compile inputs {:?}: {}", inputs, e);
+            Some(wax::any([Glob::new("**").expect("valid glob")]).expect("valid glob"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+    use turborepo_scm::SCM;
+
+    use super::{HashSpec, HashWatcher};
+    use crate::{cookie_jar::CookieJar, FileSystemWatcher};
+
+    fn temp_dir() -> (AbsoluteSystemPathBuf, tempfile::TempDir) {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = AbsoluteSystemPathBuf::try_from(tmp.path())
+            .unwrap()
+            .to_realpath()
+            .unwrap();
+        (path, tmp)
+    }
+
+    fn setup(repo_root: &AbsoluteSystemPathBuf) {
+        // Directory layout:
+        // <repo_root>/
+        //   .gitignore
+        //   my-pkg/
+        //     package.json
+        //     src/
+        //       index.js
+        repo_root
+            .join_component(".gitignore")
+            .create_with_contents("dist\n.turbo\n")
+            .unwrap();
+        let pkg_path = repo_root.join_component("my-pkg");
+        pkg_path.join_component("src").create_dir_all().unwrap();
+        pkg_path
+            .join_component("package.json")
+            .create_with_contents("{}")
+            .unwrap();
+        pkg_path
+            .join_components(&["src", "index.js"])
+            .create_with_contents("console.log('hello')")
+            .unwrap();
+        for args in [
+            &["init", "."][..],
+            &["config", "--local", "user.name", "test"],
+            &["config", "--local", "user.email", "test@example.com"],
+            &["add", "."],
+            &["commit", "-m", "initial"],
+        ] {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(repo_root)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+        }
+    }
+
+    async fn get_file_hashes(
+        hash_watcher: &HashWatcher,
+        spec: &HashSpec,
+    ) -> turborepo_scm::package_deps::GitHashes {
+        // Hashing happens in the background after the first request
+        for _ in 0..50 {
+            let mut hashes = hash_watcher
+                .get_file_hashes(vec![spec.clone()])
+                .await
+                .unwrap();
+            if let Some(hashes) = hashes.pop().flatten() {
+                return hashes;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("timed out waiting for hashes of {:?}", spec);
+    }
+
+    #[tokio::test]
+    async fn test_file_hashes_track_changes() {
+        let (repo_root, _tmp_dir) = temp_dir();
+        setup(&repo_root);
+
+        let watcher = FileSystemWatcher::new_with_default_cookie_dir(&repo_root)
+            .await
+            .unwrap();
+        let cookie_jar = CookieJar::new(
+            watcher.cookie_dir(),
+            Duration::from_secs(2),
+            watcher.subscribe(),
+        );
+        let hash_watcher = HashWatcher::new(repo_root.clone(), cookie_jar, watcher.subscribe());
+
+        let package_path = AnchoredSystemPathBuf::from_raw("my-pkg").unwrap();
+        let scm = SCM::new(&repo_root);
+        let specs = [
+            HashSpec {
+                package_path: package_path.clone(),
+                inputs: vec![],
+            },
+            HashSpec {
+                package_path: package_path.clone(),
+                inputs: vec!["src/**".to_string()],
+            },
+        ];
+        let expected = |spec: &HashSpec| {
+            scm.get_package_file_hashes(&repo_root, &spec.package_path, &spec.inputs)
+                .unwrap()
+        };
+
+        for spec in &specs {
+            assert_eq!(get_file_hashes(&hash_watcher, spec).await, expected(spec));
+        }
+
+        // Modify a file, add a file, and write an ignored output
+        let pkg_path = repo_root.join_component("my-pkg");
+        pkg_path
+            .join_components(&["src", "index.js"])
+            .create_with_contents("console.log('goodbye')")
+            .unwrap();
+        pkg_path
+            .join_components(&["src", "util.js"])
+            .create_with_contents("export {}")
+            .unwrap();
+        pkg_path.join_component("dist").create_dir_all().unwrap();
+        pkg_path
+            .join_components(&["dist", "index.js"])
+            .create_with_contents("bundled")
+            .unwrap();
+        for spec in &specs {
+            let hashes = get_file_hashes(&hash_watcher, spec).await;
+            assert!(hashes.contains_key(&RelativeUnixPathBuf::new("src/util.js").unwrap()));
+            assert_eq!(hashes, expected(spec));
+        }
+
+        // Delete a file
+        pkg_path
+            .join_components(&["src", "util.js"])
+            .remove_file()
+            .unwrap();
+        for spec in &specs {
+            assert_eq!(get_file_hashes(&hash_watcher, spec).await, expected(spec));
+        }
+    }
+}
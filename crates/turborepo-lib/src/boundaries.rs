@@ -0,0 +1,228 @@
+//! Workspace tags and the boundaries between them. Workspaces are tagged in
+//! their package.json or turbo.json, and the root turbo.json declares which
+//! tags a tagged workspace can depend on.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+use turbopath::AbsoluteSystemPath;
+use turborepo_repository::package_graph::{PackageGraph, WorkspaceName};
+
+use crate::config::{self, TurboJson};
+
+/// The dependencies allowed for workspaces with a tag
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BoundaryRule {
+    // If set, every dependency needs at least one of these tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+    // Dependencies can't have any of these tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
+}
+
+pub type WorkspaceTags = HashMap<WorkspaceName, BTreeSet<String>>;
+
+/// A dependency between workspaces that breaks the rule for one of the
+/// dependent's tags
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Violation {
+    pub dependent: WorkspaceName,
+    pub dependency: WorkspaceName,
+    pub tag: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ViolationKind {
+    /// The dependency has a denied tag
+    Denied(String),
+    /// The dependency has none of the allowed tags
+    NotAllowed(Vec<String>),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} depends on {}", self.dependent, self.dependency)?;
+        match &self.kind {
+            ViolationKind::Denied(denied) => write!(
+                f,
+                ", but \"{}\" workspaces can't depend on \"{}\" workspaces",
+                self.tag, denied
+            ),
+            ViolationKind::NotAllowed(allowed) => write!(
+                f,
+                ", but \"{}\" workspaces can only depend on workspaces tagged {}",
+                self.tag,
+                allowed
+                    .iter()
+                    .map(|tag| format!("\"{tag}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Collects the tags of every workspace from its package.json and, if it has
+/// one, its turbo.json
+pub fn workspace_tags(
+    repo_root: &AbsoluteSystemPath,
+    pkg_graph: &PackageGraph,
+) -> Result<WorkspaceTags, config::Error> {
+    let mut workspace_tags = HashMap::new();
+    for (name, info) in pkg_graph.workspaces() {
+        let mut tags = info
+            .package_json
+            .tags
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let workspace_dir = repo_root.resolve(info.package_path());
+        match TurboJson::load(&workspace_dir, &info.package_json, false) {
+            Ok(turbo_json) => tags.extend(turbo_json.tags),
+            Err(config::Error::NoTurboJSON) => {}
+            Err(e) => return Err(e),
+        }
+        workspace_tags.insert(name.to_owned(), tags);
+    }
+
+    Ok(workspace_tags)
+}
+
+/// Checks every dependency between workspaces against the rules for the
+/// dependent's tags. Violations are sorted by dependent and dependency.
+pub fn check<'a>(
+    edges: impl Iterator<Item = (&'a WorkspaceName, &'a WorkspaceName)>,
+    tags: &WorkspaceTags,
+    rules: &BTreeMap<String, BoundaryRule>,
+) -> Vec<Violation> {
+    let untagged = BTreeSet::new();
+    let mut violations = Vec::new();
+    for (dependent, dependency) in edges {
+        let dependency_tags = tags.get(dependency).unwrap_or(&untagged);
+        for tag in tags.get(dependent).into_iter().flatten() {
+            let Some(rule) = rules.get(tag) else {
+                continue;
+            };
+            let violation = |kind| Violation {
+                dependent: dependent.clone(),
+                dependency: dependency.clone(),
+                tag: tag.clone(),
+                kind,
+            };
+
+            for denied in rule.deny.iter().flatten() {
+                if dependency_tags.contains(denied) {
+                    violations.push(violation(ViolationKind::Denied(denied.clone())));
+                }
+            }
+            if let Some(allowed) = &rule.allow {
+                if !allowed
+                    .iter()
+                    .any(|allowed_tag| dependency_tags.contains(allowed_tag))
+                {
+                    violations.push(violation(ViolationKind::NotAllowed(allowed.clone())));
+                }
+            }
+        }
+    }
+
+    violations.sort();
+    violations
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tags(tags: &[(&str, &[&str])]) -> WorkspaceTags {
+        tags.iter()
+            .map(|(name, tags)| {
+                (
+                    WorkspaceName::from(*name),
+                    tags.iter().map(|tag| tag.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn rules(value: serde_json::Value) -> BTreeMap<String, BoundaryRule> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn edges(edges: &[(&str, &str)]) -> Vec<(WorkspaceName, WorkspaceName)> {
+        edges
+            .iter()
+            .map(|(from, to)| (WorkspaceName::from(*from), WorkspaceName::from(*to)))
+            .collect()
+    }
+
+    #[test]
+    fn test_denied_tags() {
+        let tags = tags(&[
+            ("ui-button", &["ui"]),
+            ("ui-theme", &["ui"]),
+            ("server-db", &["server"]),
+        ]);
+        let rules = rules(json!({ "ui": { "deny": ["server"] } }));
+        let edges = edges(&[
+            ("ui-button", "ui-theme"),
+            ("ui-button", "server-db"),
+            ("server-db", "ui-theme"),
+        ]);
+
+        let violations = check(edges.iter().map(|(a, b)| (a, b)), &tags, &rules);
+        assert_eq!(
+            violations,
+            vec![Violation {
+                dependent: WorkspaceName::from("ui-button"),
+                dependency: WorkspaceName::from("server-db"),
+                tag: "ui".to_string(),
+                kind: ViolationKind::Denied("server".to_string()),
+            }]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "ui-button depends on server-db, but \"ui\" workspaces can't depend on \"server\" \
+             workspaces"
+        );
+    }
+
+    #[test]
+    fn test_allowed_tags() {
+        let tags = tags(&[
+            ("web", &["app"]),
+            ("ui-button", &["ui"]),
+            ("server-db", &["server"]),
+            ("utils", &[]),
+        ]);
+        let rules = rules(json!({ "app": { "allow": ["ui"] } }));
+        let edges = edges(&[
+            ("web", "ui-button"),
+            ("web", "utils"),
+            ("web", "server-db"),
+            // Workspaces without a rule can depend on anything
+            ("ui-button", "server-db"),
+        ]);
+
+        let violations = check(edges.iter().map(|(a, b)| (a, b)), &tags, &rules);
+        assert_eq!(
+            violations
+                .iter()
+                .map(|violation| violation.dependency.to_string())
+                .collect::<Vec<_>>(),
+            vec!["server-db", "utils"]
+        );
+        assert_eq!(
+            violations[0].kind,
+            ViolationKind::NotAllowed(vec!["ui".to_string()])
+        );
+    }
+}
@@ -6,7 +6,7 @@ use turbopath::AbsoluteSystemPathBuf;
 use turborepo_repository::package_graph;
 
 use crate::{
-    commands::{bin, generate, prune},
+    commands::{bin, boundaries, cache, generate, prune, watch, why},
     daemon::DaemonError,
     rewrite_json::RewriteError,
     run,
@@ -19,6 +19,10 @@ pub enum Error {
     #[error("{0}")]
     Bin(#[from] bin::Error, #[backtrace] backtrace::Backtrace),
     #[error(transparent)]
+    Boundaries(#[from] boundaries::Error),
+    #[error(transparent)]
+    Cache(#[from] cache::Error),
+    #[error(transparent)]
     Path(#[from] turbopath::PathError),
     #[error("at least one task must be specified")]
     NoTasks(#[backtrace] backtrace::Backtrace),
@@ -69,4 +73,8 @@ pub enum Error {
     Run(#[from] run::Error),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    Watch(#[from] watch::Error),
+    #[error(transparent)]
+    Why(#[from] why::Error),
 }
@@ -1,4 +1,4 @@
-use std::{backtrace, backtrace::Backtrace, env, io, mem, process};
+use std::{backtrace, backtrace::Backtrace, env, io, mem, process, time::Duration};
 
 use camino::{Utf8Path, Utf8PathBuf};
 use clap::{
@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, error};
 use turbopath::AbsoluteSystemPathBuf;
 use turborepo_api_client::AnonAPIClient;
+use turborepo_cache::CacheLimits;
 use turborepo_repository::inference::{RepoMode, RepoState};
 use turborepo_telemetry::{
     events::{
@@ -24,7 +25,8 @@ use turborepo_ui::UI;
 
 use crate::{
     commands::{
-        bin, daemon, generate, info, link, login, logout, prune, telemetry, unlink, CommandBase,
+        bin, boundaries, cache, daemon, generate, info, link, login, logout, prune, telemetry,
+        unlink, watch, why, CommandBase,
     },
     get_version,
     tracing::TurboSubscriber,
@@ -81,6 +83,25 @@ pub enum DryRunMode {
     Json,
 }
 
+/// The format `--summarize` writes the run summary in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SummarizeMode {
+    #[value(name = "true", alias = "json")]
+    Json,
+    #[value(name = "false")]
+    Disabled,
+    Junit,
+    Trace,
+}
+
+// The Go codepath can only write JSON summaries so it is only told whether to
+// write one
+impl Serialize for SummarizeMode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(!matches!(self, SummarizeMode::Disabled))
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
 pub enum EnvMode {
     #[default]
@@ -89,6 +110,21 @@ pub enum EnvMode {
     Strict,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckOutputsMode {
+    Warn,
+    Error,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UIMode {
+    #[default]
+    Stream,
+    Tui,
+}
+
 #[derive(Parser, Clone, Default, Debug, PartialEq, Serialize)]
 #[clap(author, about = "The build system that makes ship happen", long_about = None)]
 #[clap(disable_help_subcommand = true)]
@@ -211,6 +247,35 @@ pub enum DaemonCommand {
     Clean,
 }
 
+#[derive(Subcommand, Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "command")]
+pub enum CacheCommand {
+    /// Lists the artifacts in the cache
+    Ls,
+    /// Shows the metadata of an artifact and the files in its archive
+    Show { hash: String },
+    /// Checks that an artifact can be read and, if signature verification is
+    /// enabled, that the remote artifact's signature is valid
+    Verify { hash: String },
+    /// Removes an artifact from the cache
+    Rm { hash: String },
+    /// Removes every artifact from the cache
+    Clear,
+    /// Removes the least recently used artifacts until the cache is within
+    /// its configured limits
+    Prune {
+        /// Maximum combined size of all artifacts in bytes
+        #[clap(long, value_name = "BYTES")]
+        max_size: Option<u64>,
+        /// Maximum time in seconds since an artifact was last used
+        #[clap(long, value_name = "SECONDS")]
+        max_age: Option<u64>,
+        /// Maximum number of artifacts
+        #[clap(long, value_name = "COUNT")]
+        max_entries: Option<usize>,
+    },
+}
+
 #[derive(Subcommand, Copy, Clone, Debug, Serialize, PartialEq)]
 #[serde(tag = "command")]
 pub enum TelemetryCommand {
@@ -265,7 +330,9 @@ impl Args {
                     run_args.single_package = is_single_package
                 }
 
-                if let Some(Command::Run(ref mut run_args)) = args.command {
+                if let Some(Command::Run(ref mut run_args) | Command::Watch(ref mut run_args)) =
+                    args.command
+                {
                     run_args.single_package = is_single_package;
                 }
 
@@ -314,7 +381,9 @@ impl Args {
 
     pub fn get_tasks(&self) -> &[String] {
         match &self.command {
-            Some(Command::Run(box RunArgs { tasks, .. })) => tasks,
+            Some(
+                Command::Run(box RunArgs { tasks, .. }) | Command::Watch(box RunArgs { tasks, .. }),
+            ) => tasks,
             _ => self
                 .run_args
                 .as_ref()
@@ -333,6 +402,22 @@ pub enum Command {
     // them as `{ "Bin": {} }` instead of as `"Bin"`.
     /// Get the path to the Turbo binary
     Bin {},
+    /// Check dependencies between workspaces against the boundaries in
+    /// turbo.json
+    ///
+    /// Workspaces are tagged with `tags` in their package.json or turbo.json,
+    /// and the `boundaries` in the root turbo.json declare which tags each
+    /// tag can depend on.
+    Boundaries {},
+    /// Manage the local filesystem cache
+    Cache {
+        /// Override the filesystem cache directory.
+        #[clap(long, global = true)]
+        cache_dir: Option<Utf8PathBuf>,
+        #[clap(subcommand)]
+        #[serde(flatten)]
+        command: CacheCommand,
+    },
     /// Generate the autocompletion script for the specified shell
     #[serde(skip)]
     Completion { shell: Shell },
@@ -382,6 +467,10 @@ pub enum Command {
         // We output turbo info as json. Currently just for internal testing
         #[clap(long)]
         json: bool,
+        /// Show the chain of dependencies that causes an external package to
+        /// be installed for the workspace
+        #[clap(long, requires = "workspace", value_name = "PACKAGE")]
+        why: Option<String>,
     },
     /// Link your local directory to a Vercel organization and enable remote
     /// caching.
@@ -416,6 +505,10 @@ pub enum Command {
         docker: bool,
         #[clap(long = "out-dir", default_value_t = String::from("out"), value_parser)]
         output_dir: String,
+        /// Show the chain of dependencies that causes an external package to
+        /// be included in the pruned lockfile
+        #[clap(long, value_name = "PACKAGE")]
+        why: Option<String>,
     },
 
     /// Run tasks across projects in your monorepo
@@ -435,6 +528,29 @@ pub enum Command {
         #[clap(long, value_enum, default_value_t = LinkTarget::RemoteCache)]
         target: LinkTarget,
     },
+    /// Watch for file changes and re-run the tasks they affect
+    ///
+    /// Persistent tasks are only restarted when the workspace structure
+    /// changes, unless they are marked as `interruptible` in which case they
+    /// are restarted after every change. Caching is disabled while watching.
+    Watch(Box<RunArgs>),
+    /// Explain why a task or workspace is part of a run
+    ///
+    /// Prints the chains of tasks from the requested tasks to the target,
+    /// along with the `dependsOn` entry that created each step. Only the
+    /// first `--max-paths` chains are printed.
+    Why(Box<WhyArgs>),
+}
+
+#[derive(Parser, Clone, Debug, Default, Serialize, PartialEq)]
+pub struct WhyArgs {
+    /// A task id (e.g. web#build) or a workspace name
+    pub target: String,
+    /// The maximum number of paths to print
+    #[clap(long, default_value_t = 10)]
+    pub max_paths: usize,
+    #[clap(flatten)]
+    pub run_args: RunArgs,
 }
 
 #[derive(Parser, Clone, Debug, Default, Serialize, PartialEq)]
@@ -527,6 +643,11 @@ pub struct RunArgs {
     /// turbo's documentation https://turbo.build/repo/docs/reference/command-line-reference/run#--filter
     #[clap(short = 'F', long, action = ArgAction::Append)]
     pub filter: Vec<String>,
+    /// Only run packages that changed in this CI build, and their dependents.
+    /// Changes are compared against the branch a pull request targets, or
+    /// the previous commit when the CI provider doesn't expose one
+    #[clap(long, conflicts_with_all = ["filter", "scope", "since"])]
+    pub affected: bool,
     /// Ignore the existing cache (to force execution)
     #[clap(long, env = "TURBO_FORCE", default_missing_value = "true")]
     pub force: Option<Option<bool>>,
@@ -549,6 +670,15 @@ pub struct RunArgs {
     /// "globalPassThroughEnv" in turbo.json. (default infer)
     #[clap(long = "env-mode", default_value = "infer", num_args = 0..=1, default_missing_value = "infer")]
     pub env_mode: EnvMode,
+    /// Record the hash inputs of every task and, when a task misses the
+    /// cache, print which inputs changed since its last cached run.
+    #[clap(long)]
+    pub explain_miss: bool,
+    /// Check that tasks only write files covered by their outputs and that
+    /// every declared output is produced. Use "warn" to report problems or
+    /// "error" to also fail the task. (default warn)
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "warn")]
+    pub check_outputs: Option<CheckOutputsMode>,
     /// Files to ignore when calculating changed files (i.e. --since).
     /// Supports globs.
     #[clap(long)]
@@ -591,6 +721,12 @@ pub struct RunArgs {
     /// turbo decide based on its own heuristics. (default auto)
     #[clap(long, env = "TURBO_LOG_ORDER", value_enum, default_value_t = LogOrder::Auto)]
     pub log_order: LogOrder,
+    /// Set how the run is displayed. Use "stream" to print task output
+    /// as it happens. Use "tui" for a full screen interface with a task list
+    /// and the output of the selected task, which falls back to "stream"
+    /// when not attached to a terminal. (default stream)
+    #[clap(long, env = "TURBO_UI", value_enum, default_value_t = UIMode::Stream)]
+    pub ui: UIMode,
     /// Only executes the tasks specified, does not execute parent tasks.
     #[clap(long)]
     pub only: bool,
@@ -626,9 +762,10 @@ pub struct RunArgs {
     /// to identify which packages have changed.
     #[clap(long)]
     pub since: Option<String>,
-    /// Generate a summary of the turbo run
-    #[clap(long, env = "TURBO_RUN_SUMMARY", default_missing_value = "true")]
-    pub summarize: Option<Option<bool>>,
+    /// Generate a summary of the turbo run. Use "junit" to write it as JUnit
+    /// XML or "trace" to write it as a Chrome trace
+    #[clap(long, env = "TURBO_RUN_SUMMARY", num_args = 0..=1, default_missing_value = "true")]
+    pub summarize: Option<SummarizeMode>,
 
     /// Use "none" to remove prefixes from task logs. Use "task" to get task id
     /// prefixing. Use "auto" to let turbo decide how to prefix the logs
@@ -756,7 +893,7 @@ pub async fn run(
     };
 
     // Set some run flags if we have the data and are executing a Run
-    if let Command::Run(run_args) = &mut command {
+    if let Command::Run(run_args) | Command::Watch(run_args) = &mut command {
         // Don't overwrite the flag if it's already been set for whatever reason
         run_args.single_package = run_args.single_package
             || repo_state
@@ -818,6 +955,50 @@ pub async fn run(
 
             Ok(Payload::Rust(Ok(0)))
         }
+        Command::Boundaries { .. } => {
+            CommandEventBuilder::new("boundaries")
+                .with_parent(&root_telemetry)
+                .track_call();
+            let base = CommandBase::new(cli_args.clone(), repo_root, version, ui);
+            let exit_code = boundaries::boundaries(&base).await?;
+
+            Ok(Payload::Rust(Ok(exit_code)))
+        }
+        Command::Cache { cache_dir, command } => {
+            CommandEventBuilder::new("cache")
+                .with_parent(&root_telemetry)
+                .track_call();
+            let cache_dir = cache_dir.clone();
+            let command = command.clone();
+            let base = CommandBase::new(cli_args, repo_root, version, ui);
+            let cache_dir = cache_dir.as_deref();
+
+            match command {
+                CacheCommand::Ls => cache::ls(&base, cache_dir)?,
+                CacheCommand::Show { hash } => cache::show(&base, cache_dir, &hash)?,
+                CacheCommand::Verify { hash } => {
+                    if !cache::verify(&base, cache_dir, &hash).await? {
+                        return Ok(Payload::Rust(Ok(1)));
+                    }
+                }
+                CacheCommand::Rm { hash } => cache::rm(&base, cache_dir, &hash)?,
+                CacheCommand::Clear => cache::clear(&base, cache_dir)?,
+                CacheCommand::Prune {
+                    max_size,
+                    max_age,
+                    max_entries,
+                } => {
+                    let overrides = CacheLimits {
+                        max_size,
+                        max_age: max_age.map(Duration::from_secs),
+                        max_entries,
+                    };
+                    cache::prune(&base, cache_dir, overrides)?;
+                }
+            }
+
+            Ok(Payload::Rust(Ok(0)))
+        }
         #[allow(unused_variables)]
         Command::Daemon { command, idle_time } => {
             CommandEventBuilder::new("daemon")
@@ -866,14 +1047,19 @@ pub async fn run(
             telemetry::configure(command, &mut base, child_event);
             Ok(Payload::Rust(Ok(0)))
         }
-        Command::Info { workspace, json } => {
+        Command::Info {
+            workspace,
+            json,
+            why,
+        } => {
             CommandEventBuilder::new("info")
                 .with_parent(&root_telemetry)
                 .track_call();
             let json = *json;
             let workspace = workspace.clone();
+            let why = why.clone();
             let mut base = CommandBase::new(cli_args, repo_root, version, ui);
-            info::run(&mut base, workspace.as_deref(), json).await?;
+            info::run(&mut base, workspace.as_deref(), json, why.as_deref()).await?;
 
             Ok(Payload::Rust(Ok(0)))
         }
@@ -980,11 +1166,43 @@ pub async fn run(
                 Ok(Payload::Rust(Ok(exit_code)))
             }
         }
+        Command::Watch(args) => {
+            CommandEventBuilder::new("watch")
+                .with_parent(&root_telemetry)
+                .track_call();
+            if args.tasks.is_empty() {
+                return Err(Error::NoTasks(backtrace::Backtrace::capture()));
+            }
+
+            let base = CommandBase::new(cli_args.clone(), repo_root, version, ui);
+            let exit_code = watch::watch(base).await?;
+            Ok(Payload::Rust(Ok(exit_code)))
+        }
+        Command::Why(args) => {
+            CommandEventBuilder::new("why")
+                .with_parent(&root_telemetry)
+                .track_call();
+            let WhyArgs {
+                target,
+                max_paths,
+                run_args,
+            } = args.as_ref().clone();
+            if run_args.tasks.is_empty() {
+                return Err(Error::NoTasks(backtrace::Backtrace::capture()));
+            }
+
+            let mut args = cli_args.clone();
+            args.command = Some(Command::Run(Box::new(run_args)));
+            let base = CommandBase::new(args, repo_root, version, ui);
+            why::why(&base, &target, max_paths).await?;
+            Ok(Payload::Rust(Ok(0)))
+        }
         Command::Prune {
             scope,
             scope_arg,
             docker,
             output_dir,
+            why,
         } => {
             let event = CommandEventBuilder::new("prune").with_parent(&root_telemetry);
             event.track_call();
@@ -995,9 +1213,18 @@ pub async fn run(
                 .unwrap_or_default();
             let docker = *docker;
             let output_dir = output_dir.clone();
+            let why = why.clone();
             let base = CommandBase::new(cli_args, repo_root, version, ui);
             let event_child = event.child();
-            prune::prune(&base, &scope, docker, &output_dir, event_child).await?;
+            prune::prune(
+                &base,
+                &scope,
+                docker,
+                &output_dir,
+                why.as_deref(),
+                event_child,
+            )
+            .await?;
             Ok(Payload::Rust(Ok(0)))
         }
         Command::Completion { shell } => {
@@ -1084,7 +1311,8 @@ mod test {
     use anyhow::Result;
 
     use crate::cli::{
-        Args, Command, DryRunMode, EnvMode, LogOrder, LogPrefix, OutputLogsMode, RunArgs, Verbosity,
+        Args, CacheCommand, CheckOutputsMode, Command, DryRunMode, EnvMode, LogOrder, LogPrefix,
+        OutputLogsMode, RunArgs, SummarizeMode, UIMode, Verbosity,
     };
 
     #[test_case::test_case(
@@ -1283,6 +1511,72 @@ mod test {
             ..Args::default()
         }
 	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--check-outputs"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                check_outputs: Some(CheckOutputsMode::Warn),
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--check-outputs=error"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                check_outputs: Some(CheckOutputsMode::Error),
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--ui", "tui"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                ui: UIMode::Tui,
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--summarize"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                summarize: Some(SummarizeMode::Json),
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--summarize=junit"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                summarize: Some(SummarizeMode::Junit),
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
+    #[test_case::test_case(
+		&["turbo", "run", "build", "--affected"],
+        Args {
+            command: Some(Command::Run(Box::new(RunArgs {
+                tasks: vec!["build".to_string()],
+                affected: true,
+                ..get_default_run_args()
+            }))),
+            ..Args::default()
+        }
+	)]
     #[test_case::test_case(
 		&["turbo", "run", "build", "--filter", "water", "--filter", "earth", "--filter", "fire", "--filter", "air"],
         Args {
@@ -1719,6 +2013,17 @@ mod test {
         .test();
     }
 
+    #[test]
+    fn test_parse_boundaries() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "boundaries"]).unwrap(),
+            Args {
+                command: Some(Command::Boundaries {}),
+                ..Args::default()
+            }
+        );
+    }
+
     #[test]
     fn test_parse_login() {
         assert_eq!(
@@ -1806,6 +2111,114 @@ mod test {
         .test();
     }
 
+    #[test]
+    fn test_parse_watch() {
+        let default_watch = Command::Watch(Box::new(RunArgs {
+            tasks: vec!["build".to_string()],
+            ..get_default_run_args()
+        }));
+
+        assert_eq!(
+            Args::try_parse_from(["turbo", "watch", "build"]).unwrap(),
+            Args {
+                command: Some(default_watch.clone()),
+                ..Args::default()
+            }
+        );
+
+        CommandTestCase {
+            command: "watch",
+            command_args: vec![vec!["build"], vec!["--filter", "web"]],
+            global_args: vec![vec!["--cwd", "../examples/with-yarn"]],
+            expected_output: Args {
+                command: Some(Command::Watch(Box::new(RunArgs {
+                    tasks: vec!["build".to_string()],
+                    filter: vec!["web".to_string()],
+                    ..get_default_run_args()
+                }))),
+                cwd: Some(Utf8PathBuf::from("../examples/with-yarn")),
+                ..Args::default()
+            },
+        }
+        .test();
+    }
+
+    #[test]
+    fn test_parse_why() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "why", "web#lint", "build", "--filter", "web"]).unwrap(),
+            Args {
+                command: Some(Command::Why(Box::new(WhyArgs {
+                    target: "web#lint".to_string(),
+                    max_paths: 10,
+                    run_args: RunArgs {
+                        tasks: vec!["build".to_string()],
+                        filter: vec!["web".to_string()],
+                        ..get_default_run_args()
+                    },
+                }))),
+                ..Args::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_cache() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "cache", "prune"]).unwrap(),
+            Args {
+                command: Some(Command::Cache {
+                    cache_dir: None,
+                    command: CacheCommand::Prune {
+                        max_size: None,
+                        max_age: None,
+                        max_entries: None,
+                    },
+                }),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "cache",
+                "prune",
+                "--max-entries",
+                "10",
+                "--cache-dir",
+                "foobar"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Cache {
+                    cache_dir: Some(Utf8PathBuf::from("foobar")),
+                    command: CacheCommand::Prune {
+                        max_size: None,
+                        max_age: None,
+                        max_entries: Some(10),
+                    },
+                }),
+                ..Args::default()
+            }
+        );
+
+        assert_eq!(
+            Args::try_parse_from(["turbo", "cache", "show", "abc123"]).unwrap(),
+            Args {
+                command: Some(Command::Cache {
+                    cache_dir: None,
+                    command: CacheCommand::Show {
+                        hash: "abc123".to_string()
+                    },
+                }),
+                ..Args::default()
+            }
+        );
+
+        assert!(Args::try_parse_from(["turbo", "cache", "rm"]).is_err());
+    }
+
     #[test]
     fn test_parse_prune() {
         let default_prune = Command::Prune {
@@ -1813,6 +2226,7 @@ mod test {
             scope_arg: Some(vec!["foo".into()]),
             docker: false,
             output_dir: "out".to_string(),
+            why: None,
         };
 
         assert_eq!(
@@ -1843,6 +2257,7 @@ mod test {
                     scope_arg: None,
                     docker: false,
                     output_dir: "out".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             }
@@ -1856,6 +2271,7 @@ mod test {
                     scope_arg: Some(vec!["foo".to_string(), "bar".to_string()]),
                     docker: false,
                     output_dir: "out".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             }
@@ -1869,6 +2285,7 @@ mod test {
                     scope_arg: Some(vec!["foo".into()]),
                     docker: true,
                     output_dir: "out".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             }
@@ -1882,6 +2299,7 @@ mod test {
                     scope_arg: Some(vec!["foo".into()]),
                     docker: false,
                     output_dir: "dist".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             }
@@ -1897,6 +2315,7 @@ mod test {
                     scope_arg: Some(vec!["foo".into()]),
                     docker: true,
                     output_dir: "dist".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             },
@@ -1913,6 +2332,7 @@ mod test {
                     scope_arg: Some(vec!["foo".into()]),
                     docker: true,
                     output_dir: "dist".to_string(),
+                    why: None,
                 }),
                 cwd: Some(Utf8PathBuf::from("../examples/with-yarn")),
                 ..Args::default()
@@ -1934,11 +2354,26 @@ mod test {
                     scope_arg: None,
                     docker: true,
                     output_dir: "dist".to_string(),
+                    why: None,
                 }),
                 ..Args::default()
             },
         }
         .test();
+
+        assert_eq!(
+            Args::try_parse_from(["turbo", "prune", "foo", "--why", "react"]).unwrap(),
+            Args {
+                command: Some(Command::Prune {
+                    scope: None,
+                    scope_arg: Some(vec!["foo".into()]),
+                    docker: false,
+                    output_dir: "out".to_string(),
+                    why: Some("react".to_string()),
+                }),
+                ..Args::default()
+            }
+        );
     }
 
     #[test]
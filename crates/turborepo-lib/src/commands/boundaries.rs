@@ -0,0 +1,73 @@
+//! Checks every dependency between workspaces against the `boundaries` rules
+//! in the root turbo.json
+use turborepo_repository::{
+    package_graph::{self, PackageGraph},
+    package_json::{self, PackageJson},
+};
+use turborepo_ui::{BOLD, GREY, UI};
+
+use super::CommandBase;
+use crate::{
+    boundaries::{self, Violation},
+    config::{self, TurboJson},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    PackageJson(#[from] package_json::Error),
+    #[error(transparent)]
+    PackageGraph(#[from] package_graph::Error),
+    #[error(transparent)]
+    Config(#[from] config::Error),
+}
+
+/// Returns the exit code, which is non-zero if any dependency breaks a rule
+pub async fn boundaries(base: &CommandBase) -> Result<i32, Error> {
+    let root_package_json = PackageJson::load(&base.repo_root.join_component("package.json"))?;
+    let root_turbo_json = TurboJson::load(&base.repo_root, &root_package_json, false)?;
+    let package_graph = PackageGraph::builder(&base.repo_root, root_package_json)
+        .build()
+        .await?;
+
+    let tags = boundaries::workspace_tags(&base.repo_root, &package_graph)?;
+    let violations = boundaries::check(
+        package_graph.dependency_edges(),
+        &tags,
+        &root_turbo_json.boundaries,
+    );
+    if violations.is_empty() {
+        println!(
+            "Checked {} workspace dependencies, no boundaries were crossed",
+            package_graph.dependency_edges().count()
+        );
+        return Ok(0);
+    }
+
+    for violation in &violations {
+        print_violation(base.ui, violation);
+    }
+    println!();
+    println!(
+        "{} boundary {} found",
+        violations.len(),
+        match violations.len() {
+            1 => "violation",
+            _ => "violations",
+        }
+    );
+
+    Ok(1)
+}
+
+fn print_violation(ui: UI, violation: &Violation) {
+    println!(
+        "{} {}",
+        ui.apply(BOLD.apply_to(format!(
+            "{} -> {}",
+            violation.dependent, violation.dependency
+        ))),
+        ui.apply(GREY.apply_to(format!("(tag \"{}\")", violation.tag)))
+    );
+    println!("  {violation}");
+}
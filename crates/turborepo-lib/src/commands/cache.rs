@@ -0,0 +1,319 @@
+use std::time::{Duration, SystemTime};
+
+use camino::Utf8Path;
+use turborepo_cache::{
+    cache_archive::CacheEntryKind,
+    fs::{FSCache, FSCacheArtifact},
+    http::{ArtifactVerification, HTTPCache},
+    CacheError, CacheLimits, CacheOpts, RemoteCacheOpts,
+};
+use turborepo_ui::{BOLD, BOLD_GREEN, BOLD_RED, GREY};
+
+use super::CommandBase;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Cache(#[from] CacheError),
+    #[error(transparent)]
+    Config(#[from] crate::config::Error),
+    #[error("artifact {0} not found in the cache")]
+    ArtifactNotFound(String),
+    #[error("invalid artifact hash {0}: hashes only contain hexadecimal digits")]
+    InvalidHash(String),
+}
+
+const SIZE_UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, SIZE_UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, SIZE_UNITS[unit])
+    }
+}
+
+// Hashes are used as file names in the cache directory, so anything else could
+// refer to a file outside of it
+fn check_hash(hash: &str) -> Result<(), Error> {
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::InvalidHash(hash.to_string()));
+    }
+
+    Ok(())
+}
+
+fn open_cache(base: &CommandBase, cache_dir: Option<&Utf8Path>) -> Result<FSCache, Error> {
+    Ok(FSCache::new(cache_dir, &base.repo_root, None)?)
+}
+
+fn format_last_used(last_used: SystemTime) -> String {
+    match SystemTime::now().duration_since(last_used) {
+        // Seconds are precise enough, and keep the output short
+        Ok(age) => format!(
+            "{} ago",
+            humantime::format_duration(Duration::from_secs(age.as_secs()))
+        ),
+        Err(_) => "just now".to_string(),
+    }
+}
+
+fn print_removed(base: &CommandBase, removed: &[FSCacheArtifact]) {
+    let freed: u64 = removed.iter().map(|artifact| artifact.size).sum();
+    println!(
+        "Removed {} artifacts, freeing {}",
+        base.ui.apply(BOLD.apply_to(removed.len())),
+        base.ui.apply(BOLD.apply_to(format_size(freed))),
+    );
+}
+
+/// Lists the artifacts in the filesystem cache, most recently used first
+pub fn ls(base: &CommandBase, cache_dir: Option<&Utf8Path>) -> Result<(), Error> {
+    let mut artifacts = open_cache(base, cache_dir)?.artifacts()?;
+    artifacts.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+
+    for artifact in &artifacts {
+        println!(
+            "{}  {:>10}  {}",
+            artifact.hash,
+            format_size(artifact.size),
+            base.ui
+                .apply(GREY.apply_to(format_last_used(artifact.last_used)))
+        );
+    }
+
+    let total: u64 = artifacts.iter().map(|artifact| artifact.size).sum();
+    println!(
+        "{} artifacts, {}",
+        base.ui.apply(BOLD.apply_to(artifacts.len())),
+        base.ui.apply(BOLD.apply_to(format_size(total))),
+    );
+
+    Ok(())
+}
+
+/// Prints the metadata of an artifact and the entries in its archive
+pub fn show(base: &CommandBase, cache_dir: Option<&Utf8Path>, hash: &str) -> Result<(), Error> {
+    check_hash(hash)?;
+    let cache = open_cache(base, cache_dir)?;
+    let mut reader = cache
+        .open(hash)?
+        .ok_or_else(|| Error::ArtifactNotFound(hash.to_string()))?;
+    let entries = reader.entries()?;
+
+    println!("{}", base.ui.apply(BOLD.apply_to(hash)));
+    match cache.metadata(hash) {
+        Ok(metadata) => println!("  Duration: {}ms", metadata.duration),
+        Err(err) => println!("  Duration: {}", base.ui.apply(BOLD_RED.apply_to(err))),
+    }
+    println!("  Entries: {}", entries.len());
+    println!();
+
+    for entry in &entries {
+        match &entry.kind {
+            CacheEntryKind::File => {
+                println!("{:>10}  {}", format_size(entry.size), entry.path.as_str())
+            }
+            CacheEntryKind::Directory => println!("{:>10}  {}", "dir", entry.path.as_str()),
+            CacheEntryKind::Symlink { target } => {
+                println!("{:>10}  {} -> {}", "symlink", entry.path.as_str(), target)
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the local archive of an artifact can be read and, if
+/// signature verification is enabled, that the remote artifact's tag matches
+/// its contents. Returns whether all checks passed.
+pub async fn verify(
+    base: &CommandBase,
+    cache_dir: Option<&Utf8Path>,
+    hash: &str,
+) -> Result<bool, Error> {
+    check_hash(hash)?;
+    let cache = open_cache(base, cache_dir)?;
+    let ok = base.ui.apply(BOLD_GREEN.apply_to("ok"));
+    let mut found = false;
+    let mut passed = true;
+
+    println!("{}", base.ui.apply(BOLD.apply_to(hash)));
+
+    match cache.open(hash)? {
+        Some(mut reader) => {
+            found = true;
+            match reader.entries().and_then(|_| cache.metadata(hash)) {
+                Ok(_) => println!("  Local: {}", ok),
+                Err(err) => {
+                    passed = false;
+                    println!("  Local: {}", base.ui.apply(BOLD_RED.apply_to(err)));
+                }
+            }
+        }
+        None => println!("  Local: {}", base.ui.apply(GREY.apply_to("not found"))),
+    }
+
+    let config = base.config()?;
+    let api_auth = base.api_auth()?.filter(|api_auth| api_auth.is_linked());
+    match api_auth {
+        None => println!("  Remote: {}", base.ui.apply(GREY.apply_to("not linked"))),
+        Some(_) if !config.signature() => println!(
+            "  Remote: {}",
+            base.ui
+                .apply(GREY.apply_to("signature verification is not enabled"))
+        ),
+        Some(api_auth) => {
+            let opts = CacheOpts {
+                remote_cache_opts: Some(
+                    RemoteCacheOpts::new(config.team_id().unwrap_or_default().to_string(), true)
+                        .with_signature_keys(
+                            config.signature_algorithm(),
+                            config.signature_public_keys(),
+                        ),
+                ),
+                ..CacheOpts::default()
+            };
+            let http = HTTPCache::new(
+                base.api_client()?,
+                &opts,
+                base.repo_root.clone(),
+                api_auth,
+                None,
+            );
+
+            let message = match http.verify(hash).await? {
+                Some(verification) => {
+                    found = true;
+                    match verification {
+                        ArtifactVerification::Valid => ok,
+                        ArtifactVerification::Invalid => {
+                            passed = false;
+                            base.ui.apply(BOLD_RED.apply_to("invalid signature"))
+                        }
+                        ArtifactVerification::MissingTag => {
+                            passed = false;
+                            base.ui.apply(BOLD_RED.apply_to("not signed"))
+                        }
+                        ArtifactVerification::UnknownKey => {
+                            passed = false;
+                            base.ui
+                                .apply(BOLD_RED.apply_to("signed with an unknown key"))
+                        }
+                    }
+                }
+                None => base.ui.apply(GREY.apply_to("not found")),
+            };
+            println!("  Remote: {}", message);
+        }
+    }
+
+    if !found {
+        return Err(Error::ArtifactNotFound(hash.to_string()));
+    }
+
+    Ok(passed)
+}
+
+/// Removes a single artifact from the filesystem cache
+pub fn rm(base: &CommandBase, cache_dir: Option<&Utf8Path>, hash: &str) -> Result<(), Error> {
+    check_hash(hash)?;
+    let removed = open_cache(base, cache_dir)?
+        .remove(hash)?
+        .ok_or_else(|| Error::ArtifactNotFound(hash.to_string()))?;
+    print_removed(base, &[removed]);
+
+    Ok(())
+}
+
+/// Removes every artifact from the filesystem cache
+pub fn clear(base: &CommandBase, cache_dir: Option<&Utf8Path>) -> Result<(), Error> {
+    let removed = open_cache(base, cache_dir)?.clear()?;
+    print_removed(base, &removed);
+
+    Ok(())
+}
+
+/// Evicts artifacts from the filesystem cache until it's within the
+/// configured limits. Limits passed in `overrides` take precedence over the
+/// configured ones.
+pub fn prune(
+    base: &CommandBase,
+    cache_dir: Option<&Utf8Path>,
+    overrides: CacheLimits,
+) -> Result<(), Error> {
+    let configured = base.config()?.cache_limits();
+    let limits = CacheLimits {
+        max_size: overrides.max_size.or(configured.max_size),
+        max_age: overrides.max_age.or(configured.max_age),
+        max_entries: overrides.max_entries.or(configured.max_entries),
+    };
+
+    if limits.is_unlimited() {
+        println!("No cache limits are configured, so there is nothing to prune");
+        return Ok(());
+    }
+
+    let evicted = open_cache(base, cache_dir)?.evict(&limits)?;
+    for artifact in &evicted {
+        println!(
+            " - Removed {} {}",
+            artifact.hash,
+            base.ui
+                .apply(GREY.apply_to(format!("({})", format_size(artifact.size))))
+        );
+    }
+
+    print_removed(base, &evicted);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_ui::UI;
+
+    use super::*;
+    use crate::cli::Args;
+
+    #[test_case(0, "0 B" ; "empty")]
+    #[test_case(1023, "1023 B" ; "bytes")]
+    #[test_case(1536, "1.5 KB" ; "kilobytes")]
+    #[test_case(5 * 1024 * 1024 * 1024, "5.0 GB" ; "gigabytes")]
+    fn test_format_size(bytes: u64, expected: &str) {
+        assert_eq!(format_size(bytes), expected);
+    }
+
+    #[test_case("../../x" ; "parent directory")]
+    #[test_case("a/b" ; "separator")]
+    #[test_case(".." ; "dot dot")]
+    #[test_case("" ; "empty")]
+    #[test_case("4b1e7c1.tar.zst" ; "suffix")]
+    fn test_invalid_hash(hash: &str) {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(tmp.path()).unwrap();
+        let base = CommandBase::new(Args::default(), repo_root.clone(), "test", UI::new(true));
+
+        assert!(matches!(
+            show(&base, None, hash),
+            Err(Error::InvalidHash(_))
+        ));
+        assert!(matches!(rm(&base, None, hash), Err(Error::InvalidHash(_))));
+        // Nothing is created before the hash is checked
+        assert!(!repo_root.join_component("node_modules").exists());
+    }
+
+    #[test]
+    fn test_valid_hash() {
+        assert!(check_hash("4b1e7c1a9f3d0e26").is_ok());
+    }
+}
@@ -32,12 +32,23 @@ struct RepositoryWorkspaceDetails<'a> {
 struct WorkspaceDetails<'a> {
     name: &'a str,
     dependencies: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    why: Option<ExternalDependencyPath<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExternalDependencyPath<'a> {
+    package: &'a str,
+    // Lockfile keys from a direct dependency of the workspace to the package,
+    // empty if the workspace doesn't depend on it
+    path: Vec<&'a str>,
 }
 
 pub async fn run(
     base: &mut CommandBase,
     workspace: Option<&str>,
     json: bool,
+    why: Option<&str>,
 ) -> Result<(), cli::Error> {
     let root_package_json = PackageJson::load(&base.repo_root.join_component("package.json"))?;
 
@@ -48,7 +59,7 @@ pub async fn run(
     let config = base.config()?;
 
     if let Some(workspace) = workspace {
-        let workspace_details = WorkspaceDetails::new(&package_graph, workspace);
+        let workspace_details = WorkspaceDetails::new(&package_graph, workspace, why);
         if json {
             println!("{}", serde_json::to_string_pretty(&workspace_details)?);
         } else {
@@ -116,7 +127,7 @@ impl<'a> RepositoryDetails<'a> {
 }
 
 impl<'a> WorkspaceDetails<'a> {
-    fn new(package_graph: &'a PackageGraph, workspace_name: &'a str) -> Self {
+    fn new(package_graph: &'a PackageGraph, workspace_name: &'a str, why: Option<&'a str>) -> Self {
         let workspace_node = match workspace_name {
             "//" => WorkspaceNode::Root,
             name => WorkspaceNode::Workspace(WorkspaceName::Other(name.to_string())),
@@ -138,9 +149,24 @@ impl<'a> WorkspaceDetails<'a> {
             .collect();
         workspace_dep_names.sort();
 
+        let why = why.map(|package| {
+            let workspace = match workspace_name {
+                "//" => WorkspaceName::Root,
+                name => WorkspaceName::Other(name.to_string()),
+            };
+            let path = package_graph
+                .why_external_dependency(&workspace, package)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|pkg| pkg.key.as_str())
+                .collect();
+            ExternalDependencyPath { package, path }
+        });
+
         Self {
             name: workspace_name,
             dependencies: workspace_dep_names,
+            why,
         }
     }
 
@@ -149,5 +175,16 @@ impl<'a> WorkspaceDetails<'a> {
         for dep_name in &self.dependencies {
             println!("- {}", dep_name);
         }
+
+        if let Some(why) = &self.why {
+            if why.path.is_empty() {
+                println!("\n{} does not depend on {}", self.name, why.package);
+            } else {
+                println!("\n{} depends on {} through:", self.name, why.package);
+                for key in &why.path {
+                    println!("- {}", key);
+                }
+            }
+        }
     }
 }
@@ -15,6 +15,8 @@ use crate::{
 };
 
 pub(crate) mod bin;
+pub(crate) mod boundaries;
+pub(crate) mod cache;
 pub(crate) mod daemon;
 pub(crate) mod generate;
 pub(crate) mod info;
@@ -25,6 +27,8 @@ pub(crate) mod prune;
 pub(crate) mod run;
 pub(crate) mod telemetry;
 pub(crate) mod unlink;
+pub(crate) mod watch;
+pub(crate) mod why;
 
 #[derive(Debug)]
 pub struct CommandBase {
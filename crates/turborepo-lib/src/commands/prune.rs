@@ -85,11 +85,14 @@ pub async fn prune(
     scope: &[String],
     docker: bool,
     output_dir: &str,
+    why: Option<&str>,
     telemetry: CommandEventBuilder,
 ) -> Result<(), Error> {
     telemetry.track_prune_method(docker);
     let prune = Prune::new(base, scope, docker, output_dir).await?;
 
+    // Bun reads bun.lockb as a binary lockfile, and we can only write the
+    // text format
     if matches!(
         prune.package_graph.package_manager(),
         turborepo_repository::package_manager::PackageManager::Bun
@@ -123,6 +126,9 @@ pub async fn prune(
         .into_iter()
         .map(|pkg| pkg.key.clone())
         .collect();
+    if let Some(package) = why {
+        prune.print_why(&workspaces, package);
+    }
     for workspace in workspaces {
         let entry = prune
             .package_graph
@@ -416,6 +422,32 @@ impl<'a> Prune<'a> {
         names
     }
 
+    // Prints why an external package is included for each of the pruned
+    // workspaces
+    fn print_why(&self, workspaces: &[WorkspaceName], package: &str) {
+        let mut found = false;
+        for workspace in workspaces {
+            let Some(path) = self
+                .package_graph
+                .why_external_dependency(workspace, package)
+            else {
+                continue;
+            };
+            found = true;
+            let path = path
+                .into_iter()
+                .map(|pkg| pkg.key.as_str())
+                .collect::<Vec<_>>();
+            println!(
+                " - {workspace} includes {package} through {}",
+                path.join(" -> ")
+            );
+        }
+        if !found {
+            println!(" - {package} is not a dependency of the pruned workspaces");
+        }
+    }
+
     fn copy_turbo_json(&self, workspaces: &[String]) -> Result<(), Error> {
         let original_turbo_path = self.root.resolve(turbo_json());
 
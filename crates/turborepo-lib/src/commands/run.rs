@@ -1,8 +1,10 @@
+use std::future::Future;
+
 use tracing::{debug, error};
 
 use crate::{commands::CommandBase, run, run::Run, signal::SignalHandler};
 
-pub async fn run(base: CommandBase) -> Result<i32, run::Error> {
+pub(crate) fn get_signal() -> Result<impl Future<Output = Option<()>>, run::Error> {
     #[cfg(windows)]
     let signal = {
         let mut ctrl_c = tokio::signal::windows::ctrl_c().map_err(run::Error::SignalHandler)?;
@@ -27,6 +29,11 @@ pub async fn run(base: CommandBase) -> Result<i32, run::Error> {
         }
     };
 
+    Ok(signal)
+}
+
+pub async fn run(base: CommandBase) -> Result<i32, run::Error> {
+    let signal = get_signal()?;
     let handler = SignalHandler::new(signal);
 
     let mut run = Run::new(&base);
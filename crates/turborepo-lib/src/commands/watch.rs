@@ -0,0 +1,480 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Duration,
+};
+
+use tokio::{
+    select,
+    task::{JoinError, JoinHandle},
+    time::Instant,
+};
+use tracing::{debug, warn};
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+use turborepo_repository::{
+    discovery::{LocalPackageDiscoveryBuilder, PackageDiscoveryBuilder},
+    package_graph::{self, PackageGraph, WorkspaceName},
+    package_json::PackageJson,
+};
+use turborepo_ui::{cprintln, GREY, UI};
+use wax::{Glob, Pattern};
+
+use super::CommandBase;
+use crate::{
+    cli::Command,
+    config::TurboJson,
+    daemon::{proto, DaemonConnector, DaemonConnectorError, DaemonError},
+    engine::{self, EngineBuilder},
+    process::ProcessManager,
+    run::{self, task_id::TaskName, EngineFilter, Run},
+    signal::SignalHandler,
+    Args,
+};
+
+// How long to wait for more file changes before starting a run
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// Changes in these directories never affect a task, and tasks tend to write
+// to them while running
+const IGNORED_DIRECTORIES: [&str; 3] = [".git", "node_modules", ".turbo"];
+
+// Changes to these files can change the shape of the task graph
+const WORKSPACE_FILES: [&str; 2] = ["package.json", "turbo.json"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("turbo watch requires the daemon: {0}")]
+    DaemonConnector(#[from] DaemonConnectorError),
+    #[error("lost connection to the daemon: {0}")]
+    Daemon(#[from] DaemonError),
+    #[error("--{0} is not supported by turbo watch")]
+    UnsupportedFlag(&'static str),
+    #[error(transparent)]
+    Run(#[from] run::Error),
+    #[error(transparent)]
+    PackageJson(#[from] turborepo_repository::package_json::Error),
+    #[error(transparent)]
+    PackageManager(#[from] turborepo_repository::package_manager::Error),
+    #[error(transparent)]
+    PackageGraphBuilder(#[from] package_graph::builder::Error),
+    #[error(transparent)]
+    Config(#[from] crate::config::Error),
+    #[error(transparent)]
+    EngineBuilder(#[from] engine::BuilderError),
+}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Self::Daemon(status.into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ChangedPackages {
+    All,
+    Some(HashSet<WorkspaceName>),
+}
+
+impl Default for ChangedPackages {
+    fn default() -> Self {
+        Self::Some(HashSet::new())
+    }
+}
+
+impl ChangedPackages {
+    fn is_empty(&self) -> bool {
+        matches!(self, ChangedPackages::Some(packages) if packages.is_empty())
+    }
+
+    fn extend(&mut self, other: ChangedPackages) {
+        match (self, other) {
+            (ChangedPackages::All, _) => (),
+            (this, ChangedPackages::All) => *this = ChangedPackages::All,
+            (ChangedPackages::Some(packages), ChangedPackages::Some(other)) => {
+                packages.extend(other)
+            }
+        }
+    }
+}
+
+/// A run that has been started in the background along with a handle to the
+/// processes it spawned.
+struct RunHandle {
+    processes: ProcessManager,
+    run: JoinHandle<Result<i32, run::Error>>,
+}
+
+impl RunHandle {
+    async fn stop(self) {
+        self.processes.stop().await;
+        // Stopping the processes makes the run wrap up with failures that we
+        // don't want to report, so we drop it instead of waiting on it.
+        self.run.abort();
+        let _ = self.run.await;
+    }
+}
+
+/// Maps file changes to the workspaces they belong to
+struct ChangeMapper {
+    package_graph: PackageGraph,
+    global_deps: Vec<Glob<'static>>,
+    // The outputs of the watched tasks in each workspace
+    outputs: HashMap<WorkspaceName, Vec<Glob<'static>>>,
+}
+
+impl ChangeMapper {
+    async fn new(
+        repo_root: &AbsoluteSystemPathBuf,
+        single_package: bool,
+        tasks: &[String],
+    ) -> Result<Self, Error> {
+        let root_package_json = PackageJson::load(&repo_root.join_component("package.json"))?;
+        let package_graph = PackageGraph::builder(repo_root, root_package_json.clone())
+            .with_single_package_mode(single_package)
+            .with_package_discovery(
+                LocalPackageDiscoveryBuilder::new(
+                    repo_root.clone(),
+                    None,
+                    Some(root_package_json.clone()),
+                )
+                .build()?,
+            )
+            .build()
+            .await?;
+        let root_turbo_json = TurboJson::load(repo_root, &root_package_json, single_package)?;
+
+        let global_deps = compile_globs(root_turbo_json.global_deps.iter().map(String::as_str));
+
+        // Workspaces can add to or override the outputs of a task in their own
+        // turbo.json, so we let the engine resolve the task definitions.
+        let engine = EngineBuilder::new(repo_root, &package_graph, single_package)
+            .with_root_tasks(root_turbo_json.pipeline.keys().cloned())
+            .with_turbo_jsons(Some(
+                Some((WorkspaceName::Root, root_turbo_json))
+                    .into_iter()
+                    .collect(),
+            ))
+            .with_workspaces(
+                package_graph
+                    .workspaces()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            )
+            .with_tasks(
+                tasks
+                    .iter()
+                    .map(|task| TaskName::from(task.as_str()).into_owned()),
+            )
+            .build()?;
+
+        // Outputs are configured relative to their workspace, so they get matched
+        // against the path of the changed file within its workspace.
+        let mut outputs: HashMap<WorkspaceName, Vec<Glob<'static>>> = HashMap::new();
+        for (task_id, definition) in engine.task_definitions() {
+            outputs
+                .entry(WorkspaceName::from(task_id.package()))
+                .or_default()
+                .extend(compile_globs(
+                    definition.outputs.inclusions.iter().map(String::as_str),
+                ));
+        }
+
+        Ok(Self {
+            package_graph,
+            global_deps,
+            outputs,
+        })
+    }
+
+    fn changed_packages(&self, event: proto::FileChangeEvent) -> ChangedPackages {
+        if event.rediscover {
+            return ChangedPackages::All;
+        }
+
+        let lockfile = self.package_graph.package_manager().lockfile_name();
+        let mut changed_packages = HashSet::new();
+        for file in event.changed_files {
+            let Ok(file) = AnchoredSystemPathBuf::from_raw(&file) else {
+                continue;
+            };
+            if file
+                .components()
+                .any(|component| IGNORED_DIRECTORIES.contains(&component.as_str()))
+            {
+                continue;
+            }
+
+            let file_name = file.as_path().file_name().and_then(|name| name.to_str());
+            if file_name.map_or(false, |name| WORKSPACE_FILES.contains(&name))
+                || file.as_str() == lockfile
+                || self
+                    .global_deps
+                    .iter()
+                    .any(|glob| glob.is_match(file.as_path()))
+            {
+                return ChangedPackages::All;
+            }
+
+            let (workspace, workspace_relative_path) = self.workspace_for_file(file.as_path());
+            if self.outputs.get(&workspace).map_or(false, |outputs| {
+                outputs
+                    .iter()
+                    .any(|glob| glob.is_match(workspace_relative_path))
+            }) {
+                continue;
+            }
+            changed_packages.insert(workspace);
+        }
+
+        ChangedPackages::Some(changed_packages)
+    }
+
+    fn workspace_for_file<'a>(&self, file: &'a Path) -> (WorkspaceName, &'a Path) {
+        for (name, info) in self.package_graph.workspaces() {
+            if name == &WorkspaceName::Root {
+                continue;
+            }
+            if let Ok(relative_path) = file.strip_prefix(info.package_path().as_path()) {
+                return (name.clone(), relative_path);
+            }
+        }
+        // if the file is not in any package, it must be in the root package
+        (WorkspaceName::Root, file)
+    }
+}
+
+fn compile_globs<'a>(globs: impl Iterator<Item = &'a str>) -> Vec<Glob<'static>> {
+    globs
+        .filter_map(|glob| match Glob::new(glob) {
+            Ok(glob) => Some(glob.into_owned()),
+            Err(e) => {
+                warn!("ignoring invalid glob {glob}: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+struct WatchClient {
+    // The arguments of the watch command, turned into a `turbo run`
+    args: Args,
+    repo_root: AbsoluteSystemPathBuf,
+    version: &'static str,
+    ui: UI,
+    single_package: bool,
+    // The tasks being watched, used to find the outputs to ignore
+    tasks: Vec<String>,
+    handler: SignalHandler,
+    // Tasks that are expected to exit
+    main: Option<RunHandle>,
+    // Persistent tasks that get restarted after every change
+    interruptible: Option<RunHandle>,
+    // Persistent tasks that only get restarted when the workspace changes
+    persistent: Option<RunHandle>,
+}
+
+impl WatchClient {
+    fn spawn_run(&self, engine_filter: EngineFilter) -> RunHandle {
+        let processes = ProcessManager::new();
+        let base = CommandBase::new(
+            self.args.clone(),
+            self.repo_root.clone(),
+            self.version,
+            self.ui,
+        );
+        let handler = self.handler.clone();
+        let run_processes = processes.clone();
+        let run = tokio::task::spawn_local(async move {
+            let mut run = Run::new(&base)
+                .with_engine_filter(engine_filter)
+                .with_process_manager(run_processes);
+            run.run(&handler).await
+        });
+
+        RunHandle { processes, run }
+    }
+
+    async fn stop_all(&mut self) {
+        for handle in [
+            self.main.take(),
+            self.interruptible.take(),
+            self.persistent.take(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            handle.stop().await;
+        }
+    }
+
+    async fn start(&mut self, changes: ChangedPackages) {
+        if let Some(main) = self.main.take() {
+            main.stop().await;
+        }
+        if let Some(interruptible) = self.interruptible.take() {
+            interruptible.stop().await;
+        }
+
+        let packages = match changes {
+            ChangedPackages::All => {
+                if let Some(persistent) = self.persistent.take() {
+                    persistent.stop().await;
+                }
+                None
+            }
+            ChangedPackages::Some(packages) => Some(packages),
+        };
+        self.main = Some(self.spawn_run(EngineFilter::WithoutPersistent(packages)));
+    }
+
+    // Once all tasks that are expected to exit have succeeded, the persistent
+    // tasks can be (re)started.
+    fn start_persistent(&mut self) {
+        self.interruptible = Some(self.spawn_run(EngineFilter::Persistent {
+            interruptible: true,
+        }));
+        if self.persistent.is_none() {
+            self.persistent = Some(self.spawn_run(EngineFilter::Persistent {
+                interruptible: false,
+            }));
+        }
+    }
+
+    async fn watch(&mut self, connector: DaemonConnector) -> Result<i32, Error> {
+        let mut client = connector.connect().await?;
+        let mut events = client.file_changes().await?;
+
+        let mut mapper =
+            ChangeMapper::new(&self.repo_root, self.single_package, &self.tasks).await?;
+        // Changes that haven't been picked up by a run yet
+        let mut pending = ChangedPackages::default();
+        // Changes that the current run is handling. If it gets interrupted they
+        // still need to be run.
+        let mut in_progress = ChangedPackages::All;
+        let mut deadline = None;
+        self.start(ChangedPackages::All).await;
+
+        loop {
+            select! {
+                biased;
+                _ = self.handler.done() => {
+                    return Ok(0);
+                }
+                event = events.message() => {
+                    let Some(event) = event? else {
+                        return Err(Error::Daemon(DaemonError::Unavailable));
+                    };
+                    let changes = mapper.changed_packages(event);
+                    if !changes.is_empty() {
+                        debug!("files changed in {:?}", changes);
+                        pending.extend(changes);
+                        deadline = Some(Instant::now() + DEBOUNCE);
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    deadline = None;
+                    let mut changes = std::mem::take(&mut pending);
+                    changes.extend(std::mem::take(&mut in_progress));
+                    if changes == ChangedPackages::All {
+                        let new_mapper =
+                            ChangeMapper::new(&self.repo_root, self.single_package, &self.tasks)
+                                .await;
+                        match new_mapper {
+                            Ok(new_mapper) => mapper = new_mapper,
+                            Err(e) => {
+                                // The workspace is likely in the middle of being edited,
+                                // so we wait for the next change before trying again.
+                                warn!("unable to load workspace: {e}");
+                                pending = changes;
+                                continue;
+                            }
+                        }
+                    }
+                    in_progress = changes.clone();
+                    self.start(changes).await;
+                }
+                result = wait_for_run(&mut self.main) => {
+                    self.main = None;
+                    in_progress = ChangedPackages::default();
+                    match result {
+                        Ok(Ok(0)) => self.start_persistent(),
+                        Ok(Ok(_)) => (),
+                        // Every run would fail the same way until turbo.json changes
+                        Ok(Err(e @ run::Error::WatchDependencyOnPersistentTask { .. })) => {
+                            return Err(e.into())
+                        }
+                        Ok(Err(e)) => warn!("run failed: {e}"),
+                        Err(e) => warn!("run failed to complete: {e}"),
+                    }
+                    cprintln!(self.ui, GREY, "• Watching for changes...");
+                }
+            }
+        }
+    }
+}
+
+async fn wait_for_run(
+    handle: &mut Option<RunHandle>,
+) -> Result<Result<i32, run::Error>, JoinError> {
+    match handle {
+        Some(handle) => (&mut handle.run).await,
+        None => std::future::pending().await,
+    }
+}
+
+pub async fn watch(base: CommandBase) -> Result<i32, Error> {
+    let Some(Command::Watch(run_args)) = base.args().command.clone() else {
+        unreachable!("watch must be called with the watch command");
+    };
+    if run_args.dry_run.is_some() {
+        return Err(Error::UnsupportedFlag("dry-run"));
+    }
+    if run_args.graph.is_some() {
+        return Err(Error::UnsupportedFlag("graph"));
+    }
+
+    // Tasks are hashed as if only part of the task graph exists,
+    // so we can't use the cache safely.
+    let mut run_args = run_args;
+    run_args.force = Some(Some(true));
+    run_args.no_cache = true;
+    let single_package = run_args.single_package;
+    let tasks = run_args.tasks.clone();
+    let mut args = base.args().clone();
+    args.command = Some(Command::Run(run_args));
+
+    let connector = DaemonConnector {
+        can_start_server: true,
+        can_kill_server: true,
+        pid_file: base.daemon_file_root().join_component("turbod.pid"),
+        sock_file: base.daemon_file_root().join_component("turbod.sock"),
+    };
+
+    let signal = super::run::get_signal()?;
+    let handler = SignalHandler::new(signal);
+
+    let mut client = WatchClient {
+        args,
+        repo_root: base.repo_root.clone(),
+        version: base.version(),
+        ui: base.ui,
+        single_package,
+        tasks,
+        handler: handler.clone(),
+        main: None,
+        interruptible: None,
+        persistent: None,
+    };
+
+    // Runs hold on to state that can't be sent between threads
+    let local = tokio::task::LocalSet::new();
+    let result = local
+        .run_until(async {
+            let result = client.watch(connector).await;
+            client.stop_all().await;
+            result
+        })
+        .await;
+    handler.close().await;
+    result
+}
@@ -0,0 +1,115 @@
+//! Explains why a task ends up in a run by printing the chains of
+//! `dependsOn` rules that lead from the requested tasks to it
+use turborepo_repository::package_graph::{PackageGraph, WorkspaceName, WorkspaceNode};
+use turborepo_ui::{BOLD, GREY, UI};
+
+use super::CommandBase;
+use crate::{
+    engine::{DependencyReason, Engine},
+    run::{self, task_id::TaskId, Run},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Run(#[from] run::Error),
+    #[error("{0} is not a task id or a workspace in this repository")]
+    UnknownTarget(String),
+}
+
+pub async fn why(base: &CommandBase, target: &str, max_paths: usize) -> Result<(), Error> {
+    let run = Run::new(base);
+    let (package_graph, engine) = run.build_graphs().await?;
+
+    let target_task = TaskId::try_from(target).ok();
+    if target_task.is_none()
+        && package_graph
+            .workspace_info(&WorkspaceName::from(target))
+            .is_none()
+    {
+        return Err(Error::UnknownTarget(target.to_string()));
+    }
+
+    let (paths, total) = engine.paths_to(
+        |task_id| match &target_task {
+            Some(target_task) => task_id == target_task,
+            None => task_id.package() == target,
+        },
+        max_paths,
+    );
+    if total == 0 {
+        println!("{target} is not part of this run");
+        return Ok(());
+    }
+
+    for path in &paths {
+        print_path(base.ui, &engine, &package_graph, path);
+    }
+    let omitted = total - paths.len();
+    if omitted > 0 {
+        println!(
+            "{}",
+            base.ui.apply(GREY.apply_to(format!(
+                "{omitted} more {} omitted, use --max-paths to print more",
+                match omitted {
+                    1 => "path",
+                    _ => "paths",
+                }
+            )))
+        );
+    }
+
+    Ok(())
+}
+
+fn print_path(ui: UI, engine: &Engine, package_graph: &PackageGraph, path: &[&TaskId<'static>]) {
+    let Some((first, _)) = path.split_first() else {
+        return;
+    };
+    println!(
+        "{} {}",
+        ui.apply(BOLD.apply_to(first)),
+        ui.apply(GREY.apply_to("(requested)"))
+    );
+    for (indent, edge) in path.windows(2).enumerate() {
+        let [dependent, dependency] = edge else {
+            continue;
+        };
+        let reason = match engine.dependency_reason(dependent, dependency) {
+            Some(DependencyReason::Task(task_name)) => {
+                format!("{dependent} dependsOn \"{task_name}\"")
+            }
+            Some(DependencyReason::Topological(task_name)) => {
+                let internal = package_graph
+                    .immediate_dependencies(&WorkspaceNode::Workspace(WorkspaceName::from(
+                        dependent.package(),
+                    )))
+                    .is_some_and(|dependencies| {
+                        dependencies.contains(&WorkspaceNode::Workspace(WorkspaceName::from(
+                            dependency.package(),
+                        )))
+                    });
+                let workspace_edge = match internal {
+                    true => format!(
+                        ", {} depends on {}",
+                        dependent.package(),
+                        dependency.package()
+                    ),
+                    false => String::new(),
+                };
+                format!(
+                    "{dependent} dependsOn \"^{}\"{workspace_edge}",
+                    task_name.task()
+                )
+            }
+            None => "unknown rule".to_string(),
+        };
+        println!(
+            "{}└─ {} {}",
+            "   ".repeat(indent),
+            dependency,
+            ui.apply(GREY.apply_to(format!("({reason})")))
+        );
+    }
+    println!();
+}
@@ -5,7 +5,8 @@ use std::io;
 
 use thiserror::Error;
 pub use turbo::{
-    validate_extends, validate_no_package_task_syntax, RawTaskDefinition, RawTurboJson, TurboJson,
+    validate_extends, validate_no_boundaries, validate_no_package_task_syntax,
+    validate_no_resources, RawTaskDefinition, RawTurboJson, TurboJson,
 };
 pub use turbo_config::{ConfigurationOptions, TurborepoConfigBuilder};
 use turbopath::AbsoluteSystemPathBuf;
@@ -69,6 +70,16 @@ pub enum Error {
     ExtendFromNonRoot,
     #[error("No \"extends\" key found")]
     NoExtends,
+    #[error("\"resources\" can only be declared in the root turbo.json")]
+    ResourcesInWorkspace,
+    #[error("\"boundaries\" can only be declared in the root turbo.json")]
+    BoundariesInWorkspace,
+    #[error("\"readyWhen\" can only be used with persistent tasks")]
+    ReadyWhenNotPersistent,
+    #[error("\"timeout\" can't be used with persistent tasks")]
+    TimeoutOnPersistent,
+    #[error("Invalid \"readyWhen\" value \"{value}\": {reason}")]
+    InvalidReadyWhen { value: String, reason: String },
     #[error("Failed to create APIClient: {0}")]
     ApiClient(#[source] turborepo_api_client::Error),
     #[error("{0} is not UTF8.")]
@@ -79,6 +90,10 @@ pub enum Error {
     InvalidRemoteCacheEnabled,
     #[error("TURBO_REMOTE_CACHE_TIMEOUT: error parsing timeout.")]
     InvalidRemoteCacheTimeout(#[source] std::num::ParseIntError),
+    #[error("{0}: error parsing cache limit.")]
+    InvalidCacheLimit(&'static str, #[source] std::num::ParseIntError),
+    #[error("TURBO_CACHE_LAYOUT should be one of archive, content-addressed or hardlinked.")]
+    InvalidCacheLayout,
     #[error("TURBO_PREFLIGHT should be either 1 or 0.")]
     InvalidPreflight,
 }
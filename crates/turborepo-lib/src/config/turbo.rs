@@ -3,6 +3,7 @@ use std::{
     io::Write,
     ops::{Deref, DerefMut},
     path::Path,
+    time::Duration,
 };
 
 use camino::Utf8Path;
@@ -11,10 +12,11 @@ use turbopath::{AbsoluteSystemPath, RelativeUnixPathBuf};
 use turborepo_repository::{package_graph::ROOT_PKG_NAME, package_json::PackageJson};
 
 use crate::{
+    boundaries::BoundaryRule,
     cli::OutputLogsMode,
     config::{ConfigurationOptions, Error},
     run::task_id::{TaskId, TaskName},
-    task_graph::{TaskDefinition, TaskOutputs},
+    task_graph::{ReadyWhen, TaskDefinition, TaskOutputs},
 };
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
@@ -40,7 +42,10 @@ pub struct TurboJson {
     pub(crate) global_pass_through_env: Option<Vec<String>>,
     pub(crate) pipeline: Pipeline,
     pub(crate) remote_cache: Option<ConfigurationOptions>,
+    pub(crate) resources: BTreeMap<String, u32>,
     pub(crate) space_id: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) boundaries: BTreeMap<String, BoundaryRule>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
@@ -71,6 +76,15 @@ pub struct RawTurboJson {
     // Configuration options when interfacing with the remote cache
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) remote_cache: Option<ConfigurationOptions>,
+    // Number of slots available for each resource that tasks can require
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<BTreeMap<String, u32>>,
+    // Tags of the workspace, in addition to those in its package.json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+    // Rules for which tagged workspaces can depend on each other
+    #[serde(skip_serializing_if = "Option::is_none")]
+    boundaries: Option<BTreeMap<String, BoundaryRule>>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, PartialEq, Clone)]
@@ -118,9 +132,21 @@ pub struct RawTaskDefinition {
     #[serde(skip_serializing_if = "Option::is_none")]
     persistent: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    interruptible: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ready_when: Option<ReadyWhen>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     outputs: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     output_mode: Option<OutputLogsMode>,
+    // Timeout in seconds for a single attempt of the task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    // Slots of each resource the task holds while it runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<BTreeMap<String, u32>>,
 }
 
 macro_rules! set_field {
@@ -143,9 +169,14 @@ impl RawTaskDefinition {
         set_field!(self, other, inputs);
         set_field!(self, other, output_mode);
         set_field!(self, other, persistent);
+        set_field!(self, other, interruptible);
+        set_field!(self, other, ready_when);
         set_field!(self, other, env);
         set_field!(self, other, pass_through_env);
         set_field!(self, other, dot_env);
+        set_field!(self, other, timeout);
+        set_field!(self, other, retries);
+        set_field!(self, other, resources);
     }
 }
 
@@ -290,6 +321,31 @@ impl TryFrom<RawTaskDefinition> for TaskDefinition {
             })
             .transpose()?;
 
+        let persistent = raw_task.persistent.unwrap_or_default();
+        if persistent && raw_task.timeout.is_some() {
+            return Err(Error::TimeoutOnPersistent);
+        }
+        if let Some(ready_when) = &raw_task.ready_when {
+            if !persistent {
+                return Err(Error::ReadyWhenNotPersistent);
+            }
+            match ready_when {
+                ReadyWhen::Output(pattern) => {
+                    regex::Regex::new(pattern).map_err(|err| Error::InvalidReadyWhen {
+                        value: pattern.clone(),
+                        reason: err.to_string(),
+                    })?;
+                }
+                ReadyWhen::Url(url) => {
+                    url::Url::parse(url).map_err(|err| Error::InvalidReadyWhen {
+                        value: url.clone(),
+                        reason: err.to_string(),
+                    })?;
+                }
+                ReadyWhen::Port(_) => (),
+            }
+        }
+
         Ok(TaskDefinition {
             outputs,
             cache: cache.unwrap_or(true),
@@ -300,7 +356,12 @@ impl TryFrom<RawTaskDefinition> for TaskDefinition {
             pass_through_env,
             dot_env,
             output_mode: raw_task.output_mode.unwrap_or_default(),
-            persistent: raw_task.persistent.unwrap_or_default(),
+            persistent,
+            interruptible: raw_task.interruptible.unwrap_or_default(),
+            ready_when: raw_task.ready_when,
+            timeout: raw_task.timeout.map(Duration::from_secs),
+            retries: raw_task.retries.unwrap_or_default(),
+            resources: raw_task.resources.unwrap_or_default(),
         })
     }
 }
@@ -407,6 +468,9 @@ impl TryFrom<RawTurboJson> for TurboJson {
             pipeline: raw_turbo.pipeline.unwrap_or_default(),
             // copy these over, we don't need any changes here.
             remote_cache: raw_turbo.remote_cache,
+            resources: raw_turbo.resources.unwrap_or_default(),
+            tags: raw_turbo.tags.unwrap_or_default(),
+            boundaries: raw_turbo.boundaries.unwrap_or_default(),
             extends: raw_turbo.extends.unwrap_or_default(),
             // Directly to space_id, we don't need to keep the struct
             space_id: raw_turbo.experimental_spaces.and_then(|s| s.id),
@@ -533,6 +597,20 @@ pub fn validate_no_package_task_syntax(turbo_json: &TurboJson) -> Vec<Error> {
         .collect()
 }
 
+pub fn validate_no_resources(turbo_json: &TurboJson) -> Vec<Error> {
+    match turbo_json.resources.is_empty() {
+        true => vec![],
+        false => vec![Error::ResourcesInWorkspace],
+    }
+}
+
+pub fn validate_no_boundaries(turbo_json: &TurboJson) -> Vec<Error> {
+    match turbo_json.boundaries.is_empty() {
+        true => vec![],
+        false => vec![Error::BoundariesInWorkspace],
+    }
+}
+
 pub fn validate_extends(turbo_json: &TurboJson) -> Vec<Error> {
     match turbo_json.extends.first() {
         Some(package_name) if package_name != ROOT_PKG_NAME || turbo_json.extends.len() > 1 => {
@@ -563,7 +641,7 @@ fn gather_env_vars(vars: Vec<String>, key: &str, into: &mut HashSet<String>) ->
 
 #[cfg(test)]
 mod tests {
-    use std::fs;
+    use std::{fs, time::Duration};
 
     use anyhow::Result;
     use pretty_assertions::assert_eq;
@@ -574,13 +652,14 @@ mod tests {
 
     use super::RawTurboJson;
     use crate::{
+        boundaries::BoundaryRule,
         cli::OutputLogsMode,
         config::{
             turbo::{Pipeline, RawTaskDefinition},
             TurboJson,
         },
         run::task_id::TaskName,
-        task_graph::{TaskDefinition, TaskOutputs},
+        task_graph::{ReadyWhen, TaskDefinition, TaskOutputs},
     };
 
     #[test_case(r"{}", TurboJson::default() ; "empty")]
@@ -596,6 +675,22 @@ mod tests {
             ..TurboJson::default()
         }
     ; "global dot env (unsorted)")]
+    #[test_case(r#"{ "resources": { "browser": 1, "docker": 2 } }"#,
+        TurboJson {
+            resources: [("browser".to_string(), 1), ("docker".to_string(), 2)].into_iter().collect(),
+            ..TurboJson::default()
+        }
+    ; "resources")]
+    #[test_case(r#"{ "tags": ["ui"], "boundaries": { "ui": { "deny": ["server"] } } }"#,
+        TurboJson {
+            tags: vec!["ui".to_string()],
+            boundaries: [(
+                "ui".to_string(),
+                BoundaryRule { allow: None, deny: Some(vec!["server".to_string()]) },
+            )].into_iter().collect(),
+            ..TurboJson::default()
+        }
+    ; "tags and boundaries")]
     #[test_case(r#"{ "globalPassThroughEnv": ["GITHUB_TOKEN", "AWS_SECRET_KEY"] }"#,
         TurboJson {
             global_pass_through_env: Some(vec!["AWS_SECRET_KEY".to_string(), "GITHUB_TOKEN".to_string()]),
@@ -715,6 +810,20 @@ mod tests {
         }
         ; "empty dotenv"
     )]
+    #[test_case(
+        r#"{ "persistent": true, "readyWhen": { "output": "listening on \\d+" } }"#,
+        RawTaskDefinition {
+            persistent: Some(true),
+            ready_when: Some(ReadyWhen::Output("listening on \\d+".to_string())),
+            ..RawTaskDefinition::default()
+        },
+        TaskDefinition {
+            persistent: true,
+            ready_when: Some(ReadyWhen::Output("listening on \\d+".to_string())),
+            ..Default::default()
+        }
+        ; "ready when output matches"
+    )]
     #[test_case(
         r#"{
           "dependsOn": ["cli#build"],
@@ -725,7 +834,11 @@ mod tests {
           "cache": false,
           "inputs": ["package/a/src/**"],
           "outputMode": "full",
-          "persistent": true
+          "persistent": true,
+          "interruptible": true,
+          "timeout": 600,
+          "retries": 2,
+          "resources": { "browser": 1 }
         }"#,
         RawTaskDefinition {
             depends_on: Some(vec!["cli#build".to_string()]),
@@ -737,6 +850,10 @@ mod tests {
             inputs: Some(vec!["package/a/src/**".to_string()]),
             output_mode: Some(OutputLogsMode::Full),
             persistent: Some(true),
+            interruptible: Some(true),
+            timeout: Some(600),
+            retries: Some(2),
+            resources: Some([("browser".to_string(), 1)].into_iter().collect()),
         },
         TaskDefinition {
           dot_env: Some(vec![RelativeUnixPathBuf::new("package/a/.env").unwrap()]),
@@ -752,6 +869,10 @@ mod tests {
           task_dependencies: vec!["cli#build".into()],
           topological_dependencies: vec![],
           persistent: true,
+          interruptible: true,
+          timeout: Some(Duration::from_secs(600)),
+          retries: 2,
+          resources: [("browser".to_string(), 1)].into_iter().collect(),
         }
     )]
     fn test_deserialize_task_definition(
@@ -768,6 +889,25 @@ mod tests {
         Ok(())
     }
 
+    #[test_case(r#"{ "readyWhen": { "port": 3000 } }"# ; "not persistent")]
+    #[test_case(r#"{ "persistent": true, "readyWhen": { "output": "(" } }"# ; "invalid pattern")]
+    #[test_case(r#"{ "persistent": true, "readyWhen": { "url": "/health" } }"# ; "relative url")]
+    fn test_invalid_ready_when(task_definition_content: &str) {
+        let raw_task_definition: RawTaskDefinition =
+            serde_json::from_str(task_definition_content).unwrap();
+        assert!(TaskDefinition::try_from(raw_task_definition).is_err());
+    }
+
+    #[test]
+    fn test_timeout_on_persistent() {
+        let raw_task_definition: RawTaskDefinition =
+            serde_json::from_str(r#"{ "persistent": true, "timeout": 60 }"#).unwrap();
+        assert!(matches!(
+            TaskDefinition::try_from(raw_task_definition),
+            Err(Error::TimeoutOnPersistent)
+        ));
+    }
+
     #[test_case("[]", TaskOutputs::default())]
     #[test_case(r#"["target/**"]"#, TaskOutputs { inclusions: vec!["target/**".to_string()], exclusions: vec![] })]
     #[test_case(
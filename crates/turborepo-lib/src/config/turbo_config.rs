@@ -1,8 +1,13 @@
-use std::{collections::HashMap, ffi::OsString};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    time::Duration,
+};
 
 use dirs_next::config_dir;
 use serde::{Deserialize, Serialize};
 use turbopath::AbsoluteSystemPathBuf;
+use turborepo_cache::{CacheLayout, CacheLimits, SignatureAlgorithm};
 use turborepo_repository::package_json::{Error as PackageJsonError, PackageJson};
 
 use crate::{
@@ -44,9 +49,18 @@ pub struct ConfigurationOptions {
     pub(crate) team_id: Option<String>,
     pub(crate) token: Option<String>,
     pub(crate) signature: Option<bool>,
+    pub(crate) signature_algorithm: Option<SignatureAlgorithm>,
+    // Trusted Ed25519 public keys by key id, base64 encoded
+    pub(crate) signature_public_keys: Option<BTreeMap<String, String>>,
     pub(crate) preflight: Option<bool>,
     pub(crate) timeout: Option<u64>,
     pub(crate) enabled: Option<bool>,
+    pub(crate) shared_cache_dir: Option<String>,
+    // Limits for the local filesystem cache, in bytes, seconds and artifacts
+    pub(crate) cache_max_size: Option<u64>,
+    pub(crate) cache_max_age: Option<u64>,
+    pub(crate) cache_max_entries: Option<u64>,
+    pub(crate) cache_layout: Option<CacheLayout>,
 }
 
 #[derive(Default)]
@@ -88,6 +102,14 @@ impl ConfigurationOptions {
         self.signature.unwrap_or_default()
     }
 
+    pub fn signature_algorithm(&self) -> SignatureAlgorithm {
+        self.signature_algorithm.unwrap_or_default()
+    }
+
+    pub fn signature_public_keys(&self) -> BTreeMap<String, String> {
+        self.signature_public_keys.clone().unwrap_or_default()
+    }
+
     pub fn enabled(&self) -> bool {
         self.enabled.unwrap_or(true)
     }
@@ -99,6 +121,24 @@ impl ConfigurationOptions {
     pub fn timeout(&self) -> u64 {
         self.timeout.unwrap_or(DEFAULT_TIMEOUT)
     }
+
+    pub fn shared_cache_dir(&self) -> Option<&str> {
+        self.shared_cache_dir.as_deref()
+    }
+
+    pub fn cache_limits(&self) -> CacheLimits {
+        CacheLimits {
+            max_size: self.cache_max_size,
+            max_age: self.cache_max_age.map(Duration::from_secs),
+            max_entries: self
+                .cache_max_entries
+                .map(|max_entries| max_entries.try_into().unwrap_or(usize::MAX)),
+        }
+    }
+
+    pub fn cache_layout(&self) -> CacheLayout {
+        self.cache_layout.unwrap_or_default()
+    }
 }
 
 trait ResolvedConfigurationOptions {
@@ -159,6 +199,14 @@ fn get_env_var_config(
     turbo_mapping.insert(OsString::from("turbo_teamid"), "team_id");
     turbo_mapping.insert(OsString::from("turbo_token"), "token");
     turbo_mapping.insert(OsString::from("turbo_remote_cache_timeout"), "timeout");
+    turbo_mapping.insert(OsString::from("turbo_shared_cache_dir"), "shared_cache_dir");
+    turbo_mapping.insert(OsString::from("turbo_cache_max_size"), "cache_max_size");
+    turbo_mapping.insert(OsString::from("turbo_cache_max_age"), "cache_max_age");
+    turbo_mapping.insert(
+        OsString::from("turbo_cache_max_entries"),
+        "cache_max_entries",
+    );
+    turbo_mapping.insert(OsString::from("turbo_cache_layout"), "cache_layout");
 
     // We do not enable new config sources:
     // turbo_mapping.insert(String::from("turbo_signature"), "signature"); // new
@@ -228,20 +276,57 @@ fn get_env_var_config(
         None
     };
 
+    // Process cache limits
+    let parse_cache_limit = |key: &str, env_var: &'static str| {
+        output_map
+            .get(key)
+            .map(|limit| {
+                limit
+                    .parse::<u64>()
+                    .map_err(|e| ConfigError::InvalidCacheLimit(env_var, e))
+            })
+            .transpose()
+    };
+    let cache_max_size = parse_cache_limit("cache_max_size", "TURBO_CACHE_MAX_SIZE")?;
+    let cache_max_age = parse_cache_limit("cache_max_age", "TURBO_CACHE_MAX_AGE")?;
+    let cache_max_entries = parse_cache_limit("cache_max_entries", "TURBO_CACHE_MAX_ENTRIES")?;
+
+    // Process cache layout
+    let cache_layout = if let Some(cache_layout) = output_map.get("cache_layout") {
+        match cache_layout.as_str() {
+            "archive" => Some(CacheLayout::Archive),
+            "content-addressed" => Some(CacheLayout::ContentAddressed),
+            "hardlinked" => Some(CacheLayout::Hardlinked),
+            _ => return Err(ConfigError::InvalidCacheLayout),
+        }
+    } else {
+        None
+    };
+
     let output = ConfigurationOptions {
         api_url: output_map.get("api_url").cloned(),
         login_url: output_map.get("login_url").cloned(),
         team_slug: output_map.get("team_slug").cloned(),
         team_id: output_map.get("team_id").cloned(),
         token: output_map.get("token").cloned(),
+        shared_cache_dir: output_map.get("shared_cache_dir").cloned(),
 
         // Processed booleans
         signature,
         preflight,
         enabled,
 
+        // Only read from config files
+        signature_algorithm: None,
+        signature_public_keys: None,
+
         // Processed numbers
         timeout,
+        cache_max_size,
+        cache_max_age,
+        cache_max_entries,
+
+        cache_layout,
     };
 
     Ok(output)
@@ -282,9 +367,16 @@ fn get_override_env_var_config(
         token: output_map.get("token").cloned(),
 
         signature: None,
+        signature_algorithm: None,
+        signature_public_keys: None,
         preflight: None,
         enabled: None,
         timeout: None,
+        shared_cache_dir: None,
+        cache_max_size: None,
+        cache_max_age: None,
+        cache_max_entries: None,
+        cache_layout: None,
     };
 
     Ok(output)
@@ -456,6 +548,14 @@ impl TurborepoConfigBuilder {
                     if let Some(signature) = current_source_config.signature {
                         acc.signature = Some(signature);
                     }
+                    if let Some(signature_algorithm) = current_source_config.signature_algorithm {
+                        acc.signature_algorithm = Some(signature_algorithm);
+                    }
+                    if let Some(signature_public_keys) =
+                        current_source_config.signature_public_keys.clone()
+                    {
+                        acc.signature_public_keys = Some(signature_public_keys);
+                    }
                     if let Some(enabled) = current_source_config.enabled {
                         acc.enabled = Some(enabled);
                     }
@@ -465,6 +565,21 @@ impl TurborepoConfigBuilder {
                     if let Some(timeout) = current_source_config.timeout {
                         acc.timeout = Some(timeout);
                     }
+                    if let Some(shared_cache_dir) = current_source_config.shared_cache_dir.clone() {
+                        acc.shared_cache_dir = Some(shared_cache_dir);
+                    }
+                    if let Some(cache_max_size) = current_source_config.cache_max_size {
+                        acc.cache_max_size = Some(cache_max_size);
+                    }
+                    if let Some(cache_max_age) = current_source_config.cache_max_age {
+                        acc.cache_max_age = Some(cache_max_age);
+                    }
+                    if let Some(cache_max_entries) = current_source_config.cache_max_entries {
+                        acc.cache_max_entries = Some(cache_max_entries);
+                    }
+                    if let Some(cache_layout) = current_source_config.cache_layout {
+                        acc.cache_layout = Some(cache_layout);
+                    }
 
                     acc
                 })
@@ -488,9 +603,17 @@ mod test {
         assert_eq!(defaults.team_id(), None);
         assert_eq!(defaults.token(), None);
         assert!(!defaults.signature());
+        assert_eq!(
+            defaults.signature_algorithm(),
+            SignatureAlgorithm::HmacSha256
+        );
+        assert!(defaults.signature_public_keys().is_empty());
         assert!(defaults.enabled());
         assert!(!defaults.preflight());
         assert_eq!(defaults.timeout(), DEFAULT_TIMEOUT);
+        assert_eq!(defaults.shared_cache_dir(), None);
+        assert!(defaults.cache_limits().is_unlimited());
+        assert_eq!(defaults.cache_layout(), CacheLayout::Archive);
     }
 
     #[test]
@@ -503,6 +626,9 @@ mod test {
         let turbo_teamid = "team_nLlpyC6REAqxydlFKbrMDlud";
         let turbo_token = "abcdef1234567890abcdef";
         let turbo_remote_cache_timeout = 200;
+        let turbo_shared_cache_dir = "/mnt/turbo-cache";
+        let turbo_cache_max_size = 1024;
+        let turbo_cache_max_age = 3600;
 
         env.insert("turbo_api".into(), turbo_api.into());
         env.insert("turbo_login".into(), turbo_login.into());
@@ -513,6 +639,19 @@ mod test {
             "turbo_remote_cache_timeout".into(),
             turbo_remote_cache_timeout.to_string().into(),
         );
+        env.insert(
+            "turbo_shared_cache_dir".into(),
+            turbo_shared_cache_dir.into(),
+        );
+        env.insert(
+            "turbo_cache_max_size".into(),
+            turbo_cache_max_size.to_string().into(),
+        );
+        env.insert(
+            "turbo_cache_max_age".into(),
+            turbo_cache_max_age.to_string().into(),
+        );
+        env.insert("turbo_cache_layout".into(), "content-addressed".into());
 
         let config = get_env_var_config(&env).unwrap();
         assert_eq!(turbo_api, config.api_url.unwrap());
@@ -521,6 +660,19 @@ mod test {
         assert_eq!(turbo_teamid, config.team_id.unwrap());
         assert_eq!(turbo_token, config.token.unwrap());
         assert_eq!(turbo_remote_cache_timeout, config.timeout.unwrap());
+        assert_eq!(
+            turbo_shared_cache_dir,
+            config.shared_cache_dir.as_deref().unwrap()
+        );
+        assert_eq!(
+            config.cache_limits(),
+            CacheLimits {
+                max_size: Some(turbo_cache_max_size),
+                max_age: Some(Duration::from_secs(turbo_cache_max_age)),
+                max_entries: None,
+            }
+        );
+        assert_eq!(config.cache_layout(), CacheLayout::ContentAddressed);
     }
 
     #[test]
@@ -29,6 +29,11 @@ impl BumpTimeout {
         }
     }
 
+    /// The amount of time that each reset pushes the deadline forward by.
+    pub fn increment(&self) -> Duration {
+        self.increment
+    }
+
     pub fn duration(&self) -> Duration {
         Duration::from_millis(self.deadline.load(Ordering::Relaxed))
     }
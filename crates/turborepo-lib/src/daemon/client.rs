@@ -3,7 +3,8 @@ use std::io;
 use thiserror::Error;
 use tonic::{Code, Status};
 use tracing::info;
-use turbopath::AbsoluteSystemPathBuf;
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPath, RelativeUnixPathBuf};
+use turborepo_scm::package_deps::GitHashes;
 
 use super::{
     connector::{DaemonConnector, DaemonConnectorError},
@@ -131,6 +132,65 @@ impl<T> DaemonClient<T> {
 
         Ok(response)
     }
+
+    /// Subscribe to the files that change in the repository. The stream stays
+    /// open until either side hangs up.
+    pub async fn file_changes(
+        &mut self,
+    ) -> Result<tonic::Streaming<proto::FileChangeEvent>, DaemonError> {
+        let stream = self
+            .client
+            .file_changes(proto::FileChangesRequest {})
+            .await?
+            .into_inner();
+
+        Ok(stream)
+    }
+
+    /// Get the hashes of the files of each package that are picked up by the
+    /// package's inputs. A package's hashes are `None` if the daemon hasn't
+    /// indexed them yet.
+    pub async fn get_file_hashes(
+        &mut self,
+        packages: &[(&AnchoredSystemPath, &[String])],
+    ) -> Result<Vec<Option<GitHashes>>, DaemonError> {
+        let response = self
+            .client
+            .get_file_hashes(proto::GetFileHashesRequest {
+                packages: packages
+                    .iter()
+                    .map(|(package_path, inputs)| proto::PackageInputs {
+                        package_path: package_path.to_string(),
+                        input_globs: inputs.to_vec(),
+                    })
+                    .collect(),
+            })
+            .await?
+            .into_inner();
+
+        if response.results.len() != packages.len() {
+            return Err(DaemonError::MalformedResponse);
+        }
+        response
+            .results
+            .into_iter()
+            .map(|result| {
+                let Some(file_hashes) = result.file_hashes else {
+                    return Ok(None);
+                };
+                file_hashes
+                    .hashes
+                    .into_iter()
+                    .map(|(path, hash)| {
+                        let path = RelativeUnixPathBuf::new(path)
+                            .map_err(|_| DaemonError::MalformedResponse)?;
+                        Ok((path, hash))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Some)
+            })
+            .collect()
+    }
 }
 
 impl DaemonClient<DaemonConnector> {
@@ -591,6 +591,9 @@ mod test {
 
     #[tonic::async_trait]
     impl proto::turbod_server::Turbod for DummyServer {
+        type FileChangesStream =
+            tokio_stream::wrappers::ReceiverStream<tonic::Result<proto::FileChangeEvent>>;
+
         async fn shutdown(
             &self,
             req: tonic::Request<proto::ShutdownRequest>,
@@ -644,6 +647,20 @@ mod test {
         ) -> Result<tonic::Response<proto::DiscoverPackagesResponse>, tonic::Status> {
             unimplemented!()
         }
+
+        async fn file_changes(
+            &self,
+            _req: tonic::Request<proto::FileChangesRequest>,
+        ) -> tonic::Result<tonic::Response<Self::FileChangesStream>> {
+            unimplemented!()
+        }
+
+        async fn get_file_hashes(
+            &self,
+            _req: tonic::Request<proto::GetFileHashesRequest>,
+        ) -> tonic::Result<tonic::Response<proto::GetFileHashesResponse>> {
+            unimplemented!()
+        }
     }
 
     #[tokio::test]
@@ -10,7 +10,9 @@
 //! holds a `HashGlobWatcher` which holds data about hashes, globs to watch for
 //! that hash, and files that have been updated for that hash. In addition, this
 //! server can be interrogated over grpc to register interest in particular
-//! globs, and to query for changes for those globs.
+//! globs, and to query for changes for those globs. It also holds a
+//! `HashWatcher` which keeps the git hashes of package files up to date so
+//! that runs don't need to rehash unchanged packages.
 
 use std::{
     collections::{HashMap, HashSet},
@@ -26,21 +28,24 @@ use semver::Version;
 use thiserror::Error;
 use tokio::{
     select,
-    sync::{mpsc, oneshot, watch, Mutex as AsyncMutex},
+    sync::{broadcast, mpsc, oneshot, watch, Mutex as AsyncMutex},
 };
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::{NamedService, Server};
 use tower::ServiceBuilder;
 use tracing::{error, info, trace, warn};
-use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf, PathError};
 use turborepo_filewatch::{
     cookie_jar::CookieJar,
     globwatcher::{Error as GlobWatcherError, GlobError, GlobSet, GlobWatcher},
+    hash_watcher::{Error as HashWatcherError, HashSpec, HashWatcher},
     package_watcher::PackageWatcher,
     FileSystemWatcher, WatchError,
 };
 use turborepo_repository::discovery::{
     LocalPackageDiscoveryBuilder, PackageDiscovery, PackageDiscoveryBuilder,
 };
+use turborepo_scm::package_deps::GitHashes;
 
 use super::{
     bump_timeout::BumpTimeout,
@@ -67,6 +72,7 @@ pub struct FileWatching {
     _watcher: FileSystemWatcher,
     pub glob_watcher: GlobWatcher,
     pub package_watcher: PackageWatcher,
+    pub hash_watcher: HashWatcher,
 }
 
 #[derive(Debug, Error)]
@@ -79,6 +85,10 @@ enum RpcError {
     GlobWatching(#[from] GlobWatcherError),
     #[error("filewatching unavailable")]
     NoFileWatching,
+    #[error("invalid package path: {0}")]
+    InvalidPackagePath(#[from] PathError),
+    #[error("file hashing failed: {0}")]
+    FileHashing(#[from] HashWatcherError),
 }
 
 impl From<RpcError> for tonic::Status {
@@ -90,6 +100,8 @@ impl From<RpcError> for tonic::Status {
             RpcError::InvalidGlob(e) => tonic::Status::invalid_argument(e.to_string()),
             RpcError::GlobWatching(e) => tonic::Status::unavailable(e.to_string()),
             RpcError::NoFileWatching => tonic::Status::unavailable("filewatching unavailable"),
+            RpcError::InvalidPackagePath(e) => tonic::Status::invalid_argument(e.to_string()),
+            RpcError::FileHashing(e) => tonic::Status::unavailable(e.to_string()),
         }
     }
 }
@@ -106,6 +118,18 @@ async fn start_filewatching<PD: PackageDiscovery + Send + 'static>(
         watcher.subscribe(),
     );
     let glob_watcher = GlobWatcher::new(&repo_root, cookie_jar, watcher.subscribe());
+    // The hash watcher gets its own cookies so that its requests don't wait on
+    // the glob watcher's
+    let hash_cookie_dir = watcher.cookie_dir().join_component("hashes");
+    hash_cookie_dir
+        .create_dir_all()
+        .map_err(|e| WatchError::Setup(format!("{:?}", e)))?;
+    let hash_cookie_jar = CookieJar::new(
+        &hash_cookie_dir,
+        Duration::from_millis(100),
+        watcher.subscribe(),
+    );
+    let hash_watcher = HashWatcher::new(repo_root.clone(), hash_cookie_jar, watcher.subscribe());
     let package_watcher =
         PackageWatcher::new(repo_root.clone(), watcher.subscribe(), backup_discovery)
             .await
@@ -116,6 +140,7 @@ async fn start_filewatching<PD: PackageDiscovery + Send + 'static>(
         _watcher: watcher,
         glob_watcher,
         package_watcher,
+        hash_watcher,
     })));
     Ok(())
 }
@@ -278,6 +303,8 @@ where
             times_saved: Arc::new(Mutex::new(HashMap::new())),
             start_time: Instant::now(),
             log_file: log_file.to_owned(),
+            repo_root: repo_root.to_owned(),
+            bump_timeout: bump_timeout.clone(),
         };
         let server_fut = {
             let service = ServiceBuilder::new()
@@ -323,6 +350,8 @@ struct TurboGrpcServiceInner<PD> {
     start_time: Instant,
     log_file: AbsoluteSystemPathBuf,
     package_discovery: AsyncMutex<PD>,
+    repo_root: AbsoluteSystemPathBuf,
+    bump_timeout: Arc<BumpTimeout>,
 }
 
 impl<PD> TurboGrpcServiceInner<PD> {
@@ -366,6 +395,83 @@ impl<PD> TurboGrpcServiceInner<PD> {
         let changed_globs = fw.glob_watcher.get_changed_globs(hash, candidates).await?;
         Ok((changed_globs, time_saved))
     }
+
+    async fn get_file_hashes(
+        &self,
+        packages: Vec<proto::PackageInputs>,
+    ) -> Result<Vec<Option<GitHashes>>, RpcError> {
+        let specs = packages
+            .into_iter()
+            .map(|package| {
+                Ok(HashSpec {
+                    package_path: AnchoredSystemPathBuf::from_raw(package.package_path)?,
+                    inputs: package.input_globs,
+                })
+            })
+            .collect::<Result<Vec<_>, RpcError>>()?;
+        let fw = self.wait_for_filewatching().await?;
+        let hashes = fw.hash_watcher.get_file_hashes(specs).await?;
+        Ok(hashes)
+    }
+
+    async fn file_changes(&self) -> Result<mpsc::Receiver<FileChangeResult>, RpcError> {
+        let fw = self.wait_for_filewatching().await?;
+        let mut events = fw._watcher.subscribe();
+        let repo_root = self.repo_root.clone();
+        let bump_timeout = self.bump_timeout.clone();
+        let (tx, rx) = mpsc::channel(FILE_CHANGES_BUFFER);
+        tokio::task::spawn(async move {
+            // Streaming requests only bump the timeout when they are opened, so we
+            // keep bumping it for as long as the client is listening.
+            let mut keep_alive = tokio::time::interval(bump_timeout.increment() / 2);
+            loop {
+                let event = select! {
+                    _ = tx.closed() => return,
+                    _ = keep_alive.tick() => {
+                        bump_timeout.reset();
+                        continue;
+                    }
+                    event = events.recv() => event,
+                };
+                let change = match event {
+                    Ok(Ok(event)) => {
+                        let changed_files = event
+                            .paths
+                            .iter()
+                            .filter_map(|path| {
+                                let path = AbsoluteSystemPath::from_std_path(path).ok()?;
+                                repo_root.anchor(path).ok()
+                            })
+                            .map(|path| path.to_string())
+                            .collect::<Vec<_>>();
+                        if changed_files.is_empty() {
+                            continue;
+                        }
+                        proto::FileChangeEvent {
+                            changed_files,
+                            rediscover: false,
+                        }
+                    }
+                    // We either missed events or the watcher hit an error, so we
+                    // can no longer say precisely which files changed.
+                    Ok(Err(_)) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        proto::FileChangeEvent {
+                            changed_files: vec![],
+                            rediscover: true,
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = tx.send(Err(RpcError::NoFileWatching.into())).await;
+                        return;
+                    }
+                };
+                if tx.send(Ok(change)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(rx)
+    }
 }
 
 async fn wait_for_filewatching(
@@ -423,11 +529,19 @@ async fn watch_root(
     }
 }
 
+type FileChangeResult = Result<proto::FileChangeEvent, tonic::Status>;
+
+/// The number of file change events that can be queued up for a client
+/// before we stop reading from the file watcher
+const FILE_CHANGES_BUFFER: usize = 64;
+
 #[tonic::async_trait]
 impl<PD> proto::turbod_server::Turbod for TurboGrpcServiceInner<PD>
 where
     PD: PackageDiscovery + Send + 'static,
 {
+    type FileChangesStream = ReceiverStream<FileChangeResult>;
+
     async fn hello(
         &self,
         request: tonic::Request<proto::HelloRequest>,
@@ -535,6 +649,35 @@ where
             })
             .map_err(|e| tonic::Status::internal(format!("{}", e)))
     }
+
+    async fn file_changes(
+        &self,
+        _request: tonic::Request<proto::FileChangesRequest>,
+    ) -> Result<tonic::Response<Self::FileChangesStream>, tonic::Status> {
+        let rx = self.file_changes().await?;
+        Ok(tonic::Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_file_hashes(
+        &self,
+        request: tonic::Request<proto::GetFileHashesRequest>,
+    ) -> Result<tonic::Response<proto::GetFileHashesResponse>, tonic::Status> {
+        let inner = request.into_inner();
+        let hashes = self.get_file_hashes(inner.packages).await?;
+        Ok(tonic::Response::new(proto::GetFileHashesResponse {
+            results: hashes
+                .into_iter()
+                .map(|hashes| proto::FileHashesResult {
+                    file_hashes: hashes.map(|hashes| proto::FileHashes {
+                        hashes: hashes
+                            .into_iter()
+                            .map(|(path, hash)| (path.to_string(), hash))
+                            .collect(),
+                    }),
+                })
+                .collect(),
+        }))
+    }
 }
 
 /// Determine whether a server can serve a client's request based on its
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 use itertools::Itertools;
 use turbopath::AbsoluteSystemPath;
@@ -9,7 +9,10 @@ use turborepo_repository::package_graph::{
 
 use super::Engine;
 use crate::{
-    config::{validate_extends, validate_no_package_task_syntax, RawTaskDefinition, TurboJson},
+    config::{
+        validate_extends, validate_no_boundaries, validate_no_package_task_syntax,
+        validate_no_resources, RawTaskDefinition, TurboJson,
+    },
     run::task_id::{TaskId, TaskName},
     task_graph::TaskDefinition,
 };
@@ -37,6 +40,21 @@ pub enum Error {
     Graph(#[from] graph::Error),
     #[error("Invalid task name {task_name}: {reason}")]
     InvalidTaskName { task_name: String, reason: String },
+    #[error(
+        "\"{task_id}\" requires resource \"{resource}\" which is not declared in the root \
+         turbo.json"
+    )]
+    UndeclaredResource { task_id: String, resource: String },
+    #[error(
+        "\"{task_id}\" requires {required} slots of resource \"{resource}\" but only {available} \
+         are declared in the root turbo.json"
+    )]
+    InsufficientResource {
+        task_id: String,
+        resource: String,
+        required: u32,
+        available: u32,
+    },
 }
 
 pub struct EngineBuilder<'a> {
@@ -145,8 +163,14 @@ impl<'a> EngineBuilder<'a> {
             return Err(Error::MissingTasks(missing_tasks.into_iter().join(", ")));
         }
 
+        let resources = self
+            .turbo_json(&mut turbo_jsons, &WorkspaceName::Root)?
+            .map(|turbo_json| turbo_json.resources.clone())
+            .unwrap_or_default();
+
         let mut visited = HashSet::new();
         let mut engine = Engine::default();
+        engine.set_resources(resources.clone());
 
         while let Some(task_id) = traversal_queue.pop_front() {
             if task_id.package() == ROOT_PKG_NAME
@@ -183,6 +207,7 @@ impl<'a> EngineBuilder<'a> {
             )?);
 
             let task_definition = TaskDefinition::try_from(raw_task_definition)?;
+            validate_task_resources(&task_id, &task_definition, &resources)?;
 
             // Skip this iteration of the loop if we've already seen this taskID
             if visited.contains(&task_id) {
@@ -325,8 +350,12 @@ impl<'a> EngineBuilder<'a> {
         if task_id.package() != ROOT_PKG_NAME {
             match self.turbo_json(turbo_jsons, &WorkspaceName::from(task_id.package())) {
                 Ok(Some(workspace_json)) => {
-                    let validation_errors = workspace_json
-                        .validate(&[validate_no_package_task_syntax, validate_extends]);
+                    let validation_errors = workspace_json.validate(&[
+                        validate_no_package_task_syntax,
+                        validate_extends,
+                        validate_no_resources,
+                        validate_no_boundaries,
+                    ]);
                     if !validation_errors.is_empty() {
                         let error_lines = validation_errors
                             .into_iter()
@@ -391,6 +420,32 @@ impl<'a> EngineBuilder<'a> {
     }
 }
 
+// A task that requires more of a resource than the repo declares could never be
+// scheduled, so we reject it up front instead of waiting forever
+fn validate_task_resources(
+    task_id: &TaskId,
+    task_definition: &TaskDefinition,
+    resources: &BTreeMap<String, u32>,
+) -> Result<(), Error> {
+    for (resource, required) in &task_definition.resources {
+        let Some(available) = resources.get(resource) else {
+            return Err(Error::UndeclaredResource {
+                task_id: task_id.to_string(),
+                resource: resource.clone(),
+            });
+        };
+        if required > available {
+            return Err(Error::InsufficientResource {
+                task_id: task_id.to_string(),
+                resource: resource.clone(),
+                required: *required,
+                available: *available,
+            });
+        }
+    }
+    Ok(())
+}
+
 impl Error {
     fn is_missing_turbo_json(&self) -> bool {
         matches!(self, Self::Config(crate::config::Error::NoTurboJSON))
@@ -416,7 +471,7 @@ fn validate_task_name(task: &str) -> Result<(), Error> {
 
 #[cfg(test)]
 mod test {
-    use std::assert_matches::assert_matches;
+    use std::{assert_matches::assert_matches, time::Duration};
 
     use pretty_assertions::assert_eq;
     use serde_json::json;
@@ -429,7 +484,10 @@ mod test {
     };
 
     use super::*;
-    use crate::{config::RawTurboJson, engine::TaskNode};
+    use crate::{
+        config::RawTurboJson,
+        engine::{DependencyReason, TaskNode, ValidateError},
+    };
 
     // Only used to prevent package graph construction from attempting to read
     // lockfile from disk
@@ -467,6 +525,10 @@ mod test {
         fn global_change(&self, _other: &dyn Lockfile) -> bool {
             unreachable!()
         }
+
+        fn diff(&self, _other: &dyn Lockfile) -> turborepo_lockfiles::LockfileDiff {
+            unreachable!()
+        }
     }
 
     struct MockDiscovery;
@@ -745,6 +807,78 @@ mod test {
         assert_eq!(all_dependencies(&engine), expected);
     }
 
+    #[test]
+    fn test_engine_subgraphs() {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let package_graph = mock_package_graph(
+            &repo_root,
+            package_jsons! {
+                repo_root,
+                "a" => [],
+                "b" => [],
+                "c" => ["a", "b"]
+            },
+        );
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "pipeline": {
+                    "build": { "dependsOn": ["^build"] },
+                    "dev": { "persistent": true },
+                    "b#dev": { "persistent": true, "interruptible": true },
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(vec![TaskName::from("build"), TaskName::from("dev")])
+            .with_workspaces(vec![
+                WorkspaceName::from("a"),
+                WorkspaceName::from("b"),
+                WorkspaceName::from("c"),
+            ])
+            .build()
+            .unwrap();
+
+        let subgraph = engine
+            .create_engine_for_subgraph(&Some(WorkspaceName::from("a")).into_iter().collect());
+        let expected = deps! {
+            "a#build" => ["___ROOT___"],
+            "a#dev" => ["___ROOT___"],
+            "c#build" => ["a#build"]
+        };
+        assert_eq!(all_dependencies(&subgraph), expected);
+
+        let expected = deps! {
+            "a#build" => ["___ROOT___"],
+            "c#build" => ["a#build"]
+        };
+        assert_eq!(
+            all_dependencies(&subgraph.create_engine_without_persistent_tasks()),
+            expected
+        );
+
+        let expected = deps! {
+            "a#dev" => ["___ROOT___"],
+            "c#dev" => ["___ROOT___"]
+        };
+        assert_eq!(
+            all_dependencies(&engine.create_engine_for_persistent_tasks(false)),
+            expected
+        );
+
+        let expected = deps! {
+            "b#dev" => ["___ROOT___"]
+        };
+        assert_eq!(
+            all_dependencies(&engine.create_engine_for_persistent_tasks(true)),
+            expected
+        );
+    }
+
     #[test]
     fn test_run_package_task() {
         let repo_root_dir = TempDir::new("repo").unwrap();
@@ -879,6 +1013,276 @@ mod test {
         assert_eq!(all_dependencies(&engine), expected);
     }
 
+    #[test]
+    fn test_paths_to_task() {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let package_graph = mock_package_graph(
+            &repo_root,
+            package_jsons! {
+                repo_root,
+                "app1" => ["libA"],
+                "libA" => []
+            },
+        );
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "pipeline": {
+                    "build": { "dependsOn": ["^build"] },
+                    "libA#build": { "dependsOn": ["//#root-task"] },
+                    "//#root-task": {},
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(Some(TaskName::from("build")))
+            .with_workspaces(vec![WorkspaceName::from("app1")])
+            .with_root_tasks(vec![
+                TaskName::from("//#root-task"),
+                TaskName::from("libA#build"),
+                TaskName::from("build"),
+            ])
+            .build()
+            .unwrap();
+
+        let root_task = TaskId::new("//", "root-task");
+        let (paths, total) = engine.paths_to(|task_id| task_id == &root_task, 10);
+        let app_build = TaskId::new("app1", "build").into_owned();
+        let lib_build = TaskId::new("libA", "build").into_owned();
+        assert_eq!(paths, vec![vec![&app_build, &lib_build, &root_task]]);
+        assert_eq!(total, 1);
+
+        // Both builds are targets, app1#build is requested directly and libA#build
+        // through app1#build
+        let (paths, total) = engine.paths_to(|task_id| task_id.task() == "build", 1);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(total, 2);
+
+        assert_eq!(
+            engine.dependency_reason(&app_build, &lib_build),
+            Some(DependencyReason::Topological(&TaskName::from("build")))
+        );
+        assert_eq!(
+            engine.dependency_reason(&lib_build, &root_task),
+            Some(DependencyReason::Task(&TaskName::from("//#root-task")))
+        );
+        assert_eq!(engine.dependency_reason(&root_task, &app_build), None);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let package_graph = mock_package_graph(
+            &repo_root,
+            package_jsons! {
+                repo_root,
+                "a" => [],
+                "b" => [],
+                "c" => ["a", "b"]
+            },
+        );
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "pipeline": {
+                    "build": { "dependsOn": ["^build"] },
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(Some(TaskName::from("build")))
+            .with_workspaces(vec![
+                WorkspaceName::from("a"),
+                WorkspaceName::from("b"),
+                WorkspaceName::from("c"),
+            ])
+            .build()
+            .unwrap();
+
+        let a_build = TaskId::new("a", "build").into_owned();
+        let b_build = TaskId::new("b", "build").into_owned();
+        let c_build = TaskId::new("c", "build").into_owned();
+        let durations: HashMap<_, _> = [
+            (a_build.clone(), Duration::from_secs(10)),
+            (b_build.clone(), Duration::from_secs(1)),
+            (c_build.clone(), Duration::from_secs(5)),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            engine.critical_path(&durations),
+            Some((vec![&a_build, &c_build], Duration::from_secs(15)))
+        );
+
+        let remaining = engine.remaining_critical_paths(&durations);
+        let remaining_for = |task_id: &TaskId<'static>| remaining[&engine.task_lookup[task_id]];
+        assert_eq!(remaining_for(&a_build), Duration::from_secs(15));
+        assert_eq!(remaining_for(&b_build), Duration::from_secs(6));
+        assert_eq!(remaining_for(&c_build), Duration::from_secs(5));
+
+        // Tasks without history are assumed to take the average duration
+        let durations = [(a_build.clone(), Duration::from_secs(10))]
+            .into_iter()
+            .collect();
+        let remaining = engine.remaining_critical_paths(&durations);
+        let remaining_for = |task_id: &TaskId<'static>| remaining[&engine.task_lookup[task_id]];
+        assert_eq!(remaining_for(&b_build), Duration::from_secs(20));
+    }
+
+    #[test_case(json!({ "browser": 1 }), true ; "resource fits")]
+    #[test_case(json!({ "docker": 1 }), false ; "undeclared resource")]
+    #[test_case(json!({ "browser": 2 }), false ; "more slots than declared")]
+    fn test_task_resources(task_resources: serde_json::Value, expected_ok: bool) {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let package_graph = mock_package_graph(
+            &repo_root,
+            package_jsons! {
+                repo_root,
+                "web" => []
+            },
+        );
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "resources": { "browser": 1 },
+                "pipeline": {
+                    "e2e": { "resources": task_resources },
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(Some(TaskName::from("e2e")))
+            .with_workspaces(vec![WorkspaceName::from("web")])
+            .build();
+
+        match engine {
+            Ok(engine) => {
+                assert!(expected_ok);
+                assert_eq!(
+                    engine.resources(),
+                    &[("browser".to_string(), 1)].into_iter().collect()
+                );
+            }
+            Err(e) => {
+                assert!(!expected_ok, "unexpected error: {e}");
+                assert_matches!(
+                    e,
+                    Error::UndeclaredResource { .. } | Error::InsufficientResource { .. }
+                );
+            }
+        }
+    }
+
+    #[test_case(json!({ "persistent": true }), false ; "persistent")]
+    #[test_case(json!({ "persistent": true, "readyWhen": { "port": 3000 } }), true ; "persistent with probe")]
+    fn test_depend_on_persistent_task(dev_definition: serde_json::Value, expected_ok: bool) {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let mut package_jsons = package_jsons! {
+            repo_root,
+            "api" => [],
+            "web" => ["api"]
+        };
+        for package_json in package_jsons.values_mut() {
+            package_json.scripts = [
+                ("dev".to_string(), "node server.js".to_string()),
+                ("e2e".to_string(), "playwright test".to_string()),
+            ]
+            .into_iter()
+            .collect();
+        }
+        let package_graph = mock_package_graph(&repo_root, package_jsons);
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "pipeline": {
+                    "dev": dev_definition,
+                    "e2e": { "dependsOn": ["^dev"] },
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(Some(TaskName::from("e2e")))
+            .with_workspaces(vec![WorkspaceName::from("web")])
+            .build()
+            .unwrap();
+
+        let result = engine.validate(&package_graph, 1);
+        match expected_ok {
+            true => assert!(result.is_ok()),
+            false => assert_matches!(
+                result.unwrap_err().as_slice(),
+                [
+                    ValidateError::DependencyOnPersistentTask { .. },
+                    ValidateError::PersistentTasksExceedConcurrency { .. }
+                ]
+            ),
+        }
+    }
+
+    #[test_case(json!({ "dependsOn": ["^dev"] }), Some(("web#e2e", "api#dev")) ; "task depends on service")]
+    #[test_case(json!({ "dependsOn": ["^build"] }), None ; "task depends on task")]
+    #[test_case(json!({ "dependsOn": ["^dev"], "persistent": true }), None ; "service depends on service")]
+    #[test_case(json!({ "dependsOn": ["^dev"], "persistent": true, "interruptible": true }), Some(("web#e2e", "api#dev")) ; "interruptible depends on service")]
+    fn test_watch_dependency_on_service(
+        e2e_definition: serde_json::Value,
+        expected: Option<(&str, &str)>,
+    ) {
+        let repo_root_dir = TempDir::new("repo").unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(repo_root_dir.path().to_str().unwrap()).unwrap();
+        let package_graph = mock_package_graph(
+            &repo_root,
+            package_jsons! {
+                repo_root,
+                "api" => [],
+                "web" => ["api"]
+            },
+        );
+        let turbo_jsons = vec![(
+            WorkspaceName::Root,
+            turbo_json(json!({
+                "pipeline": {
+                    "build": {},
+                    "dev": { "persistent": true, "readyWhen": { "port": 3000 } },
+                    "e2e": e2e_definition,
+                }
+            })),
+        )]
+        .into_iter()
+        .collect();
+        let engine = EngineBuilder::new(&repo_root, &package_graph, false)
+            .with_turbo_jsons(Some(turbo_jsons))
+            .with_tasks(Some(TaskName::from("e2e")))
+            .with_workspaces(vec![WorkspaceName::from("web")])
+            .build()
+            .unwrap();
+
+        // turbo watch runs the service separately, so e2e couldn't wait for it
+        let dependency = engine
+            .dependency_across_watch_runs()
+            .map(|(dependent, dependency)| (dependent.to_string(), dependency.to_string()));
+        assert_eq!(
+            dependency,
+            expected.map(|(dependent, dependency)| (dependent.to_string(), dependency.to_string()))
+        );
+    }
+
     #[test]
     fn test_depend_on_missing_task() {
         let repo_root_dir = TempDir::new("repo").unwrap();
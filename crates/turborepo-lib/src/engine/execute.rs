@@ -1,7 +1,12 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use futures::{stream::FuturesUnordered, StreamExt};
-use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::sync::{mpsc, oneshot};
 use tracing::log::debug;
 use turborepo_graph_utils::Walker;
 
@@ -18,10 +23,11 @@ pub struct Message<T, U> {
 type VisitorData = TaskId<'static>;
 type VisitorResult = Result<(), StopExecution>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExecutionOptions {
     parallel: bool,
     concurrency: usize,
+    task_durations: HashMap<TaskId<'static>, Duration>,
 }
 
 impl ExecutionOptions {
@@ -29,14 +35,23 @@ impl ExecutionOptions {
         Self {
             parallel,
             concurrency,
+            task_durations: HashMap::new(),
         }
     }
+
+    /// Estimated durations of tasks, used to start the tasks with the longest
+    /// remaining critical path first when several tasks are ready to run
+    pub fn with_task_durations(
+        mut self,
+        task_durations: HashMap<TaskId<'static>, Duration>,
+    ) -> Self {
+        self.task_durations = task_durations;
+        self
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ExecuteError {
-    #[error("Semaphore closed before all tasks finished")]
-    Semaphore(#[from] tokio::sync::AcquireError),
     #[error("Engine visitor closed channel before walk finished")]
     Visitor,
 }
@@ -72,8 +87,10 @@ impl Engine {
         let ExecutionOptions {
             parallel,
             concurrency,
+            task_durations,
         } = options;
-        let sema = Arc::new(Semaphore::new(concurrency));
+        let priorities = self.remaining_critical_paths(&task_durations);
+        let scheduler = Arc::new(Scheduler::new(concurrency, self.resources.clone()));
         let mut tasks: FuturesUnordered<tokio::task::JoinHandle<Result<(), ExecuteError>>> =
             FuturesUnordered::new();
 
@@ -82,9 +99,10 @@ impl Engine {
 
         while let Some((node_id, done)) = nodes.recv().await {
             let visitor = visitor.clone();
-            let sema = sema.clone();
+            let scheduler = scheduler.clone();
             let walker = walker.clone();
             let this = self.clone();
+            let priority = priorities.get(&node_id).copied().unwrap_or_default();
 
             tasks.push(tokio::spawn(async move {
                 let TaskNode::Task(task_id) = this
@@ -102,14 +120,17 @@ impl Engine {
                     return Ok(());
                 };
 
-                // Acquire the semaphore unless parallel
-                let _permit = match parallel {
-                    false => Some(sema.acquire().await.expect(
-                        "Graph concurrency semaphore closed while tasks are still attempting to \
-                         acquire permits",
-                    )),
-                    true => None,
+                // Acquire a concurrency slot unless parallel along with any resources the
+                // task requires
+                let request = Request {
+                    slot: !parallel,
+                    resources: this
+                        .task_definitions
+                        .get(task_id)
+                        .map(|definition| definition.resources.clone())
+                        .unwrap_or_default(),
                 };
+                let _permit = scheduler.acquire(priority, request).await;
 
                 let (message, result) = Message::new(task_id.clone());
                 visitor.send(message).await?;
@@ -143,9 +164,255 @@ impl Engine {
     }
 }
 
+/// Hands out slots of the global concurrency limit along with the slots of any
+/// resources a task requires. All of a task's slots are acquired at once so a
+/// waiting task never holds some slots while waiting on others, which means
+/// tasks contending for resources can't deadlock. Slots go to the waiter with
+/// the highest priority that they fit, so a task waiting on a busy resource
+/// doesn't stop other tasks from starting.
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+}
+
+struct SchedulerState {
+    available: usize,
+    resources: BTreeMap<String, u32>,
+    // Ordered by highest priority first, ties are broken by arrival order
+    waiters: BTreeMap<(Reverse<Duration>, u64), Waiter>,
+    next_seq: u64,
+}
+
+struct Waiter {
+    request: Request,
+    sender: oneshot::Sender<Permit>,
+}
+
+#[derive(Debug, Clone)]
+struct Request {
+    // Tasks run with --parallel don't count towards the concurrency limit
+    slot: bool,
+    resources: BTreeMap<String, u32>,
+}
+
+struct Permit {
+    scheduler: Arc<Scheduler>,
+    request: Request,
+}
+
+impl Scheduler {
+    fn new(concurrency: usize, resources: BTreeMap<String, u32>) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                available: concurrency,
+                resources,
+                waiters: BTreeMap::new(),
+                next_seq: 0,
+            }),
+        }
+    }
+
+    async fn acquire(self: &Arc<Self>, priority: Duration, request: Request) -> Permit {
+        let receiver = {
+            let mut state = self.state.lock().expect("scheduler mutex poisoned");
+            if state.try_take(&request) {
+                return Permit {
+                    scheduler: self.clone(),
+                    request,
+                };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state
+                .waiters
+                .insert((Reverse(priority), seq), Waiter { request, sender });
+            receiver
+        };
+        receiver
+            .await
+            .expect("waiters are always sent a permit before being dropped")
+    }
+
+    fn release(self: &Arc<Self>, request: &Request) {
+        let granted = {
+            let mut state = self.state.lock().expect("scheduler mutex poisoned");
+            state.give_back(request);
+            let mut ready = Vec::new();
+            let SchedulerState {
+                available,
+                resources,
+                waiters,
+                ..
+            } = &mut *state;
+            for (key, waiter) in waiters.iter() {
+                if try_take(available, resources, &waiter.request) {
+                    ready.push(*key);
+                }
+            }
+            ready
+                .into_iter()
+                .filter_map(|key| state.waiters.remove(&key))
+                .collect::<Vec<_>>()
+        };
+        // Permits are sent without holding the lock since a permit for a waiter
+        // that has gone away gets dropped, which releases its slots again
+        for Waiter { request, sender } in granted {
+            let _ = sender.send(Permit {
+                scheduler: self.clone(),
+                request,
+            });
+        }
+    }
+}
+
+impl SchedulerState {
+    fn try_take(&mut self, request: &Request) -> bool {
+        try_take(&mut self.available, &mut self.resources, request)
+    }
+
+    fn give_back(&mut self, request: &Request) {
+        if request.slot {
+            self.available += 1;
+        }
+        for (resource, required) in &request.resources {
+            if let Some(available) = self.resources.get_mut(resource) {
+                *available += required;
+            }
+        }
+    }
+}
+
+// Takes all of the slots for the request or none of them
+fn try_take(
+    available: &mut usize,
+    resources: &mut BTreeMap<String, u32>,
+    request: &Request,
+) -> bool {
+    let fits = (!request.slot || *available > 0)
+        && request.resources.iter().all(|(resource, required)| {
+            resources
+                .get(resource)
+                .map_or(false, |available| available >= required)
+        });
+    if !fits {
+        return false;
+    }
+    if request.slot {
+        *available -= 1;
+    }
+    for (resource, required) in &request.resources {
+        if let Some(available) = resources.get_mut(resource) {
+            *available -= required;
+        }
+    }
+    true
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.request);
+    }
+}
+
 impl<T, U> Message<T, U> {
     pub fn new(info: T) -> (Self, oneshot::Receiver<U>) {
         let (callback, receiver) = oneshot::channel();
         (Self { info, callback }, receiver)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+    use super::{Request, Scheduler};
+
+    fn request(resources: &[(&str, u32)]) -> Request {
+        Request {
+            slot: true,
+            resources: resources
+                .iter()
+                .map(|(resource, slots)| (resource.to_string(), *slots))
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_priority_scheduler() {
+        let scheduler = Arc::new(Scheduler::new(1, BTreeMap::new()));
+        let permit = scheduler.acquire(Duration::ZERO, request(&[])).await;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut waiters = Vec::new();
+        for priority in [1, 3, 2, 3] {
+            let scheduler = scheduler.clone();
+            let tx = tx.clone();
+            waiters.push(tokio::spawn(async move {
+                let _permit = scheduler
+                    .acquire(Duration::from_secs(priority), request(&[]))
+                    .await;
+                tx.send(priority).unwrap();
+            }));
+            // Make sure the waiter is queued before the next one
+            tokio::task::yield_now().await;
+        }
+        drop(tx);
+        drop(permit);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+
+        let mut order = Vec::new();
+        while let Some(priority) = rx.recv().await {
+            order.push(priority);
+        }
+        assert_eq!(order, vec![3, 3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_resources() {
+        let resources = [("browser".to_string(), 1), ("docker".to_string(), 2)]
+            .into_iter()
+            .collect();
+        let scheduler = Arc::new(Scheduler::new(3, resources));
+        let browser = scheduler
+            .acquire(Duration::ZERO, request(&[("browser", 1)]))
+            .await;
+
+        // Needs the browser which is held, so it has to wait without taking
+        // any of the docker slots
+        let waiting = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move {
+                scheduler
+                    .acquire(
+                        Duration::from_secs(10),
+                        request(&[("browser", 1), ("docker", 2)]),
+                    )
+                    .await
+            }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        // A lower priority task can still use the free slots in the meantime
+        let docker = scheduler
+            .acquire(Duration::ZERO, request(&[("docker", 2)]))
+            .await;
+        drop(docker);
+
+        drop(browser);
+        let permit = waiting.await.unwrap();
+        {
+            let state = scheduler.state.lock().unwrap();
+            assert_eq!(state.available, 2);
+            assert_eq!(state.resources.get("browser"), Some(&0));
+            assert_eq!(state.resources.get("docker"), Some(&0));
+        }
+        drop(permit);
+        let state = scheduler.state.lock().unwrap();
+        assert_eq!(state.available, 3);
+        assert_eq!(state.resources.get("browser"), Some(&1));
+        assert_eq!(state.resources.get("docker"), Some(&2));
+    }
+}
@@ -5,8 +5,9 @@ mod dot;
 mod mermaid;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt,
+    time::Duration,
 };
 
 pub use builder::{EngineBuilder, Error as BuilderError};
@@ -16,7 +17,10 @@ use petgraph::Graph;
 use thiserror::Error;
 use turborepo_repository::package_graph::{PackageGraph, WorkspaceName};
 
-use crate::{run::task_id::TaskId, task_graph::TaskDefinition};
+use crate::{
+    run::task_id::{TaskId, TaskName},
+    task_graph::TaskDefinition,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum TaskNode {
@@ -30,6 +34,20 @@ impl From<TaskId<'static>> for TaskNode {
     }
 }
 
+/// The `dependsOn` entry that caused a task to depend on another task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyReason<'a> {
+    /// The task is listed directly e.g. `build` or `ui#build`
+    Task(&'a TaskName<'static>),
+    /// The task is run in the workspace's internal dependencies e.g. `^build`
+    Topological(&'a TaskName<'static>),
+}
+
+// The duration of the longest path from a task through its dependents, along
+// with the next task on that path
+type RemainingDurations =
+    HashMap<petgraph::graph::NodeIndex, (Duration, Option<petgraph::graph::NodeIndex>)>;
+
 #[derive(Debug, Default)]
 pub struct Building;
 #[derive(Debug, Default)]
@@ -42,6 +60,8 @@ pub struct Engine<S = Built> {
     root_index: petgraph::graph::NodeIndex,
     task_lookup: HashMap<TaskId<'static>, petgraph::graph::NodeIndex>,
     task_definitions: HashMap<TaskId<'static>, TaskDefinition>,
+    // Number of slots of each resource that can be held by running tasks
+    resources: BTreeMap<String, u32>,
 }
 
 impl Engine<Building> {
@@ -54,6 +74,7 @@ impl Engine<Building> {
             root_index,
             task_lookup: HashMap::default(),
             task_definitions: HashMap::default(),
+            resources: BTreeMap::default(),
         }
     }
 
@@ -78,6 +99,10 @@ impl Engine<Building> {
         self.task_definitions.insert(task_id, definition)
     }
 
+    pub fn set_resources(&mut self, resources: BTreeMap<String, u32>) {
+        self.resources = resources;
+    }
+
     // Seals the task graph from being mutated
     pub fn seal(self) -> Engine<Built> {
         let Engine {
@@ -85,6 +110,7 @@ impl Engine<Building> {
             task_lookup,
             root_index,
             task_definitions,
+            resources,
             ..
         } = self;
         Engine {
@@ -93,6 +119,7 @@ impl Engine<Building> {
             task_lookup,
             root_index,
             task_definitions,
+            resources,
         }
     }
 }
@@ -140,10 +167,332 @@ impl Engine<Built> {
         self.task_graph.node_weights()
     }
 
+    /// Returns the tasks ordered so that each task comes after all of its
+    /// dependencies, or `None` if the task graph contains a cycle
+    pub fn tasks_in_dependency_order(&self) -> Option<Vec<&TaskId<'static>>> {
+        // Edges point from a task to its dependencies, so a topological sort
+        // puts dependents first
+        let sorted = petgraph::algo::toposort(&self.task_graph, None).ok()?;
+        Some(
+            sorted
+                .into_iter()
+                .rev()
+                .filter_map(|index| match &self.task_graph[index] {
+                    TaskNode::Task(task_id) => Some(task_id),
+                    TaskNode::Root => None,
+                })
+                .collect(),
+        )
+    }
+
     pub fn task_definitions(&self) -> &HashMap<TaskId<'static>, TaskDefinition> {
         &self.task_definitions
     }
 
+    pub fn resources(&self) -> &BTreeMap<String, u32> {
+        &self.resources
+    }
+
+    /// Returns up to `limit` chains of tasks that lead from a task that no
+    /// other task depends on to a task matching `is_target`, along with the
+    /// total number of chains. Each chain starts with the task that was
+    /// requested and ends with the target.
+    pub fn paths_to(
+        &self,
+        is_target: impl Fn(&TaskId) -> bool,
+        limit: usize,
+    ) -> (Vec<Vec<&TaskId<'static>>>, usize) {
+        let targets = self
+            .task_lookup
+            .iter()
+            .filter(|(task_id, _)| is_target(task_id))
+            .map(|(_, index)| *index)
+            .collect::<Vec<_>>();
+        // The number of chains can grow exponentially with the size of the
+        // graph, so they're counted without being enumerated
+        let total = self.count_paths_to(&targets);
+
+        let mut stack = targets
+            .into_iter()
+            .map(|index| vec![index])
+            .collect::<Vec<_>>();
+        let mut paths = Vec::new();
+        while let Some(path) = stack.pop() {
+            if paths.len() >= limit {
+                break;
+            }
+            let last = *path.last().expect("paths are never empty");
+            let mut has_dependents = false;
+            for dependent in self
+                .task_graph
+                .neighbors_directed(last, petgraph::Direction::Incoming)
+            {
+                if path.contains(&dependent) {
+                    continue;
+                }
+                has_dependents = true;
+                let mut path = path.clone();
+                path.push(dependent);
+                stack.push(path);
+            }
+            if !has_dependents {
+                paths.push(
+                    path.into_iter()
+                        .rev()
+                        .filter_map(|index| match &self.task_graph[index] {
+                            TaskNode::Task(task_id) => Some(task_id),
+                            TaskNode::Root => None,
+                        })
+                        .collect(),
+                );
+            }
+        }
+        paths.sort();
+        let total = total.unwrap_or(paths.len());
+        (paths, total)
+    }
+
+    // Counts the chains of dependents leading to the targets, returns None if
+    // the task graph has a cycle
+    fn count_paths_to(&self, targets: &[petgraph::graph::NodeIndex]) -> Option<usize> {
+        // Edges point from a task to its dependencies, so a topological sort
+        // puts dependents first
+        let sorted = petgraph::algo::toposort(&self.task_graph, None).ok()?;
+        let mut counts = HashMap::with_capacity(sorted.len());
+        for index in sorted {
+            let count = self
+                .task_graph
+                .neighbors_directed(index, petgraph::Direction::Incoming)
+                .fold(0usize, |count, dependent| {
+                    count.saturating_add(counts[&dependent])
+                });
+            // A task that nothing depends on is the start of a single chain
+            counts.insert(index, count.max(1));
+        }
+        Some(
+            targets
+                .iter()
+                .fold(0usize, |total, target| total.saturating_add(counts[target])),
+        )
+    }
+
+    /// Returns the chain of tasks that took the longest to run, ordered from
+    /// the first task to run to the last, along with its total duration. Tasks
+    /// without a duration are treated as taking no time.
+    pub fn critical_path(
+        &self,
+        durations: &HashMap<TaskId<'static>, Duration>,
+    ) -> Option<(Vec<&TaskId<'static>>, Duration)> {
+        let remaining = self
+            .remaining_durations(|task_id| durations.get(task_id).copied().unwrap_or_default())?;
+        let (mut index, (duration, _)) = remaining
+            .iter()
+            .filter(|(index, _)| matches!(self.task_graph[**index], TaskNode::Task(_)))
+            .max_by_key(|(index, (duration, _))| (*duration, std::cmp::Reverse(**index)))?;
+        let duration = *duration;
+        let mut path = Vec::new();
+        loop {
+            if let TaskNode::Task(task_id) = &self.task_graph[*index] {
+                path.push(task_id);
+            }
+            match &remaining[index].1 {
+                Some(next) => index = next,
+                None => break,
+            }
+        }
+        Some((path, duration))
+    }
+
+    /// For each task, estimates how long it will take from when the task
+    /// starts until every task that depends on it has finished. Tasks that
+    /// haven't run before are assumed to take the average of those that have.
+    pub fn remaining_critical_paths(
+        &self,
+        durations: &HashMap<TaskId<'static>, Duration>,
+    ) -> HashMap<petgraph::graph::NodeIndex, Duration> {
+        let average = match durations.len() {
+            0 => Duration::ZERO,
+            len => durations.values().sum::<Duration>() / len as u32,
+        };
+        self.remaining_durations(|task_id| durations.get(task_id).copied().unwrap_or(average))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(index, (duration, _))| (index, duration))
+            .collect()
+    }
+
+    // Computes the longest path starting at each task and continuing through
+    // its dependents along with the next task on that path, or `None` if the
+    // task graph contains a cycle
+    fn remaining_durations(
+        &self,
+        duration: impl Fn(&TaskId<'static>) -> Duration,
+    ) -> Option<RemainingDurations> {
+        // Edges point from a task to its dependencies, so a topological sort
+        // visits every task after the tasks that depend on it
+        let sorted = petgraph::algo::toposort(&self.task_graph, None).ok()?;
+        let mut remaining = HashMap::with_capacity(sorted.len());
+        for index in sorted {
+            let own_duration = match &self.task_graph[index] {
+                TaskNode::Task(task_id) => duration(task_id),
+                TaskNode::Root => Duration::ZERO,
+            };
+            let next = self
+                .task_graph
+                .neighbors_directed(index, petgraph::Direction::Incoming)
+                .max_by_key(|dependent| {
+                    let (dependent_duration, _) = remaining[dependent];
+                    (dependent_duration, std::cmp::Reverse(*dependent))
+                });
+            let next_duration = next.map_or(Duration::ZERO, |next| remaining[&next].0);
+            remaining.insert(index, (own_duration + next_duration, next));
+        }
+        Some(remaining)
+    }
+
+    /// Determines which `dependsOn` entry of `dependent` created its edge to
+    /// `dependency`
+    pub fn dependency_reason(
+        &self,
+        dependent: &TaskId<'static>,
+        dependency: &TaskId,
+    ) -> Option<DependencyReason> {
+        let definition = self.task_definitions.get(dependent)?;
+        let task_dependency = definition.task_dependencies.iter().find(|task_name| {
+            let package = task_name.package().unwrap_or(dependent.package());
+            package == dependency.package() && task_name.task() == dependency.task()
+        });
+        if let Some(task_name) = task_dependency {
+            return Some(DependencyReason::Task(task_name));
+        }
+
+        definition
+            .topological_dependencies
+            .iter()
+            .find(|task_name| {
+                dependent.package() != dependency.package() && task_name.task() == dependency.task()
+            })
+            .map(DependencyReason::Topological)
+    }
+
+    /// Creates an engine with only the tasks that belong to one of the given
+    /// packages, along with every task that depends on them. Dependencies on
+    /// tasks outside of this subgraph are dropped.
+    pub fn create_engine_for_subgraph(
+        &self,
+        changed_packages: &HashSet<WorkspaceName>,
+    ) -> Engine<Built> {
+        let mut affected = HashSet::new();
+        let mut stack = self
+            .task_lookup
+            .iter()
+            .filter(|(task_id, _)| {
+                changed_packages.contains(&WorkspaceName::from(task_id.package()))
+            })
+            .map(|(_, index)| *index)
+            .collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            let TaskNode::Task(task_id) = &self.task_graph[index] else {
+                continue;
+            };
+            if affected.insert(task_id) {
+                stack.extend(
+                    self.task_graph
+                        .neighbors_directed(index, petgraph::Direction::Incoming),
+                );
+            }
+        }
+
+        self.retain_tasks(|task_id| affected.contains(task_id))
+    }
+
+    /// Creates an engine with only the persistent tasks that are (or aren't)
+    /// interruptible.
+    pub fn create_engine_for_persistent_tasks(&self, interruptible: bool) -> Engine<Built> {
+        self.retain_tasks(|task_id| {
+            self.task_definitions
+                .get(task_id)
+                .map_or(false, |definition| {
+                    definition.persistent && definition.interruptible == interruptible
+                })
+        })
+    }
+
+    /// Creates an engine with all of the tasks that are expected to exit.
+    pub fn create_engine_without_persistent_tasks(&self) -> Engine<Built> {
+        self.retain_tasks(|task_id| {
+            self.task_definitions
+                .get(task_id)
+                .map_or(true, |definition| !definition.persistent)
+        })
+    }
+
+    /// Finds a task that depends on a persistent task that `turbo watch` runs
+    /// separately from it. Watch only starts persistent tasks once the tasks
+    /// that exit have finished, and restarts interruptible ones on their own,
+    /// so those dependencies would be dropped.
+    pub fn dependency_across_watch_runs(&self) -> Option<(&TaskId<'static>, &TaskId<'static>)> {
+        // The persistent tasks that run together, `None` for tasks that exit
+        let watch_run = |task_id: &TaskId| {
+            self.task_definitions
+                .get(task_id)
+                .filter(|definition| definition.persistent)
+                .map(|definition| definition.interruptible)
+        };
+
+        self.task_lookup
+            .iter()
+            .flat_map(|(task_id, index)| {
+                self.task_graph
+                    .neighbors_directed(*index, petgraph::Direction::Outgoing)
+                    .filter_map(move |dep_index| match &self.task_graph[dep_index] {
+                        TaskNode::Task(dep_id) => Some((task_id, dep_id)),
+                        TaskNode::Root => None,
+                    })
+            })
+            .filter(|(task_id, dep_id)| {
+                let dep_run = watch_run(*dep_id);
+                dep_run.is_some() && dep_run != watch_run(*task_id)
+            })
+            .min()
+    }
+
+    // Rebuilds the graph with only the tasks that we should keep. Any task that
+    // loses all of its dependencies gets connected to the root.
+    fn retain_tasks(&self, should_keep: impl Fn(&TaskId<'static>) -> bool) -> Engine<Built> {
+        let mut engine = Engine::<Building>::new();
+        engine.set_resources(self.resources.clone());
+        for (task_id, index) in self.task_lookup.iter() {
+            if !should_keep(task_id) {
+                continue;
+            }
+            let source = engine.get_index(task_id);
+            if let Some(definition) = self.task_definitions.get(task_id) {
+                engine.add_definition(task_id.clone(), definition.clone());
+            }
+
+            let mut has_dependencies = false;
+            for dep_index in self
+                .task_graph
+                .neighbors_directed(*index, petgraph::Direction::Outgoing)
+            {
+                let TaskNode::Task(dep_id) = &self.task_graph[dep_index] else {
+                    continue;
+                };
+                if should_keep(dep_id) {
+                    let target = engine.get_index(dep_id);
+                    engine.task_graph.update_edge(source, target, ());
+                    has_dependencies = true;
+                }
+            }
+            if !has_dependencies {
+                engine.connect_to_root(task_id);
+            }
+        }
+
+        engine.seal()
+    }
+
     pub fn validate(
         &self,
         package_graph: &PackageGraph,
@@ -163,10 +512,14 @@ impl Engine<Built> {
                     // No need to check the root node if that's where we are.
                     return Ok(false);
                 };
+                // Persistent tasks with a readiness probe stop holding a concurrency slot
+                // once they're ready, so they don't count towards the limit
                 let is_persistent = self
                     .task_definitions
                     .get(task_id)
-                    .map_or(false, |task_def| task_def.persistent);
+                    .map_or(false, |task_def| {
+                        task_def.persistent && task_def.ready_when.is_none()
+                    });
 
                 for dep_index in self
                     .task_graph
@@ -193,7 +546,10 @@ impl Engine<Built> {
                         .ok_or_else(|| ValidateError::MissingPackageJson {
                             package: dep_id.package().to_string(),
                         })?;
+                    // Tasks can only depend on a persistent task if there is a way to tell
+                    // when it is ready
                     if task_definition.persistent
+                        && task_definition.ready_when.is_none()
                         && package_json.scripts.contains_key(dep_id.task())
                     {
                         return Err(ValidateError::DependencyOnPersistentTask {
@@ -237,7 +593,10 @@ pub enum ValidateError {
     },
     #[error("Cannot find package {package}")]
     MissingPackageJson { package: String },
-    #[error("\"{persistent_task}\" is a persistent task, \"{dependant}\" cannot depend on it")]
+    #[error(
+        "\"{persistent_task}\" is a persistent task, \"{dependant}\" cannot depend on it unless \
+         it has \"readyWhen\" set"
+    )]
     DependencyOnPersistentTask {
         persistent_task: String,
         dependant: String,
@@ -5,7 +5,10 @@ use turbopath::AnchoredSystemPathBuf;
 use turborepo_cache::CacheOpts;
 
 use crate::{
-    cli::{Command, DryRunMode, EnvMode, LogOrder, LogPrefix, OutputLogsMode, RunArgs},
+    cli::{
+        CheckOutputsMode, Command, DryRunMode, EnvMode, LogOrder, LogPrefix, OutputLogsMode,
+        RunArgs, SummarizeMode, UIMode,
+    },
     run::task_id::TaskId,
     Args,
 };
@@ -51,6 +54,10 @@ impl<'a> Opts<'a> {
             cmd.push_str(pattern);
         }
 
+        if self.scope_opts.affected {
+            cmd.push_str(" --affected");
+        }
+
         if self.run_opts.parallel {
             cmd.push_str(" --parallel");
         }
@@ -129,13 +136,16 @@ pub struct RunOpts<'a> {
     pub(crate) continue_on_error: bool,
     pub(crate) pass_through_args: &'a [String],
     pub(crate) only: bool,
+    pub(crate) explain_miss: bool,
+    pub(crate) check_outputs: Option<CheckOutputsMode>,
     pub(crate) dry_run: Option<DryRunMode>,
     pub graph: Option<GraphOpts<'a>>,
     pub(crate) daemon: Option<bool>,
     pub(crate) single_package: bool,
     pub log_prefix: ResolvedLogPrefix,
     pub log_order: ResolvedLogOrder,
-    pub summarize: Option<Option<bool>>,
+    pub ui: UIMode,
+    pub summarize: Option<SummarizeMode>,
     pub(crate) experimental_space_id: Option<String>,
     pub is_github_actions: bool,
 }
@@ -212,6 +222,7 @@ impl<'a> TryFrom<&'a RunArgs> for RunOpts<'a> {
             tasks: args.tasks.as_slice(),
             log_prefix,
             log_order,
+            ui: args.ui,
             summarize: args.summarize,
             experimental_space_id: args.experimental_space_id.clone(),
             framework_inference: args.framework_inference,
@@ -222,6 +233,8 @@ impl<'a> TryFrom<&'a RunArgs> for RunOpts<'a> {
             continue_on_error: args.continue_execution,
             pass_through_args: args.pass_through_args.as_ref(),
             only: args.only,
+            explain_miss: args.explain_miss,
+            check_outputs: args.check_outputs,
             daemon: args.daemon(),
             single_package: args.single_package,
             graph,
@@ -313,6 +326,7 @@ pub struct ScopeOpts {
     pub global_deps: Vec<String>,
     pub filter_patterns: Vec<String>,
     pub ignore_patterns: Vec<String>,
+    pub affected: bool,
 }
 
 impl<'a> TryFrom<&'a RunArgs> for ScopeOpts {
@@ -337,6 +351,7 @@ impl<'a> TryFrom<&'a RunArgs> for ScopeOpts {
             legacy_filter,
             filter_patterns: args.filter.clone(),
             ignore_patterns: args.ignore.clone(),
+            affected: args.affected,
         })
     }
 }
@@ -523,12 +538,15 @@ mod test {
             continue_on_error: opts_input.continue_on_error,
             pass_through_args: &opts_input.pass_through_args,
             only: opts_input.only,
+            explain_miss: false,
+            check_outputs: None,
             dry_run: opts_input.dry_run,
             graph: None,
             daemon: None,
             single_package: false,
             log_prefix: crate::opts::ResolvedLogPrefix::Task,
             log_order: crate::opts::ResolvedLogOrder::Stream,
+            ui: crate::cli::UIMode::Stream,
             summarize: None,
             experimental_space_id: None,
             is_github_actions: false,
@@ -542,6 +560,7 @@ mod test {
             global_deps: vec![],
             filter_patterns: opts_input.filter_patterns,
             ignore_patterns: vec![],
+            affected: false,
         };
         let opts = Opts {
             run_opts,
@@ -105,6 +105,16 @@ impl RunCache {
         }
     }
 
+    /// Checks the remote cache for all of `hashes` at once, ahead of their
+    /// tasks being executed
+    pub async fn prefetch(&self, hashes: &[String]) {
+        if self.reads_disabled {
+            return;
+        }
+
+        self.cache.prefetch_exists(hashes).await
+    }
+
     pub async fn wait_for_cache(&self) {
         self.cache.wait().await
     }
@@ -124,6 +134,20 @@ pub struct TaskCache {
 }
 
 impl TaskCache {
+    /// Whether outputs of this task can be restored from the cache
+    pub fn is_readable(&self) -> bool {
+        !self.caching_disabled && !self.run_cache.reads_disabled
+    }
+
+    /// Whether outputs of this task are saved to the cache
+    pub fn is_writable(&self) -> bool {
+        !self.caching_disabled && !self.run_cache.writes_disabled
+    }
+
+    pub fn log_file(&self) -> &AbsoluteSystemPath {
+        &self.log_file_path
+    }
+
     pub fn replay_log_file(&self, prefixed_ui: &mut PrefixedUI<impl Write>) -> Result<(), Error> {
         if self.log_file_path.exists() {
             replay_logs(prefixed_ui, &self.log_file_path)?;
@@ -13,6 +13,14 @@ use crate::{
 pub enum Error {
     #[error("error preparing engine: Invalid persistent task configuration:\n{0}")]
     EngineValidation(String),
+    #[error(
+        "\"{dependent}\" depends on the persistent task \"{dependency}\", which turbo watch runs \
+         separately and can't wait for. Use turbo run for these tasks instead."
+    )]
+    WatchDependencyOnPersistentTask {
+        dependent: String,
+        dependency: String,
+    },
     #[error(transparent)]
     Graph(#[from] graph_visualizer::Error),
     #[error(transparent)]
@@ -0,0 +1,330 @@
+//! Records the inputs that went into each task hash so that a cache miss can
+//! be explained by comparing them with the inputs of the last cached run of
+//! the same task.
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, RelativeUnixPathBuf};
+use turborepo_env::EnvironmentVariablePairs;
+
+use super::{global_hash::GlobalHashableInputs, task_id::TaskId};
+
+const HASH_INPUTS_DIR: &str = "hash-inputs";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unable to access hash inputs: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unable to parse hash inputs: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalHashInputs {
+    pub hash: String,
+    pub files: BTreeMap<RelativeUnixPathBuf, String>,
+    // Map of environment variable names to hashes of their values
+    pub env: BTreeMap<String, String>,
+    pub external_dependencies_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskHashInputs {
+    pub hash: String,
+    pub global: GlobalHashInputs,
+    pub files: BTreeMap<RelativeUnixPathBuf, String>,
+    // Map of environment variable names to hashes of their values
+    pub env: BTreeMap<String, String>,
+    // Map of dependency task ids to their hashes
+    pub dependencies: BTreeMap<String, String>,
+    pub external_dependencies_hash: Option<String>,
+    // Fields of the task definition that are part of the hash
+    pub definition: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A single difference between the inputs of two task hashes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputChange {
+    GlobalFile(RelativeUnixPathBuf, Change),
+    GlobalEnv(String, Change),
+    GlobalExternalDependencies,
+    // The global hash changed, but none of the recorded global inputs did
+    Global,
+    File(RelativeUnixPathBuf, Change),
+    Env(String, Change),
+    Dependency(String, Change),
+    ExternalDependencies,
+    Definition(String),
+}
+
+/// Reads and writes the hash inputs of the last cached run of each task
+#[derive(Debug, Clone)]
+pub struct HashInputsStore {
+    dir: AbsoluteSystemPathBuf,
+}
+
+impl GlobalHashInputs {
+    pub fn new(hash: String, inputs: &GlobalHashableInputs) -> Self {
+        Self {
+            hash,
+            files: inputs
+                .global_file_hash_map
+                .iter()
+                .map(|(path, hash)| (path.clone(), hash.clone()))
+                .collect(),
+            env: inputs
+                .resolved_env_vars
+                .as_ref()
+                .map(|env| env_map(env.all.to_secret_hashable()))
+                .unwrap_or_default(),
+            external_dependencies_hash: inputs.root_external_dependencies_hash.map(String::from),
+        }
+    }
+}
+
+impl TaskHashInputs {
+    /// Lists every input that differs between `previous` and `self`
+    pub fn changes_since(&self, previous: &TaskHashInputs) -> Vec<InputChange> {
+        let mut changes = Vec::new();
+
+        if self.global.hash != previous.global.hash {
+            changes.extend(
+                diff(&previous.global.files, &self.global.files)
+                    .map(|(path, change)| InputChange::GlobalFile(path, change)),
+            );
+            changes.extend(
+                diff(&previous.global.env, &self.global.env)
+                    .map(|(name, change)| InputChange::GlobalEnv(name, change)),
+            );
+            if self.global.external_dependencies_hash != previous.global.external_dependencies_hash
+            {
+                changes.push(InputChange::GlobalExternalDependencies);
+            }
+            if changes.is_empty() {
+                changes.push(InputChange::Global);
+            }
+        }
+
+        changes.extend(
+            diff(&previous.files, &self.files)
+                .map(|(path, change)| InputChange::File(path, change)),
+        );
+        changes.extend(
+            diff(&previous.env, &self.env).map(|(name, change)| InputChange::Env(name, change)),
+        );
+        changes.extend(
+            diff(&previous.dependencies, &self.dependencies)
+                .map(|(task_id, change)| InputChange::Dependency(task_id, change)),
+        );
+        if self.external_dependencies_hash != previous.external_dependencies_hash {
+            changes.push(InputChange::ExternalDependencies);
+        }
+        changes.extend(
+            diff(&previous.definition, &self.definition)
+                .map(|(field, _)| InputChange::Definition(field)),
+        );
+
+        changes
+    }
+}
+
+impl HashInputsStore {
+    pub fn new(repo_root: &AbsoluteSystemPath) -> Self {
+        Self {
+            dir: repo_root.join_components(&[".turbo", HASH_INPUTS_DIR]),
+        }
+    }
+
+    pub fn read(&self, task_id: &TaskId) -> Result<Option<TaskHashInputs>, Error> {
+        let contents = self
+            .path(task_id)
+            .read_existing_to_string_or(Ok::<_, std::io::Error>(""))?;
+        if contents.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub fn write(&self, task_id: &TaskId, inputs: &TaskHashInputs) -> Result<(), Error> {
+        let path = self.path(task_id);
+        path.ensure_dir()?;
+        path.create_with_contents(serde_json::to_string(inputs)?)?;
+        Ok(())
+    }
+
+    fn path(&self, task_id: &TaskId) -> AbsoluteSystemPathBuf {
+        // Package names can contain a scope and task names can contain colons
+        let filename = format!(
+            "{}.json",
+            task_id
+                .to_string()
+                .replace('/', "$slash$")
+                .replace(':', "$colon$")
+        );
+        self.dir.join_component(&filename)
+    }
+}
+
+/// Converts `NAME=hash` pairs into a map from name to hash
+pub fn env_map(pairs: EnvironmentVariablePairs) -> BTreeMap<String, String> {
+    pairs
+        .into_iter()
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn diff<'a, K: Ord + Clone, V: PartialEq>(
+    previous: &'a BTreeMap<K, V>,
+    current: &'a BTreeMap<K, V>,
+) -> impl Iterator<Item = (K, Change)> + 'a {
+    let removed = previous
+        .keys()
+        .filter(|key| !current.contains_key(key))
+        .map(|key| (key.clone(), Change::Removed));
+    let added_or_modified = current.iter().filter_map(|(key, value)| {
+        match previous.get(key) {
+            None => Some(Change::Added),
+            Some(previous) if previous != value => Some(Change::Modified),
+            Some(_) => None,
+        }
+        .map(|change| (key.clone(), change))
+    });
+    removed.chain(added_or_modified)
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Modified => "changed",
+        })
+    }
+}
+
+impl fmt::Display for InputChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputChange::GlobalFile(path, change) => write!(f, "global dependency {path} {change}"),
+            InputChange::GlobalEnv(name, change) => {
+                write!(f, "global environment variable {name} {change}")
+            }
+            InputChange::GlobalExternalDependencies => {
+                f.write_str("external dependencies of the root workspace changed")
+            }
+            InputChange::Global => f.write_str("global configuration changed"),
+            InputChange::File(path, change) => write!(f, "file {path} {change}"),
+            InputChange::Env(name, change) => write!(f, "environment variable {name} {change}"),
+            InputChange::Dependency(task_id, change) => write!(f, "dependency {task_id} {change}"),
+            InputChange::ExternalDependencies => f.write_str("external dependencies changed"),
+            InputChange::Definition(field) => write!(f, "task definition field {field} changed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn path(path: &str) -> RelativeUnixPathBuf {
+        RelativeUnixPathBuf::new(path).unwrap()
+    }
+
+    fn inputs() -> TaskHashInputs {
+        TaskHashInputs {
+            hash: "a".into(),
+            global: GlobalHashInputs {
+                hash: "global".into(),
+                files: [(path(".env"), "1".to_string())].into_iter().collect(),
+                ..Default::default()
+            },
+            files: [
+                (path("src/index.ts"), "1".to_string()),
+                (path("src/util.ts"), "1".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            env: env_map(vec!["API_URL=1".into(), "NODE_ENV=1".into()]),
+            dependencies: [("ui#build".to_string(), "1".to_string())]
+                .into_iter()
+                .collect(),
+            external_dependencies_hash: Some("1".into()),
+            definition: [("outputs".to_string(), json!(["dist/**"]))]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_no_changes() {
+        assert!(inputs().changes_since(&inputs()).is_empty());
+    }
+
+    #[test]
+    fn test_changes() {
+        let previous = inputs();
+        let mut current = inputs();
+        current.hash = "b".into();
+        current.files.remove(&path("src/util.ts"));
+        current.files.insert(path("src/index.ts"), "2".into());
+        current.files.insert(path("src/new.ts"), "1".into());
+        current.env = env_map(vec!["API_URL=2".into(), "NODE_ENV=1".into()]);
+        current.dependencies.insert("ui#build".into(), "2".into());
+        current
+            .definition
+            .insert("outputs".into(), json!(["build/**"]));
+
+        assert_eq!(
+            current.changes_since(&previous),
+            vec![
+                InputChange::File(path("src/util.ts"), Change::Removed),
+                InputChange::File(path("src/index.ts"), Change::Modified),
+                InputChange::File(path("src/new.ts"), Change::Added),
+                InputChange::Env("API_URL".into(), Change::Modified),
+                InputChange::Dependency("ui#build".into(), Change::Modified),
+                InputChange::Definition("outputs".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_global_changes() {
+        let previous = inputs();
+        let mut current = inputs();
+        current.global.hash = "other".into();
+        assert_eq!(current.changes_since(&previous), vec![InputChange::Global]);
+
+        current.global.files.clear();
+        assert_eq!(
+            current.changes_since(&previous),
+            vec![InputChange::GlobalFile(path(".env"), Change::Removed)]
+        );
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path()).unwrap();
+        let store = HashInputsStore::new(&repo_root);
+        let task_id = TaskId::new("@scope/web", "build:prod");
+
+        assert_eq!(store.read(&task_id).unwrap(), None);
+        store.write(&task_id, &inputs()).unwrap();
+        assert_eq!(store.read(&task_id).unwrap(), Some(inputs()));
+    }
+}
@@ -4,9 +4,12 @@ mod cache;
 mod error;
 pub(crate) mod global_hash;
 mod graph_visualizer;
+pub(crate) mod hash_inputs;
+pub(crate) mod output_check;
 pub(crate) mod package_discovery;
 mod scope;
 pub(crate) mod summary;
+pub(crate) mod task_history;
 pub mod task_id;
 
 use std::{
@@ -17,13 +20,14 @@ use std::{
 };
 
 pub use cache::{RunCache, TaskCache};
+use camino::Utf8PathBuf;
 use chrono::{DateTime, Local};
 use itertools::Itertools;
 use rayon::iter::ParallelBridge;
-use tracing::debug;
+use tracing::{debug, warn};
 use turborepo_analytics::{start_analytics, AnalyticsHandle, AnalyticsSender};
 use turborepo_api_client::{APIAuth, APIClient};
-use turborepo_cache::{AsyncCache, RemoteCacheOpts};
+use turborepo_cache::{fs::FSCache, AsyncCache, CacheLimits, RemoteCacheOpts};
 use turborepo_ci::Vendor;
 use turborepo_env::EnvironmentVariableMap;
 use turborepo_repository::{
@@ -32,35 +36,94 @@ use turborepo_repository::{
     package_json::PackageJson,
 };
 use turborepo_scm::SCM;
-use turborepo_ui::{cprint, cprintln, ColorSelector, BOLD_GREY, GREY};
+use turborepo_ui::{cprint, cprintln, tui::AppSender, ColorSelector, BOLD_GREY, GREY};
 
 use self::task_id::TaskName;
 pub use crate::run::error::Error;
 use crate::{
-    cli::{DryRunMode, EnvMode},
+    cli::{DryRunMode, EnvMode, UIMode},
     commands::CommandBase,
     config::TurboJson,
     daemon::DaemonConnector,
-    engine::{Engine, EngineBuilder},
+    engine::{Engine, EngineBuilder, TaskNode},
     opts::Opts,
     process::ProcessManager,
-    run::{global_hash::get_global_hash_inputs, summary::RunTracker},
+    run::{
+        global_hash::get_global_hash_inputs, hash_inputs::GlobalHashInputs, summary::RunTracker,
+    },
     shim::TurboState,
     signal::{SignalHandler, SignalSubscriber},
     task_graph::Visitor,
-    task_hash::{get_external_deps_hash, PackageInputsHashes},
+    task_hash::{get_external_deps_hash, DaemonFileHashes, PackageInputsHashes},
 };
 
+/// Selects which tasks of the task graph a run executes.
+/// `turbo run` always executes all of them, but `turbo watch` splits the
+/// graph up so it can restart parts of it independently.
+#[derive(Debug, Clone, Default)]
+pub enum EngineFilter {
+    #[default]
+    All,
+    /// Only tasks that are expected to exit. If packages are provided, the run
+    /// is limited to their tasks and the tasks that depend on them.
+    WithoutPersistent(Option<HashSet<WorkspaceName>>),
+    /// Only persistent tasks that are (or aren't) interruptible
+    Persistent { interruptible: bool },
+}
+
+impl EngineFilter {
+    fn apply(&self, engine: Engine) -> Result<Engine, Error> {
+        if !matches!(self, EngineFilter::All) {
+            if let Some((dependent, dependency)) = engine.dependency_across_watch_runs() {
+                return Err(Error::WatchDependencyOnPersistentTask {
+                    dependent: dependent.to_string(),
+                    dependency: dependency.to_string(),
+                });
+            }
+        }
+
+        Ok(match self {
+            EngineFilter::All => engine,
+            EngineFilter::WithoutPersistent(None) => {
+                engine.create_engine_without_persistent_tasks()
+            }
+            EngineFilter::WithoutPersistent(Some(packages)) => engine
+                .create_engine_for_subgraph(packages)
+                .create_engine_without_persistent_tasks(),
+            EngineFilter::Persistent { interruptible } => {
+                engine.create_engine_for_persistent_tasks(*interruptible)
+            }
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Run<'a> {
     base: &'a CommandBase,
     processes: ProcessManager,
+    engine_filter: EngineFilter,
 }
 
 impl<'a> Run<'a> {
     pub fn new(base: &'a CommandBase) -> Self {
         let processes = ProcessManager::new();
-        Self { base, processes }
+        Self {
+            base,
+            processes,
+            engine_filter: EngineFilter::default(),
+        }
+    }
+
+    pub fn with_engine_filter(mut self, engine_filter: EngineFilter) -> Self {
+        self.engine_filter = engine_filter;
+        self
+    }
+
+    /// Use a process manager owned by the caller, allowing it to stop the
+    /// run's tasks without stopping everything via the signal handler.
+    pub fn with_process_manager(mut self, processes: ProcessManager) -> Self {
+        self.processes = processes;
+        self
     }
 
     fn connect_process_manager(&self, signal_subscriber: SignalSubscriber) {
@@ -118,6 +181,15 @@ impl<'a> Run<'a> {
         } else {
             cprintln!(self.base.ui, GREY, "• Remote caching disabled");
         }
+
+        if let Some(shared_dir) = &opts.cache_opts.shared_dir {
+            cprintln!(
+                self.base.ui,
+                GREY,
+                "• Shared cache enabled at {}",
+                shared_dir
+            );
+        }
     }
 
     #[tracing::instrument(skip(self, signal_handler))]
@@ -185,6 +257,11 @@ impl<'a> Run<'a> {
             opts.cache_opts.skip_remote = !enabled;
         }
 
+        // A shared cache directory doesn't depend on the repo being linked
+        opts.cache_opts.shared_dir = config.shared_cache_dir().map(Utf8PathBuf::from);
+        opts.cache_opts.layout = config.cache_layout();
+        let cache_limits = config.cache_limits();
+
         let _is_structured_output = opts.run_opts.graph.is_some()
             || matches!(opts.run_opts.dry_run, Some(DryRunMode::Json));
 
@@ -192,7 +269,7 @@ impl<'a> Run<'a> {
 
         let is_ci_or_not_tty = turborepo_ci::is_ci() || !std::io::stdout().is_terminal();
 
-        let daemon = match (is_ci_or_not_tty, opts.run_opts.daemon) {
+        let mut daemon = match (is_ci_or_not_tty, opts.run_opts.daemon) {
             (true, None) => {
                 debug!("skipping turbod since we appear to be in a non-interactive context");
                 None
@@ -222,19 +299,9 @@ impl<'a> Run<'a> {
             }
         };
 
-        let mut pkg_dep_graph =
-            PackageGraph::builder(&self.base.repo_root, root_package_json.clone())
-                .with_single_package_mode(opts.run_opts.single_package)
-                .with_package_discovery(
-                    LocalPackageDiscoveryBuilder::new(
-                        self.base.repo_root.clone(),
-                        None,
-                        Some(root_package_json.clone()),
-                    )
-                    .build()?,
-                )
-                .build()
-                .await?;
+        let mut pkg_dep_graph = self
+            .build_package_graph(&root_package_json, opts.run_opts.single_package)
+            .await?;
 
         let root_turbo_json =
             TurboJson::load(&self.base.repo_root, &root_package_json, is_single_package)?;
@@ -251,7 +318,21 @@ impl<'a> Run<'a> {
             .and_then(|configuration_options| configuration_options.signature)
             .unwrap_or_default();
 
-        opts.cache_opts.remote_cache_opts = Some(RemoteCacheOpts::new(team_id, signature));
+        let (signature_algorithm, signature_public_keys) = root_turbo_json
+            .remote_cache
+            .as_ref()
+            .map(|configuration_options| {
+                (
+                    configuration_options.signature_algorithm(),
+                    configuration_options.signature_public_keys(),
+                )
+            })
+            .unwrap_or_default();
+
+        opts.cache_opts.remote_cache_opts = Some(
+            RemoteCacheOpts::new(team_id, signature)
+                .with_signature_keys(signature_algorithm, signature_public_keys),
+        );
 
         if opts.run_opts.experimental_space_id.is_none() {
             opts.run_opts.experimental_space_id = root_turbo_json.space_id.clone();
@@ -261,31 +342,8 @@ impl<'a> Run<'a> {
 
         let scm = SCM::new(&self.base.repo_root);
 
-        let filtered_pkgs = {
-            let (mut filtered_pkgs, is_all_packages) = scope::resolve_packages(
-                &opts.scope_opts,
-                &self.base.repo_root,
-                &pkg_dep_graph,
-                &scm,
-            )?;
-
-            if is_all_packages {
-                for target in self.targets() {
-                    let mut task_name = TaskName::from(target.as_str());
-                    // If it's not a package task, we convert to a root task
-                    if !task_name.is_package_task() {
-                        task_name = task_name.into_root_task()
-                    }
-
-                    if root_turbo_json.pipeline.contains_key(&task_name) {
-                        filtered_pkgs.insert(WorkspaceName::Root);
-                        break;
-                    }
-                }
-            };
-
-            filtered_pkgs
-        };
+        let filtered_pkgs =
+            self.filtered_packages(&opts, &pkg_dep_graph, &root_turbo_json, &scm)?;
 
         let env_at_execution_start = EnvironmentVariableMap::infer();
 
@@ -300,6 +358,14 @@ impl<'a> Run<'a> {
         let mut engine =
             self.build_engine(&pkg_dep_graph, &opts, &root_turbo_json, &filtered_pkgs)?;
 
+        // A filtered engine can easily end up without any tasks, e.g. when
+        // there aren't any persistent tasks. There's nothing to report on then.
+        if !matches!(self.engine_filter, EngineFilter::All)
+            && engine.tasks().all(|task| matches!(task, TaskNode::Root))
+        {
+            return Ok(0);
+        }
+
         if opts.run_opts.dry_run.is_none() && opts.run_opts.graph.is_none() {
             self.print_run_prelude(&opts, &filtered_pkgs);
         }
@@ -328,11 +394,31 @@ impl<'a> Run<'a> {
         )?;
 
         let global_hash = global_hash_inputs.calculate_global_hash_from_inputs();
+        let recorded_global_hash_inputs = opts
+            .run_opts
+            .explain_miss
+            .then(|| GlobalHashInputs::new(global_hash.clone(), &global_hash_inputs));
 
         debug!("global hash: {}", global_hash);
 
         let color_selector = ColorSelector::default();
 
+        let workspaces = pkg_dep_graph.workspaces().collect();
+        // Ask the daemon for the file hashes it has already indexed before it
+        // is handed off to the run cache
+        let daemon_file_hashes = match &mut daemon {
+            Some(daemon) => {
+                PackageInputsHashes::query_daemon(
+                    daemon,
+                    engine.tasks(),
+                    &workspaces,
+                    engine.task_definitions(),
+                )
+                .await
+            }
+            None => DaemonFileHashes::new(),
+        };
+
         let runcache = Arc::new(RunCache::new(
             async_cache,
             &self.base.repo_root,
@@ -359,12 +445,12 @@ impl<'a> Run<'a> {
             global_env_mode = EnvMode::Strict;
         }
 
-        let workspaces = pkg_dep_graph.workspaces().collect();
         let package_inputs_hashes = PackageInputsHashes::calculate_file_hashes(
             &scm,
             engine.tasks().par_bridge(),
             workspaces,
             engine.task_definitions(),
+            &daemon_file_hashes,
             &self.base.repo_root,
         )?;
 
@@ -412,12 +498,13 @@ impl<'a> Run<'a> {
 
         let mut visitor = Visitor::new(
             pkg_dep_graph.clone(),
-            runcache,
+            runcache.clone(),
             run_tracker,
             &opts,
             package_inputs_hashes,
             &env_at_execution_start,
             &global_hash,
+            recorded_global_hash_inputs,
             global_env_mode,
             self.base.ui,
             false,
@@ -430,11 +517,50 @@ impl<'a> Run<'a> {
             visitor.dry_run();
         }
 
+        // The TUI needs a terminal to draw in and read keys from, otherwise
+        // output is streamed like usual
+        let use_tui = matches!(opts.run_opts.ui, UIMode::Tui)
+            && opts.run_opts.dry_run.is_none()
+            && std::io::stdout().is_terminal()
+            && std::io::stdin().is_terminal();
+        let terminal = match use_tui.then(turborepo_ui::tui::startup) {
+            Some(Ok(terminal)) => Some(terminal),
+            Some(Err(e)) => {
+                warn!("unable to start terminal UI, streaming output instead: {e}");
+                None
+            }
+            None => None,
+        };
+        let tui = terminal.map(|terminal| {
+            let (sender, receiver) = AppSender::new();
+            let tasks = visitor.tui_task_names(&engine);
+            let manager = self.processes.clone();
+            let runtime = tokio::runtime::Handle::current();
+            let handle = tokio::task::spawn_blocking(move || {
+                turborepo_ui::tui::run_app(terminal, tasks, receiver, move || {
+                    runtime.spawn(async move { manager.stop().await });
+                })
+            });
+            visitor.use_tui(sender.clone());
+            (sender, handle)
+        });
+
         // we look for this log line to mark the start of the run
         // in benchmarks, so please don't remove it
         debug!("running visitor");
 
-        let errors = visitor.visit(engine.clone()).await?;
+        let errors = visitor.visit(engine.clone()).await;
+
+        // Give the terminal back before anything else is printed
+        if let Some((sender, handle)) = tui {
+            sender.stop();
+            match handle.await {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => warn!("unable to run terminal UI: {e}"),
+                Err(e) => warn!("terminal UI exited unexpectedly: {e}"),
+            }
+        }
+        let errors = errors?;
 
         let exit_code = errors
             .iter()
@@ -462,9 +588,100 @@ impl<'a> Run<'a> {
             )
             .await?;
 
+        let should_evict = !opts.cache_opts.skip_filesystem
+            && opts.run_opts.dry_run.is_none()
+            && !cache_limits.is_unlimited();
+        if should_evict {
+            // Wait for this run's artifacts to be written so they're accounted for
+            runcache.wait_for_cache().await;
+            self.evict_cache(&opts, &cache_limits);
+        }
+
         Ok(exit_code)
     }
 
+    /// Builds the package and task graphs for this run without executing any
+    /// tasks
+    pub async fn build_graphs(&self) -> Result<(PackageGraph, Engine), Error> {
+        let package_json_path = self.base.repo_root.join_component("package.json");
+        let root_package_json = PackageJson::load(&package_json_path)?;
+        let opts = self.opts()?;
+        let is_single_package = opts.run_opts.single_package;
+
+        let pkg_dep_graph = self
+            .build_package_graph(&root_package_json, is_single_package)
+            .await?;
+        let root_turbo_json =
+            TurboJson::load(&self.base.repo_root, &root_package_json, is_single_package)?;
+        pkg_dep_graph.validate()?;
+
+        let scm = SCM::new(&self.base.repo_root);
+        let filtered_pkgs =
+            self.filtered_packages(&opts, &pkg_dep_graph, &root_turbo_json, &scm)?;
+        let engine = self.build_engine(&pkg_dep_graph, &opts, &root_turbo_json, &filtered_pkgs)?;
+
+        Ok((pkg_dep_graph, engine))
+    }
+
+    async fn build_package_graph(
+        &self,
+        root_package_json: &PackageJson,
+        single_package: bool,
+    ) -> Result<PackageGraph, Error> {
+        Ok(
+            PackageGraph::builder(&self.base.repo_root, root_package_json.clone())
+                .with_single_package_mode(single_package)
+                .with_package_discovery(
+                    LocalPackageDiscoveryBuilder::new(
+                        self.base.repo_root.clone(),
+                        None,
+                        Some(root_package_json.clone()),
+                    )
+                    .build()?,
+                )
+                .build()
+                .await?,
+        )
+    }
+
+    fn filtered_packages(
+        &self,
+        opts: &Opts,
+        pkg_dep_graph: &PackageGraph,
+        root_turbo_json: &TurboJson,
+        scm: &SCM,
+    ) -> Result<HashSet<WorkspaceName>, Error> {
+        let (mut filtered_pkgs, is_all_packages) =
+            scope::resolve_packages(&opts.scope_opts, &self.base.repo_root, pkg_dep_graph, scm)?;
+
+        if is_all_packages {
+            for target in self.targets() {
+                let mut task_name = TaskName::from(target.as_str());
+                // If it's not a package task, we convert to a root task
+                if !task_name.is_package_task() {
+                    task_name = task_name.into_root_task()
+                }
+
+                if root_turbo_json.pipeline.contains_key(&task_name) {
+                    filtered_pkgs.insert(WorkspaceName::Root);
+                    break;
+                }
+            }
+        };
+
+        Ok(filtered_pkgs)
+    }
+
+    fn evict_cache(&self, opts: &Opts, limits: &CacheLimits) {
+        let evicted = FSCache::new(opts.cache_opts.override_dir, &self.base.repo_root, None)
+            .and_then(|cache| cache.evict(limits));
+        match evicted {
+            Ok(evicted) => debug!("evicted {} artifacts from the cache", evicted.len()),
+            // Failing to evict shouldn't fail the run
+            Err(err) => warn!("failed to evict artifacts from the cache: {}", err),
+        }
+    }
+
     fn build_engine(
         &self,
         pkg_dep_graph: &PackageGraph,
@@ -507,6 +724,6 @@ impl<'a> Run<'a> {
                 })?;
         }
 
-        Ok(engine)
+        self.engine_filter.apply(engine)
     }
 }
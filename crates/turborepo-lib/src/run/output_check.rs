@@ -0,0 +1,255 @@
+//! Checks that tasks only write the files that their `outputs` declare. The
+//! workspace is snapshotted before a task runs and the files that were
+//! written are found by comparing it with the workspace once the task is done.
+//! Only files that were created or changed count as written, so files left
+//! over from earlier runs don't hide missing outputs.
+use std::{
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+use crate::task_graph::TaskOutputs;
+
+// Directories that turbo and package managers write to, these are never
+// task outputs
+const IGNORED_DIRS: &[&str] = &["node_modules", ".git", ".turbo"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unable to read workspace files: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Globwalk(#[from] globwalk::WalkError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// The files in a workspace at a point in time
+#[derive(Debug)]
+pub struct OutputSnapshot {
+    workspace_dir: AbsoluteSystemPathBuf,
+    // Workspaces nested in this one, which are checked for their own tasks
+    nested_workspaces: Vec<AbsoluteSystemPathBuf>,
+    files: HashMap<AnchoredSystemPathBuf, FileState>,
+}
+
+/// Differences between the files a task wrote and its declared outputs
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputCheck {
+    // Workspace relative paths of files that were written, but aren't covered
+    // by the task's outputs
+    pub undeclared_writes: Vec<AnchoredSystemPathBuf>,
+    // Output globs that didn't match any files written by the task
+    pub missing_outputs: Vec<String>,
+}
+
+impl OutputSnapshot {
+    pub fn take(
+        workspace_dir: &AbsoluteSystemPath,
+        nested_workspaces: Vec<AbsoluteSystemPathBuf>,
+    ) -> Result<Self, Error> {
+        let mut snapshot = Self {
+            workspace_dir: workspace_dir.to_owned(),
+            nested_workspaces,
+            files: HashMap::new(),
+        };
+        snapshot.walk()?;
+        Ok(snapshot)
+    }
+
+    /// Compares the current files in the workspace with the snapshot.
+    /// `other_outputs` are the outputs of the workspace's other tasks, which
+    /// can run at the same time, so files they cover aren't attributed to this
+    /// task.
+    pub fn check(
+        &self,
+        outputs: &TaskOutputs,
+        other_outputs: &[TaskOutputs],
+    ) -> Result<OutputCheck, Error> {
+        let after = Self::take(&self.workspace_dir, self.nested_workspaces.clone())?;
+        let written = after
+            .files
+            .iter()
+            .filter(|(path, state)| self.files.get(*path) != Some(state))
+            .map(|(path, _)| path)
+            .collect::<HashSet<_>>();
+
+        let declared = self.expand(&outputs.inclusions, &outputs.exclusions)?;
+        let mut others = HashSet::new();
+        for other in other_outputs {
+            others.extend(self.expand(&other.inclusions, &other.exclusions)?);
+        }
+        let mut undeclared_writes = written
+            .iter()
+            .filter(|path| !declared.contains(**path) && !others.contains(**path))
+            .map(|path| (*path).clone())
+            .collect::<Vec<_>>();
+        undeclared_writes.sort();
+
+        let mut missing_outputs = Vec::new();
+        for glob in &outputs.inclusions {
+            if !self
+                .expand(std::slice::from_ref(glob), &outputs.exclusions)?
+                .iter()
+                .any(|path| written.contains(path))
+            {
+                missing_outputs.push(glob.clone());
+            }
+        }
+
+        Ok(OutputCheck {
+            undeclared_writes,
+            missing_outputs,
+        })
+    }
+
+    fn walk(&mut self) -> Result<(), Error> {
+        let mut dirs = vec![self.workspace_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                let path = dir.join_component(&name);
+                // Files can be removed while we walk the workspace
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    if !IGNORED_DIRS.contains(&name.as_ref())
+                        && !self.nested_workspaces.contains(&path)
+                    {
+                        dirs.push(path);
+                    }
+                } else if metadata.is_file() {
+                    self.files.insert(
+                        AnchoredSystemPathBuf::relative_path_between(&self.workspace_dir, &path),
+                        FileState {
+                            modified: metadata.modified().ok(),
+                            len: metadata.len(),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Workspace relative paths of the files matching the globs
+    fn expand(
+        &self,
+        inclusions: &[String],
+        exclusions: &[String],
+    ) -> Result<HashSet<AnchoredSystemPathBuf>, Error> {
+        if inclusions.is_empty() {
+            return Ok(HashSet::new());
+        }
+        Ok(globwalk::globwalk(
+            &self.workspace_dir,
+            inclusions,
+            exclusions,
+            globwalk::WalkType::Files,
+        )?
+        .into_iter()
+        .map(|path| AnchoredSystemPathBuf::relative_path_between(&self.workspace_dir, &path))
+        .collect())
+    }
+}
+
+impl OutputCheck {
+    pub fn is_empty(&self) -> bool {
+        self.undeclared_writes.is_empty() && self.missing_outputs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn outputs(inclusions: &[&str], exclusions: &[&str]) -> TaskOutputs {
+        TaskOutputs {
+            inclusions: inclusions.iter().map(|glob| glob.to_string()).collect(),
+            exclusions: exclusions.iter().map(|glob| glob.to_string()).collect(),
+        }
+    }
+
+    fn paths(paths: &[&str]) -> Vec<AnchoredSystemPathBuf> {
+        paths
+            .iter()
+            .map(|path| AnchoredSystemPathBuf::from_raw(path).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace_dir = AbsoluteSystemPathBuf::try_from(dir.path()).unwrap();
+        let file = |path: &str| workspace_dir.join_components(&path.split('/').collect::<Vec<_>>());
+        file("src/index.ts").ensure_dir().unwrap();
+        file("src/index.ts")
+            .create_with_contents("export {}")
+            .unwrap();
+        file("dist/stale.js").ensure_dir().unwrap();
+        file("dist/stale.js").create_with_contents("").unwrap();
+        // Left over from an earlier run, so it doesn't count as produced
+        file("lib/stale.js").ensure_dir().unwrap();
+        file("lib/stale.js").create_with_contents("").unwrap();
+
+        let snapshot = OutputSnapshot::take(&workspace_dir, Vec::new()).unwrap();
+        file("dist/index.js").create_with_contents("").unwrap();
+        file("dist/index.js.map").create_with_contents("").unwrap();
+        file("src/generated.ts").create_with_contents("").unwrap();
+        // Written by another task in the workspace
+        file("coverage/lcov.info").ensure_dir().unwrap();
+        file("coverage/lcov.info").create_with_contents("").unwrap();
+        file(".turbo/turbo-build.log").ensure_dir().unwrap();
+        file(".turbo/turbo-build.log")
+            .create_with_contents("")
+            .unwrap();
+        file("node_modules/.cache/index").ensure_dir().unwrap();
+        file("node_modules/.cache/index")
+            .create_with_contents("")
+            .unwrap();
+
+        let check = snapshot
+            .check(
+                &outputs(&["dist/**", "lib/**", "types/**"], &["dist/**/*.map"]),
+                &[outputs(&["coverage/**"], &[])],
+            )
+            .unwrap();
+        assert_eq!(
+            check.undeclared_writes,
+            paths(&["dist/index.js.map", "src/generated.ts"])
+        );
+        assert_eq!(
+            check.missing_outputs,
+            vec!["lib/**".to_string(), "types/**".to_string()]
+        );
+        assert!(!check.is_empty());
+    }
+
+    #[test]
+    fn test_nested_workspaces_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path()).unwrap();
+        let nested = repo_root.join_components(&["packages", "web"]);
+        nested.create_dir_all().unwrap();
+
+        let snapshot = OutputSnapshot::take(&repo_root, vec![nested.clone()]).unwrap();
+        nested
+            .join_component("index.js")
+            .create_with_contents("")
+            .unwrap();
+
+        let check = snapshot.check(&outputs(&[], &[]), &[]).unwrap();
+        assert!(check.is_empty());
+    }
+}
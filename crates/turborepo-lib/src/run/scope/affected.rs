@@ -0,0 +1,155 @@
+//! Works out which commits `--affected` compares by reading them from the
+//! environment of the CI provider. Builds without a base ref, such as pushes
+//! or providers like CircleCI and Travis that don't expose one, compare the
+//! commit being built against its parent.
+use tracing::{debug, warn};
+use turbopath::AbsoluteSystemPath;
+use turborepo_ci::Vendor;
+use turborepo_scm::SCM;
+
+// Used when not running in CI, or when the provider doesn't expose the commit
+const DEFAULT_HEAD: &str = "HEAD";
+
+/// Returns a filter selecting the packages that changed between the merge base
+/// of the build and the commit being built, plus their dependents. Returns
+/// `None` if the merge base can't be found, in which case every package
+/// should run.
+pub(crate) fn affected_filter(turbo_root: &AbsoluteSystemPath, scm: &SCM) -> Option<String> {
+    filter_between(
+        Vendor::get_base_ref(),
+        Vendor::get_head_sha(),
+        turbo_root,
+        scm,
+    )
+}
+
+fn filter_between(
+    base: Option<String>,
+    head: Option<String>,
+    turbo_root: &AbsoluteSystemPath,
+    scm: &SCM,
+) -> Option<String> {
+    let head = head.unwrap_or_else(|| DEFAULT_HEAD.to_string());
+    let base = base.unwrap_or_else(|| {
+        debug!("no base ref for this build, comparing {head} against its parent");
+        format!("{head}^")
+    });
+
+    let mut last_error = None;
+    for base in base_candidates(&base) {
+        match scm.merge_base(&base, &head, turbo_root) {
+            Ok(merge_base) => {
+                debug!("comparing {head} against {merge_base}, the merge base with {base}");
+                return Some(filter(&merge_base, &head));
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    if scm.is_shallow(turbo_root).unwrap_or_default() {
+        warn!(
+            "unable to find where {head} branched from {base} because the repository is a shallow \
+             clone. Running all packages instead, fetch more history to use --affected"
+        );
+    } else if let Some(e) = last_error {
+        warn!("unable to find where {head} branched from {base}, running all packages: {e}");
+    }
+    None
+}
+
+// CI checkouts often only have the remote tracking branch for the base
+fn base_candidates(base: &str) -> Vec<String> {
+    if base.starts_with("origin/") || base.ends_with('^') {
+        vec![base.to_string()]
+    } else {
+        vec![base.to_string(), format!("origin/{base}")]
+    }
+}
+
+fn filter(merge_base: &str, head: &str) -> String {
+    format!("...[{merge_base}...{head}]")
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use test_case::test_case;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::*;
+
+    fn git(root: &AbsoluteSystemPath, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap().trim().to_owned()
+    }
+
+    fn commit(root: &AbsoluteSystemPath, file: &str) -> String {
+        root.join_component(file)
+            .create_with_contents(file)
+            .unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-m", file]);
+        git(root, &["rev-parse", "HEAD"])
+    }
+
+    fn setup_repository() -> (tempfile::TempDir, AbsoluteSystemPathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = AbsoluteSystemPathBuf::try_from(tmp.path())
+            .unwrap()
+            .to_realpath()
+            .unwrap();
+        git(&root, &["init", "--initial-branch", "main"]);
+        git(&root, &["config", "user.name", "test"]);
+        git(&root, &["config", "user.email", "test@example.com"]);
+        (tmp, root)
+    }
+
+    #[test]
+    fn test_affected_without_base_compares_parent() {
+        let (_tmp, root) = setup_repository();
+        let first = commit(&root, "a.txt");
+        commit(&root, "b.txt");
+        let scm = SCM::new(&root);
+
+        assert_eq!(
+            filter_between(None, None, &root, &scm),
+            Some(format!("...[{first}...HEAD]"))
+        );
+    }
+
+    #[test]
+    fn test_affected_with_base_uses_merge_base() {
+        let (_tmp, root) = setup_repository();
+        let branch_point = commit(&root, "a.txt");
+        git(&root, &["checkout", "-b", "feature"]);
+        commit(&root, "b.txt");
+        git(&root, &["checkout", "main"]);
+        commit(&root, "c.txt");
+        git(&root, &["checkout", "feature"]);
+        let scm = SCM::new(&root);
+
+        assert_eq!(
+            filter_between(Some("main".to_string()), None, &root, &scm),
+            Some(format!("...[{branch_point}...HEAD]"))
+        );
+    }
+
+    #[test_case("main", &["main", "origin/main"] ; "branch")]
+    #[test_case("origin/main", &["origin/main"] ; "remote branch")]
+    #[test_case("HEAD^", &["HEAD^"] ; "parent")]
+    #[test_case("4b1e7c1", &["4b1e7c1", "origin/4b1e7c1"] ; "sha")]
+    fn test_base_candidates(base: &str, expected: &[&str]) {
+        assert_eq!(base_candidates(base), expected);
+    }
+
+    #[test]
+    fn test_filter_includes_dependents() {
+        assert_eq!(filter("4b1e7c1", "HEAD"), "...[4b1e7c1...HEAD]");
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    cell::OnceCell,
     collections::{HashMap, HashSet},
     path::Path,
     str::FromStr,
@@ -15,6 +16,10 @@ use super::{
     simple_glob::{Match, SimpleGlob},
     target_selector::{InvalidSelectorError, TargetSelector},
 };
+use crate::{
+    boundaries::{self, WorkspaceTags},
+    config,
+};
 
 pub struct PackageInference {
     package_name: Option<String>,
@@ -65,8 +70,8 @@ impl PackageInference {
     }
 
     pub fn apply(&self, selector: &mut TargetSelector) {
-        // if the name pattern is provided, do not attempt inference
-        if !selector.name_pattern.is_empty() {
+        // if the name pattern or tag is provided, do not attempt inference
+        if !selector.name_pattern.is_empty() || !selector.tag.is_empty() {
             return;
         };
 
@@ -99,6 +104,8 @@ pub struct FilterResolver<'a, T: PackageChangeDetector> {
     inference: Option<PackageInference>,
     scm: &'a SCM,
     change_detector: T,
+    // Only loaded if a selector uses a tag
+    workspace_tags: OnceCell<WorkspaceTags>,
 }
 
 impl<'a> FilterResolver<'a, SCMChangeDetector<'a>> {
@@ -134,6 +141,7 @@ impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
             inference,
             scm,
             change_detector,
+            workspace_tags: OnceCell::new(),
         }
     }
 
@@ -358,6 +366,11 @@ impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
             }
         }
 
+        if !selector.tag.is_empty() {
+            let tagged = self.workspaces_with_tag(&selector.tag)?;
+            entry_packages.retain(|package| tagged.contains(package));
+        }
+
         // if we have a filter, use it to filter the entry packages
         let filtered_entry_packages = if !selector.name_pattern.is_empty() {
             match_package_names(&selector.name_pattern, entry_packages)?
@@ -461,6 +474,16 @@ impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
             }
         }
 
+        if !selector.tag.is_empty() {
+            let tagged = self.workspaces_with_tag(&selector.tag)?;
+            if !selector_valid {
+                entry_packages = tagged;
+                selector_valid = true;
+            } else {
+                entry_packages.retain(|package| tagged.contains(package));
+            }
+        }
+
         if !selector.name_pattern.is_empty() {
             if !selector_valid {
                 entry_packages = self.match_package_names_to_vertices(
@@ -476,8 +499,8 @@ impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
             }
         }
 
-        // if neither a name pattern, parent dir, or from ref is provided, then
-        // the selector is invalid
+        // if neither a name pattern, tag, parent dir, or from ref is provided,
+        // then the selector is invalid
         if !selector_valid {
             Err(ResolutionError::InvalidSelector(
                 InvalidSelectorError::InvalidSelector(selector.raw.clone()),
@@ -495,6 +518,18 @@ impl<'a, T: PackageChangeDetector> FilterResolver<'a, T> {
         self.change_detector.changed_packages(from_ref, to_ref)
     }
 
+    fn workspaces_with_tag(&self, tag: &str) -> Result<HashSet<WorkspaceName>, ResolutionError> {
+        let workspace_tags = self
+            .workspace_tags
+            .get_or_try_init(|| boundaries::workspace_tags(self.turbo_root, self.pkg_graph))?;
+
+        Ok(workspace_tags
+            .iter()
+            .filter(|(_, tags)| tags.contains(tag))
+            .map(|(name, _)| name.to_owned())
+            .collect())
+    }
+
     fn match_package_names_to_vertices(
         &self,
         name_pattern: &str,
@@ -568,11 +603,13 @@ pub enum ResolutionError {
     Scm(#[from] turborepo_scm::Error),
     #[error("Unable to calculate changes: {0}")]
     ChangeDetectError(#[from] ChangeDetectError),
+    #[error("Unable to read workspace tags: {0}")]
+    Tags(#[from] config::Error),
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::{HashMap, HashSet};
+    use std::collections::{BTreeSet, HashMap, HashSet};
 
     use test_case::test_case;
     use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
@@ -966,6 +1003,56 @@ mod test {
         );
     }
 
+    #[test]
+    fn match_tag() {
+        let resolver = make_project(
+            &[("packages/web", "packages/ui-button")],
+            &["packages/ui-theme", "packages/server-db"],
+            None,
+            TestChangeDetector::new(&[]),
+        );
+        resolver
+            .workspace_tags
+            .set(
+                [
+                    ("ui-button", "ui"),
+                    ("ui-theme", "ui"),
+                    ("server-db", "server"),
+                ]
+                .into_iter()
+                .map(|(name, tag)| (WorkspaceName::from(name), BTreeSet::from([tag.to_string()])))
+                .collect(),
+            )
+            .unwrap();
+
+        let packages = resolver
+            .get_filtered_packages(vec![TargetSelector {
+                tag: "ui".to_string(),
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(
+            packages,
+            ["ui-button", "ui-theme"]
+                .into_iter()
+                .map(WorkspaceName::from)
+                .collect()
+        );
+
+        let packages = resolver
+            .get_filtered_packages(vec![TargetSelector {
+                tag: "ui".to_string(),
+                include_dependents: true,
+                exclude_self: true,
+                ..Default::default()
+            }])
+            .unwrap();
+        assert_eq!(
+            packages,
+            vec![WorkspaceName::from("web")].into_iter().collect()
+        );
+    }
+
     #[test]
     fn match_scoped_package() {
         let resolver = make_project(
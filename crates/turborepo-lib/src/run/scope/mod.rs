@@ -1,3 +1,4 @@
+mod affected;
 mod change_detector;
 mod filter;
 mod simple_glob;
@@ -24,6 +25,12 @@ pub fn resolve_packages(
         PackageInference::calculate(turbo_root, pkg_inference_path, pkg_graph)
     });
 
-    FilterResolver::new(opts, pkg_graph, turbo_root, pkg_inference, scm)
-        .resolve(&opts.get_filters())
+    let mut filters = opts.get_filters();
+    if opts.affected {
+        // Without a filter every package runs, which is the fallback when the
+        // changed packages can't be worked out
+        filters.extend(affected::affected_filter(turbo_root, scm));
+    }
+
+    FilterResolver::new(opts, pkg_graph, turbo_root, pkg_inference, scm).resolve(&filters)
 }
@@ -14,6 +14,8 @@ pub struct TargetSelector {
     pub follow_prod_deps_only: bool,
     pub parent_dir: AnchoredSystemPathBuf,
     pub name_pattern: String,
+    // Selects the workspaces with this tag, from a `tag:<tag>` selector
+    pub tag: String,
     pub from_ref: String,
     pub to_ref_override: String,
     pub raw: String,
@@ -33,6 +35,7 @@ impl TargetSelector {
         !self.from_ref.is_empty()
             || self.parent_dir != AnchoredSystemPathBuf::default()
             || !self.name_pattern.is_empty()
+            || !self.tag.is_empty()
     }
 }
 
@@ -112,6 +115,10 @@ impl FromStr for TargetSelector {
         let name_pattern = captures
             .name("name")
             .map_or(String::new(), |m| m.as_str().to_string());
+        let (name_pattern, tag) = match name_pattern.strip_prefix("tag:") {
+            Some(tag) => (String::new(), tag.to_string()),
+            None => (name_pattern, String::new()),
+        };
 
         let mut parent_dir = AnchoredSystemPathBuf::default();
 
@@ -131,7 +138,10 @@ impl FromStr for TargetSelector {
 
         let (from_ref, to_ref_override) = if let Some(commits) = captures.name("commits") {
             let commits_str = if let Some(commits) = commits.as_str().strip_prefix("...") {
-                if parent_dir == AnchoredSystemPathBuf::default() && name_pattern.is_empty() {
+                if parent_dir == AnchoredSystemPathBuf::default()
+                    && name_pattern.is_empty()
+                    && tag.is_empty()
+                {
                     return Err(InvalidSelectorError::CantMatchDependencies);
                 }
                 pre_add_dependencies = true;
@@ -167,6 +177,7 @@ impl FromStr for TargetSelector {
             include_dependents,
             match_dependencies: pre_add_dependencies,
             name_pattern,
+            tag,
             parent_dir,
             raw: raw_selector.to_string(),
             ..Default::default()
@@ -244,6 +255,10 @@ mod test {
     #[test_case("foo...[master]", TargetSelector { raw: "foo...[master]".to_string(), from_ref: "master".to_string(), name_pattern: "foo".to_string(), match_dependencies: true, ..Default::default() }; "foo...[master]")]
     #[test_case("foo...[master]...", TargetSelector { raw: "foo...[master]...".to_string(), from_ref: "master".to_string(), name_pattern: "foo".to_string(), match_dependencies: true, include_dependencies: true, ..Default::default() }; "foo...[master] dot dot dot")]
     #[test_case("{foo}...[master]", TargetSelector { raw: "{foo}...[master]".to_string(), from_ref: "master".to_string(), parent_dir: AnchoredSystemPathBuf::try_from("foo").unwrap(), match_dependencies: true, ..Default::default() }; "curly brackets foo...[master]")]
+    #[test_case("tag:ui", TargetSelector { raw: "tag:ui".to_string(), tag: "ui".to_string(), ..Default::default() }; "tag")]
+    #[test_case("...tag:ui", TargetSelector { raw: "...tag:ui".to_string(), tag: "ui".to_string(), include_dependents: true, ..Default::default() }; "dot dot dot tag")]
+    #[test_case("tag:ui{./packages/*}", TargetSelector { raw: "tag:ui{./packages/*}".to_string(), tag: "ui".to_string(), parent_dir: AnchoredSystemPathBuf::try_from(if cfg!(windows) { "packages\\*" } else { "packages/*" }).unwrap(), ..Default::default() }; "tag with directory")]
+    #[test_case("tag:ui...[main]", TargetSelector { raw: "tag:ui...[main]".to_string(), tag: "ui".to_string(), from_ref: "main".to_string(), match_dependencies: true, ..Default::default() }; "tag...[main]")]
     fn parse_target_selector(raw_selector: &str, want: TargetSelector) {
         let result = TargetSelector::from_str(raw_selector);
 
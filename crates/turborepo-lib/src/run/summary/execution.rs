@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{collections::HashMap, fmt, time::Duration};
 
 use chrono::{DateTime, Local};
 use serde::Serialize;
@@ -7,7 +7,10 @@ use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPath};
 use turborepo_ui::{color, cprintln, BOLD, BOLD_GREEN, BOLD_RED, MAGENTA, UI, YELLOW};
 
 use super::TurboDuration;
-use crate::run::{summary::task::TaskSummary, task_id::TaskId};
+use crate::{
+    engine::Engine,
+    run::{summary::task::TaskSummary, task_id::TaskId},
+};
 
 // Just used to make changing the type that gets passed to the state management
 // thread easy
@@ -26,7 +29,7 @@ pub struct ExecutionTracker {
 #[serde(rename_all = "camelCase")]
 pub struct ExecutionSummary<'a> {
     // a synthesized turbo command to produce this invocation
-    command: String,
+    pub(crate) command: String,
     // the (possibly empty) path from the turborepo root to where the command was run
     #[serde(rename = "repoPath")]
     repo_path: &'a AnchoredSystemPath,
@@ -38,6 +41,8 @@ pub struct ExecutionSummary<'a> {
     cached: usize,
     // number of tasks that started
     attempted: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    critical_path: Option<CriticalPath>,
     pub(crate) start_time: i64,
     pub(crate) end_time: i64,
     #[serde(skip)]
@@ -45,10 +50,37 @@ pub struct ExecutionSummary<'a> {
     pub(crate) exit_code: i32,
 }
 
+/// The chain of dependent tasks that took the longest to run
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CriticalPath {
+    tasks: Vec<TaskId<'static>>,
+    // combined duration of the tasks in milliseconds
+    duration: u64,
+}
+
+impl CriticalPath {
+    pub fn new(engine: &Engine, tasks: &[TaskState]) -> Option<Self> {
+        let durations = tasks
+            .iter()
+            .filter_map(|task| {
+                let execution = task.execution.as_ref()?;
+                Some((task.task_id.clone(), execution.duration()))
+            })
+            .collect::<HashMap<_, _>>();
+        let (tasks, duration) = engine.critical_path(&durations)?;
+        Some(Self {
+            tasks: tasks.into_iter().cloned().collect(),
+            duration: duration.as_millis() as u64,
+        })
+    }
+}
+
 impl<'a> ExecutionSummary<'a> {
     pub fn new(
         command: String,
         state: SummaryState,
+        critical_path: Option<CriticalPath>,
         package_inference_root: Option<&'a AnchoredSystemPath>,
         exit_code: i32,
         start_time: DateTime<Local>,
@@ -61,6 +93,7 @@ impl<'a> ExecutionSummary<'a> {
             failed: state.failed,
             cached: state.cached,
             attempted: state.attempted,
+            critical_path,
             // We're either at some path in the repo, or at the root, which is an empty path
             repo_path: package_inference_root.unwrap_or_else(|| AnchoredSystemPath::empty()),
             start_time: start_time.timestamp_millis(),
@@ -195,6 +228,7 @@ pub struct TaskTracker<T> {
     sender: mpsc::Sender<Message>,
     started_at: T,
     task_id: TaskId<'static>,
+    attempts: Vec<TaskAttemptSummary>,
 }
 
 #[derive(Debug, Clone)]
@@ -222,9 +256,28 @@ pub struct TaskExecutionSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
     pub exit_code: Option<i32>,
+    // Only recorded for tasks that configure a timeout or retries
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<TaskAttemptSummary>,
+}
+
+/// A single run of a task's command
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskAttemptSummary {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub exit_code: Option<i32>,
+    // Set if the attempt was stopped for exceeding the task timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
 }
 
 impl TaskExecutionSummary {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis((self.end_time - self.start_time).max(0) as u64)
+    }
+
     pub fn is_failure(&self) -> bool {
         // We consider None as a failure as it indicates the task failed to start
         // or was killed in a manner where we didn't collect an exit code.
@@ -264,6 +317,7 @@ impl ExecutionTracker {
             sender: self.sender.clone(),
             task_id,
             started_at: (),
+            attempts: Vec::new(),
         }
     }
 
@@ -288,7 +342,10 @@ impl TaskTracker<()> {
     // Start the tracker
     pub async fn start(self) -> TaskTracker<DateTime<Local>> {
         let TaskTracker {
-            sender, task_id, ..
+            sender,
+            task_id,
+            attempts,
+            ..
         } = self;
         let started_at = Local::now();
         sender
@@ -302,6 +359,7 @@ impl TaskTracker<()> {
             sender,
             started_at,
             task_id,
+            attempts,
         }
     }
 
@@ -329,11 +387,17 @@ impl TaskTracker<chrono::DateTime<Local>> {
     // internal turbo error
     pub fn cancel(self) {}
 
+    /// Record a finished attempt of the task's command
+    pub fn attempt(&mut self, attempt: TaskAttemptSummary) {
+        self.attempts.push(attempt);
+    }
+
     pub async fn cached(self) -> TaskExecutionSummary {
         let Self {
             sender,
             started_at,
             task_id,
+            attempts,
         } = self;
 
         let ended_at = Local::now();
@@ -343,6 +407,7 @@ impl TaskTracker<chrono::DateTime<Local>> {
             // Go synthesizes a zero exit code on cache hits
             exit_code: Some(0),
             error: None,
+            attempts,
         };
 
         let state = TaskState {
@@ -364,6 +429,7 @@ impl TaskTracker<chrono::DateTime<Local>> {
             sender,
             started_at,
             task_id,
+            attempts,
         } = self;
 
         let ended_at = Local::now();
@@ -372,6 +438,7 @@ impl TaskTracker<chrono::DateTime<Local>> {
             end_time: ended_at.timestamp_millis(),
             exit_code: Some(exit_code),
             error: None,
+            attempts,
         };
 
         let state = TaskState {
@@ -397,6 +464,7 @@ impl TaskTracker<chrono::DateTime<Local>> {
             sender,
             started_at,
             task_id,
+            attempts,
         } = self;
 
         let ended_at = Local::now();
@@ -405,6 +473,7 @@ impl TaskTracker<chrono::DateTime<Local>> {
             end_time: ended_at.timestamp_millis(),
             exit_code,
             error: Some(error.to_string()),
+            attempts,
         };
 
         let state = TaskState {
@@ -518,7 +587,8 @@ mod test {
             start_time: 123,
             end_time: 234,
             exit_code: Some(0),
-            error: None
+            error: None,
+            attempts: vec![],
         },
         json!({ "startTime": 123, "endTime": 234, "exitCode": 0 })
         ; "success"
@@ -529,10 +599,43 @@ mod test {
             end_time: 234,
             exit_code: Some(1),
             error: Some("cannot find anything".into()),
+            attempts: vec![],
         },
         json!({ "startTime": 123, "endTime": 234, "exitCode": 1, "error": "cannot find anything" })
         ; "failure"
     )]
+    #[test_case(
+        TaskExecutionSummary {
+            start_time: 123,
+            end_time: 345,
+            exit_code: Some(0),
+            error: None,
+            attempts: vec![
+                TaskAttemptSummary {
+                    start_time: 123,
+                    end_time: 234,
+                    exit_code: None,
+                    timeout: Some("timed out after 10s".into()),
+                },
+                TaskAttemptSummary {
+                    start_time: 234,
+                    end_time: 345,
+                    exit_code: Some(0),
+                    timeout: None,
+                },
+            ],
+        },
+        json!({
+            "startTime": 123,
+            "endTime": 345,
+            "exitCode": 0,
+            "attempts": [
+                { "startTime": 123, "endTime": 234, "exitCode": null, "timeout": "timed out after 10s" },
+                { "startTime": 234, "endTime": 345, "exitCode": 0 },
+            ]
+        })
+        ; "retried"
+    )]
     fn test_serialization(value: impl serde::Serialize, expected: serde_json::Value) {
         assert_eq!(serde_json::to_value(value).unwrap(), expected);
     }
@@ -0,0 +1,459 @@
+//! Converts a run summary into formats understood by other tools: JUnit XML
+//! for CI test reports and Chrome trace events for timeline viewers such as
+//! Perfetto.
+use std::{collections::BTreeMap, fmt::Write};
+
+use serde::Serialize;
+use serde_json::json;
+use turbopath::AbsoluteSystemPath;
+
+use super::{execution::ExecutionSummary, task::TaskSummary};
+
+/// Renders each task as a JUnit testcase, grouped into a testsuite per package
+pub(super) fn junit(
+    execution: Option<&ExecutionSummary>,
+    tasks: &[TaskSummary],
+    repo_root: &AbsoluteSystemPath,
+) -> String {
+    let command = execution.map_or("turbo run", |execution| execution.command.as_str());
+
+    let mut packages: BTreeMap<&str, Vec<&TaskSummary>> = BTreeMap::new();
+    for task in tasks {
+        packages.entry(&task.package).or_default().push(task);
+    }
+
+    let mut suites = String::new();
+    let (mut total_tests, mut total_failures, mut total_skipped, mut total_time) = (0, 0, 0, 0.0);
+    for (package, tasks) in packages {
+        let mut cases = String::new();
+        let (mut failures, mut skipped, mut time) = (0, 0, 0.0);
+        for task in &tasks {
+            let execution = task.shared.execution.as_ref();
+            let duration = execution.map_or(0.0, |execution| execution.duration().as_secs_f64());
+            time += duration;
+
+            let mut body = String::new();
+            let cache_status = match task.shared.cache.is_hit() {
+                true => "HIT",
+                false => "MISS",
+            };
+            let _ = write!(
+                body,
+                "      <properties>\n        <property name=\"hash\" value=\"{}\"/>\n        \
+                 <property name=\"cache\" value=\"{cache_status}\"/>\n      </properties>\n",
+                escape(&task.shared.hash),
+            );
+            match execution {
+                None => {
+                    skipped += 1;
+                    body.push_str("      <skipped/>\n");
+                }
+                Some(execution) if execution.is_failure() => {
+                    failures += 1;
+                    let message =
+                        execution
+                            .error
+                            .clone()
+                            .unwrap_or_else(|| match execution.exit_code {
+                                Some(code) => format!("command exited ({code})"),
+                                None => "command failed".to_string(),
+                            });
+                    let _ = writeln!(body, "      <failure message=\"{}\"/>", escape(&message));
+                }
+                Some(_) => (),
+            }
+            if let Some(logs) = read_logs(repo_root, task) {
+                let _ = writeln!(body, "      <system-out>{}</system-out>", escape(&logs));
+            }
+
+            let _ = writeln!(
+                cases,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{duration:.3}\">\n{body}    \
+                 </testcase>",
+                escape(&task.task),
+                escape(package),
+            );
+        }
+
+        let _ = writeln!(
+            suites,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\" \
+             time=\"{time:.3}\">\n{cases}  </testsuite>",
+            escape(package),
+            tasks.len(),
+        );
+        total_tests += tasks.len();
+        total_failures += failures;
+        total_skipped += skipped;
+        total_time += time;
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{}\" \
+         tests=\"{total_tests}\" failures=\"{total_failures}\" skipped=\"{total_skipped}\" \
+         time=\"{total_time:.3}\">\n{suites}</testsuites>\n",
+        escape(command),
+    )
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+// See https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cat: Option<&'static str>,
+    ph: &'static str,
+    // Timestamps and durations are in microseconds
+    ts: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<i64>,
+    pid: u32,
+    tid: usize,
+    args: serde_json::Value,
+}
+
+const TRACE_PID: u32 = 1;
+
+/// Renders the run as a Chrome trace with a track for each concurrency slot
+/// that was in use
+pub(super) fn chrome_trace(
+    execution: Option<&ExecutionSummary>,
+    tasks: &[TaskSummary],
+) -> Result<String, serde_json::Error> {
+    let Some(execution) = execution else {
+        return serde_json::to_string(&Trace {
+            trace_events: Vec::new(),
+            display_time_unit: "ms",
+        });
+    };
+
+    let mut tasks = tasks
+        .iter()
+        .filter_map(|task| Some((task, task.shared.execution.as_ref()?)))
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|(_, task_execution)| task_execution.start_time);
+    let slots = assign_slots(
+        tasks
+            .iter()
+            .map(|(_, task_execution)| (task_execution.start_time, task_execution.end_time)),
+    );
+
+    let mut trace_events = vec![TraceEvent {
+        name: "process_name".to_string(),
+        cat: None,
+        ph: "M",
+        ts: 0,
+        dur: None,
+        pid: TRACE_PID,
+        tid: 0,
+        args: json!({ "name": execution.command }),
+    }];
+    let slot_count = slots.iter().max().map_or(0, |slot| slot + 1);
+    trace_events.extend((0..slot_count).map(|slot| TraceEvent {
+        name: "thread_name".to_string(),
+        cat: None,
+        ph: "M",
+        ts: 0,
+        dur: None,
+        pid: TRACE_PID,
+        tid: slot,
+        args: json!({ "name": format!("slot {}", slot + 1) }),
+    }));
+    trace_events.extend(
+        tasks
+            .iter()
+            .zip(slots)
+            .map(|((task, task_execution), slot)| TraceEvent {
+                name: task.task_id.to_string(),
+                cat: Some("task"),
+                ph: "X",
+                ts: (task_execution.start_time - execution.start_time) * 1000,
+                dur: Some((task_execution.end_time - task_execution.start_time).max(0) * 1000),
+                pid: TRACE_PID,
+                tid: slot,
+                args: json!({
+                    "hash": task.shared.hash,
+                    "cache": match task.shared.cache.is_hit() {
+                        true => "HIT",
+                        false => "MISS",
+                    },
+                    "exitCode": task_execution.exit_code,
+                }),
+            }),
+    );
+
+    serde_json::to_string(&Trace {
+        trace_events,
+        display_time_unit: "ms",
+    })
+}
+
+// Places each span, ordered by start time, in the first slot that is free when
+// it starts. This reconstructs which concurrency slot each task ran in.
+fn assign_slots(spans: impl Iterator<Item = (i64, i64)>) -> Vec<usize> {
+    let mut slot_ends: Vec<i64> = Vec::new();
+    spans
+        .map(
+            |(start, end)| match slot_ends.iter().position(|slot_end| *slot_end <= start) {
+                Some(slot) => {
+                    slot_ends[slot] = end;
+                    slot
+                }
+                None => {
+                    slot_ends.push(end);
+                    slot_ends.len() - 1
+                }
+            },
+        )
+        .collect()
+}
+
+fn read_logs(repo_root: &AbsoluteSystemPath, task: &TaskSummary) -> Option<String> {
+    let log_file = repo_root.as_std_path().join(&task.shared.log_file);
+    let logs = std::fs::read_to_string(log_file).ok()?;
+    Some(console::strip_ansi_codes(&logs).into_owned())
+}
+
+// Escapes text for use in XML attributes and content, dropping control
+// characters that XML 1.0 doesn't allow
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use chrono::{Local, TimeZone};
+    use serde_json::Value;
+    use test_case::test_case;
+    use turbopath::AbsoluteSystemPathBuf;
+    use turborepo_cache::{CacheHitMetadata, CacheSource};
+
+    use super::{assign_slots, chrome_trace, escape, junit};
+    use crate::run::{
+        summary::{
+            execution::{ExecutionSummary, SummaryState, TaskExecutionSummary},
+            task::{
+                SharedTaskSummary, TaskCacheSummary, TaskEnvConfiguration, TaskEnvVarSummary,
+                TaskSummary,
+            },
+            EnvMode,
+        },
+        task_id::TaskId,
+    };
+
+    fn task(
+        package: &'static str,
+        name: &'static str,
+        cache_hit: bool,
+        execution: Option<(i64, i64, i32)>,
+    ) -> TaskSummary {
+        let cache = TaskCacheSummary::from(cache_hit.then_some(CacheHitMetadata {
+            source: CacheSource::Local,
+            time_saved: 0,
+        }));
+        TaskSummary {
+            task_id: TaskId::new(package, name),
+            task: name.to_string(),
+            package: package.to_string(),
+            shared: SharedTaskSummary {
+                hash: format!("{package}-{name}"),
+                inputs: BTreeMap::new(),
+                hash_of_external_dependencies: String::new(),
+                cache,
+                command: format!("echo {name}"),
+                cli_arguments: Vec::new(),
+                outputs: None,
+                excluded_outputs: None,
+                log_file: format!("{package}/.turbo/turbo-{name}.log"),
+                directory: Some(package.to_string()),
+                dependencies: Vec::new(),
+                dependents: Vec::new(),
+                resolved_task_definition: Default::default(),
+                expanded_outputs: Vec::new(),
+                framework: String::new(),
+                env_mode: EnvMode::Infer,
+                environment_variables: TaskEnvVarSummary {
+                    specified: TaskEnvConfiguration {
+                        env: Vec::new(),
+                        pass_through_env: None,
+                    },
+                    configured: Vec::new(),
+                    inferred: Vec::new(),
+                    pass_through: None,
+                },
+                dot_env: None,
+                execution: execution.map(|(start_time, end_time, exit_code)| {
+                    TaskExecutionSummary {
+                        start_time,
+                        end_time,
+                        error: None,
+                        exit_code: Some(exit_code),
+                        attempts: Vec::new(),
+                    }
+                }),
+                output_check: None,
+            },
+        }
+    }
+
+    // A run that started at 1s, where web#lint fails and docs#test never ran
+    fn tasks() -> Vec<TaskSummary> {
+        vec![
+            task("web", "build", true, Some((1_000, 1_500, 0))),
+            task("web", "lint", false, Some((1_000, 1_250, 1))),
+            task("docs", "build", false, Some((1_300, 2_000, 0))),
+            task("docs", "test", false, None),
+        ]
+    }
+
+    fn execution() -> ExecutionSummary<'static> {
+        ExecutionSummary::new(
+            "turbo run build lint test".to_string(),
+            SummaryState::default(),
+            None,
+            None,
+            1,
+            Local.timestamp_millis_opt(1_000).unwrap(),
+            Local.timestamp_millis_opt(2_000).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_junit() {
+        let repo_root = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(repo_root.path()).unwrap();
+        let tasks = tasks();
+        let execution = execution();
+        let xml = junit(Some(&execution), &tasks, &repo_root);
+
+        assert!(xml.contains(
+            "<testsuites name=\"turbo run build lint test\" tests=\"4\" failures=\"1\" \
+             skipped=\"1\" time=\"1.450\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"docs\" tests=\"2\" failures=\"0\" skipped=\"1\" time=\"0.700\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"web\" tests=\"2\" failures=\"1\" skipped=\"0\" time=\"0.750\">"
+        ));
+        // The cache hit is marked in the testcase's properties
+        assert!(
+            xml.contains(
+                "    <testcase name=\"build\" classname=\"web\" time=\"0.500\">\n      \
+                 <properties>\n        <property name=\"hash\" value=\"web-build\"/>\n        \
+                 <property name=\"cache\" value=\"HIT\"/>\n      </properties>\n    </testcase>"
+            )
+        );
+        assert!(
+            xml.contains(
+                "    <testcase name=\"lint\" classname=\"web\" time=\"0.250\">\n      \
+                 <properties>\n        <property name=\"hash\" value=\"web-lint\"/>\n        \
+                 <property name=\"cache\" value=\"MISS\"/>\n      </properties>\n      <failure \
+                 message=\"command exited (1)\"/>\n    </testcase>"
+            )
+        );
+        assert!(
+            xml.contains(
+                "<property name=\"cache\" value=\"MISS\"/>\n      </properties>\n      \
+                 <skipped/>\n    </testcase>"
+            )
+        );
+        assert_eq!(xml.matches("<failure").count(), 1);
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let tasks = tasks();
+        let execution = execution();
+        let trace: Value =
+            serde_json::from_str(&chrome_trace(Some(&execution), &tasks).unwrap()).unwrap();
+        assert_eq!(trace["displayTimeUnit"], "ms");
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        let metadata = events
+            .iter()
+            .filter(|event| event["ph"] == "M")
+            .map(|event| {
+                (
+                    event["name"].as_str().unwrap(),
+                    event["tid"].as_u64().unwrap(),
+                    event["args"]["name"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            metadata,
+            vec![
+                ("process_name", 0, "turbo run build lint test"),
+                ("thread_name", 0, "slot 1"),
+                ("thread_name", 1, "slot 2"),
+            ]
+        );
+
+        // Times are relative to the start of the run, in microseconds. docs#build
+        // reuses the slot web#lint finished in, and docs#test never ran.
+        let spans = events
+            .iter()
+            .filter(|event| event["ph"] == "X")
+            .map(|event| {
+                (
+                    event["name"].as_str().unwrap(),
+                    event["ts"].as_i64().unwrap(),
+                    event["dur"].as_i64().unwrap(),
+                    event["tid"].as_u64().unwrap(),
+                    event["args"]["cache"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![
+                ("web#build", 0, 500_000, 0, "HIT"),
+                ("web#lint", 0, 250_000, 1, "MISS"),
+                ("docs#build", 300_000, 700_000, 1, "MISS"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace_without_execution() {
+        let trace: Value = serde_json::from_str(&chrome_trace(None, &tasks()).unwrap()).unwrap();
+        assert_eq!(trace["traceEvents"], Value::Array(Vec::new()));
+    }
+
+    #[test_case(&[], &[] ; "empty")]
+    #[test_case(&[(0, 10), (0, 5), (5, 10), (10, 20)], &[0, 1, 1, 0] ; "reuses free slots")]
+    #[test_case(&[(0, 10), (1, 10), (2, 10)], &[0, 1, 2] ; "overlapping")]
+    fn test_assign_slots(spans: &[(i64, i64)], expected: &[usize]) {
+        assert_eq!(assign_slots(spans.iter().copied()), expected);
+    }
+
+    #[test_case("build", "build" ; "plain")]
+    #[test_case("<a & \"b\">", "&lt;a &amp; &quot;b&quot;&gt;" ; "special characters")]
+    #[test_case("done\u{7}\n", "done\n" ; "control characters")]
+    fn test_escape(value: &str, expected: &str) {
+        assert_eq!(escape(value), expected);
+    }
+}
@@ -6,6 +6,7 @@
 #[allow(dead_code)]
 mod duration;
 mod execution;
+mod export;
 mod global_hash;
 mod scm;
 mod spaces;
@@ -15,7 +16,7 @@ use std::{collections::HashSet, io, io::Write};
 
 use chrono::{DateTime, Local};
 pub use duration::TurboDuration;
-pub use execution::{TaskExecutionSummary, TaskTracker};
+pub use execution::{TaskAttemptSummary, TaskExecutionSummary, TaskTracker};
 pub use global_hash::GlobalHashSummary;
 use itertools::Itertools;
 use serde::Serialize;
@@ -23,7 +24,7 @@ pub use spaces::{SpacesTaskClient, SpacesTaskInformation};
 use svix_ksuid::{Ksuid, KsuidLike};
 use tabwriter::TabWriter;
 use thiserror::Error;
-use tracing::{error, log::warn};
+use tracing::{debug, error, log::warn};
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPath};
 use turborepo_api_client::{spaces::CreateSpaceRunPayload, APIAuth, APIClient};
 use turborepo_env::EnvironmentVariableMap;
@@ -33,14 +34,17 @@ use turborepo_ui::{color, cprintln, cwriteln, BOLD, BOLD_CYAN, GREY, UI};
 use self::{
     execution::TaskState, task::SinglePackageTaskSummary, task_factory::TaskSummaryFactory,
 };
-use super::task_id::TaskId;
+use super::{
+    task_history::{self, TaskHistory, TaskHistoryStore},
+    task_id::TaskId,
+};
 use crate::{
     cli,
-    cli::DryRunMode,
+    cli::{DryRunMode, SummarizeMode},
     engine::Engine,
     opts::RunOpts,
     run::summary::{
-        execution::{ExecutionSummary, ExecutionTracker},
+        execution::{CriticalPath, ExecutionSummary, ExecutionTracker},
         scm::SCMState,
         spaces::{SpaceRequest, SpacesClient, SpacesClientHandle},
         task::TaskSummary,
@@ -121,8 +125,9 @@ pub struct RunSummary<'a> {
     scm: SCMState,
     #[serde(skip)]
     repo_root: &'a AbsoluteSystemPath,
+    // The format to save the summary in, if it should be saved
     #[serde(skip)]
-    should_save: bool,
+    summarize: Option<SummarizeMode>,
     #[serde(skip)]
     run_type: RunType,
     #[serde(skip)]
@@ -191,6 +196,7 @@ impl RunTracker {
         run_opts,
         packages,
         global_hash_summary,
+        engine,
         task_factory,
     ))]
     pub async fn to_summary<'a>(
@@ -203,10 +209,13 @@ impl RunTracker {
         packages: HashSet<WorkspaceName>,
         global_hash_summary: GlobalHashSummary<'a>,
         global_env_mode: EnvMode,
+        engine: &'a Engine,
         task_factory: TaskSummaryFactory<'a>,
     ) -> Result<RunSummary<'a>, Error> {
         let single_package = run_opts.single_package;
-        let should_save = run_opts.summarize.flatten().is_some_and(|s| s);
+        let summarize = run_opts
+            .summarize
+            .filter(|mode| *mode != SummarizeMode::Disabled);
 
         let run_type = match run_opts.dry_run {
             None => RunType::Real,
@@ -222,9 +231,11 @@ impl RunTracker {
             .cloned()
             .map(|TaskState { task_id, execution }| task_factory.task_summary(task_id, execution))
             .collect::<Result<Vec<_>, task_factory::Error>>()?;
+        let critical_path = CriticalPath::new(engine, &summary_state.tasks);
         let execution_summary = ExecutionSummary::new(
             self.synthesized_command.clone(),
             summary_state,
+            critical_path,
             package_inference_root,
             exit_code,
             self.started_at,
@@ -245,7 +256,7 @@ impl RunTracker {
             user: self.user,
             monorepo: !single_package,
             repo_root,
-            should_save,
+            summarize,
             run_type,
             spaces_client_handle: self.spaces_client_handle,
         })
@@ -298,6 +309,7 @@ impl RunTracker {
                 packages,
                 global_hash_summary,
                 global_env_mode.into(),
+                engine,
                 task_factory,
             )
             .await?;
@@ -383,14 +395,18 @@ impl<'a> RunSummary<'a> {
             return self.close_dry_run(pkg_dep_graph, ui);
         }
 
-        if self.should_save {
-            if let Err(err) = self.save() {
+        if let Some(mode) = self.summarize {
+            if let Err(err) = self.save(mode) {
                 warn!("Error writing run summary: {}", err)
             }
         }
 
+        if let Err(err) = self.record_task_history() {
+            warn!("Error writing task history: {}", err)
+        }
+
         if let Some(execution) = &self.execution {
-            let path = self.get_path();
+            let path = self.get_path(self.summarize.unwrap_or(SummarizeMode::Json));
             let failed_tasks = self.get_failed_tasks();
             execution.print(ui, path, failed_tasks);
         }
@@ -403,6 +419,34 @@ impl<'a> RunSummary<'a> {
         Ok(())
     }
 
+    /// Records the durations of the tasks that ran so that future runs can
+    /// schedule them
+    fn record_task_history(&self) -> Result<(), task_history::Error> {
+        // Cache hits don't tell us anything about how long a task takes to run
+        let executed = self
+            .tasks
+            .iter()
+            .filter(|task| !task.shared.cache.is_hit())
+            .filter_map(|task| {
+                let execution = task.shared.execution.as_ref()?;
+                (!execution.is_failure()).then(|| (&task.task_id, execution.duration()))
+            })
+            .collect::<Vec<_>>();
+        if executed.is_empty() {
+            return Ok(());
+        }
+
+        let store = TaskHistoryStore::new(self.repo_root);
+        let mut history = store.read().unwrap_or_else(|err| {
+            debug!("unable to read task history: {}", err);
+            TaskHistory::default()
+        });
+        for (task_id, duration) in executed {
+            history.record(task_id, duration);
+        }
+        store.write(&history)
+    }
+
     async fn send_to_space(
         &self,
         spaces_client_handle: SpacesClientHandle,
@@ -647,6 +691,19 @@ impl<'a> RunSummary<'a> {
             };
 
             cwriteln!(tab_writer, ui, GREY, "  Dependents\t=\t{}", dependents)?;
+            let resources = task.shared.resolved_task_definition.resources();
+            if !resources.is_empty() {
+                cwriteln!(
+                    tab_writer,
+                    ui,
+                    GREY,
+                    "  Resources\t=\t{}",
+                    resources
+                        .iter()
+                        .map(|(resource, slots)| format!("{resource}={slots}"))
+                        .join(", ")
+                )?;
+            }
             cwriteln!(
                 tab_writer,
                 ui,
@@ -769,8 +826,12 @@ impl<'a> RunSummary<'a> {
         self.tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
     }
 
-    fn get_path(&self) -> AbsoluteSystemPathBuf {
-        let filename = format!("{}.json", self.id);
+    fn get_path(&self, mode: SummarizeMode) -> AbsoluteSystemPathBuf {
+        let filename = match mode {
+            SummarizeMode::Junit => format!("{}.xml", self.id),
+            SummarizeMode::Trace => format!("{}.trace.json", self.id),
+            SummarizeMode::Json | SummarizeMode::Disabled => format!("{}.json", self.id),
+        };
 
         self.repo_root
             .join_components(&[".turbo", "runs", &filename])
@@ -788,12 +849,22 @@ impl<'a> RunSummary<'a> {
             .collect()
     }
 
-    fn save(&mut self) -> Result<(), Error> {
-        let json = self.format_json()?;
+    fn save(&mut self, mode: SummarizeMode) -> Result<(), Error> {
+        let contents = match mode {
+            SummarizeMode::Junit => {
+                self.normalize();
+                export::junit(self.execution.as_ref(), &self.tasks, self.repo_root)
+            }
+            SummarizeMode::Trace => {
+                self.normalize();
+                export::chrome_trace(self.execution.as_ref(), &self.tasks)?
+            }
+            SummarizeMode::Json | SummarizeMode::Disabled => self.format_json()?,
+        };
 
-        let summary_path = self.get_path();
+        let summary_path = self.get_path(mode);
         summary_path.ensure_dir()?;
 
-        Ok(summary_path.create_with_contents(json)?)
+        Ok(summary_path.create_with_contents(contents)?)
     }
 }
@@ -9,8 +9,8 @@ use turborepo_env::{DetailedMap, EnvironmentVariableMap};
 use super::{execution::TaskExecutionSummary, EnvMode};
 use crate::{
     cli::OutputLogsMode,
-    run::task_id::TaskId,
-    task_graph::{TaskDefinition, TaskOutputs},
+    run::{output_check::OutputCheck, task_id::TaskId},
+    task_graph::{ReadyWhen, TaskDefinition, TaskOutputs},
 };
 
 #[derive(Debug, Serialize, Clone)]
@@ -85,6 +85,9 @@ pub(crate) struct SharedTaskSummary<T> {
     pub dot_env: Option<Vec<RelativeUnixPathBuf>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub execution: Option<TaskExecutionSummary>,
+    // Only present when running with `--check-outputs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_check: Option<OutputCheck>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -103,9 +106,22 @@ pub struct TaskSummaryTaskDefinition {
     inputs: Vec<String>,
     output_mode: OutputLogsMode,
     persistent: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ready_when: Option<ReadyWhen>,
     env: Vec<String>,
     pass_through_env: Option<Vec<String>>,
     dot_env: Option<Vec<RelativeUnixPathBuf>>,
+    // Timeout in seconds, omitted unless configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    #[serde(skip_serializing_if = "is_zero")]
+    retries: u32,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    resources: BTreeMap<String, u32>,
+}
+
+fn is_zero(retries: &u32) -> bool {
+    *retries == 0
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -128,6 +144,10 @@ impl TaskCacheSummary {
             source: None,
         }
     }
+
+    pub fn is_hit(&self) -> bool {
+        matches!(self.status, CacheStatus::Hit)
+    }
 }
 
 impl From<Option<CacheHitMetadata>> for TaskCacheSummary {
@@ -234,6 +254,7 @@ impl From<SharedTaskSummary<TaskId<'static>>> for SharedTaskSummary<String> {
             env_mode,
             environment_variables,
             dot_env,
+            output_check,
             ..
         } = value;
         Self {
@@ -264,6 +285,7 @@ impl From<SharedTaskSummary<TaskId<'static>>> for SharedTaskSummary<String> {
             env_mode,
             environment_variables,
             dot_env,
+            output_check,
         }
     }
 }
@@ -285,6 +307,12 @@ impl From<TaskDefinition> for TaskSummaryTaskDefinition {
             mut inputs,
             output_mode,
             persistent,
+            // Only used by `turbo watch`, so it isn't part of the summary
+            interruptible: _,
+            ready_when,
+            timeout,
+            retries,
+            resources,
         } = value;
 
         let mut outputs = inclusions;
@@ -316,14 +344,24 @@ impl From<TaskDefinition> for TaskSummaryTaskDefinition {
             inputs,
             output_mode,
             persistent,
+            ready_when,
             env,
             pass_through_env,
             // This should _not_ be sorted.
             dot_env,
+            timeout: timeout.map(|timeout| timeout.as_secs()),
+            retries,
+            resources,
         }
     }
 }
 
+impl TaskSummaryTaskDefinition {
+    pub fn resources(&self) -> &BTreeMap<String, u32> {
+        &self.resources
+    }
+}
+
 #[cfg(test)]
 mod test {
     use serde_json::json;
@@ -381,6 +419,66 @@ mod test {
         })
         ; "resolved task definition"
     )]
+    #[test_case(
+        TaskSummaryTaskDefinition {
+            timeout: Some(30),
+            retries: 2,
+            ..Default::default()
+        },
+        json!({
+            "outputs": [],
+            "cache": false,
+            "dependsOn": [],
+            "inputs": [],
+            "outputMode": "full",
+            "persistent": false,
+            "env": [],
+            "passThroughEnv": null,
+            "dotEnv": null,
+            "timeout": 30,
+            "retries": 2,
+        })
+        ; "resolved task definition with retries"
+    )]
+    #[test_case(
+        TaskSummaryTaskDefinition {
+            resources: [("browser".to_string(), 1)].into_iter().collect(),
+            ..Default::default()
+        },
+        json!({
+            "outputs": [],
+            "cache": false,
+            "dependsOn": [],
+            "inputs": [],
+            "outputMode": "full",
+            "persistent": false,
+            "env": [],
+            "passThroughEnv": null,
+            "dotEnv": null,
+            "resources": { "browser": 1 },
+        })
+        ; "resolved task definition with resources"
+    )]
+    #[test_case(
+        TaskSummaryTaskDefinition {
+            persistent: true,
+            ready_when: Some(ReadyWhen::Port(3000)),
+            ..Default::default()
+        },
+        json!({
+            "outputs": [],
+            "cache": false,
+            "dependsOn": [],
+            "inputs": [],
+            "outputMode": "full",
+            "persistent": true,
+            "readyWhen": { "port": 3000 },
+            "env": [],
+            "passThroughEnv": null,
+            "dotEnv": null,
+        })
+        ; "resolved task definition with readiness probe"
+    )]
     fn test_serialization(value: impl serde::Serialize, expected: serde_json::Value) {
         assert_eq!(serde_json::to_value(value).unwrap(), expected);
     }
@@ -199,6 +199,7 @@ impl<'a> TaskSummaryFactory<'a> {
             .expect("invalid glob in task definition should have been caught earlier"),
             dot_env: task_definition.dot_env.clone(),
             execution,
+            output_check: self.hash_tracker.output_check(task_id),
         })
     }
 
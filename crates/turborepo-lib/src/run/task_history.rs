@@ -0,0 +1,118 @@
+//! Records how long tasks took in previous runs so that the tasks with the
+//! longest remaining critical path can be started first.
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf};
+
+use super::task_id::TaskId;
+
+const TASK_HISTORY_FILE: &str = "task-history.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unable to access task history: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unable to parse task history: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskHistory {
+    // Map of task ids to their estimated duration in milliseconds
+    durations: BTreeMap<String, u64>,
+}
+
+/// Reads and writes the task history of a repository
+#[derive(Debug, Clone)]
+pub struct TaskHistoryStore {
+    path: AbsoluteSystemPathBuf,
+}
+
+impl TaskHistory {
+    pub fn estimate(&self, task_id: &TaskId) -> Option<Duration> {
+        self.durations
+            .get(&task_id.to_string())
+            .copied()
+            .map(Duration::from_millis)
+    }
+
+    /// Returns the estimated duration of each of `tasks` that has run before
+    pub fn estimates<'a>(
+        &self,
+        tasks: impl Iterator<Item = &'a TaskId<'static>>,
+    ) -> HashMap<TaskId<'static>, Duration> {
+        tasks
+            .filter_map(|task_id| Some((task_id.clone(), self.estimate(task_id)?)))
+            .collect()
+    }
+
+    /// Records a run of `task_id`. The estimate is averaged with the previous
+    /// one so that a single unusually slow or fast run doesn't dominate it.
+    pub fn record(&mut self, task_id: &TaskId, duration: Duration) {
+        let duration = duration.as_millis() as u64;
+        self.durations
+            .entry(task_id.to_string())
+            .and_modify(|estimate| *estimate = (*estimate + duration) / 2)
+            .or_insert(duration);
+    }
+}
+
+impl TaskHistoryStore {
+    pub fn new(repo_root: &AbsoluteSystemPath) -> Self {
+        Self {
+            path: repo_root.join_components(&[".turbo", TASK_HISTORY_FILE]),
+        }
+    }
+
+    pub fn read(&self) -> Result<TaskHistory, Error> {
+        let contents = self
+            .path
+            .read_existing_to_string_or(Ok::<_, std::io::Error>(""))?;
+        if contents.is_empty() {
+            return Ok(TaskHistory::default());
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn write(&self, history: &TaskHistory) -> Result<(), Error> {
+        self.path.ensure_dir()?;
+        self.path
+            .create_with_contents(serde_json::to_string(history)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let task_id = TaskId::new("web", "build");
+        let mut history = TaskHistory::default();
+        assert_eq!(history.estimate(&task_id), None);
+
+        history.record(&task_id, Duration::from_secs(10));
+        assert_eq!(history.estimate(&task_id), Some(Duration::from_secs(10)));
+        history.record(&task_id, Duration::from_secs(20));
+        assert_eq!(history.estimate(&task_id), Some(Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::try_from(dir.path()).unwrap();
+        let store = TaskHistoryStore::new(&repo_root);
+
+        assert_eq!(store.read().unwrap(), TaskHistory::default());
+        let mut history = TaskHistory::default();
+        history.record(&TaskId::new("@scope/web", "build"), Duration::from_secs(3));
+        store.write(&history).unwrap();
+        assert_eq!(store.read().unwrap(), history);
+    }
+}
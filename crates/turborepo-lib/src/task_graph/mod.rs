@@ -1,5 +1,8 @@
+mod ready;
 mod visitor;
 
+use std::{collections::BTreeMap, time::Duration};
+
 use serde::{Deserialize, Serialize};
 use turbopath::{AnchoredSystemPath, AnchoredSystemPathBuf, RelativeUnixPathBuf};
 pub use visitor::{Error as VisitorError, Visitor};
@@ -18,6 +21,19 @@ pub struct TaskOutputs {
     pub exclusions: Vec<String>,
 }
 
+// ReadyWhen is a probe that tells when a persistent Task has started up far
+// enough for the tasks that depend on it to run
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadyWhen {
+    // A regex that matches a line of the Task's output
+    Output(String),
+    // A port on localhost that accepts TCP connections
+    Port(u16),
+    // An HTTP URL that responds with a 2xx status
+    Url(String),
+}
+
 // Constructed from a RawTaskDefinition
 #[derive(Debug, Deserialize, PartialEq, Clone, Eq)]
 pub struct TaskDefinition {
@@ -53,6 +69,26 @@ pub struct TaskDefinition {
     // Persistent indicates whether the Task is expected to exit or not
     // Tasks marked Persistent do not exit (e.g. --watch mode or dev servers)
     pub persistent: bool,
+
+    // Interruptible indicates whether a persistent Task can be stopped and
+    // restarted by `turbo watch` when one of its dependencies changes
+    pub interruptible: bool,
+
+    // ReadyWhen lets tasks depend on a persistent Task. They start once the
+    // probe succeeds while the persistent Task keeps running until the run ends
+    pub(crate) ready_when: Option<ReadyWhen>,
+
+    // Timeout for a single attempt of the Task. Once it elapses the Task is
+    // stopped and counts as failed
+    pub(crate) timeout: Option<Duration>,
+
+    // Retries is the number of times a failed or timed out Task is run again
+    // before the failure is reported
+    pub(crate) retries: u32,
+
+    // Resources are the slots of repo-level resources (declared in the root
+    // turbo.json) that the Task holds while it runs
+    pub(crate) resources: BTreeMap<String, u32>,
 }
 
 impl Default for TaskDefinition {
@@ -67,7 +103,12 @@ impl Default for TaskDefinition {
             inputs: Default::default(),
             output_mode: Default::default(),
             persistent: Default::default(),
+            interruptible: Default::default(),
+            ready_when: Default::default(),
             dot_env: Default::default(),
+            timeout: Default::default(),
+            retries: Default::default(),
+            resources: Default::default(),
         }
     }
 }
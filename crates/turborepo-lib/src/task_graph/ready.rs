@@ -0,0 +1,223 @@
+//! Probes that tell when a persistent task is ready for the tasks that depend
+//! on it to start.
+use std::{fmt, io::Write, time::Duration};
+
+use regex::Regex;
+use tokio::{net::TcpStream, sync::oneshot, task::JoinHandle};
+use tracing::{debug, error};
+
+use super::ReadyWhen;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Lines longer than this are checked in pieces rather than buffered until the
+// newline is written
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// How long a task can take to become ready before a warning is printed
+pub const READY_WARNING_AFTER: Duration = Duration::from_secs(30);
+
+/// Passes output through to the inner writer while checking each line of it
+/// against the `readyWhen` output pattern
+pub struct ProbeWriter<W> {
+    inner: W,
+    probe: Option<(Regex, oneshot::Sender<()>)>,
+    // The incomplete last line of the output while probing
+    line: Vec<u8>,
+}
+
+/// A probe that is polled in the background until it is dropped
+pub struct PollingProbe(JoinHandle<()>);
+
+impl<W> ProbeWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            probe: None,
+            line: Vec::new(),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Starts probing for `ready_when`, sending on `ready` once it succeeds.
+    /// Output probes are checked as output is written, the others are polled
+    /// in the background by the returned probe.
+    pub fn start_probe(
+        &mut self,
+        ready_when: &ReadyWhen,
+        ready: oneshot::Sender<()>,
+    ) -> Option<PollingProbe> {
+        match ready_when {
+            ReadyWhen::Output(pattern) => {
+                match Regex::new(pattern) {
+                    Ok(pattern) => self.probe = Some((pattern, ready)),
+                    // Patterns are validated when turbo.json is loaded
+                    Err(e) => error!("invalid readyWhen output pattern: {e}"),
+                }
+                None
+            }
+            ReadyWhen::Port(port) => Some(PollingProbe(tokio::spawn(wait_for_port(*port, ready)))),
+            ReadyWhen::Url(url) => {
+                Some(PollingProbe(tokio::spawn(wait_for_url(url.clone(), ready))))
+            }
+        }
+    }
+}
+
+impl Drop for PollingProbe {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl<W> ProbeWriter<W> {
+    // Checks every line that `written` completes, output can be written in
+    // arbitrary chunks so a line can be split across several writes
+    fn check_lines(&mut self, written: &[u8]) {
+        let Some((pattern, _)) = &self.probe else {
+            return;
+        };
+        let mut matched = false;
+        for chunk in written.split_inclusive(|byte| *byte == b'\n') {
+            self.line.extend_from_slice(chunk);
+            if !self.line.ends_with(b"\n") && self.line.len() < MAX_LINE_LENGTH {
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line);
+            matched = pattern.is_match(line.trim_end_matches(['\r', '\n']));
+            self.line.clear();
+            if matched {
+                break;
+            }
+        }
+        if matched {
+            if let Some((_, ready)) = self.probe.take() {
+                ready.send(()).ok();
+            }
+            self.line = Vec::new();
+        }
+    }
+}
+
+impl<W: Write> Write for ProbeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.check_lines(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl fmt::Display for ReadyWhen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadyWhen::Output(pattern) => write!(f, "output matching \"{pattern}\""),
+            ReadyWhen::Port(port) => write!(f, "port {port} to accept connections"),
+            ReadyWhen::Url(url) => write!(f, "{url} to respond"),
+        }
+    }
+}
+
+async fn wait_for_port(port: u16, ready: oneshot::Sender<()>) {
+    while let Err(e) = TcpStream::connect(("localhost", port)).await {
+        debug!("port {port} is not ready: {e}");
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    ready.send(()).ok();
+}
+
+async fn wait_for_url(url: String, ready: oneshot::Sender<()>) {
+    let client = reqwest::Client::new();
+    loop {
+        match client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => break,
+            Ok(response) => debug!("{url} is not ready: {}", response.status()),
+            Err(e) => debug!("{url} is not ready: {e}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    ready.send(()).ok();
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    use super::ProbeWriter;
+    use crate::task_graph::ReadyWhen;
+
+    #[test]
+    fn test_output_probe() {
+        let (ready, mut is_ready) = oneshot::channel();
+        let mut writer = ProbeWriter::new(Vec::new());
+        let task = writer.start_probe(&ReadyWhen::Output("listening on \\d+".into()), ready);
+        assert!(task.is_none());
+
+        writer.write_all(b"starting server\n").unwrap();
+        assert!(is_ready.try_recv().is_err());
+        writer.write_all(b"listening on 3000\n").unwrap();
+        assert!(is_ready.try_recv().is_ok());
+        assert_eq!(writer.inner, b"starting server\nlistening on 3000\n");
+    }
+
+    #[test]
+    fn test_output_probe_split_lines() {
+        let (ready, mut is_ready) = oneshot::channel();
+        let mut writer = ProbeWriter::new(Vec::new());
+        writer.start_probe(&ReadyWhen::Output("^listening on \\d+$".into()), ready);
+
+        // The line is only checked once it is complete
+        writer.write_all(b"starting server\nlisten").unwrap();
+        writer.write_all(b"ing on 30").unwrap();
+        assert!(is_ready.try_recv().is_err());
+        writer.write_all(b"00\r\n").unwrap();
+        assert!(is_ready.try_recv().is_ok());
+        assert_eq!(writer.inner, b"starting server\nlistening on 3000\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_port_probe() {
+        let listener = TcpListener::bind("localhost:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (ready, is_ready) = oneshot::channel();
+        let mut writer = ProbeWriter::new(Vec::new());
+        writer.start_probe(&ReadyWhen::Port(port), ready).unwrap();
+        is_ready.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_url_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                // Read the request before responding so the connection isn't reset
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).await.unwrap();
+                assert!(read > 0);
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+        let (ready, is_ready) = oneshot::channel();
+        let mut writer = ProbeWriter::new(Vec::new());
+        writer
+            .start_probe(&ReadyWhen::Url(format!("http://{addr}/health")), ready)
+            .unwrap();
+        is_ready.await.unwrap();
+    }
+}
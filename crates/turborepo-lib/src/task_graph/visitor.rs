@@ -1,20 +1,25 @@
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     io::Write,
     process::Stdio,
-    sync::{Arc, Mutex, OnceLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     time::{Duration, Instant},
 };
 
+use chrono::Local;
 use console::{Style, StyledObject};
 use futures::{stream::FuturesUnordered, StreamExt};
 use regex::Regex;
 use tokio::{
-    process::Command,
+    io::AsyncWriteExt,
+    process::{ChildStdin, Command},
     sync::{mpsc, oneshot},
 };
-use tracing::{debug, error, Span};
+use tracing::{debug, error, warn, Span};
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf};
 use turborepo_ci::github_header_footer;
 use turborepo_env::{EnvironmentVariableMap, ResolvedEnvMode};
@@ -23,23 +28,33 @@ use turborepo_repository::{
     package_manager::PackageManager,
 };
 use turborepo_telemetry::events::{task::PackageTaskEventBuilder, EventBuilder};
-use turborepo_ui::{ColorSelector, OutputClient, OutputSink, OutputWriter, PrefixedUI, UI};
+use turborepo_ui::{
+    tui::{AppSender, TaskOutput, TaskResult, TuiTask},
+    ColorSelector, OutputClient, OutputSink, OutputWriter, PrefixedUI, UI,
+};
 use which::which;
 
 use crate::{
-    cli::EnvMode,
-    engine::{Engine, ExecutionOptions, StopExecution},
+    cli::{CheckOutputsMode, EnvMode},
+    engine::{Engine, ExecutionOptions, StopExecution, TaskNode},
     opts::Opts,
     process::{ChildExit, ProcessManager},
     run::{
         global_hash::GlobalHashableInputs,
+        hash_inputs::{GlobalHashInputs, HashInputsStore},
+        output_check::{OutputCheck, OutputSnapshot},
         summary::{
             self, GlobalHashSummary, RunTracker, SpacesTaskClient, SpacesTaskInformation,
-            TaskExecutionSummary, TaskTracker,
+            TaskAttemptSummary, TaskExecutionSummary, TaskTracker,
         },
+        task_history::{TaskHistory, TaskHistoryStore},
         task_id::TaskId,
         RunCache, TaskCache,
     },
+    task_graph::{
+        ready::{ProbeWriter, READY_WARNING_AFTER},
+        ReadyWhen, TaskDefinition, TaskOutputs,
+    },
     task_hash::{self, PackageInputsHashes, TaskHashTracker, TaskHashTrackerState, TaskHasher},
 };
 
@@ -57,6 +72,7 @@ pub struct Visitor<'a> {
     run_tracker: RunTracker,
     sink: OutputSink<StdWriter>,
     task_hasher: TaskHasher<'a>,
+    tui: Option<AppSender>,
     ui: UI,
 }
 
@@ -94,6 +110,7 @@ impl<'a> Visitor<'a> {
         package_inputs_hashes: PackageInputsHashes,
         env_at_execution_start: &'a EnvironmentVariableMap,
         global_hash: &'a str,
+        global_hash_inputs: Option<GlobalHashInputs>,
         global_env_mode: EnvMode,
         ui: UI,
         silent: bool,
@@ -106,6 +123,7 @@ impl<'a> Visitor<'a> {
             opts,
             env_at_execution_start,
             global_hash,
+            global_hash_inputs,
         );
         let sink = Self::sink(opts, silent);
         let color_cache = ColorSelector::default();
@@ -122,25 +140,122 @@ impl<'a> Visitor<'a> {
             run_tracker,
             sink,
             task_hasher,
+            tui: None,
             ui,
             global_env,
         }
     }
 
+    fn task_env_mode(&self, task_definition: &TaskDefinition) -> ResolvedEnvMode {
+        match self.global_env_mode {
+            // Task env mode is only independent when global env mode is `infer`.
+            EnvMode::Infer if task_definition.pass_through_env.is_some() => ResolvedEnvMode::Strict,
+            // If we're in infer mode we have just detected non-usage of strict env vars.
+            // But our behavior's actual meaning of this state is `loose`.
+            EnvMode::Infer => ResolvedEnvMode::Loose,
+            // Otherwise we just use the global env mode.
+            EnvMode::Strict => ResolvedEnvMode::Strict,
+            EnvMode::Loose => ResolvedEnvMode::Loose,
+        }
+    }
+
+    /// Hashes every task before execution starts, so that the remote cache can
+    /// be checked for all of them with a single request instead of one
+    /// request per task. Returns the telemetry event of each task that was
+    /// hashed so the visitor records the rest of the task's events on it.
+    async fn prefetch_cache(
+        &self,
+        engine: &Engine,
+    ) -> Result<HashMap<TaskId<'static>, PackageTaskEventBuilder>, Error> {
+        let mut package_task_events = HashMap::new();
+        let Some(tasks) = engine.tasks_in_dependency_order() else {
+            return Ok(package_task_events);
+        };
+
+        let mut hashes = Vec::new();
+        for task_id in tasks {
+            let workspace_info = self
+                .package_graph
+                .workspace_info(&WorkspaceName::from(task_id.package()))
+                .ok_or_else(|| Error::MissingPackage {
+                    package_name: WorkspaceName::from(task_id.package()),
+                    task_id: task_id.clone(),
+                })?;
+            let task_definition = engine
+                .task_definition(task_id)
+                .ok_or(Error::MissingDefinition)?;
+            let dependency_set = engine
+                .dependencies(task_id)
+                .ok_or(Error::MissingDefinition)?;
+
+            // A task is only hashed once per run, so its hash tracing and
+            // telemetry are only recorded once
+            let task_hash = match self.task_hasher.task_hash_tracker().hash(task_id) {
+                Some(task_hash) => task_hash,
+                None => {
+                    let package_task_event =
+                        PackageTaskEventBuilder::new(task_id.package(), task_id.task());
+                    let task_hash = self.task_hasher.calculate_task_hash(
+                        task_id,
+                        task_definition,
+                        self.task_env_mode(task_definition),
+                        workspace_info,
+                        dependency_set,
+                        package_task_event.child(),
+                    )?;
+                    package_task_events.insert(task_id.clone(), package_task_event);
+                    task_hash
+                }
+            };
+
+            if task_definition.cache {
+                hashes.push(task_hash);
+            }
+        }
+
+        self.run_cache.prefetch(&hashes).await;
+
+        Ok(package_task_events)
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn visit(&self, engine: Arc<Engine>) -> Result<Vec<TaskError>, Error> {
+        let mut package_task_events = self.prefetch_cache(&engine).await?;
+
         let concurrency = self.opts.run_opts.concurrency as usize;
+        let task_history = TaskHistoryStore::new(self.repo_root)
+            .read()
+            .unwrap_or_else(|err| {
+                debug!("unable to read task history: {}", err);
+                TaskHistory::default()
+            });
+        let execution_options = ExecutionOptions::new(false, concurrency).with_task_durations(
+            task_history.estimates(engine.tasks().filter_map(|task| match task {
+                TaskNode::Task(task_id) => Some(task_id),
+                TaskNode::Root => None,
+            })),
+        );
         let (node_sender, mut node_stream) = mpsc::channel(concurrency);
         let engine_handle = {
             let engine = engine.clone();
-            tokio::spawn(engine.execute(ExecutionOptions::new(false, concurrency), node_sender))
+            tokio::spawn(engine.execute(execution_options, node_sender))
         };
         let mut tasks = FuturesUnordered::new();
+        // Persistent tasks that other tasks wait on to be ready, these are stopped
+        // once the rest of the run is done
+        let mut services = FuturesUnordered::new();
+        let stopping_services = Arc::new(AtomicBool::new(false));
         let errors = Arc::new(Mutex::new(Vec::new()));
 
         let span = Span::current();
 
-        let factory = ExecContextFactory::new(self, errors.clone(), self.manager.clone(), &engine);
+        let factory = ExecContextFactory::new(
+            self,
+            errors.clone(),
+            self.manager.clone(),
+            &engine,
+            stopping_services.clone(),
+        );
 
         while let Some(message) = node_stream.recv().await {
             let span = tracing::debug_span!(parent: &span, "queue_task", task = %message.info);
@@ -156,7 +271,9 @@ impl<'a> Visitor<'a> {
                     task_id: info.clone(),
                 })?;
 
-            let package_task_event = PackageTaskEventBuilder::new(info.package(), info.task());
+            let package_task_event = package_task_events
+                .remove(&info)
+                .unwrap_or_else(|| PackageTaskEventBuilder::new(info.package(), info.task()));
             let command = workspace_info
                 .package_json
                 .scripts
@@ -178,31 +295,27 @@ impl<'a> Visitor<'a> {
                 .task_definition(&info)
                 .ok_or(Error::MissingDefinition)?;
 
-            let task_env_mode = match self.global_env_mode {
-                // Task env mode is only independent when global env mode is `infer`.
-                EnvMode::Infer if task_definition.pass_through_env.is_some() => {
-                    ResolvedEnvMode::Strict
+            let task_env_mode = self.task_env_mode(task_definition);
+
+            // Tasks are hashed up front when prefetching the cache
+            let task_hash = match self.task_hasher.task_hash_tracker().hash(&info) {
+                Some(task_hash) => task_hash,
+                None => {
+                    let dependency_set =
+                        engine.dependencies(&info).ok_or(Error::MissingDefinition)?;
+
+                    let package_task_event_child = package_task_event.child();
+                    self.task_hasher.calculate_task_hash(
+                        &info,
+                        task_definition,
+                        task_env_mode,
+                        workspace_info,
+                        dependency_set,
+                        package_task_event_child,
+                    )?
                 }
-                // If we're in infer mode we have just detected non-usage of strict env vars.
-                // But our behavior's actual meaning of this state is `loose`.
-                EnvMode::Infer => ResolvedEnvMode::Loose,
-                // Otherwise we just use the global env mode.
-                EnvMode::Strict => ResolvedEnvMode::Strict,
-                EnvMode::Loose => ResolvedEnvMode::Loose,
             };
 
-            let dependency_set = engine.dependencies(&info).ok_or(Error::MissingDefinition)?;
-
-            let package_task_event_child = package_task_event.child();
-            let task_hash = self.task_hasher.calculate_task_hash(
-                &info,
-                task_definition,
-                task_env_mode,
-                workspace_info,
-                dependency_set,
-                package_task_event_child,
-            )?;
-
             debug!("task {} hash is {}", info, task_hash);
             // We do this calculation earlier than we do in Go due to the `task_hasher`
             // being !Send. In the future we can look at doing this right before
@@ -248,11 +361,19 @@ impl<'a> Visitor<'a> {
                         execution_env,
                     );
 
-                    let output_client = self.output_client(&info);
+                    let is_service = task_definition.ready_when.is_some()
+                        && engine
+                            .dependents(&info)
+                            .map_or(false, |dependents| !dependents.is_empty());
+                    let output_client = self.output_client(&info, exec_context.tui_task.as_ref());
                     let tracker = self.run_tracker.track_task(info.clone().into_owned());
                     let spaces_client = self.run_tracker.spaces_task_client();
                     let parent_span = Span::current();
 
+                    let tasks = match is_service {
+                        true => &mut services,
+                        false => &mut tasks,
+                    };
                     tasks.push(tokio::spawn(async move {
                         exec_context
                             .execute(
@@ -274,6 +395,13 @@ impl<'a> Visitor<'a> {
         while let Some(result) = tasks.next().await {
             result.expect("task executor panicked");
         }
+        if !services.is_empty() {
+            stopping_services.store(true, Ordering::SeqCst);
+            self.manager.stop().await;
+            while let Some(result) = services.next().await {
+                result.expect("task executor panicked");
+            }
+        }
         drop(factory);
 
         let errors = Arc::into_inner(errors)
@@ -343,7 +471,22 @@ impl<'a> Visitor<'a> {
         OutputSink::new(out, err)
     }
 
-    fn output_client(&self, task_id: &TaskId) -> OutputClient<impl std::io::Write> {
+    fn output_client(
+        &self,
+        task_id: &TaskId,
+        tui_task: Option<&TuiTask>,
+    ) -> OutputClient<impl std::io::Write> {
+        // Each task has its own output pane in the TUI so output doesn't need
+        // to be grouped
+        if let Some(tui_task) = tui_task {
+            let behavior = match self.run_tracker.spaces_enabled() {
+                true => turborepo_ui::OutputClientBehavior::InMemoryBuffer,
+                false => turborepo_ui::OutputClientBehavior::Passthrough,
+            };
+            let output = tui_task.output();
+            return OutputSink::new(output.clone().into(), output.into()).logger(behavior);
+        }
+
         let behavior = match self.opts.run_opts.log_order {
             crate::opts::ResolvedLogOrder::Stream if self.run_tracker.spaces_enabled() => {
                 turborepo_ui::OutputClientBehavior::InMemoryBuffer
@@ -368,6 +511,9 @@ impl<'a> Visitor<'a> {
     }
 
     fn prefix<'b>(&self, task_id: &'b TaskId) -> Cow<'b, str> {
+        if self.tui.is_some() {
+            return "".into();
+        }
         match self.opts.run_opts.log_prefix {
             crate::opts::ResolvedLogPrefix::Task if self.opts.run_opts.single_package => {
                 task_id.task().into()
@@ -414,6 +560,32 @@ impl<'a> Visitor<'a> {
         self.task_hasher.into_task_hash_tracker_state()
     }
 
+    /// Sends the progress of tasks to the TUI instead of printing their output
+    pub fn use_tui(&mut self, tui: AppSender) {
+        self.tui = Some(tui);
+    }
+
+    /// Names of the tasks that are shown in the TUI. Tasks without a command
+    /// aren't run so they're left out.
+    pub fn tui_task_names(&self, engine: &Engine) -> Vec<String> {
+        let mut names = engine
+            .tasks()
+            .filter_map(|task| match task {
+                TaskNode::Task(task_id) => Some(task_id),
+                TaskNode::Root => None,
+            })
+            .filter(|task_id| {
+                self.package_graph
+                    .workspace_info(&WorkspaceName::from(task_id.package()))
+                    .and_then(|info| info.package_json.scripts.get(task_id.task()))
+                    .map_or(false, |command| !command.is_empty())
+            })
+            .map(|task_id| self.display_task_id(task_id))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
     pub fn dry_run(&mut self) {
         self.dry = true;
     }
@@ -425,6 +597,7 @@ enum StdWriter {
     Out(std::io::Stdout),
     Err(std::io::Stderr),
     Null(std::io::Sink),
+    Tui(TaskOutput),
 }
 
 impl StdWriter {
@@ -433,6 +606,7 @@ impl StdWriter {
             StdWriter::Out(out) => out,
             StdWriter::Err(err) => err,
             StdWriter::Null(null) => null,
+            StdWriter::Tui(tui) => tui,
         }
     }
 }
@@ -455,6 +629,12 @@ impl From<std::io::Sink> for StdWriter {
     }
 }
 
+impl From<TaskOutput> for StdWriter {
+    fn from(value: TaskOutput) -> Self {
+        Self::Tui(value)
+    }
+}
+
 impl std::io::Write for StdWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.writer().write(buf)
@@ -465,6 +645,36 @@ impl std::io::Write for StdWriter {
     }
 }
 
+/// Writes input typed into the TUI to the stdin of a task
+struct TaskStdin(mpsc::UnboundedSender<Vec<u8>>);
+
+impl TaskStdin {
+    fn new(mut stdin: ChildStdin) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+        tokio::spawn(async move {
+            while let Some(input) = receiver.recv().await {
+                if stdin.write_all(&input).await.is_err() || stdin.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self(sender)
+    }
+}
+
+impl std::io::Write for TaskStdin {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.send(buf.to_vec()).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "task is no longer running")
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 fn turbo_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| Regex::new(r"(?:^|\s)turbo(?:$|\s)").unwrap())
@@ -485,6 +695,17 @@ enum TaskErrorCause {
     Spawn { msg: String },
     #[error("command {command} exited ({exit_code})")]
     Exit { command: String, exit_code: i32 },
+    #[error("command {command} timed out after {}", humantime::format_duration(*.timeout))]
+    Timeout { command: String, timeout: Duration },
+    #[error(
+        "command {command} wrote {undeclared_writes} file(s) outside of its outputs and didn't \
+         produce {missing_outputs} of its outputs"
+    )]
+    Outputs {
+        command: String,
+        undeclared_writes: usize,
+        missing_outputs: usize,
+    },
 }
 
 impl TaskError {
@@ -522,6 +743,18 @@ impl TaskErrorCause {
     fn from_execution(command: String, exit_code: i32) -> Self {
         TaskErrorCause::Exit { command, exit_code }
     }
+
+    fn from_timeout(command: String, timeout: Duration) -> Self {
+        TaskErrorCause::Timeout { command, timeout }
+    }
+
+    fn from_output_check(command: String, check: &OutputCheck) -> Self {
+        TaskErrorCause::Outputs {
+            command,
+            undeclared_writes: check.undeclared_writes.len(),
+            missing_outputs: check.missing_outputs.len(),
+        }
+    }
 }
 
 struct ExecContextFactory<'a> {
@@ -529,6 +762,7 @@ struct ExecContextFactory<'a> {
     errors: Arc<Mutex<Vec<TaskError>>>,
     manager: ProcessManager,
     engine: &'a Arc<Engine>,
+    stopping_services: Arc<AtomicBool>,
 }
 
 impl<'a> ExecContextFactory<'a> {
@@ -537,12 +771,14 @@ impl<'a> ExecContextFactory<'a> {
         errors: Arc<Mutex<Vec<TaskError>>>,
         manager: ProcessManager,
         engine: &'a Arc<Engine>,
+        stopping_services: Arc<AtomicBool>,
     ) -> Self {
         Self {
             visitor,
             errors,
             manager,
             engine,
+            stopping_services,
         }
     }
 
@@ -556,6 +792,36 @@ impl<'a> ExecContextFactory<'a> {
     ) -> ExecContext {
         let task_id_for_display = self.visitor.display_task_id(&task_id);
         let pass_through_args = self.visitor.opts.run_opts.args_for_task(&task_id);
+        let task_definition = self.engine.task_definition(&task_id);
+        let (timeout, retries, ready_when, persistent) = task_definition
+            .map(|definition| {
+                (
+                    definition.timeout,
+                    definition.retries,
+                    definition.ready_when.clone(),
+                    definition.persistent,
+                )
+            })
+            .unwrap_or_default();
+        // Outputs only matter for tasks that are cached, and persistent tasks
+        // don't finish on their own
+        let check_outputs = self.visitor.opts.run_opts.check_outputs.zip(
+            task_definition
+                .filter(|definition| definition.cache && !definition.persistent)
+                .map(|definition| definition.outputs.clone()),
+        );
+        let (nested_workspaces, other_outputs) = match check_outputs {
+            Some(_) => (
+                self.nested_workspaces(&workspace_directory),
+                self.other_outputs(&task_id),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        let tui_task = self
+            .visitor
+            .tui
+            .as_ref()
+            .map(|tui| tui.task(task_id_for_display.clone()));
         ExecContext {
             engine: self.engine.clone(),
             ui: self.visitor.ui,
@@ -576,9 +842,54 @@ impl<'a> ExecContextFactory<'a> {
             continue_on_error: self.visitor.opts.run_opts.continue_on_error,
             pass_through_args,
             errors: self.errors.clone(),
+            timeout,
+            retries,
+            ready_when,
+            stopping_services: self.stopping_services.clone(),
+            persistent,
+            tui_task,
+            check_outputs,
+            nested_workspaces,
+            other_outputs,
+            hash_inputs_store: self
+                .visitor
+                .opts
+                .run_opts
+                .explain_miss
+                .then(|| HashInputsStore::new(self.visitor.repo_root)),
         }
     }
 
+    // Directories of the workspaces inside of `workspace_directory`, the root
+    // workspace contains all of the others
+    fn nested_workspaces(
+        &self,
+        workspace_directory: &AbsoluteSystemPath,
+    ) -> Vec<AbsoluteSystemPathBuf> {
+        self.visitor
+            .package_graph
+            .workspaces()
+            .map(|(_, info)| self.visitor.repo_root.resolve(info.package_path()))
+            .filter(|directory| {
+                directory.as_path() != workspace_directory.as_path()
+                    && directory
+                        .as_path()
+                        .starts_with(workspace_directory.as_path())
+            })
+            .collect()
+    }
+
+    // Outputs of the other tasks in the same workspace, which can write to the
+    // workspace while the task runs
+    fn other_outputs(&self, task_id: &TaskId) -> Vec<TaskOutputs> {
+        self.engine
+            .task_definitions()
+            .iter()
+            .filter(|(other, _)| other.package() == task_id.package() && *other != task_id)
+            .map(|(_, definition)| definition.outputs.clone())
+            .collect()
+    }
+
     pub fn dry_run_exec_context(
         &self,
         task_id: TaskId<'static>,
@@ -609,6 +920,16 @@ struct ExecContext {
     continue_on_error: bool,
     pass_through_args: Option<Vec<String>>,
     errors: Arc<Mutex<Vec<TaskError>>>,
+    timeout: Option<Duration>,
+    retries: u32,
+    ready_when: Option<ReadyWhen>,
+    stopping_services: Arc<AtomicBool>,
+    persistent: bool,
+    tui_task: Option<TuiTask>,
+    check_outputs: Option<(CheckOutputsMode, TaskOutputs)>,
+    nested_workspaces: Vec<AbsoluteSystemPathBuf>,
+    other_outputs: Vec<TaskOutputs>,
+    hash_inputs_store: Option<HashInputsStore>,
 }
 
 enum ExecOutcome {
@@ -645,8 +966,52 @@ impl ExecContext {
         callback: oneshot::Sender<Result<(), StopExecution>>,
         spaces_client: Option<SpacesTaskClient>,
     ) {
-        let tracker = tracker.start().await;
-        let mut result = self.execute_inner(parent_span_id, &output_client).await;
+        let mut tracker = tracker.start().await;
+        if let Some(tui_task) = &self.tui_task {
+            tui_task.start();
+        }
+        let mut callback = Some(callback);
+        let mut result = {
+            let (ready, mut is_ready) = oneshot::channel();
+            // Let the user know if tasks are held up waiting on a service
+            let ready_warning = self.ready_when.clone().map(|ready_when| {
+                let prefixed_ui = Visitor::prefixed_ui(
+                    self.ui,
+                    self.is_github_actions,
+                    &output_client,
+                    self.pretty_prefix.clone(),
+                );
+                (ready_when, prefixed_ui)
+            });
+            let is_ready = async move {
+                let Some((ready_when, mut prefixed_ui)) = ready_warning else {
+                    return is_ready.await;
+                };
+                match tokio::time::timeout(READY_WARNING_AFTER, &mut is_ready).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        prefixed_ui.warn(format!(
+                            "not ready after {}, tasks that depend on it are waiting for \
+                             {ready_when}",
+                            humantime::format_duration(READY_WARNING_AFTER)
+                        ));
+                        is_ready.await
+                    }
+                }
+            };
+            let inner = self.execute_inner(parent_span_id, &output_client, &mut tracker, ready);
+            tokio::pin!(inner);
+            tokio::select! {
+                result = &mut inner => result,
+                Ok(()) = is_ready => {
+                    // The task keeps running, but tasks that depend on it can start now
+                    if let Some(callback) = callback.take() {
+                        callback.send(Ok(())).ok();
+                    }
+                    inner.await
+                }
+            }
+        };
 
         let logs = match output_client.finish() {
             Ok(logs) => logs,
@@ -657,13 +1022,28 @@ impl ExecContext {
             }
         };
 
+        if let Some(tui_task) = &self.tui_task {
+            let (task_result, cache_hit) = match &result {
+                ExecOutcome::Success(SuccessOutcome::CacheHit) => (TaskResult::CacheHit, true),
+                ExecOutcome::Success(SuccessOutcome::Run) => (TaskResult::Success, false),
+                ExecOutcome::Internal => (TaskResult::Canceled, false),
+                ExecOutcome::Task { .. } => (TaskResult::Failure, false),
+            };
+            // The log file is only up to date if it was restored or written by this run
+            let log_file = (cache_hit || self.task_cache.is_writable())
+                .then(|| self.task_cache.log_file().to_owned());
+            tui_task.finish(task_result, log_file);
+        }
+
         match result {
             ExecOutcome::Success(outcome) => {
                 let task_summary = match outcome {
                     SuccessOutcome::CacheHit => tracker.cached().await,
                     SuccessOutcome::Run => tracker.build_succeeded(0).await,
                 };
-                callback.send(Ok(())).ok();
+                if let Some(callback) = callback {
+                    callback.send(Ok(())).ok();
+                }
                 if let Some(client) = spaces_client {
                     let logs = logs.expect("spaces enabled logs should be collected");
                     let info = self.spaces_task_info(self.task_id.clone(), task_summary, logs);
@@ -672,17 +1052,21 @@ impl ExecContext {
             }
             ExecOutcome::Internal => {
                 tracker.cancel();
-                callback.send(Err(StopExecution)).ok();
+                if let Some(callback) = callback {
+                    callback.send(Err(StopExecution)).ok();
+                }
                 self.manager.stop().await;
             }
             ExecOutcome::Task { exit_code, message } => {
                 let task_summary = tracker.build_failed(exit_code, message).await;
-                callback
-                    .send(match self.continue_on_error {
-                        true => Ok(()),
-                        false => Err(StopExecution),
-                    })
-                    .ok();
+                if let Some(callback) = callback {
+                    callback
+                        .send(match self.continue_on_error {
+                            true => Ok(()),
+                            false => Err(StopExecution),
+                        })
+                        .ok();
+                }
 
                 match (spaces_client, self.continue_on_error) {
                     // Nothing to do
@@ -713,9 +1097,10 @@ impl ExecContext {
         &mut self,
         parent_span_id: Option<tracing::Id>,
         output_client: &OutputClient<impl std::io::Write>,
+        tracker: &mut TaskTracker<chrono::DateTime<Local>>,
+        ready: oneshot::Sender<()>,
     ) -> ExecOutcome {
         let span = tracing::debug_span!("execute_task", task = %self.task_id.task());
-        let task_start = Instant::now();
         span.follows_from(parent_span_id);
         let _enter = span.enter();
 
@@ -735,9 +1120,10 @@ impl ExecContext {
                 );
                 self.hash_tracker
                     .insert_cache_status(self.task_id.clone(), status);
+                self.record_hash_inputs();
                 return ExecOutcome::Success(SuccessOutcome::CacheHit);
             }
-            Ok(None) => (),
+            Ok(None) => self.explain_miss(&mut prefixed_ui),
             Err(e) => {
                 prefixed_ui.error(format!("error fetching from cache: {e}"));
             }
@@ -747,81 +1133,160 @@ impl ExecContext {
             return ExecOutcome::Internal;
         };
 
-        let mut cmd = Command::new(package_manager_binary);
-        let mut args = vec!["run".to_string(), self.task_id.task().to_string()];
-        if let Some(pass_through_args) = &self.pass_through_args {
-            args.extend(
-                self.package_manager
-                    .arg_separator(pass_through_args.as_slice())
-                    .map(|s| s.to_string()),
-            );
-            args.extend(pass_through_args.iter().cloned());
-        }
-        cmd.args(args);
-        cmd.current_dir(self.workspace_directory.as_path());
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        // We clear the env before populating it with variables we expect
-        cmd.env_clear();
-        cmd.envs(self.execution_env.iter());
-        // Always last to make sure it overwrites any user configured env var.
-        cmd.env("TURBO_HASH", &self.task_hash);
+        let output_snapshot = self.check_outputs.as_ref().and_then(|_| {
+            OutputSnapshot::take(&self.workspace_directory, self.nested_workspaces.clone())
+                .map_err(|e| prefixed_ui.warn(format!("unable to check outputs: {e}")))
+                .ok()
+        });
 
         let mut stdout_writer = match self
             .task_cache
             .output_writer(self.pretty_prefix.clone(), output_client.stdout())
         {
-            Ok(w) => w,
+            Ok(w) => ProbeWriter::new(w),
             Err(e) => {
                 error!("failed to capture outputs for \"{}\": {e}", self.task_id);
                 return ExecOutcome::Internal;
             }
         };
+        let probe = self
+            .ready_when
+            .as_ref()
+            .and_then(|ready_when| stdout_writer.start_probe(ready_when, ready));
+
+        // Attempts are only part of the summary if the task can be retried or
+        // time out, otherwise the task execution already describes the single run
+        let record_attempts = self.timeout.is_some() || self.retries > 0;
+        let mut attempt = 0;
+        // The task's duration covers every attempt
+        let task_start = Instant::now();
+        let (label, exit_status, timed_out, task_duration) = loop {
+            let attempt_start = Local::now();
+            let cmd = self.command(&package_manager_binary);
+
+            let mut process = match self.manager.spawn(cmd, Duration::from_millis(500)) {
+                Some(Ok(child)) => child,
+                // Turbo was unable to spawn a process
+                Some(Err(e)) => {
+                    // Note: we actually failed to spawn, but this matches the Go output
+                    prefixed_ui.error(format!("command finished with error: {e}"));
+                    let error_string = e.to_string();
+                    self.errors
+                        .lock()
+                        .expect("lock poisoned")
+                        .push(TaskError::from_spawn(self.task_id_for_display.clone(), e));
+                    return ExecOutcome::Task {
+                        exit_code: None,
+                        message: error_string,
+                    };
+                }
+                // Turbo is shutting down
+                None => {
+                    return ExecOutcome::Internal;
+                }
+            };
+            if let (Some(tui_task), Some(stdin)) = (&self.tui_task, process.stdin()) {
+                tui_task.set_stdin(Box::new(TaskStdin::new(stdin)));
+            }
 
-        let mut process = match self.manager.spawn(cmd, Duration::from_millis(500)) {
-            Some(Ok(child)) => child,
-            // Turbo was unable to spawn a process
-            Some(Err(e)) => {
-                // Note: we actually failed to spawn, but this matches the Go output
-                prefixed_ui.error(format!("command finished with error: {e}"));
-                let error_string = e.to_string();
-                self.errors
-                    .lock()
-                    .expect("lock poisoned")
-                    .push(TaskError::from_spawn(self.task_id_for_display.clone(), e));
-                return ExecOutcome::Task {
-                    exit_code: None,
-                    message: error_string,
-                };
+            let (exit_status, timed_out) = match self.timeout {
+                Some(timeout) => {
+                    let result = tokio::time::timeout(
+                        timeout,
+                        process.wait_with_piped_outputs(&mut stdout_writer, None),
+                    )
+                    .await;
+                    match result {
+                        Ok(exit_status) => (exit_status, None),
+                        // The attempt took too long, stop the child and treat it as a failure
+                        Err(_) => (Ok(process.stop().await), Some(timeout)),
+                    }
+                }
+                None => (
+                    process
+                        .wait_with_piped_outputs(&mut stdout_writer, None)
+                        .await,
+                    None,
+                ),
+            };
+
+            let exit_status = match exit_status {
+                Ok(Some(exit_status)) => exit_status,
+                Err(e) => {
+                    error!("unable to pipe outputs from command: {e}");
+                    return ExecOutcome::Internal;
+                }
+                Ok(None) if timed_out.is_some() => ChildExit::Killed,
+                Ok(None) => {
+                    // TODO: how can this happen? we only update the
+                    // exit status with Some and it is only initialized with
+                    // None. Is it still running?
+                    error!("unable to determine why child exited");
+                    return ExecOutcome::Internal;
+                }
+            };
+
+            if record_attempts {
+                tracker.attempt(TaskAttemptSummary {
+                    start_time: attempt_start.timestamp_millis(),
+                    end_time: Local::now().timestamp_millis(),
+                    exit_code: match exit_status {
+                        ChildExit::Finished(exit_code) => exit_code,
+                        _ => None,
+                    },
+                    timeout: timed_out.map(|timeout| {
+                        format!("timed out after {}", humantime::format_duration(timeout))
+                    }),
+                });
             }
-            // Turbo is shutting down
-            None => {
-                return ExecOutcome::Internal;
+
+            let failed = timed_out.is_some()
+                || matches!(exit_status, ChildExit::Finished(Some(code)) if code != 0);
+            if failed && attempt < self.retries && !self.is_stopping_service() {
+                attempt += 1;
+                prefixed_ui.warn(format!(
+                    "command failed, retrying ({attempt}/{})",
+                    self.retries
+                ));
+                // Only the logs of the last attempt are cached
+                if let Err(e) = stdout_writer.get_mut().truncate_log_file() {
+                    error!("unable to reset logs before retrying: {e}");
+                }
+                continue;
             }
-        };
 
-        let exit_status = match process
-            .wait_with_piped_outputs(&mut stdout_writer, None)
-            .await
-        {
-            Ok(Some(exit_status)) => exit_status,
-            Err(e) => {
-                error!("unable to pipe outputs from command: {e}");
-                return ExecOutcome::Internal;
+            break (
+                process.label().to_string(),
+                exit_status,
+                timed_out,
+                task_start.elapsed(),
+            );
+        };
+        // Stop polling if the task exited before it was ready
+        drop(probe);
+
+        // Services are stopped once the rest of the run is done, which is how
+        // they are expected to exit
+        if self.is_stopping_service() {
+            if let Err(e) = stdout_writer.flush() {
+                error!("{e}");
             }
-            Ok(None) => {
-                // TODO: how can this happen? we only update the
-                // exit status with Some and it is only initialized with
-                // None. Is it still running?
-                error!("unable to determine why child exited");
-                return ExecOutcome::Internal;
+            return ExecOutcome::Success(SuccessOutcome::Run);
+        }
+
+        // A task that failed may not have written all of its outputs, so only
+        // successful runs are checked
+        let outputs_error = match (timed_out, &exit_status, output_snapshot) {
+            (None, ChildExit::Finished(Some(0)), Some(snapshot)) => {
+                self.check_outputs(&label, snapshot, &mut prefixed_ui)
             }
+            _ => None,
         };
-        let task_duration = task_start.elapsed();
 
-        match exit_status {
-            ChildExit::Finished(Some(0)) => {
+        let (exit_code, error) = match (timed_out, exit_status, outputs_error) {
+            (Some(timeout), _, _) => (None, TaskErrorCause::from_timeout(label, timeout)),
+            (None, ChildExit::Finished(Some(0)), Some(error)) => (None, error),
+            (None, ChildExit::Finished(Some(0)), None) => {
                 if let Err(e) = stdout_writer.flush() {
                     error!("{e}");
                 } else if let Err(e) = self
@@ -835,40 +1300,173 @@ impl ExecContext {
                         self.task_id.clone(),
                         self.task_cache.expanded_outputs().to_vec(),
                     );
+                    if self.task_cache.is_writable() {
+                        self.record_hash_inputs();
+                    }
                 }
 
-                ExecOutcome::Success(SuccessOutcome::Run)
+                return ExecOutcome::Success(SuccessOutcome::Run);
             }
-            ChildExit::Finished(Some(code)) => {
-                // If there was an error, flush the buffered output
-                if let Err(e) = stdout_writer.flush() {
-                    error!("error flushing logs: {e}");
-                }
-                if let Err(e) = self.task_cache.on_error(&mut prefixed_ui) {
-                    error!("error reading logs: {e}");
-                }
-                let error = TaskErrorCause::from_execution(process.label().to_string(), code);
-                let message = error.to_string();
-                if self.continue_on_error {
-                    prefixed_ui.warn("command finished with error, but continuing...");
-                } else {
-                    prefixed_ui.error(format!("command finished with error: {error}"));
-                }
-                self.errors.lock().expect("lock poisoned").push(TaskError {
-                    task_id: self.task_id_for_display.clone(),
-                    cause: error,
-                });
-                ExecOutcome::Task {
-                    exit_code: Some(code),
-                    message,
-                }
+            (None, ChildExit::Finished(Some(code)), _) => {
+                (Some(code), TaskErrorCause::from_execution(label, code))
             }
             // All of these indicate a failure where we don't know how to recover
-            ChildExit::Finished(None)
-            | ChildExit::Killed
-            | ChildExit::KilledExternal
-            | ChildExit::Failed => ExecOutcome::Internal,
+            (None, ChildExit::Finished(None), _)
+            | (None, ChildExit::Killed, _)
+            | (None, ChildExit::KilledExternal, _)
+            | (None, ChildExit::Failed, _) => return ExecOutcome::Internal,
+        };
+
+        // If there was an error, flush the buffered output
+        if let Err(e) = stdout_writer.flush() {
+            error!("error flushing logs: {e}");
+        }
+        if let Err(e) = self.task_cache.on_error(&mut prefixed_ui) {
+            error!("error reading logs: {e}");
+        }
+        let message = error.to_string();
+        if self.continue_on_error {
+            prefixed_ui.warn("command finished with error, but continuing...");
+        } else {
+            prefixed_ui.error(format!("command finished with error: {error}"));
+        }
+        self.errors.lock().expect("lock poisoned").push(TaskError {
+            task_id: self.task_id_for_display.clone(),
+            cause: error,
+        });
+        ExecOutcome::Task { exit_code, message }
+    }
+
+    /// Compares the files the task wrote with its outputs, returning an error
+    /// if problems were found and they should fail the task
+    fn check_outputs(
+        &self,
+        label: &str,
+        snapshot: OutputSnapshot,
+        prefixed_ui: &mut PrefixedUI<impl Write>,
+    ) -> Option<TaskErrorCause> {
+        let (mode, outputs) = self.check_outputs.as_ref()?;
+        let check = match snapshot.check(outputs, &self.other_outputs) {
+            Ok(check) => check,
+            Err(e) => {
+                prefixed_ui.warn(format!("unable to check outputs: {e}"));
+                return None;
+            }
+        };
+        for path in &check.undeclared_writes {
+            prefixed_ui.warn(format!("wrote {path}, which isn't covered by outputs"));
+        }
+        for glob in &check.missing_outputs {
+            prefixed_ui.warn(format!("declared output {glob} wasn't produced"));
+        }
+
+        let error = match mode {
+            CheckOutputsMode::Error if !check.is_empty() => {
+                Some(TaskErrorCause::from_output_check(label.to_string(), &check))
+            }
+            _ => None,
+        };
+        self.hash_tracker
+            .insert_output_check(self.task_id.clone(), check);
+        error
+    }
+
+    fn is_stopping_service(&self) -> bool {
+        self.ready_when.is_some() && self.stopping_services.load(Ordering::SeqCst)
+    }
+
+    // Saves the inputs of the current hash so later misses can be compared
+    // against them
+    fn record_hash_inputs(&self) {
+        let Some(store) = &self.hash_inputs_store else {
+            return;
+        };
+        let Some(inputs) = self.hash_tracker.hash_inputs(&self.task_id) else {
+            return;
+        };
+        if let Err(e) = store.write(&self.task_id, &inputs) {
+            warn!("unable to record hash inputs for {}: {e}", self.task_id);
+        }
+    }
+
+    fn explain_miss(&self, prefixed_ui: &mut PrefixedUI<impl Write>) {
+        let Some(store) = &self.hash_inputs_store else {
+            return;
+        };
+        if !self.task_cache.is_readable() {
+            return;
+        }
+        let Some(current) = self.hash_tracker.hash_inputs(&self.task_id) else {
+            return;
+        };
+        let previous = match store.read(&self.task_id) {
+            Ok(Some(previous)) => previous,
+            Ok(None) => {
+                prefixed_ui.warn("cache miss, no inputs were recorded for a previous run");
+                return;
+            }
+            Err(e) => {
+                prefixed_ui.warn(format!("cache miss, unable to read previous inputs: {e}"));
+                return;
+            }
+        };
+        if previous.hash == current.hash {
+            prefixed_ui.warn(format!(
+                "cache miss, inputs are unchanged but {} is no longer in the cache",
+                previous.hash
+            ));
+            return;
         }
+
+        let changes = current.changes_since(&previous);
+        if changes.is_empty() {
+            prefixed_ui.warn(format!(
+                "cache miss, hash changed from {} but none of the recorded inputs did",
+                previous.hash
+            ));
+            return;
+        }
+        prefixed_ui.warn(format!(
+            "cache miss, inputs changed since {}:",
+            previous.hash
+        ));
+        for change in changes {
+            prefixed_ui.warn(format!("  - {change}"));
+        }
+    }
+
+    // Builds the command that runs the task, a fresh command is needed for
+    // every attempt
+    fn command(&self, package_manager_binary: &std::path::Path) -> Command {
+        let mut cmd = Command::new(package_manager_binary);
+        let mut args = vec!["run".to_string(), self.task_id.task().to_string()];
+        if let Some(pass_through_args) = &self.pass_through_args {
+            args.extend(
+                self.package_manager
+                    .arg_separator(pass_through_args.as_slice())
+                    .map(|s| s.to_string()),
+            );
+            args.extend(pass_through_args.iter().cloned());
+        }
+        cmd.args(args);
+        cmd.current_dir(self.workspace_directory.as_path());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        // The TUI owns the terminal, so only persistent tasks are given input
+        // and it comes from the TUI
+        if self.tui_task.is_some() {
+            cmd.stdin(match self.persistent {
+                true => Stdio::piped(),
+                false => Stdio::null(),
+            });
+        }
+
+        // We clear the env before populating it with variables we expect
+        cmd.env_clear();
+        cmd.envs(self.execution_env.iter());
+        // Always last to make sure it overwrites any user configured env var.
+        cmd.env("TURBO_HASH", &self.task_hash);
+        cmd
     }
 
     fn spaces_task_info(
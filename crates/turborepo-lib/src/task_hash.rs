@@ -1,25 +1,31 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
 use rayon::prelude::*;
 use serde::Serialize;
+use serde_json::json;
 use thiserror::Error;
 use tracing::{debug, Span};
 use turbopath::{AbsoluteSystemPath, AnchoredSystemPath, AnchoredSystemPathBuf};
 use turborepo_cache::CacheHitMetadata;
 use turborepo_env::{BySource, DetailedMap, EnvironmentVariableMap, ResolvedEnvMode};
 use turborepo_repository::package_graph::{WorkspaceInfo, WorkspaceName};
-use turborepo_scm::SCM;
+use turborepo_scm::{package_deps::GitHashes, SCM};
 use turborepo_telemetry::events::task::PackageTaskEventBuilder;
 
 use crate::{
+    daemon::DaemonClient,
     engine::TaskNode,
     framework::infer_framework,
     hash::{FileHashes, LockFilePackages, TaskHashable, TurboHash},
     opts::Opts,
-    run::task_id::TaskId,
+    run::{
+        hash_inputs::{self, GlobalHashInputs, TaskHashInputs},
+        output_check::OutputCheck,
+        task_id::TaskId,
+    },
     task_graph::TaskDefinition,
 };
 
@@ -55,6 +61,28 @@ impl TaskHashable<'_> {
 
         self.hash()
     }
+
+    // The parts of the hash that come from the task definition, used to explain
+    // which of them changed between runs
+    fn definition_inputs(&self) -> BTreeMap<String, serde_json::Value> {
+        let (env_mode, pass_through_env) = match self.env_mode {
+            ResolvedEnvMode::Loose => ("loose", &[] as &[String]),
+            ResolvedEnvMode::Strict => ("strict", self.pass_through_env),
+        };
+        [
+            ("packageDir", json!(self.package_dir)),
+            ("task", json!(self.task)),
+            ("outputs", json!(self.outputs)),
+            ("passThroughArgs", json!(self.pass_through_args)),
+            ("env", json!(self.env)),
+            ("passThroughEnv", json!(pass_through_env)),
+            ("envMode", json!(env_mode)),
+            ("dotEnv", json!(self.dot_env)),
+        ]
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value))
+        .collect()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -63,13 +91,69 @@ pub struct PackageInputsHashes {
     expanded_hashes: HashMap<TaskId<'static>, FileHashes>,
 }
 
+/// Package file hashes that the daemon had already indexed, keyed by package
+/// path and task inputs
+pub type DaemonFileHashes = HashMap<(AnchoredSystemPathBuf, Vec<String>), GitHashes>;
+
 impl PackageInputsHashes {
-    #[tracing::instrument(skip(all_tasks, workspaces, task_definitions, repo_root, scm))]
+    /// Asks the daemon for the file hashes of every package and inputs
+    /// combination used by `all_tasks`. Anything the daemon can't answer is
+    /// left out and gets hashed locally instead.
+    pub async fn query_daemon<'a, T>(
+        daemon: &mut DaemonClient<T>,
+        all_tasks: impl Iterator<Item = &'a TaskNode>,
+        workspaces: &HashMap<&WorkspaceName, &WorkspaceInfo>,
+        task_definitions: &HashMap<TaskId<'static>, TaskDefinition>,
+    ) -> DaemonFileHashes {
+        let packages = all_tasks
+            .filter_map(|task| {
+                let TaskNode::Task(task_id) = task else {
+                    return None;
+                };
+                let task_definition = task_definitions.get(task_id)?;
+                let pkg = workspaces.get(&task_id.to_workspace_name())?;
+                Some((package_path(pkg), task_definition.inputs.as_slice()))
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut daemon_file_hashes = DaemonFileHashes::new();
+        if packages.is_empty() {
+            return daemon_file_hashes;
+        }
+        match daemon.get_file_hashes(&packages).await {
+            Ok(hashes) => {
+                for ((package_path, inputs), hashes) in packages.into_iter().zip(hashes) {
+                    if let Some(hashes) = hashes {
+                        daemon_file_hashes
+                            .insert((package_path.to_owned(), inputs.to_vec()), hashes);
+                    }
+                }
+            }
+            Err(e) => debug!("unable to get file hashes from daemon: {}", e),
+        }
+        debug!(
+            "daemon provided file hashes for {} packages",
+            daemon_file_hashes.len()
+        );
+        daemon_file_hashes
+    }
+
+    #[tracing::instrument(skip(
+        all_tasks,
+        workspaces,
+        task_definitions,
+        daemon_file_hashes,
+        repo_root,
+        scm
+    ))]
     pub fn calculate_file_hashes<'a>(
         scm: &SCM,
         all_tasks: impl ParallelIterator<Item = &'a TaskNode>,
         workspaces: HashMap<&WorkspaceName, &WorkspaceInfo>,
         task_definitions: &HashMap<TaskId<'static>, TaskDefinition>,
+        daemon_file_hashes: &DaemonFileHashes,
         repo_root: &AbsoluteSystemPath,
     ) -> Result<PackageInputsHashes, Error> {
         tracing::trace!(scm_manual=%scm.is_manual(), "scm running in {} mode", if scm.is_manual() { "manual" } else { "git" });
@@ -102,16 +186,19 @@ impl PackageInputsHashes {
                     Err(err) => return Some(Err(err)),
                 };
 
-                let package_path = pkg
-                    .package_json_path
-                    .parent()
-                    .unwrap_or_else(|| AnchoredSystemPath::new("").unwrap());
+                let package_path = package_path(pkg);
 
-                let mut hash_object = match scm.get_package_file_hashes(
-                    repo_root,
-                    package_path,
-                    &task_definition.inputs,
-                ) {
+                let hash_object = match daemon_file_hashes
+                    .get(&(package_path.to_owned(), task_definition.inputs.clone()))
+                {
+                    Some(hash_object) => Ok(hash_object.clone()),
+                    None => scm.get_package_file_hashes(
+                        repo_root,
+                        package_path,
+                        &task_definition.inputs,
+                    ),
+                };
+                let mut hash_object = match hash_object {
                     Ok(hash_object) => hash_object,
                     Err(err) => return Some(Err(err.into())),
                 };
@@ -149,6 +236,12 @@ impl PackageInputsHashes {
     }
 }
 
+fn package_path(pkg: &WorkspaceInfo) -> &AnchoredSystemPath {
+    pkg.package_json_path
+        .parent()
+        .unwrap_or_else(|| AnchoredSystemPath::new("").unwrap())
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct TaskHashTracker {
     state: Arc<Mutex<TaskHashTrackerState>>,
@@ -167,6 +260,10 @@ pub struct TaskHashTrackerState {
     package_task_cache: HashMap<TaskId<'static>, CacheHitMetadata>,
     #[serde(skip)]
     package_task_inputs_expanded_hashes: HashMap<TaskId<'static>, FileHashes>,
+    #[serde(skip)]
+    package_task_hash_inputs: HashMap<TaskId<'static>, TaskHashInputs>,
+    #[serde(skip)]
+    package_task_output_checks: HashMap<TaskId<'static>, OutputCheck>,
 }
 
 /// Caches package-inputs hashes, and package-task hashes.
@@ -175,6 +272,8 @@ pub struct TaskHasher<'a> {
     opts: &'a Opts<'a>,
     env_at_execution_start: &'a EnvironmentVariableMap,
     global_hash: &'a str,
+    // Only present when the inputs of each task hash should be recorded
+    global_hash_inputs: Option<GlobalHashInputs>,
     task_hash_tracker: TaskHashTracker,
 }
 
@@ -184,6 +283,7 @@ impl<'a> TaskHasher<'a> {
         opts: &'a Opts,
         env_at_execution_start: &'a EnvironmentVariableMap,
         global_hash: &'a str,
+        global_hash_inputs: Option<GlobalHashInputs>,
     ) -> Self {
         let PackageInputsHashes {
             hashes,
@@ -194,6 +294,7 @@ impl<'a> TaskHasher<'a> {
             opts,
             env_at_execution_start,
             global_hash,
+            global_hash_inputs,
             task_hash_tracker: TaskHashTracker::new(expanded_hashes),
         }
     }
@@ -293,6 +394,10 @@ impl<'a> TaskHasher<'a> {
 
         let hashable_env_pairs = env_vars.all.to_hashable();
         let outputs = task_definition.hashable_outputs(task_id);
+        let dependency_inputs = self
+            .global_hash_inputs
+            .is_some()
+            .then(|| self.dependency_inputs(&dependency_set));
         let task_dependency_hashes = self.calculate_dependency_hashes(dependency_set)?;
         let external_deps_hash =
             is_monorepo.then(|| get_external_deps_hash(&workspace.transitive_dependencies));
@@ -329,8 +434,30 @@ impl<'a> TaskHasher<'a> {
             dot_env: task_definition.dot_env.as_deref().unwrap_or_default(),
         };
 
+        let definition_inputs = task_hashable.definition_inputs();
+        let external_dependencies_hash = task_hashable.external_deps_hash.clone();
         let task_hash = task_hashable.calculate_task_hash();
 
+        if let (Some(global), Some(dependencies)) = (&self.global_hash_inputs, dependency_inputs) {
+            let files = self
+                .task_hash_tracker
+                .get_expanded_inputs(task_id)
+                .map(|FileHashes(files)| files.into_iter().collect())
+                .unwrap_or_default();
+            self.task_hash_tracker.insert_hash_inputs(
+                task_id.clone(),
+                TaskHashInputs {
+                    hash: task_hash.clone(),
+                    global: global.clone(),
+                    files,
+                    env: hash_inputs::env_map(env_vars.all.to_secret_hashable()),
+                    dependencies,
+                    external_dependencies_hash,
+                    definition: definition_inputs,
+                },
+            );
+        }
+
         self.task_hash_tracker.insert_hash(
             task_id.clone(),
             env_vars,
@@ -374,6 +501,20 @@ impl<'a> TaskHasher<'a> {
         Ok(dependency_hash_list)
     }
 
+    // Map of dependency task ids to their hashes
+    fn dependency_inputs(&self, dependency_set: &HashSet<&TaskNode>) -> BTreeMap<String, String> {
+        dependency_set
+            .iter()
+            .filter_map(|dependency| match dependency {
+                TaskNode::Task(task_id) => Some((
+                    task_id.to_string(),
+                    self.task_hash_tracker.hash(task_id).unwrap_or_default(),
+                )),
+                TaskNode::Root => None,
+            })
+            .collect()
+    }
+
     pub fn into_task_hash_tracker_state(self) -> TaskHashTrackerState {
         let mutex = Arc::into_inner(self.task_hash_tracker.state)
             .expect("multiple references to tracker state still exist");
@@ -507,6 +648,30 @@ impl TaskHashTracker {
         state.package_task_outputs.insert(task_id, outputs);
     }
 
+    /// The recorded inputs of a task's hash, only present when running with
+    /// `--explain-miss`
+    pub fn hash_inputs(&self, task_id: &TaskId) -> Option<TaskHashInputs> {
+        let state = self.state.lock().expect("hash tracker mutex poisoned");
+        state.package_task_hash_inputs.get(task_id).cloned()
+    }
+
+    fn insert_hash_inputs(&self, task_id: TaskId<'static>, inputs: TaskHashInputs) {
+        let mut state = self.state.lock().expect("hash tracker mutex poisoned");
+        state.package_task_hash_inputs.insert(task_id, inputs);
+    }
+
+    /// The result of checking a task's outputs, only present when running
+    /// with `--check-outputs`
+    pub fn output_check(&self, task_id: &TaskId) -> Option<OutputCheck> {
+        let state = self.state.lock().expect("hash tracker mutex poisoned");
+        state.package_task_output_checks.get(task_id).cloned()
+    }
+
+    pub fn insert_output_check(&self, task_id: TaskId<'static>, check: OutputCheck) {
+        let mut state = self.state.lock().expect("hash tracker mutex poisoned");
+        state.package_task_output_checks.insert(task_id, check);
+    }
+
     pub fn cache_status(&self, task_id: &TaskId) -> Option<CacheHitMetadata> {
         let state = self.state.lock().expect("hash tracker mutex poisoned");
         state.package_task_cache.get(task_id).copied()
@@ -18,7 +18,7 @@ use thiserror::Error;
 use turbopath::RelativeUnixPathBuf;
 
 use self::resolution::{parse_resolution, Resolution};
-use super::Lockfile;
+use super::{Lockfile, LockfileDiff};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -564,6 +564,14 @@ impl Lockfile for BerryLockfile {
         Ok(self.lockfile()?.to_string().into_bytes())
     }
 
+    fn resolves_per_workspace(&self) -> bool {
+        // Targeted resolutions are checked against the workspace in
+        // `resolve_package`
+        self.overrides
+            .keys()
+            .any(|resolution| resolution.is_targeted())
+    }
+
     fn patches(&self) -> Result<Vec<RelativeUnixPathBuf>, crate::Error> {
         let mut patches = self
             .patches
@@ -585,6 +593,21 @@ impl Lockfile for BerryLockfile {
             true
         }
     }
+
+    fn diff(&self, other: &dyn Lockfile) -> LockfileDiff {
+        let any_other = other as &dyn Any;
+        let Some(other) = any_other.downcast_ref::<Self>() else {
+            return LockfileDiff::global();
+        };
+        LockfileDiff {
+            global_change: self.global_change(other),
+            changed: crate::diff_entries(
+                &self.locator_package,
+                &other.locator_package,
+                |locator, package| crate::Package::new(locator.to_string(), &package.version),
+            ),
+        }
+    }
 }
 
 impl LockfileData {
@@ -921,6 +944,52 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_workspace_targeted_resolutions() {
+        let data: LockfileData = serde_yaml::from_str(include_str!(
+            "../../fixtures/minimal-berry-resolutions.lock"
+        ))
+        .unwrap();
+        let manifest = BerryManifest {
+            resolutions: Some(
+                [
+                    ("debug".to_string(), "1.0.0".to_string()),
+                    // Only applies to the dependencies of the b workspace
+                    ("b/ms".to_string(), "0.6.0".to_string()),
+                ]
+                .iter()
+                .cloned()
+                .collect(),
+            ),
+        };
+        let lockfile = BerryLockfile::new(data, Some(manifest)).unwrap();
+        assert!(lockfile.resolves_per_workspace());
+
+        let workspaces = ["packages/a", "packages/b"]
+            .into_iter()
+            .map(|workspace| {
+                (
+                    workspace.to_string(),
+                    HashMap::from([("debug".to_string(), "^4.3.4".to_string())]),
+                )
+            })
+            .collect();
+        let closures = crate::all_transitive_closures(&lockfile, workspaces).unwrap();
+
+        let debug = Package {
+            key: "debug@npm:1.0.0".into(),
+            version: "1.0.0".into(),
+        };
+        let ms = Package {
+            key: "ms@npm:0.6.0".into(),
+            version: "0.6.0".into(),
+        };
+        // Both workspaces share debug, but only b has its ms dependency
+        // overridden to a version that is in the lockfile
+        assert_eq!(closures["packages/a"], HashSet::from([debug.clone()]));
+        assert_eq!(closures["packages/b"], HashSet::from([debug, ms]));
+    }
+
     #[test]
     fn test_robust_resolutions_dependencies() {
         let data = LockfileData::from_bytes(include_bytes!(
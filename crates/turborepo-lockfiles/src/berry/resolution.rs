@@ -94,6 +94,12 @@ fn parse_specifier(specifier: Pair<'_, Rule>) -> Result<Option<Specifier>, Error
 }
 
 impl Resolution {
+    /// Whether the resolution only applies to dependencies of a specific
+    /// package e.g. `a/lodash`
+    pub fn is_targeted(&self) -> bool {
+        self.from.is_some()
+    }
+
     /// Returns a new descriptor if an override is applicable
     // reference: version that this resolution resolves to
     // locator: package that depends on the dependency
@@ -1,10 +1,12 @@
 use std::{any::Any, str::FromStr};
 
 use serde::Deserialize;
+use sha2::{Digest, Sha512_256};
 
-use crate::Lockfile;
+use crate::{Lockfile, LockfileDiff};
 
 mod de;
+mod ser;
 
 type Map<K, V> = std::collections::BTreeMap<K, V>;
 
@@ -16,12 +18,12 @@ pub enum Error {
     SymlStructure(#[from] serde_json::Error),
     #[error("unexpected non-utf8 yarn.lock")]
     NonUTF8(#[from] std::str::Utf8Error),
-    #[error("Turborepo cannot serialize Bun lockfiles.")]
-    NotImplemented(),
 }
 
 #[derive(Debug)]
 pub struct BunLockfile {
+    // The comments and blank lines before the first entry
+    header: String,
     inner: Map<String, Entry>,
 }
 
@@ -43,6 +45,77 @@ impl BunLockfile {
         let input = std::str::from_utf8(input).map_err(Error::from)?;
         Self::from_str(input)
     }
+
+    /// Computes the hash bun prints in the lockfile header. Bun hashes a
+    /// sorted list of every package's name and resolution with SHA-512/256
+    /// and prints the four 8 byte chunks of the digest alternating between
+    /// upper and lower case hex.
+    fn meta_hash(&self) -> String {
+        let mut packages = self
+            .inner
+            .iter()
+            .map(|(key, entry)| {
+                let name = entry.name.as_deref().unwrap_or_else(|| key_name(key));
+                let resolution = match entry.workspace_path() {
+                    Some(_) => entry.resolved.as_deref().unwrap_or(&entry.version),
+                    None => &entry.version,
+                };
+                (name, resolution)
+            })
+            .collect::<Vec<_>>();
+        packages.sort_by(|(a_name, a_version), (b_name, b_version)| {
+            a_name.cmp(b_name).then_with(|| {
+                match (
+                    semver::Version::parse(a_version),
+                    semver::Version::parse(b_version),
+                ) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a_version.cmp(b_version),
+                }
+            })
+        });
+        // Entries with several keys are only hashed once
+        packages.dedup();
+
+        let mut hasher = Sha512_256::new();
+        hasher.update("\n-- BEGIN SHA512/256(`${alphabetize(name)}@${order(version)}`) --\n");
+        for (name, resolution) in &packages {
+            hasher.update(format!("{name}@{resolution}\n"));
+        }
+        hasher.update("-- END HASH--\n");
+        let digest = hasher.finalize();
+
+        format!(
+            "{}-{}-{}-{}",
+            hex::encode_upper(&digest[..8]),
+            hex::encode(&digest[8..16]),
+            hex::encode_upper(&digest[16..24]),
+            hex::encode(&digest[24..]),
+        )
+    }
+}
+
+// Replaces the hash in the `# bun ./bun.lockb --hash: ...` header line
+fn with_meta_hash(header: &str, meta_hash: &str) -> String {
+    header
+        .split_inclusive('\n')
+        .map(|line| match line.split_once("--hash: ") {
+            Some((prefix, rest)) if line.starts_with("# bun ") => {
+                let newline = &rest[rest.trim_end().len()..];
+                format!("{prefix}--hash: {meta_hash}{newline}")
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+// The package name of a lockfile key, e.g. `@babel/types` for
+// `@babel/types@^7.18.10`
+fn key_name(key: &str) -> &str {
+    key.char_indices()
+        .skip(1)
+        .find(|(_, c)| *c == '@')
+        .map_or(key, |(i, _)| &key[..i])
 }
 
 impl FromStr for BunLockfile {
@@ -51,10 +124,22 @@ impl FromStr for BunLockfile {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let value = de::parse_syml(s)?;
         let inner = serde_json::from_value(value)?;
-        Ok(Self { inner })
+        Ok(Self {
+            header: header(s).to_string(),
+            inner,
+        })
     }
 }
 
+fn header(s: &str) -> &str {
+    let len = s
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with('#') || line.trim().is_empty())
+        .map(|line| line.len())
+        .sum();
+    &s[..len]
+}
+
 impl Lockfile for BunLockfile {
     #[tracing::instrument(skip(self, _workspace_path))]
     fn resolve_package(
@@ -93,7 +178,7 @@ impl Lockfile for BunLockfile {
 
     fn subgraph(
         &self,
-        _workspace_packages: &[String],
+        workspace_packages: &[String],
         packages: &[String],
     ) -> Result<Box<dyn Lockfile>, super::Error> {
         let mut inner = Map::new();
@@ -105,11 +190,28 @@ impl Lockfile for BunLockfile {
             inner.insert(key.clone(), entry.clone());
         }
 
-        Ok(Box::new(Self { inner }))
+        // Unlike yarn, bun lists workspaces in the lockfile as well
+        for (key, entry) in &self.inner {
+            if entry
+                .workspace_path()
+                .map_or(false, |path| workspace_packages.iter().any(|p| p == path))
+            {
+                inner.insert(key.clone(), entry.clone());
+            }
+        }
+
+        let mut subgraph = Self {
+            header: self.header.clone(),
+            inner,
+        };
+        // The hash in the header covers every package in the lockfile, so it
+        // has to be recomputed for the packages that are left
+        subgraph.header = with_meta_hash(&self.header, &subgraph.meta_hash());
+        Ok(Box::new(subgraph))
     }
 
     fn encode(&self) -> Result<Vec<u8>, crate::Error> {
-        Err(crate::Error::Bun(Error::NotImplemented()))
+        Ok(self.to_string().into_bytes())
     }
 
     fn global_change(&self, other: &dyn Lockfile) -> bool {
@@ -118,9 +220,37 @@ impl Lockfile for BunLockfile {
         // if the types don't match then we changed package managers
         any_other.downcast_ref::<Self>().is_none()
     }
+
+    fn diff(&self, other: &dyn Lockfile) -> LockfileDiff {
+        let any_other = other as &dyn Any;
+        let Some(other) = any_other.downcast_ref::<Self>() else {
+            return LockfileDiff::global();
+        };
+        LockfileDiff {
+            global_change: false,
+            changed: crate::diff_entries(&self.inner, &other.inner, |key, entry| {
+                crate::Package::new(key, &entry.version)
+            }),
+        }
+    }
+}
+
+pub fn bun_subgraph(
+    contents: &[u8],
+    workspace_packages: &[String],
+    packages: &[String],
+) -> Result<Vec<u8>, crate::Error> {
+    let lockfile = BunLockfile::from_bytes(contents)?;
+    let pruned_lockfile = lockfile.subgraph(workspace_packages, packages)?;
+    pruned_lockfile.encode()
 }
 
 impl Entry {
+    // Workspace entries are resolved to their path in the repository
+    fn workspace_path(&self) -> Option<&str> {
+        self.resolved.as_deref()?.strip_prefix("workspace:")
+    }
+
     fn dependency_entries(&self) -> impl Iterator<Item = (String, String)> + '_ {
         self.dependencies
             .iter()
@@ -141,9 +271,68 @@ fn possible_keys<'a>(name: &'a str, version: &'a str) -> impl Iterator<Item = St
 
 #[cfg(test)]
 mod test {
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
     use super::*;
+
+    const BUN: &str = include_str!("../../fixtures/bun-yarn.lock");
     const FULL: &str = include_str!("../../fixtures/yarn1full.lock");
 
+    #[test_case(BUN ; "bun lockfile")]
+    #[test_case("" ; "empty lockfile")]
+    fn test_roundtrip(input: &str) {
+        let lockfile = BunLockfile::from_str(input).unwrap();
+        assert_eq!(input, lockfile.to_string());
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let lockfile = BunLockfile::from_str(BUN).unwrap();
+        let subgraph = lockfile
+            .subgraph(
+                &["packages/ui".into()],
+                &["react@18.2.0".into(), "typescript@^5.3.3".into()],
+            )
+            .unwrap();
+        let subgraph = String::from_utf8(subgraph.encode().unwrap()).unwrap();
+
+        assert_eq!(
+            subgraph,
+            r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+# bun ./bun.lockb --hash: 88AB30DB3C6D17DB-affe4ca4b8063975-AE162D1133E57748-25cecccee5e5a99f
+
+
+"react@18.2.0":
+  version "18.2.0"
+  resolved "https://registry.npmjs.org/react/-/react-18.2.0.tgz"
+  integrity sha512-/3IjMdb2L9QbBdWiW5e3P2/npwMBaU9mHCSCUzNln0ZCYbcfTsGbTJrU/kGemdH2IWmB2ioZ+zkxtmq6g09fGQ==
+  dependencies:
+    loose-envify "^1.1.0"
+
+"typescript@^5.3.3":
+  version "5.3.3"
+  resolved "https://registry.npmjs.org/typescript/-/typescript-5.3.3.tgz"
+  integrity sha512-pXWcraxM0uxAS+tN0AG/BF2TyqmHO014Z070UsJ+pFvYuRSq8KH8DmWpnbXe0pEPDHXZV3FcAbJkijJ5oNEnWw==
+
+"ui@workspace:packages/ui":
+  version "0.0.0"
+  resolved "workspace:packages/ui"
+  dependencies:
+    react "18.2.0"
+    typescript "^5.3.3"
+"#
+        );
+    }
+
+    #[test_case("react@18.2.0", "react" ; "unscoped")]
+    #[test_case("@babel/types@^7.18.10", "@babel/types" ; "scoped")]
+    #[test_case("ui@workspace:packages/ui", "ui" ; "workspace")]
+    fn test_key_name(key: &str, expected: &str) {
+        assert_eq!(key_name(key), expected);
+    }
+
     #[test]
     fn test_key_splitting() {
         let lockfile = BunLockfile::from_str(FULL).unwrap();
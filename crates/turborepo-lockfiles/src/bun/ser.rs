@@ -0,0 +1,212 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{BunLockfile, Entry};
+
+const INDENT: &str = "  ";
+
+impl BunLockfile {
+    fn reverse_lookup(&self) -> HashMap<&Entry, HashSet<&str>> {
+        let mut reverse_lookup = HashMap::new();
+        for (key, value) in self.inner.iter() {
+            let keys: &mut HashSet<&str> = reverse_lookup.entry(value).or_default();
+            keys.insert(key);
+        }
+        reverse_lookup
+    }
+}
+
+impl fmt::Display for BunLockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Bun's header includes a hash of the lockfile's packages, which is
+        // kept up to date by `subgraph`
+        f.write_str(&self.header)?;
+        let reverse_lookup = self.reverse_lookup();
+        let mut added_keys: HashSet<&str> = HashSet::with_capacity(self.inner.len());
+        let mut leading = LeadingNewline::new();
+        for (key, entry) in self.inner.iter() {
+            if added_keys.contains(key.as_str()) {
+                continue;
+            }
+
+            let all_keys = reverse_lookup
+                .get(entry)
+                .expect("entry in lockfile should appear as a key in reverse lookup");
+            added_keys.extend(all_keys);
+            let mut keys = all_keys.iter().copied().collect::<Vec<_>>();
+            keys.sort();
+
+            // Unlike yarn, bun always quotes keys
+            let wrapped_keys = keys.into_iter().map(wrap).collect::<Vec<_>>();
+            let key_line = wrapped_keys.join(", ");
+
+            f.write_fmt(format_args!(
+                "{}{}:\n{}\n",
+                leading.leading(),
+                key_line,
+                entry
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut leading = LeadingNewline::new();
+        if let Some(name) = &self.name {
+            f.write_fmt(format_args!(
+                "{}{INDENT}name {}",
+                leading.leading(),
+                maybe_wrap(name)
+            ))?;
+        }
+        f.write_fmt(format_args!(
+            "{}{INDENT}version {}",
+            leading.leading(),
+            wrap(&self.version)
+        ))?;
+        if let Some(uid) = &self.uid {
+            f.write_fmt(format_args!(
+                "{}{INDENT}uid {}",
+                leading.leading(),
+                maybe_wrap(uid)
+            ))?;
+        }
+        if let Some(resolved) = &self.resolved {
+            f.write_fmt(format_args!(
+                "{}{INDENT}resolved {}",
+                leading.leading(),
+                wrap(resolved)
+            ))?;
+        }
+        if let Some(integrity) = &self.integrity {
+            f.write_fmt(format_args!(
+                "{}{INDENT}integrity {}",
+                leading.leading(),
+                maybe_wrap(integrity)
+            ))?;
+        }
+        if let Some(registry) = &self.registry {
+            f.write_fmt(format_args!(
+                "{}{INDENT}registry {}",
+                leading.leading(),
+                maybe_wrap(registry)
+            ))?;
+        }
+        if let Some(deps) = &self.dependencies {
+            f.write_fmt(format_args!("{}{INDENT}dependencies:", leading.leading()))?;
+            encode_map(deps.iter().map(|(k, v)| (k.as_ref(), v.as_ref())), f)?;
+        }
+        if let Some(optional_deps) = &self.optional_dependencies {
+            f.write_fmt(format_args!(
+                "{}{INDENT}optionalDependencies:",
+                leading.leading()
+            ))?;
+            encode_map(
+                optional_deps.iter().map(|(k, v)| (k.as_ref(), v.as_ref())),
+                f,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LeadingNewline {
+    First,
+    Rest,
+}
+
+impl LeadingNewline {
+    fn new() -> Self {
+        Self::First
+    }
+
+    fn leading(&mut self) -> &'static str {
+        let res = match self {
+            LeadingNewline::First => "",
+            LeadingNewline::Rest => "\n",
+        };
+        *self = Self::Rest;
+        res
+    }
+}
+
+fn encode_map<'a, I: Iterator<Item = (&'a str, &'a str)>>(
+    entries: I,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    // Dependency names are only quoted when needed, but their ranges always are
+    let mut wrapped_entries = entries
+        .map(|(k, v)| (maybe_wrap(k), wrap(v)))
+        .collect::<Vec<_>>();
+    wrapped_entries.sort_unstable_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    for (key, value) in wrapped_entries {
+        f.write_fmt(format_args!("\n{INDENT}{INDENT}{key} {value}"))?;
+    }
+
+    Ok(())
+}
+
+fn wrap(s: &str) -> String {
+    serde_json::to_string(s).expect("failed at encoding string as json")
+}
+
+fn maybe_wrap(s: &str) -> Cow<str> {
+    match should_wrap_key(s) {
+        true => wrap(s).into(),
+        false => s.into(),
+    }
+}
+
+// Determines if we need to wrap a key
+fn should_wrap_key(s: &str) -> bool {
+    // Wrap if it starts with a syml keyword
+    s.starts_with("true") ||
+    s.starts_with("false") ||
+    // Wrap if it doesn't start with a-zA-Z
+    s.chars().next().map_or(false, |c| !c.is_ascii_alphabetic()) ||
+    // Wrap if it contains any unwanted chars
+    s.chars().any(|c| matches!(
+        c,
+        ' ' | ':' | '\t' | '\r' | '\u{000B}' | '\u{000C}' | '\n' | '\\' | '"' | ',' | '[' | ']'
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_basic_serialization() {
+        let entry = Entry {
+            version: "18.2.0".into(),
+            resolved: Some("https://registry.npmjs.org/react-dom/-/react-dom-18.2.0.tgz".into()),
+            integrity: Some("sha512-6IMTriUmvsjHUjNtEDudZfuDQUoWXVxKHhlEGSk81n4YFS".into()),
+            dependencies: Some(
+                vec![
+                    ("loose-envify".into(), "^1.1.0".into()),
+                    ("scheduler".into(), "latest".into()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            entry.to_string(),
+            r#"  version "18.2.0"
+  resolved "https://registry.npmjs.org/react-dom/-/react-dom-18.2.0.tgz"
+  integrity sha512-6IMTriUmvsjHUjNtEDudZfuDQUoWXVxKHhlEGSk81n4YFS
+  dependencies:
+    loose-envify "^1.1.0"
+    scheduler "latest""#
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use petgraph::{
+    algo::kosaraju_scc,
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef,
+};
+
+use crate::{Error, Lockfile, Package};
+
+/// A graph of the external packages in a lockfile that is shared by all
+/// workspaces. Edges are labeled with the name the dependency was requested
+/// by. If the lockfile can resolve a dependency differently depending on the
+/// workspace, each workspace gets its own copy of the packages it reaches.
+#[derive(Debug, Default)]
+pub struct LockfileGraph {
+    graph: DiGraph<Package, String>,
+    // Nodes are keyed by the workspace that resolved them if resolutions are
+    // workspace specific
+    nodes: HashMap<(Option<String>, Package), NodeIndex>,
+    // Map of workspace paths to their direct external dependencies
+    workspaces: HashMap<String, Vec<(String, NodeIndex)>>,
+    // Closure of each node, nodes in the same cycle share a closure
+    closures: Vec<Arc<HashSet<NodeIndex>>>,
+}
+
+impl LockfileGraph {
+    /// Takes a lockfile, and a map of workspace directory paths -> (package
+    /// name, version) and builds the graph of all packages they depend on
+    #[tracing::instrument(skip_all)]
+    pub fn new<L: Lockfile + ?Sized>(
+        lockfile: &L,
+        workspaces: HashMap<String, HashMap<String, String>>,
+    ) -> Result<Self, Error> {
+        let mut this = Self::default();
+        // Packages whose dependencies still need to be added along with the
+        // workspace that first reached them. Lockfile entries record the
+        // resolved ranges of their dependencies so unless the lockfile has
+        // workspace specific resolutions, which workspace is used to resolve
+        // them doesn't change the result.
+        let mut pending = VecDeque::new();
+        let per_workspace = lockfile.resolves_per_workspace();
+        let scope = |workspace_path: &String| per_workspace.then(|| workspace_path.clone());
+
+        for (workspace_path, unresolved_deps) in workspaces {
+            let mut direct_deps = Vec::with_capacity(unresolved_deps.len());
+            for (name, specifier) in unresolved_deps {
+                let Some(pkg) = lockfile.resolve_package(&workspace_path, &name, &specifier)?
+                else {
+                    continue;
+                };
+                let (node, is_new) = this.add_package(scope(&workspace_path), pkg);
+                if is_new {
+                    pending.push_back((node, workspace_path.clone()));
+                }
+                direct_deps.push((name, node));
+            }
+            this.workspaces.insert(workspace_path, direct_deps);
+        }
+
+        while let Some((node, workspace_path)) = pending.pop_front() {
+            let Some(deps) = lockfile.all_dependencies(&this.graph[node].key)? else {
+                continue;
+            };
+            for (name, specifier) in deps {
+                let Some(pkg) = lockfile.resolve_package(&workspace_path, &name, &specifier)?
+                else {
+                    continue;
+                };
+                let (dependency, is_new) = this.add_package(scope(&workspace_path), pkg);
+                if is_new {
+                    pending.push_back((dependency, workspace_path.clone()));
+                }
+                this.graph.update_edge(node, dependency, name);
+            }
+        }
+
+        this.populate_closures();
+        Ok(this)
+    }
+
+    /// Returns all external packages a workspace depends on
+    pub fn transitive_closure(&self, workspace_path: &str) -> HashSet<Package> {
+        self.workspaces
+            .get(workspace_path)
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, node)| self.closures[node.index()].iter())
+            .map(|node| self.graph[*node].clone())
+            .collect()
+    }
+
+    /// Returns the closures of every workspace in the graph
+    pub fn all_transitive_closures(&self) -> HashMap<String, HashSet<Package>> {
+        self.workspaces
+            .keys()
+            .map(|workspace_path| {
+                (
+                    workspace_path.clone(),
+                    self.transitive_closure(workspace_path),
+                )
+            })
+            .collect()
+    }
+
+    /// Finds the shortest chain of dependencies that causes `package` to be
+    /// included in a workspace. `package` can either be a lockfile key or the
+    /// name of a dependency. The chain starts with a direct dependency of the
+    /// workspace and ends with the requested package.
+    pub fn why(&self, workspace_path: &str, package: &str) -> Option<Vec<&Package>> {
+        let direct_deps = self.workspaces.get(workspace_path)?;
+        let matches =
+            |name: &str, node: NodeIndex| name == package || self.graph[node].key == package;
+
+        if let Some((_, node)) = direct_deps.iter().find(|(name, node)| matches(name, *node)) {
+            return Some(vec![&self.graph[*node]]);
+        }
+
+        let mut parents: HashMap<NodeIndex, Option<NodeIndex>> =
+            direct_deps.iter().map(|(_, node)| (*node, None)).collect();
+        let mut queue: VecDeque<_> = direct_deps.iter().map(|(_, node)| *node).collect();
+        while let Some(node) = queue.pop_front() {
+            for edge in self.graph.edges(node) {
+                let dependency = edge.target();
+                if parents.contains_key(&dependency) {
+                    continue;
+                }
+                parents.insert(dependency, Some(node));
+                if matches(edge.weight(), dependency) {
+                    let mut path = vec![&self.graph[dependency]];
+                    let mut current = dependency;
+                    while let Some(Some(parent)) = parents.get(&current) {
+                        path.push(&self.graph[*parent]);
+                        current = *parent;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(dependency);
+            }
+        }
+
+        None
+    }
+
+    fn add_package(&mut self, scope: Option<String>, pkg: Package) -> (NodeIndex, bool) {
+        let key = (scope, pkg);
+        if let Some(node) = self.nodes.get(&key) {
+            return (*node, false);
+        }
+        let node = self.graph.add_node(key.1.clone());
+        self.nodes.insert(key, node);
+        (node, true)
+    }
+
+    fn populate_closures(&mut self) {
+        let mut closures = vec![Arc::default(); self.graph.node_count()];
+        // Components are returned in reverse topological order so the closures of
+        // all dependencies are known by the time we reach a package
+        for component in kosaraju_scc(&self.graph) {
+            let mut closure: HashSet<NodeIndex> = component.iter().copied().collect();
+            for node in &component {
+                for dependency in self.graph.neighbors(*node) {
+                    if !closure.contains(&dependency) {
+                        closure.extend(closures[dependency.index()].iter().copied());
+                    }
+                }
+            }
+            let closure = Arc::new(closure);
+            for node in component {
+                closures[node.index()] = closure.clone();
+            }
+        }
+        self.closures = closures;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::Yarn1Lockfile;
+
+    const LOCKFILE: &str = r#"# yarn lockfile v1
+
+
+a@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    b "^1.0.0"
+
+b@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    a "^1.0.0"
+    c "^1.0.0"
+
+c@^1.0.0:
+  version "1.0.0"
+
+d@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    c "^1.0.0"
+"#;
+
+    fn graph() -> LockfileGraph {
+        let lockfile = Yarn1Lockfile::from_bytes(LOCKFILE.as_bytes()).unwrap();
+        let workspaces = [
+            ("apps/web", vec![("a", "^1.0.0")]),
+            ("apps/docs", vec![("d", "^1.0.0")]),
+        ]
+        .into_iter()
+        .map(|(workspace, deps)| {
+            let deps = deps
+                .into_iter()
+                .map(|(name, version)| (name.to_string(), version.to_string()))
+                .collect();
+            (workspace.to_string(), deps)
+        })
+        .collect();
+        LockfileGraph::new(&lockfile, workspaces).unwrap()
+    }
+
+    fn keys(closure: HashSet<Package>) -> Vec<String> {
+        let mut keys = closure.into_iter().map(|pkg| pkg.key).collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_closures_with_cycle() {
+        let graph = graph();
+        assert_eq!(
+            keys(graph.transitive_closure("apps/web")),
+            vec!["a@^1.0.0", "b@^1.0.0", "c@^1.0.0"]
+        );
+        assert_eq!(
+            keys(graph.transitive_closure("apps/docs")),
+            vec!["c@^1.0.0", "d@^1.0.0"]
+        );
+        assert!(graph.transitive_closure("apps/missing").is_empty());
+    }
+
+    #[test]
+    fn test_why() {
+        let graph = graph();
+        let path = graph
+            .why("apps/web", "c")
+            .unwrap()
+            .into_iter()
+            .map(|pkg| pkg.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(path, vec!["a@^1.0.0", "b@^1.0.0", "c@^1.0.0"]);
+        assert_eq!(graph.why("apps/docs", "d@^1.0.0").unwrap().len(), 1);
+        assert!(graph.why("apps/docs", "a").is_none());
+    }
+}
@@ -4,18 +4,20 @@
 mod berry;
 mod bun;
 mod error;
+mod graph;
 mod npm;
 mod pnpm;
 mod yarn1;
 
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
 };
 
 pub use berry::{Error as BerryError, *};
-pub use bun::BunLockfile;
+pub use bun::{bun_subgraph, BunLockfile};
 pub use error::Error;
+pub use graph::LockfileGraph;
 pub use npm::*;
 pub use pnpm::{pnpm_global_change, pnpm_subgraph, PnpmLockfile};
 use serde::Serialize;
@@ -28,9 +30,17 @@ pub struct Package {
     pub version: String,
 }
 
-// This trait will only be used when migrating the Go lockfile implementations
-// to Rust. Once the migration is complete we will leverage petgraph for doing
-// our graph calculations.
+/// The packages that differ between two lockfiles
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct LockfileDiff {
+    /// Set if the change can affect every package e.g. a different package
+    /// manager or changed lockfile settings
+    pub global_change: bool,
+    /// Packages that were added, removed, or whose entry changed. Changed
+    /// entries are included with both their previous and current versions.
+    pub changed: HashSet<Package>,
+}
+
 pub trait Lockfile: Send + Sync + Any + std::fmt::Debug {
     // Given a workspace, a package it imports and version returns the key, resolved
     // version, and if it was found
@@ -52,6 +62,13 @@ pub trait Lockfile: Send + Sync + Any + std::fmt::Debug {
 
     fn encode(&self) -> Result<Vec<u8>, Error>;
 
+    /// Whether `resolve_package` can resolve the same dependency differently
+    /// depending on the workspace, e.g. because of an override that only
+    /// applies to some workspaces
+    fn resolves_per_workspace(&self) -> bool {
+        false
+    }
+
     /// All patch files referenced in the lockfile
     fn patches(&self) -> Result<Vec<RelativeUnixPathBuf>, Error> {
         Ok(Vec::new())
@@ -59,6 +76,9 @@ pub trait Lockfile: Send + Sync + Any + std::fmt::Debug {
 
     /// Determine if there's a global change between two lockfiles
     fn global_change(&self, other: &dyn Lockfile) -> bool;
+
+    /// Determine which packages changed between `other` and this lockfile
+    fn diff(&self, other: &dyn Lockfile) -> LockfileDiff;
 }
 
 /// Takes a lockfile, and a map of workspace directory paths -> (package name,
@@ -67,60 +87,58 @@ pub fn all_transitive_closures<L: Lockfile + ?Sized>(
     lockfile: &L,
     workspaces: HashMap<String, HashMap<String, String>>,
 ) -> Result<HashMap<String, HashSet<Package>>, Error> {
-    workspaces
-        .into_iter()
-        .map(|(workspace, unresolved_deps)| {
-            let closure = transitive_closure(lockfile, &workspace, unresolved_deps)?;
-            Ok((workspace, closure))
-        })
-        .collect()
+    let graph = LockfileGraph::new(lockfile, workspaces)?;
+    Ok(graph.all_transitive_closures())
 }
 
-// this should get replaced by petgraph in the future :)
 #[tracing::instrument(skip_all)]
 pub fn transitive_closure<L: Lockfile + ?Sized>(
     lockfile: &L,
     workspace_path: &str,
     unresolved_deps: HashMap<String, String>,
 ) -> Result<HashSet<Package>, Error> {
-    let mut transitive_deps = HashSet::new();
-    transitive_closure_helper(
-        lockfile,
-        workspace_path,
-        unresolved_deps,
-        &mut transitive_deps,
-    )?;
-
-    Ok(transitive_deps)
+    let workspaces = HashMap::from([(workspace_path.to_string(), unresolved_deps)]);
+    let graph = LockfileGraph::new(lockfile, workspaces)?;
+    Ok(graph.transitive_closure(workspace_path))
 }
 
-fn transitive_closure_helper<L: Lockfile + ?Sized>(
-    lockfile: &L,
-    workspace_path: &str,
-    unresolved_deps: HashMap<String, impl AsRef<str>>,
-    resolved_deps: &mut HashSet<Package>,
-) -> Result<(), Error> {
-    for (name, specifier) in unresolved_deps {
-        let pkg = lockfile.resolve_package(workspace_path, &name, specifier.as_ref())?;
-
-        match pkg {
-            None => {
-                continue;
-            }
-            Some(pkg) if resolved_deps.contains(&pkg) => {
-                continue;
-            }
-            Some(pkg) => {
-                let all_deps = lockfile.all_dependencies(&pkg.key)?;
-                resolved_deps.insert(pkg);
-                if let Some(deps) = all_deps {
-                    transitive_closure_helper(lockfile, workspace_path, deps, resolved_deps)?;
-                }
-            }
+// Compares the entries of two lockfiles of the same type
+fn diff_entries<K: Ord, V: PartialEq>(
+    current: &BTreeMap<K, V>,
+    previous: &BTreeMap<K, V>,
+    package: impl Fn(&K, &V) -> Package,
+) -> HashSet<Package> {
+    let mut changed = HashSet::new();
+    for (key, entry) in current {
+        if previous.get(key) != Some(entry) {
+            changed.insert(package(key, entry));
+        }
+    }
+    for (key, entry) in previous {
+        if current.get(key) != Some(entry) {
+            changed.insert(package(key, entry));
+        }
+    }
+    changed
+}
+
+impl LockfileDiff {
+    /// A diff where every package should be considered changed
+    pub fn global() -> Self {
+        Self {
+            global_change: true,
+            changed: HashSet::new(),
         }
     }
 
-    Ok(())
+    pub fn is_empty(&self) -> bool {
+        !self.global_change && self.changed.is_empty()
+    }
+
+    /// Returns true if any package in the closure was changed
+    pub fn affects(&self, closure: &HashSet<Package>) -> bool {
+        self.global_change || !self.changed.is_disjoint(closure)
+    }
 }
 
 impl Package {
@@ -3,7 +3,7 @@ use std::{any::Any, collections::HashMap};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::{Error, Lockfile, Package};
+use super::{Error, Lockfile, LockfileDiff, Package};
 
 type Map<K, V> = std::collections::BTreeMap<K, V>;
 
@@ -25,7 +25,7 @@ pub struct NpmLockfile {
     other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 struct NpmPackage {
     version: Option<String>,
@@ -147,6 +147,19 @@ impl Lockfile for NpmLockfile {
             true
         }
     }
+
+    fn diff(&self, other: &dyn Lockfile) -> LockfileDiff {
+        let any_other = other as &dyn Any;
+        let Some(other) = any_other.downcast_ref::<Self>() else {
+            return LockfileDiff::global();
+        };
+        LockfileDiff {
+            global_change: self.global_change(other),
+            changed: crate::diff_entries(&self.packages, &other.packages, |key, pkg| {
+                Package::new(key, pkg.version.clone().unwrap_or_default())
+            }),
+        }
+    }
 }
 
 impl NpmLockfile {
@@ -422,6 +435,30 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_diff() -> Result<(), Error> {
+        let contents = include_str!("../fixtures/npm-lock.json");
+        let previous = NpmLockfile::load(contents.as_bytes())?;
+        let current = NpmLockfile::load(
+            contents
+                .replace("code-frame-7.18.6.tgz", "code-frame-7.18.7.tgz")
+                .as_bytes(),
+        )?;
+
+        let diff = current.diff(&previous);
+        assert!(!diff.global_change);
+        assert_eq!(
+            diff.changed,
+            [Package::new("node_modules/@babel/code-frame", "7.18.6")]
+                .into_iter()
+                .collect()
+        );
+
+        let yarn = crate::Yarn1Lockfile::from_bytes(b"")?;
+        assert!(current.diff(&yarn).global_change);
+        Ok(())
+    }
+
     #[test]
     fn test_workspace_peer_dependencies() -> Result<(), Error> {
         let lockfile =
@@ -14,6 +14,8 @@ pub struct PnpmLockfile {
     #[serde(skip_serializing_if = "Option::is_none")]
     settings: Option<LockfileSettings>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    catalogs: Option<Map<String, Map<String, Dependency>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     never_built_dependencies: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     only_built_dependencies: Option<Vec<String>>,
@@ -22,10 +24,18 @@ pub struct PnpmLockfile {
     #[serde(skip_serializing_if = "Option::is_none")]
     package_extensions_checksum: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pnpmfile_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     patched_dependencies: Option<Map<String, PatchFile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ignored_optional_dependencies: Option<Vec<String>>,
     importers: Map<String, ProjectSnapshot>,
     #[serde(skip_serializing_if = "Option::is_none")]
     packages: Option<Map<String, PackageSnapshot>>,
+    // Starting with v9 the dependencies of each package live in a separate
+    // section keyed by the dependency path including peer suffixes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshots: Option<Map<String, SnapshotEntry>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     time: Option<Map<String, String>>,
 }
@@ -103,6 +113,18 @@ pub struct PackageSnapshot {
     other: Map<String, serde_yaml::Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional_dependencies: Option<Map<String, String>>,
+
+    #[serde(flatten)]
+    other: Map<String, serde_yaml::Value>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct DependenciesMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -139,7 +161,9 @@ pub struct PackageResolution {
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 #[serde(rename_all = "camelCase")]
 struct LockfileSettings {
-    auto_install_peer_deps: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_install_peers: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     exclude_links_from_lockfile: Option<bool>,
 }
 
@@ -150,9 +174,31 @@ impl PnpmLockfile {
     }
 
     fn get_packages(&self, key: &str) -> Option<&PackageSnapshot> {
-        self.packages
+        let packages = self.packages.as_ref()?;
+        match self.is_v9() {
+            // v9 keys point at a snapshot, the package metadata is shared by
+            // all snapshots of the same name and version
+            true => {
+                self.get_snapshot(key)?;
+                packages.get(&Self::package_key(key))
+            }
+            false => packages.get(key),
+        }
+    }
+
+    fn get_snapshot(&self, key: &str) -> Option<&SnapshotEntry> {
+        self.snapshots
             .as_ref()
-            .and_then(|packages| packages.get(key))
+            .and_then(|snapshots| snapshots.get(key))
+    }
+
+    // Strips any peer or patch suffix from a v9 snapshot key to get the key of
+    // the package entry
+    fn package_key(key: &str) -> String {
+        match DepPath::parse_v9(key) {
+            Ok(dp) => format!("{}@{}", dp.name, dp.version),
+            Err(_) => key.to_string(),
+        }
     }
 
     fn get_workspace(&self, workspace_path: &str) -> Result<&ProjectSnapshot, crate::Error> {
@@ -171,16 +217,35 @@ impl PnpmLockfile {
         matches!(self.lockfile_version.format, super::VersionFormat::String)
     }
 
+    fn is_v9(&self) -> bool {
+        self.lockfile_version
+            .version
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+            .is_some_and(|major| major >= 9)
+    }
+
     fn format_key(&self, name: &str, version: &str) -> String {
-        match self.is_v6() {
-            true => format!("/{name}@{version}"),
-            false => format!("/{name}/{version}"),
+        if self.is_v9() {
+            format!("{name}@{version}")
+        } else if self.is_v6() {
+            format!("/{name}@{version}")
+        } else {
+            format!("/{name}/{version}")
         }
     }
 
+    fn dep_path<'a>(&self, key: &'a str) -> Result<DepPath<'a>, Error> {
+        Ok(match self.is_v9() {
+            true => DepPath::parse_v9(key)?,
+            false => DepPath::try_from(key)?,
+        })
+    }
+
     // Extracts the version from a dependency path
     fn extract_version<'a>(&self, key: &'a str) -> Result<Cow<'a, str>, Error> {
-        let dp = DepPath::try_from(key)?;
+        let dp = self.dep_path(key)?;
         // If there's a suffix, the suffix gets included as part of the version
         // so we can track patch file changes
         if let Some(suffix) = dp.peer_suffix {
@@ -234,13 +299,16 @@ impl PnpmLockfile {
         }
     }
 
-    fn prune_patches(
+    // Given the dependency paths in the pruned lockfile finds the patches that
+    // are still in use
+    fn prune_patches<'a>(
+        &self,
         patches: &Map<String, PatchFile>,
-        pruned_packages: &Map<String, PackageSnapshot>,
+        dependency_paths: impl Iterator<Item = &'a String>,
     ) -> Result<Map<String, PatchFile>, Error> {
         let mut pruned_patches = Map::new();
-        for dependency in pruned_packages.keys() {
-            let dp = DepPath::try_from(dependency.as_str())?;
+        for dependency in dependency_paths {
+            let dp = self.dep_path(dependency)?;
             let patch_key = format!("{}@{}", dp.name, dp.version);
             if let Some(patch) = patches
                 .get(&patch_key)
@@ -252,12 +320,23 @@ impl PnpmLockfile {
         Ok(pruned_patches)
     }
 
+    // Pairs each v9 snapshot with the package entry it refers to so a change
+    // to either is attributed to the snapshot key
+    fn v9_entries(&self) -> Map<&str, (Option<&PackageSnapshot>, &SnapshotEntry)> {
+        self.snapshots
+            .iter()
+            .flatten()
+            .map(|(key, snapshot)| (key.as_str(), (self.get_packages(key), snapshot)))
+            .collect()
+    }
+
     // Create a projection of all fields in the lockfile that could affect all
     // workspaces
     fn global_fields(&self) -> GlobalFields {
         GlobalFields {
             version: &self.lockfile_version.version,
             checksum: self.package_extensions_checksum.as_deref(),
+            pnpmfile_checksum: self.pnpmfile_checksum.as_deref(),
             overrides: self.overrides.as_ref(),
             patched_dependencies: self.patched_dependencies.as_ref(),
             settings: self.settings.as_ref(),
@@ -269,6 +348,7 @@ impl PnpmLockfile {
 struct GlobalFields<'a> {
     version: &'a str,
     checksum: Option<&'a str>,
+    pnpmfile_checksum: Option<&'a str>,
     overrides: Option<&'a BTreeMap<String, String>>,
     patched_dependencies: Option<&'a BTreeMap<String, PatchFile>>,
     settings: Option<&'a LockfileSettings>,
@@ -327,15 +407,22 @@ impl crate::Lockfile for PnpmLockfile {
         &self,
         key: &str,
     ) -> Result<Option<std::collections::HashMap<String, String>>, crate::Error> {
-        let Some(entry) = self.packages.as_ref().and_then(|pkgs| pkgs.get(key)) else {
-            return Ok(None);
+        let (dependencies, optional_dependencies) = if self.is_v9() {
+            let Some(entry) = self.get_snapshot(key) else {
+                return Ok(None);
+            };
+            (&entry.dependencies, &entry.optional_dependencies)
+        } else {
+            let Some(entry) = self.packages.as_ref().and_then(|pkgs| pkgs.get(key)) else {
+                return Ok(None);
+            };
+            (&entry.dependencies, &entry.optional_dependencies)
         };
         Ok(Some(
-            entry
-                .dependencies
+            dependencies
                 .iter()
                 .flatten()
-                .chain(entry.optional_dependencies.iter().flatten())
+                .chain(optional_dependencies.iter().flatten())
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect(),
         ))
@@ -354,11 +441,24 @@ impl crate::Lockfile for PnpmLockfile {
             .collect::<Map<_, _>>();
 
         let mut pruned_packages = Map::new();
-        for package in packages {
+        let mut pruned_snapshots = Map::new();
+        let mut insert_package = |key: &str| -> Result<(), crate::Error> {
             let entry = self
-                .get_packages(package.as_str())
-                .ok_or_else(|| crate::Error::MissingPackage(package.clone()))?;
-            pruned_packages.insert(package.clone(), entry.clone());
+                .get_packages(key)
+                .ok_or_else(|| crate::Error::MissingPackage(key.into()))?;
+            if self.is_v9() {
+                let snapshot = self
+                    .get_snapshot(key)
+                    .ok_or_else(|| crate::Error::MissingPackage(key.into()))?;
+                pruned_snapshots.insert(key.to_string(), snapshot.clone());
+                pruned_packages.insert(Self::package_key(key), entry.clone());
+            } else {
+                pruned_packages.insert(key.to_string(), entry.clone());
+            }
+            Ok(())
+        };
+        for package in packages {
+            insert_package(package)?;
         }
         for importer in importers.values() {
             // Find all injected packages in each workspace and include it in
@@ -378,17 +478,22 @@ impl crate::Lockfile for PnpmLockfile {
                     .find_resolution(dependency)
                     .ok_or_else(|| Error::MissingInjectedPackage(dependency.clone()))?;
 
-                let entry = self
-                    .get_packages(version)
-                    .ok_or_else(|| crate::Error::MissingPackage(version.into()))?;
-                pruned_packages.insert(version.to_string(), entry.clone());
+                // v9 importers only list the version of injected packages
+                let key = match self.get_packages(version) {
+                    Some(_) => version.to_string(),
+                    None => self.format_key(dependency, version),
+                };
+                insert_package(&key)?;
             }
         }
 
         let patches = self
             .patched_dependencies
             .as_ref()
-            .map(|patches| Self::prune_patches(patches, &pruned_packages))
+            .map(|patches| match self.is_v9() {
+                true => self.prune_patches(patches, pruned_snapshots.keys()),
+                false => self.prune_patches(patches, pruned_packages.keys()),
+            })
             .transpose()?;
 
         Ok(Box::new(Self {
@@ -397,12 +502,19 @@ impl crate::Lockfile for PnpmLockfile {
                 false => Some(pruned_packages),
                 true => None,
             },
+            snapshots: match pruned_snapshots.is_empty() {
+                false => Some(pruned_snapshots),
+                true => None,
+            },
             lockfile_version: self.lockfile_version.clone(),
+            catalogs: self.catalogs.clone(),
             never_built_dependencies: self.never_built_dependencies.clone(),
             only_built_dependencies: self.only_built_dependencies.clone(),
             overrides: self.overrides.clone(),
             package_extensions_checksum: self.package_extensions_checksum.clone(),
+            pnpmfile_checksum: self.pnpmfile_checksum.clone(),
             patched_dependencies: patches,
+            ignored_optional_dependencies: self.ignored_optional_dependencies.clone(),
             time: None,
             settings: self.settings.clone(),
         }))
@@ -431,6 +543,36 @@ impl crate::Lockfile for PnpmLockfile {
             true
         }
     }
+
+    fn diff(&self, other: &dyn crate::Lockfile) -> crate::LockfileDiff {
+        let any_other = other as &dyn Any;
+        let Some(other) = any_other.downcast_ref::<Self>() else {
+            return crate::LockfileDiff::global();
+        };
+        let package = |key: &str, version: Option<&String>| {
+            let version = version.cloned().unwrap_or_else(|| {
+                self.extract_version(key)
+                    .map_or_else(|_| key.to_string(), |version| version.into_owned())
+            });
+            crate::Package::new(key, version)
+        };
+        let changed = if self.is_v9() {
+            crate::diff_entries(&self.v9_entries(), &other.v9_entries(), |key, (pkg, _)| {
+                package(key, pkg.and_then(|pkg| pkg.version.as_ref()))
+            })
+        } else {
+            let empty = Map::new();
+            let current = self.packages.as_ref().unwrap_or(&empty);
+            let previous = other.packages.as_ref().unwrap_or(&empty);
+            crate::diff_entries(current, previous, |key, pkg| {
+                package(key, pkg.version.as_ref())
+            })
+        };
+        crate::LockfileDiff {
+            global_change: self.global_change(other),
+            changed,
+        }
+    }
 }
 
 impl DependencyInfo {
@@ -481,6 +623,7 @@ pub fn pnpm_global_change(
     let curr_data = PnpmLockfile::from_bytes(curr_contents)?;
     Ok(prev_data.lockfile_version != curr_data.lockfile_version
         || prev_data.package_extensions_checksum != curr_data.package_extensions_checksum
+        || prev_data.pnpmfile_checksum != curr_data.pnpmfile_checksum
         || prev_data.overrides != curr_data.overrides
         || prev_data.patched_dependencies != curr_data.patched_dependencies
         || prev_data.settings != curr_data.settings)
@@ -495,6 +638,7 @@ mod tests {
     const PNPM7: &[u8] = include_bytes!("../../fixtures/pnpm7-workspace.yaml").as_slice();
     const PNPM8: &[u8] = include_bytes!("../../fixtures/pnpm8.yaml").as_slice();
     const PNPM8_6: &[u8] = include_bytes!("../../fixtures/pnpm-v6.1.yaml").as_slice();
+    const PNPM9: &[u8] = include_bytes!("../../fixtures/pnpm9.yaml").as_slice();
     const PNPM_ABSOLUTE: &[u8] = include_bytes!("../../fixtures/pnpm-absolute.yaml").as_slice();
     const PNPM_ABSOLUTE_V6: &[u8] =
         include_bytes!("../../fixtures/pnpm-absolute-v6.yaml").as_slice();
@@ -510,7 +654,7 @@ mod tests {
 
     #[test]
     fn test_roundtrip() {
-        for fixture in &[PNPM6, PNPM7, PNPM8, PNPM8_6] {
+        for fixture in &[PNPM6, PNPM7, PNPM8, PNPM8_6, PNPM9] {
             let lockfile = PnpmLockfile::from_bytes(fixture).unwrap();
             let serialized_lockfile = serde_yaml::to_string(&lockfile).unwrap();
             let lockfile_from_serialized =
@@ -629,6 +773,38 @@ mod tests {
         Err("Workspace 'apps/bad_workspace' not found in lockfile")
         ; "v6 missing workspace"
     )]
+    #[test_case(
+        PNPM9,
+        "apps/web",
+        "next",
+        "catalog:",
+        Ok(Some("14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)"))
+        ; "v9 catalog"
+    )]
+    #[test_case(
+        PNPM9,
+        "apps/web",
+        "react-dom",
+        "18.2.0(react@18.2.0)",
+        Ok(Some("18.2.0(react@18.2.0)"))
+        ; "v9 transitive peer"
+    )]
+    #[test_case(
+        PNPM9,
+        "packages/ui",
+        "@types/prop-types",
+        "15.7.11",
+        Ok(Some("15.7.11"))
+        ; "v9 transitive"
+    )]
+    #[test_case(
+        PNPM9,
+        "packages/ui",
+        "lodash",
+        "^4.17.21",
+        Ok(None)
+        ; "v9 missing"
+    )]
     fn test_specifier_resolution(
         lockfile: &[u8],
         workspace_path: &str,
@@ -719,6 +895,39 @@ mod tests {
         }))
         ; "pnpm override"
     )]
+    #[test_case(
+        PNPM9,
+        "apps/web",
+        "next",
+        "catalog:",
+        Ok(Some(crate::Package {
+            key: "next@14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)".into(),
+            version: "14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)".into(),
+        }))
+        ; "v9 peer package"
+    )]
+    #[test_case(
+        PNPM9,
+        "apps/web",
+        "string-width-cjs",
+        "npm:string-width@^4.2.0",
+        Ok(Some(crate::Package {
+            key: "string-width@4.2.3".into(),
+            version: "4.2.3".into(),
+        }))
+        ; "v9 alias"
+    )]
+    #[test_case(
+        PNPM9,
+        "apps/web",
+        "lodash",
+        "^4.17.21",
+        Ok(Some(crate::Package {
+            key: "lodash@4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)".into(),
+            version: "4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)".into(),
+        }))
+        ; "v9 patched package"
+    )]
     fn test_resolve_package(
         lockfile: &[u8],
         workspace_path: &str,
@@ -906,4 +1115,136 @@ c:
             ]
         )
     }
+
+    fn v9_web_closure(lockfile: &PnpmLockfile) -> Vec<Package> {
+        let closures = crate::all_transitive_closures(
+            lockfile,
+            vec![(
+                "apps/web".to_string(),
+                vec![
+                    ("lodash".to_string(), "^4.17.21".to_string()),
+                    ("next".to_string(), "catalog:".to_string()),
+                    ("react".to_string(), "catalog:".to_string()),
+                    ("react-dom".to_string(), "^18.2.0".to_string()),
+                    (
+                        "string-width-cjs".to_string(),
+                        "npm:string-width@^4.2.0".to_string(),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            )]
+            .into_iter()
+            .collect(),
+        )
+        .unwrap();
+        let mut closure = closures
+            .get("apps/web")
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        closure.sort();
+        closure
+    }
+
+    #[test]
+    fn test_v9_closure() {
+        let lockfile = PnpmLockfile::from_bytes(PNPM9).unwrap();
+        assert_eq!(
+            v9_web_closure(&lockfile),
+            vec![
+                Package::new("ansi-regex@5.0.1", "5.0.1"),
+                Package::new("emoji-regex@8.0.0", "8.0.0"),
+                Package::new("is-fullwidth-code-point@3.0.0", "3.0.0"),
+                Package::new("js-tokens@4.0.0", "4.0.0"),
+                Package::new(
+                    "lodash@4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)",
+                    "4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)"
+                ),
+                Package::new("loose-envify@1.4.0", "1.4.0"),
+                Package::new(
+                    "next@14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)",
+                    "14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)"
+                ),
+                Package::new("react-dom@18.2.0(react@18.2.0)", "18.2.0(react@18.2.0)"),
+                Package::new("react@18.2.0", "18.2.0"),
+                Package::new("scheduler@0.23.0", "0.23.0"),
+                Package::new("string-width@4.2.3", "4.2.3"),
+                Package::new("strip-ansi@6.0.1", "6.0.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_v9_subgraph() {
+        let lockfile = PnpmLockfile::from_bytes(PNPM9).unwrap();
+        let packages = v9_web_closure(&lockfile)
+            .into_iter()
+            .map(|package| package.key)
+            .collect::<Vec<_>>();
+        let pruned = lockfile
+            .subgraph(&["apps/web".into(), "packages/ui".into()], &packages)
+            .unwrap();
+        let pruned = PnpmLockfile::from_bytes(&pruned.encode().unwrap()).unwrap();
+
+        assert_eq!(
+            pruned.importers.keys().collect::<Vec<_>>(),
+            vec![".", "apps/web", "packages/ui"]
+        );
+        assert_eq!(
+            pruned
+                .snapshots
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            packages.iter().collect::<Vec<_>>()
+        );
+        // Snapshots with different peers share a single package entry
+        assert_eq!(
+            pruned.packages.as_ref().unwrap().keys().collect::<Vec<_>>(),
+            vec![
+                "ansi-regex@5.0.1",
+                "emoji-regex@8.0.0",
+                "is-fullwidth-code-point@3.0.0",
+                "js-tokens@4.0.0",
+                "lodash@4.17.21",
+                "loose-envify@1.4.0",
+                "next@14.0.0",
+                "react-dom@18.2.0",
+                "react@18.2.0",
+                "scheduler@0.23.0",
+                "string-width@4.2.3",
+                "strip-ansi@6.0.1",
+            ]
+        );
+        assert_eq!(pruned.catalogs, lockfile.catalogs);
+        assert_eq!(pruned.settings, lockfile.settings);
+        assert_eq!(
+            pruned.patches().unwrap(),
+            vec![RelativeUnixPathBuf::new("patches/lodash@4.17.21.patch").unwrap()]
+        );
+        assert_eq!(v9_web_closure(&pruned), v9_web_closure(&lockfile));
+    }
+
+    #[test]
+    fn test_v9_diff() {
+        let previous = PnpmLockfile::from_bytes(PNPM9).unwrap();
+        let mut current = previous.clone();
+        current
+            .snapshots
+            .as_mut()
+            .unwrap()
+            .get_mut("react@18.2.0")
+            .unwrap()
+            .dependencies = None;
+
+        let diff = current.diff(&previous);
+        assert!(!diff.global_change);
+        assert_eq!(
+            diff.changed.into_iter().collect::<Vec<_>>(),
+            vec![Package::new("react@18.2.0", "18.2.0")]
+        );
+    }
 }
@@ -35,6 +35,15 @@ impl<'a> DepPath<'a> {
         self
     }
 
+    /// Parses a dependency path from a v9 lockfile, these have no leading
+    /// slash and always separate the name and version with an '@'
+    pub fn parse_v9(value: &'a str) -> Result<Self, nom::error::Error<String>> {
+        let (_, dep_path) = parse_v9_dep_path(value)
+            .map_err(|e| e.to_owned())
+            .finish()?;
+        Ok(dep_path)
+    }
+
     pub fn patch_hash(&self) -> Option<&str> {
         self.peer_suffix.and_then(|s| {
             if s.starts_with('(') {
@@ -82,6 +91,18 @@ fn parse_dep_path(i: &str) -> IResult<&str, DepPath> {
     ))
 }
 
+fn parse_v9_dep_path(i: &str) -> IResult<&str, DepPath> {
+    let (i, name) = alt((parse_name_with_scope, is_not("@")))(i)?;
+    let (i, _) = nom::character::complete::char('@')(i)?;
+    let (i, version) = is_not("(")(i)?;
+    let (i, peer_suffix) = opt(parse_new_peer_suffix)(i)?;
+    let (_, _) = nom::combinator::eof(i)?;
+    Ok((
+        "",
+        DepPath::new(name, version).with_peer_suffix(peer_suffix),
+    ))
+}
+
 fn parse_host(i: &str) -> IResult<&str, Option<&str>> {
     let (i, host) = opt(is_not("/"))(i)?;
     Ok((i, host))
@@ -114,11 +135,28 @@ fn parse_new_peer_suffix(i: &str) -> IResult<&str, &str> {
     Ok((i, suffix))
 }
 
+// Suffixes can be nested e.g. `(react-dom@18.2.0(react@18.2.0))` so we
+// need to find the matching closing paren
 fn parse_v6_suffix(i: &str) -> IResult<&str, &str> {
     let (i, _) = tag("(")(i)?;
-    let (i, entry) = is_not(")")(i)?;
-    let (i, _) = tag(")")(i)?;
-    Ok((i, entry))
+    let mut depth = 0;
+    for (idx, c) in i.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                if idx == 0 {
+                    break;
+                }
+                return Ok((&i[idx + 1..], &i[..idx]));
+            }
+            ')' => depth -= 1,
+            _ => (),
+        }
+    }
+    Err(nom::Err::Error(nom::error::Error::new(
+        i,
+        nom::error::ErrorKind::TakeUntil,
+    )))
 }
 
 fn parse_v6_suffixes(i: &str) -> IResult<&str, Vec<&str>> {
@@ -143,12 +181,23 @@ mod tests {
     #[test_case("/is-even@1.0.0_foobar", DepPath::new("is-even", "1.0.0").with_peer_suffix(Some("foobar")); "v6 dep path with suffix")]
     #[test_case("/foo@1.0.0(bar@1.0.0)(baz@1.0.0)", DepPath::new("foo", "1.0.0").with_peer_suffix(Some("(bar@1.0.0)(baz@1.0.0)")); "v6 with multiple peers")]
     #[test_case("/@babel/helper-string-parser@7.19.4(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@babel/core@7.21.0)", DepPath::new("@babel/helper-string-parser", "7.19.4").with_peer_suffix(Some("(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@babel/core@7.21.0)")); "v6 with scope")]
+    #[test_case("/foo@1.0.0(bar@1.0.0(baz@1.0.0))", DepPath::new("foo", "1.0.0").with_peer_suffix(Some("(bar@1.0.0(baz@1.0.0))")); "v6 with nested peers")]
     fn dep_path_parse_tests(s: &str, expected: DepPath) {
         let (rest, actual) = parse_dep_path(s).unwrap();
         assert_eq!(rest, "");
         assert_eq!(actual, expected);
     }
 
+    #[test_case("foo@1.0.0", DepPath::new("foo", "1.0.0"); "basic")]
+    #[test_case("@scope/foo@1.0.0", DepPath::new("@scope/foo", "1.0.0"); "scoped")]
+    #[test_case("next@14.0.0(react-dom@18.2.0(react@18.2.0))(react@18.2.0)", DepPath::new("next", "14.0.0").with_peer_suffix(Some("(react-dom@18.2.0(react@18.2.0))(react@18.2.0)")); "nested peers")]
+    #[test_case("lodash@4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)", DepPath::new("lodash", "4.17.21").with_peer_suffix(Some("(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)")); "patched")]
+    #[test_case("ui@file:packages/ui", DepPath::new("ui", "file:packages/ui"); "injected workspace")]
+    #[test_case("dashboard-icons@https://codeload.github.com/peerigon/dashboard-icons/tar.gz/ce27ef9", DepPath::new("dashboard-icons", "https://codeload.github.com/peerigon/dashboard-icons/tar.gz/ce27ef9"); "tarball")]
+    fn dep_path_v9_parse_tests(s: &str, expected: DepPath) {
+        assert_eq!(DepPath::parse_v9(s).unwrap(), expected);
+    }
+
     #[test_case("/@babel/helper-string-parser/7.19.4(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@babel/core@7.21.0)", Some("wjhgmpzh47qmycrzgpeyoyh3ce"); "v6 patch")]
     #[test_case("/foo/1.0.0_patchHash_peerHash", Some("patchHash"); "pre v6 patch")]
     #[test_case("/foo/1.0.0", None; "no suffix")]
@@ -2,7 +2,7 @@ use std::{any::Any, str::FromStr};
 
 use serde::Deserialize;
 
-use crate::Lockfile;
+use crate::{Lockfile, LockfileDiff};
 
 mod de;
 mod ser;
@@ -117,6 +117,19 @@ impl Lockfile for Yarn1Lockfile {
         // if the types don't match then we changed package managers
         any_other.downcast_ref::<Self>().is_none()
     }
+
+    fn diff(&self, other: &dyn Lockfile) -> LockfileDiff {
+        let any_other = other as &dyn Any;
+        let Some(other) = any_other.downcast_ref::<Self>() else {
+            return LockfileDiff::global();
+        };
+        LockfileDiff {
+            global_change: false,
+            changed: crate::diff_entries(&self.inner, &other.inner, |key, entry| {
+                crate::Package::new(key, &entry.version)
+            }),
+        }
+    }
 }
 
 pub fn yarn_subgraph(contents: &[u8], packages: &[String]) -> Result<Vec<u8>, crate::Error> {
@@ -176,4 +189,31 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_diff() {
+        let previous = Yarn1Lockfile::from_str(MINIMAL).unwrap();
+        let current = Yarn1Lockfile::from_str(
+            &MINIMAL
+                .replace(
+                    "nextjs/-/nextjs-0.0.3.tgz#4f4d",
+                    "nextjs/-/nextjs-0.0.3.tgz#0000",
+                )
+                .replace("\n    turbo-windows-arm64 \"1.9.3\"", ""),
+        )
+        .unwrap();
+
+        let diff = current.diff(&previous);
+        assert!(!diff.global_change);
+        let mut changed = diff.changed.into_iter().collect::<Vec<_>>();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![
+                crate::Package::new("nextjs@^0.0.3", "0.0.3"),
+                crate::Package::new("turbo@^1.9.3", "1.9.3"),
+            ]
+        );
+        assert!(current.diff(&current).is_empty());
+    }
 }
@@ -11,7 +11,7 @@ use turbopath::{
     RelativeUnixPathBuf,
 };
 use turborepo_graph_utils as graph;
-use turborepo_lockfiles::Lockfile;
+use turborepo_lockfiles::{Lockfile, LockfileGraph};
 
 use super::{PackageGraph, WorkspaceInfo, WorkspaceName, WorkspaceNode};
 use crate::{
@@ -328,6 +328,7 @@ impl<'a, T: PackageDiscovery> BuildState<'a, ResolvedPackageManager, T> {
             workspaces,
             lockfile,
             package_manager,
+            lockfile_graph: None,
         })
     }
 }
@@ -470,26 +471,25 @@ impl<'a, T: PackageDiscovery> BuildState<'a, ResolvedLockfile, T> {
             .collect()
     }
 
-    fn populate_transitive_dependencies(&mut self) -> Result<(), Error> {
+    fn populate_transitive_dependencies(&mut self) -> Result<Option<LockfileGraph>, Error> {
         let Some(lockfile) = self.lockfile.as_deref() else {
-            return Ok(());
+            return Ok(None);
         };
 
-        let mut closures = turborepo_lockfiles::all_transitive_closures(
-            lockfile,
-            self.all_external_dependencies()?,
-        )?;
+        let lockfile_graph = LockfileGraph::new(lockfile, self.all_external_dependencies()?)?;
+        let mut closures = lockfile_graph.all_transitive_closures();
         for (_, entry) in self.workspaces.iter_mut() {
             entry.transitive_dependencies = closures.remove(&entry.unix_dir_str()?);
         }
-        Ok(())
+        Ok(Some(lockfile_graph))
     }
 
     #[tracing::instrument(skip(self))]
     async fn build_inner(mut self) -> Result<PackageGraph, discovery::Error> {
-        if let Err(e) = self.populate_transitive_dependencies() {
+        let lockfile_graph = self.populate_transitive_dependencies().unwrap_or_else(|e| {
             warn!("Unable to calculate transitive closures: {}", e);
-        }
+            None
+        });
         let package_manager = self
             .package_discovery
             .discover_packages()
@@ -508,6 +508,7 @@ impl<'a, T: PackageDiscovery> BuildState<'a, ResolvedLockfile, T> {
             workspaces,
             package_manager,
             lockfile,
+            lockfile_graph,
         })
     }
 }
@@ -7,7 +7,7 @@ use petgraph::visit::{depth_first_search, Reversed};
 use serde::Serialize;
 use turbopath::{AbsoluteSystemPath, AnchoredSystemPath, AnchoredSystemPathBuf};
 use turborepo_graph_utils as graph;
-use turborepo_lockfiles::Lockfile;
+use turborepo_lockfiles::{Lockfile, LockfileGraph};
 
 use crate::{
     discovery::LocalPackageDiscoveryBuilder, package_json::PackageJson,
@@ -28,6 +28,7 @@ pub struct PackageGraph {
     workspaces: HashMap<WorkspaceName, WorkspaceInfo>,
     package_manager: PackageManager,
     lockfile: Option<Box<dyn Lockfile>>,
+    lockfile_graph: Option<LockfileGraph>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -211,6 +212,28 @@ impl PackageGraph {
         )
     }
 
+    /// Returns every dependency between workspaces as a pair of the
+    /// dependent workspace and the workspace it depends on. The edges to the
+    /// synthetic root node are left out.
+    ///
+    /// Example:
+    ///
+    /// a -> b -> c
+    ///
+    /// dependency_edges() -> [(a, b), (b, c)]
+    pub fn dependency_edges(&self) -> impl Iterator<Item = (&WorkspaceName, &WorkspaceName)> + '_ {
+        self.workspace_graph.edge_indices().filter_map(|edge| {
+            let (from, to) = self
+                .workspace_graph
+                .edge_endpoints(edge)
+                .expect("edge index from graph should be present");
+            match (&self.workspace_graph[from], &self.workspace_graph[to]) {
+                (WorkspaceNode::Workspace(from), WorkspaceNode::Workspace(to)) => Some((from, to)),
+                _ => None,
+            }
+        })
+    }
+
     /// For a given workspace in the repo, returns the set of workspaces
     /// that this one depends on, excluding those that are unresolved.
     ///
@@ -300,6 +323,19 @@ impl PackageGraph {
             .collect()
     }
 
+    /// Returns the shortest chain of external dependencies that causes
+    /// `package` to be installed for a workspace. `package` can be either a
+    /// package name or a lockfile key.
+    pub fn why_external_dependency(
+        &self,
+        workspace: &WorkspaceName,
+        package: &str,
+    ) -> Option<Vec<&turborepo_lockfiles::Package>> {
+        let lockfile_graph = self.lockfile_graph.as_ref()?;
+        let info = self.workspaces.get(workspace)?;
+        lockfile_graph.why(info.package_path().to_unix().as_str(), package)
+    }
+
     /// Returns a list of changed packages based on the contents of a previous
     /// `Lockfile`. This assumes that none of the package.json in the workspace
     /// change, it is the responsibility of the caller to verify this.
@@ -309,6 +345,11 @@ impl PackageGraph {
     ) -> Result<Vec<WorkspaceName>, ChangedPackagesError> {
         let current = self.lockfile().ok_or(ChangedPackagesError::NoLockfile)?;
 
+        let diff = current.diff(previous);
+        if diff.global_change {
+            return Ok(self.workspaces.keys().cloned().collect());
+        }
+
         let external_deps = self
             .workspaces()
             .filter_map(|(_name, info)| {
@@ -325,25 +366,27 @@ impl PackageGraph {
 
         let closures = turborepo_lockfiles::all_transitive_closures(previous, external_deps)?;
 
-        let global_change = current.global_change(previous);
-
-        let changed = if global_change {
-            None
-        } else {
-            self.workspaces
-                .iter()
-                .filter(|(_name, info)| {
-                    closures.get(info.package_path().to_unix().as_str())
-                        != info.transitive_dependencies.as_ref()
-                })
-                .map(|(name, _info)| match name {
-                    WorkspaceName::Other(n) => Some(WorkspaceName::Other(n.to_owned())),
-                    // if the root package has changed, then we should report `None`
-                    // since all packages need to be revalidated
-                    WorkspaceName::Root => None,
-                })
-                .collect::<Option<Vec<WorkspaceName>>>()
-        };
+        let changed = self
+            .workspaces
+            .iter()
+            .filter(|(_name, info)| {
+                let previous_closure = closures.get(info.package_path().to_unix().as_str());
+                let current_closure = info.transitive_dependencies.as_ref();
+                // A workspace changes if it now resolves to a different set of packages
+                // or if one of the packages it depends on had its entry changed.
+                previous_closure != current_closure
+                    || previous_closure
+                        .into_iter()
+                        .chain(current_closure)
+                        .any(|closure| diff.affects(closure))
+            })
+            .map(|(name, _info)| match name {
+                WorkspaceName::Other(n) => Some(WorkspaceName::Other(n.to_owned())),
+                // if the root package has changed, then we should report `None`
+                // since all packages need to be revalidated
+                WorkspaceName::Root => None,
+            })
+            .collect::<Option<Vec<WorkspaceName>>>();
 
         Ok(changed.unwrap_or_else(|| self.workspaces.keys().cloned().collect()))
     }
@@ -507,6 +550,44 @@ mod test {
         assert_eq!(pkg_version, "1.2.3");
     }
 
+    #[tokio::test]
+    async fn test_dependency_edges() {
+        let root =
+            AbsoluteSystemPathBuf::new(if cfg!(windows) { r"C:\repo" } else { "/repo" }).unwrap();
+        let pkg_graph = PackageGraph::builder(
+            &root,
+            PackageJson::from_value(json!({ "name": "root" })).unwrap(),
+        )
+        .with_package_discovery(MockDiscovery)
+        .with_package_jsons(Some({
+            let mut map = HashMap::new();
+            map.insert(
+                root.join_component("package_a"),
+                PackageJson::from_value(json!({
+                    "name": "a",
+                    "dependencies": { "b": "workspace:*" }
+                }))
+                .unwrap(),
+            );
+            map.insert(
+                root.join_component("package_b"),
+                PackageJson::from_value(json!({ "name": "b" })).unwrap(),
+            );
+            map
+        }))
+        .build()
+        .await
+        .unwrap();
+
+        // Workspaces without internal dependencies depend on the root node,
+        // which isn't an edge between workspaces
+        let edges = pkg_graph
+            .dependency_edges()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(edges, vec![("a".to_string(), "b".to_string())]);
+    }
+
     #[derive(Debug)]
     struct MockLockfile {}
     impl turborepo_lockfiles::Lockfile for MockLockfile {
@@ -563,6 +644,10 @@ mod test {
         fn global_change(&self, _other: &dyn Lockfile) -> bool {
             unreachable!("global change detection not necessary for package graph construction")
         }
+
+        fn diff(&self, _other: &dyn Lockfile) -> turborepo_lockfiles::LockfileDiff {
+            turborepo_lockfiles::LockfileDiff::default()
+        }
     }
 
     #[tokio::test]
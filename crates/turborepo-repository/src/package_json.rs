@@ -30,6 +30,8 @@ pub struct PackageJson {
     pub resolutions: Option<BTreeMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pnpm: Option<PnpmConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
     // Unstructured fields kept for round trip capabilities
     #[serde(flatten)]
     pub other: BTreeMap<String, Value>,
@@ -88,6 +90,7 @@ mod test {
     #[test_case(json!({"name": "foo", "resolutions": {"foo": "1.0.0"}}) ; "berry resolutions")]
     #[test_case(json!({"name": "foo", "pnpm": {"patchedDependencies": {"some-pkg": "./patchfile"}, "another-field": 1}}) ; "pnpm")]
     #[test_case(json!({"name": "foo", "pnpm": {"another-field": 1}}) ; "pnpm without patches")]
+    #[test_case(json!({"name": "foo", "tags": ["ui", "shared"]}) ; "tags")]
     fn test_roundtrip(json: Value) {
         let package_json: PackageJson = serde_json::from_value(json.clone()).unwrap();
         let actual = serde_json::to_value(package_json).unwrap();
@@ -33,6 +33,27 @@ impl SCM {
         }
     }
 
+    /// Finds the best common ancestor of two commits. This fails if the
+    /// history of a shallow clone doesn't reach it.
+    pub fn merge_base(
+        &self,
+        base: &str,
+        head: &str,
+        path: &AbsoluteSystemPath,
+    ) -> Result<String, Error> {
+        match self {
+            Self::Git(git) => git.merge_base(base, head),
+            Self::Manual => Err(Error::GitRequired(path.to_owned())),
+        }
+    }
+
+    pub fn is_shallow(&self, path: &AbsoluteSystemPath) -> Result<bool, Error> {
+        match self {
+            Self::Git(git) => git.is_shallow(),
+            Self::Manual => Err(Error::GitRequired(path.to_owned())),
+        }
+    }
+
     pub fn previous_content(
         &self,
         from_commit: &str,
@@ -92,6 +113,18 @@ impl Git {
         Ok(output.trim().to_owned())
     }
 
+    fn merge_base(&self, base: &str, head: &str) -> Result<String, Error> {
+        let output = self.execute_git_command(&["merge-base", base, head], "")?;
+        let output = String::from_utf8(output)?;
+        Ok(output.trim().to_owned())
+    }
+
+    fn is_shallow(&self) -> Result<bool, Error> {
+        let output = self.execute_git_command(&["rev-parse", "--is-shallow-repository"], "")?;
+        let output = String::from_utf8(output)?;
+        Ok(output.trim() == "true")
+    }
+
     fn changed_files(
         &self,
         turbo_root: &AbsoluteSystemPath,
@@ -236,7 +269,7 @@ mod tests {
     use which::which;
 
     use super::previous_content;
-    use crate::{git::changed_files, Error};
+    use crate::{git::changed_files, Error, SCM};
 
     fn setup_repository() -> Result<(TempDir, Repository), Error> {
         let repo_root = tempfile::tempdir()?;
@@ -382,6 +415,18 @@ mod tests {
 
         assert_eq!(merge_base, second_commit_oid);
 
+        let git_root = AbsoluteSystemPathBuf::try_from(repo_root.path())?;
+        let scm = SCM::new(&git_root);
+        assert_eq!(
+            scm.merge_base(
+                &third_commit_oid.to_string(),
+                &fourth_commit_oid.to_string(),
+                &git_root
+            )?,
+            second_commit_oid.to_string()
+        );
+        assert!(!scm.is_shallow(&git_root)?);
+
         let files = changed_files(
             repo_root.path().to_path_buf(),
             repo_root.path().to_path_buf(),
@@ -2,12 +2,33 @@ use std::collections::HashMap;
 
 use itertools::{Either, Itertools};
 use tracing::debug;
-use turbopath::{AbsoluteSystemPath, AnchoredSystemPath, PathError, RelativeUnixPathBuf};
+use turbopath::{
+    AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPath, PathError, RelativeUnixPathBuf,
+};
 
 use crate::{hash_object::hash_objects, Error, Git, SCM};
 
 pub type GitHashes = HashMap<RelativeUnixPathBuf, String>;
 
+/// Checks paths against the ignore rules of a git repository. The repository
+/// is only opened once so that it can be asked about many paths.
+pub struct GitIgnore {
+    root: AbsoluteSystemPathBuf,
+    repo: git2::Repository,
+}
+
+impl GitIgnore {
+    pub fn is_ignored(&self, path: &AbsoluteSystemPath) -> bool {
+        let Ok(relative_path) = self.root.anchor(path) else {
+            // Paths outside of the repository can't be part of a package
+            return true;
+        };
+        self.repo
+            .is_path_ignored(relative_path.as_path())
+            .unwrap_or(false)
+    }
+}
+
 impl SCM {
     pub fn get_hashes_for_files(
         &self,
@@ -51,6 +72,24 @@ impl SCM {
         }
     }
 
+    /// Returns a checker for ignored files, or `None` if we aren't in a git
+    /// repository
+    pub fn git_ignore(&self) -> Option<GitIgnore> {
+        match self {
+            SCM::Manual => None,
+            SCM::Git(git) => match git2::Repository::open(&git.root) {
+                Ok(repo) => Some(GitIgnore {
+                    root: git.root.clone(),
+                    repo,
+                }),
+                Err(e) => {
+                    debug!("failed to open git repository: {}", e);
+                    None
+                }
+            },
+        }
+    }
+
     pub fn hash_files(
         &self,
         turbo_root: &AbsoluteSystemPath,
@@ -252,6 +291,23 @@ mod tests {
         assert!(manual_hashes.is_empty());
     }
 
+    #[test]
+    fn test_git_ignore() {
+        let (_repo_root_tmp, repo_root) = tmp_dir();
+        setup_repository(&repo_root);
+        repo_root
+            .join_component(".gitignore")
+            .create_with_contents("dist\n*.log\n")
+            .unwrap();
+        let my_pkg_dir = repo_root.join_component("my-pkg");
+        my_pkg_dir.create_dir_all().unwrap();
+
+        let git_ignore = SCM::new(&repo_root).git_ignore().unwrap();
+        assert!(git_ignore.is_ignored(&my_pkg_dir.join_components(&["dist", "index.js"])));
+        assert!(git_ignore.is_ignored(&my_pkg_dir.join_component("debug.log")));
+        assert!(!git_ignore.is_ignored(&my_pkg_dir.join_component("index.js")));
+    }
+
     #[test]
     fn test_get_package_deps_fallback() {
         let (_repo_root_tmp, repo_root) = tmp_dir();
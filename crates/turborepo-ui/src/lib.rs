@@ -6,6 +6,7 @@ mod color_selector;
 mod logs;
 mod output;
 mod prefixed;
+pub mod tui;
 
 use std::{borrow::Cow, env, f64::consts::PI, io, time::Duration};
 
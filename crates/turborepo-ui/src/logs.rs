@@ -1,6 +1,6 @@
 use std::{
     fs::File,
-    io::{BufRead, BufReader, BufWriter, Write},
+    io::{BufRead, BufReader, BufWriter, Seek, Write},
 };
 
 use tracing::{debug, warn};
@@ -46,6 +46,19 @@ impl<W: Write> LogWriter<W> {
     pub fn with_prefixed_writer(&mut self, prefixed_writer: PrefixedWriter<W>) {
         self.prefixed_writer = Some(prefixed_writer);
     }
+
+    /// Discards everything written to the log file so far, output that was
+    /// already passed to the prefixed writer is unaffected
+    pub fn truncate_log_file(&mut self) -> Result<(), Error> {
+        let Some(log_file) = &mut self.log_file else {
+            return Ok(());
+        };
+        log_file.flush().map_err(Error::CannotWriteLogs)?;
+        let file = log_file.get_mut();
+        file.set_len(0).map_err(Error::CannotWriteLogs)?;
+        file.rewind().map_err(Error::CannotWriteLogs)?;
+        Ok(())
+    }
 }
 
 impl<W: Write> Write for LogWriter<W> {
@@ -170,6 +183,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_truncate_log_file() -> Result<()> {
+        let dir = tempdir()?;
+        let log_file_path = AbsoluteSystemPathBuf::try_from(dir.path().join("test.txt"))?;
+        let mut prefixed_writer_output = Vec::new();
+        let mut log_writer = LogWriter::default();
+        let ui = UI::new(false);
+
+        log_writer.with_log_file(&log_file_path)?;
+        log_writer.with_prefixed_writer(PrefixedWriter::new(
+            ui,
+            CYAN.apply_to(">".to_string()),
+            &mut prefixed_writer_output,
+        ));
+
+        writeln!(log_writer, "first attempt")?;
+        log_writer.truncate_log_file()?;
+        writeln!(log_writer, "second attempt")?;
+        log_writer.flush()?;
+
+        assert_eq!(log_file_path.read_to_string()?, "second attempt\n");
+        assert_eq!(
+            String::from_utf8(prefixed_writer_output)?,
+            "\u{1b}[36m>\u{1b}[0mfirst attempt\n\u{1b}[36m>\u{1b}[0msecond attempt\n"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_replay_logs() -> Result<()> {
         let ui = UI::new(false);
@@ -0,0 +1,461 @@
+use std::{
+    io::{self, Stdout},
+    sync::mpsc::TryRecvError,
+    time::Duration,
+};
+
+use crossterm::{
+    event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+
+use super::{
+    event::Event,
+    handle::AppReceiver,
+    task::{Task, TaskStatus},
+    Error,
+};
+
+const FRAME_RATE: Duration = Duration::from_millis(50);
+// Room for the status symbol, duration and borders next to the task name
+const SIDEBAR_PADDING: usize = 14;
+
+struct App {
+    tasks: Vec<Task>,
+    selected: usize,
+    // First line of output shown, `None` follows the end of the output
+    scroll: Option<usize>,
+    // Height of the output pane the last time it was drawn
+    pane_height: usize,
+    // Whether keys are sent to the stdin of the selected task
+    interactive: bool,
+}
+
+/// A terminal that has been switched to the alternate screen for the app
+pub struct AppTerminal(Terminal<CrosstermBackend<Stdout>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    // The run finished
+    Finished,
+    // The user asked to stop the run
+    Interrupted,
+}
+
+/// Takes over the terminal so the app can be run in it. This is separate from
+/// `run_app` so callers can fall back to plain output if it fails.
+pub fn startup() -> Result<AppTerminal, Error> {
+    enable_raw_mode()?;
+    match alternate_screen() {
+        Ok(terminal) => Ok(AppTerminal(terminal)),
+        Err(e) => {
+            // Don't leave the terminal in raw mode for whatever prints next
+            disable_raw_mode()?;
+            Err(e.into())
+        }
+    }
+}
+
+/// Shows the progress of `tasks` in `terminal` until the sender stops the
+/// app. `on_interrupt` is called if the user stops the run instead.
+pub fn run_app(
+    AppTerminal(mut terminal): AppTerminal,
+    tasks: Vec<String>,
+    receiver: AppReceiver,
+    on_interrupt: impl FnOnce(),
+) -> Result<(), Error> {
+    let mut app = App::new(tasks);
+    let result = run_app_inner(&mut terminal, &mut app, &receiver);
+    cleanup(terminal)?;
+
+    if result? == Exit::Interrupted {
+        on_interrupt();
+    }
+    Ok(())
+}
+
+fn run_app_inner(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    receiver: &AppReceiver,
+) -> Result<Exit, Error> {
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(Event::Stop) | Err(TryRecvError::Disconnected) => return Ok(Exit::Finished),
+                Ok(event) => app.handle_event(event),
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        terminal.draw(|frame| view(app, frame))?;
+
+        if event::poll(FRAME_RATE)? {
+            if let TermEvent::Key(key) = event::read()? {
+                // Windows also reports key releases
+                if key.kind == KeyEventKind::Press && app.handle_key(key) {
+                    return Ok(Exit::Interrupted);
+                }
+            }
+        }
+    }
+}
+
+fn alternate_screen() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    terminal.hide_cursor()?;
+    Ok(terminal)
+}
+
+fn cleanup(mut terminal: Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+impl App {
+    fn new(tasks: Vec<String>) -> Self {
+        Self {
+            tasks: tasks.into_iter().map(Task::new).collect(),
+            selected: 0,
+            scroll: None,
+            pane_height: 0,
+            interactive: false,
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::StartTask { task } => self.task(task).start(),
+            Event::TaskOutput { task, output } => self.task(task).push_output(&output),
+            Event::SetStdin { task, stdin } => self.task(task).set_stdin(stdin),
+            Event::EndTask {
+                task,
+                result,
+                log_file,
+            } => self.task(task).finish(result, log_file),
+            // Handled by the event loop
+            Event::Stop => (),
+        }
+        if self.interactive && !self.selected_task().map_or(false, Task::is_interactive) {
+            self.interactive = false;
+        }
+    }
+
+    /// Handles a key press, returning true if the run should be stopped
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            return true;
+        }
+
+        if self.interactive {
+            if ctrl && key.code == KeyCode::Char('z') {
+                self.interactive = false;
+            } else if let Some(input) = key_input(key) {
+                let selected = self.selected;
+                if let Some(task) = self.tasks.get_mut(selected) {
+                    self.interactive = task.write_stdin(&input);
+                }
+            }
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1),
+            KeyCode::Char('f') => self.next_failure(),
+            KeyCode::PageUp | KeyCode::Char('u') => self.scroll_up(),
+            KeyCode::PageDown | KeyCode::Char('d') => self.scroll_down(),
+            KeyCode::End | KeyCode::Char('G') => self.scroll = None,
+            KeyCode::Enter | KeyCode::Char('i') => {
+                self.interactive = self.selected_task().map_or(false, Task::is_interactive);
+            }
+            _ => (),
+        }
+        false
+    }
+
+    // Tasks that weren't known when the app started are added as they're seen
+    fn task(&mut self, name: String) -> &mut Task {
+        let index = match self.tasks.iter().position(|task| task.name() == name) {
+            Some(index) => index,
+            None => {
+                self.tasks.push(Task::new(name));
+                self.tasks.len() - 1
+            }
+        };
+        &mut self.tasks[index]
+    }
+
+    fn selected_task(&self) -> Option<&Task> {
+        self.tasks.get(self.selected)
+    }
+
+    fn select(&mut self, index: usize) {
+        if index < self.tasks.len() && index != self.selected {
+            self.selected = index;
+            self.scroll = None;
+        }
+    }
+
+    // Selects the next failed task after the selected one, wrapping around
+    fn next_failure(&mut self) {
+        let failure = (1..=self.tasks.len())
+            .map(|offset| (self.selected + offset) % self.tasks.len())
+            .find(|index| self.tasks[*index].status() == TaskStatus::Failed);
+        if let Some(index) = failure {
+            self.select(index);
+        }
+    }
+
+    fn last_page(&self) -> usize {
+        let lines = self
+            .selected_task()
+            .map_or(0, |task| task.output().line_count());
+        lines.saturating_sub(self.pane_height)
+    }
+
+    fn scroll_up(&mut self) {
+        let top = self.scroll.unwrap_or_else(|| self.last_page());
+        self.scroll = Some(top.saturating_sub(self.pane_height.max(1)));
+    }
+
+    fn scroll_down(&mut self) {
+        let Some(top) = self.scroll else {
+            return;
+        };
+        let top = top + self.pane_height.max(1);
+        self.scroll = (top < self.last_page()).then_some(top);
+    }
+}
+
+// The bytes a terminal would send for a key
+fn key_input(key: KeyEvent) -> Option<Vec<u8>> {
+    match key.code {
+        KeyCode::Char(c) => Some(c.to_string().into_bytes()),
+        KeyCode::Enter => Some(b"\n".to_vec()),
+        KeyCode::Tab => Some(b"\t".to_vec()),
+        KeyCode::Backspace => Some(vec![0x7f]),
+        KeyCode::Esc => Some(vec![0x1b]),
+        KeyCode::Up => Some(b"\x1b[A".to_vec()),
+        KeyCode::Down => Some(b"\x1b[B".to_vec()),
+        KeyCode::Right => Some(b"\x1b[C".to_vec()),
+        KeyCode::Left => Some(b"\x1b[D".to_vec()),
+        _ => None,
+    }
+}
+
+fn view(app: &mut App, frame: &mut Frame) {
+    let [main, help] = {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.size());
+        [rows[0], rows[1]]
+    };
+    let sidebar_width = app
+        .tasks
+        .iter()
+        .map(|task| task.name().len() + SIDEBAR_PADDING)
+        .max()
+        .unwrap_or_default()
+        .min(usize::from(main.width / 2));
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(sidebar_width as u16), Constraint::Min(0)])
+        .split(main);
+
+    let items = app
+        .tasks
+        .iter()
+        .map(|task| {
+            let (symbol, color) = match task.status() {
+                TaskStatus::Pending => ("·", Color::DarkGray),
+                TaskStatus::Running => ("»", Color::Yellow),
+                TaskStatus::Succeeded => ("✔", Color::Green),
+                TaskStatus::Cached => ("⚡", Color::Cyan),
+                TaskStatus::Failed => ("✘", Color::Red),
+                TaskStatus::Canceled => ("-", Color::DarkGray),
+            };
+            let duration = task
+                .duration()
+                .map(|duration| format!(" {:.1}s", duration.as_secs_f64()))
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{symbol} "), Style::default().fg(color)),
+                Span::raw(task.name().to_string()),
+                Span::styled(duration, Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(" Tasks "))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, columns[0], &mut list_state);
+
+    // Leave room for the borders
+    app.pane_height = usize::from(columns[1].height.saturating_sub(2));
+    let top = app.scroll.unwrap_or_else(|| app.last_page());
+    let (title, lines) = match app.selected_task() {
+        Some(task) => (
+            match app.interactive {
+                true => format!(" {} (interactive, ctrl-z to stop) ", task.name()),
+                false => format!(" {} ", task.name()),
+            },
+            task.output()
+                .lines()
+                .skip(top)
+                .take(app.pane_height)
+                .map(Line::from)
+                .collect::<Vec<_>>(),
+        ),
+        None => (String::new(), Vec::new()),
+    };
+    let output = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(output, columns[1]);
+
+    let help_text = match app.interactive {
+        true => "ctrl-z stop interacting  ctrl-c quit",
+        false => "↑/↓ select  f next failure  pgup/pgdn scroll  end follow  enter interact  q quit",
+    };
+    frame.render_widget(
+        Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+        help,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::tui::TaskResult;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn app(tasks: &[&str]) -> App {
+        App::new(tasks.iter().map(|task| task.to_string()).collect())
+    }
+
+    fn end_task(app: &mut App, task: &str, result: TaskResult) {
+        app.handle_event(Event::StartTask { task: task.into() });
+        app.handle_event(Event::EndTask {
+            task: task.into(),
+            result,
+            log_file: None,
+        });
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let mut app = app(&["a#build", "b#build"]);
+        app.handle_key(key(KeyCode::Up));
+        assert_eq!(app.selected, 0);
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(app.selected, 1);
+        assert!(app.handle_key(key(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn test_next_failure() {
+        let mut app = app(&["a#build", "b#build", "c#build", "d#build"]);
+        end_task(&mut app, "a#build", TaskResult::Failure);
+        end_task(&mut app, "b#build", TaskResult::Success);
+        end_task(&mut app, "c#build", TaskResult::Failure);
+
+        app.handle_key(key(KeyCode::Char('f')));
+        assert_eq!(app.selected, 2);
+        app.handle_key(key(KeyCode::Char('f')));
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn test_tasks_added_when_seen() {
+        let mut app = app(&["a#build"]);
+        end_task(&mut app, "b#build", TaskResult::CacheHit);
+        assert_eq!(app.tasks.len(), 2);
+        assert_eq!(app.tasks[1].status(), TaskStatus::Cached);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut app = app(&["a#build"]);
+        app.pane_height = 10;
+        let output = (0..25).map(|i| format!("{i}\n")).collect::<String>();
+        app.handle_event(Event::TaskOutput {
+            task: "a#build".into(),
+            output: output.into_bytes(),
+        });
+
+        app.handle_key(key(KeyCode::PageUp));
+        assert_eq!(app.scroll, Some(5));
+        app.handle_key(key(KeyCode::PageUp));
+        assert_eq!(app.scroll, Some(0));
+        app.handle_key(key(KeyCode::PageDown));
+        assert_eq!(app.scroll, Some(10));
+        app.handle_key(key(KeyCode::PageDown));
+        assert_eq!(app.scroll, None);
+    }
+
+    #[test]
+    fn test_interactive() {
+        let mut app = app(&["web#dev"]);
+        let stdin = SharedBuffer::default();
+        app.handle_key(key(KeyCode::Enter));
+        assert!(
+            !app.interactive,
+            "tasks without stdin can't be interacted with"
+        );
+
+        app.handle_event(Event::StartTask {
+            task: "web#dev".into(),
+        });
+        app.handle_event(Event::SetStdin {
+            task: "web#dev".into(),
+            stdin: Box::new(stdin.clone()),
+        });
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.interactive);
+
+        app.handle_key(key(KeyCode::Char('q')));
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(stdin.0.lock().unwrap().as_slice(), b"q\n");
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert!(!app.interactive);
+        assert!(app.handle_key(key(KeyCode::Char('q'))));
+    }
+}
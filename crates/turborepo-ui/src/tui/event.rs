@@ -0,0 +1,34 @@
+use std::io::Write;
+
+use turbopath::AbsoluteSystemPathBuf;
+
+pub(crate) enum Event {
+    StartTask {
+        task: String,
+    },
+    TaskOutput {
+        task: String,
+        output: Vec<u8>,
+    },
+    SetStdin {
+        task: String,
+        stdin: Box<dyn Write + Send>,
+    },
+    EndTask {
+        task: String,
+        result: TaskResult,
+        // The log file of the task, if it was written during this run
+        log_file: Option<AbsoluteSystemPathBuf>,
+    },
+    Stop,
+}
+
+/// How a task finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskResult {
+    Success,
+    CacheHit,
+    Failure,
+    // The task was stopped before it finished
+    Canceled,
+}
@@ -0,0 +1,108 @@
+use std::{
+    io::{self, Write},
+    sync::mpsc,
+};
+
+use turbopath::AbsoluteSystemPathBuf;
+
+use super::event::{Event, TaskResult};
+
+/// Sends updates about the run to the TUI. Updates are dropped if the TUI has
+/// already exited.
+#[derive(Debug, Clone)]
+pub struct AppSender {
+    primary: mpsc::Sender<Event>,
+}
+
+/// Receives the updates that an `AppSender` sends
+pub struct AppReceiver {
+    primary: mpsc::Receiver<Event>,
+}
+
+/// Handle for sending updates about a single task to the TUI
+#[derive(Debug, Clone)]
+pub struct TuiTask {
+    name: String,
+    handle: AppSender,
+}
+
+/// Writer that sends everything written to it to the TUI as output of a task
+#[derive(Debug, Clone)]
+pub struct TaskOutput {
+    name: String,
+    handle: AppSender,
+}
+
+impl AppSender {
+    pub fn new() -> (AppSender, AppReceiver) {
+        let (primary, receiver) = mpsc::channel();
+        (AppSender { primary }, AppReceiver { primary: receiver })
+    }
+
+    pub fn task(&self, name: String) -> TuiTask {
+        TuiTask {
+            name,
+            handle: self.clone(),
+        }
+    }
+
+    /// Tells the TUI that the run is over so it gives up the terminal
+    pub fn stop(&self) {
+        self.send(Event::Stop);
+    }
+
+    fn send(&self, event: Event) {
+        self.primary.send(event).ok();
+    }
+}
+
+impl AppReceiver {
+    pub(crate) fn try_recv(&self) -> Result<Event, mpsc::TryRecvError> {
+        self.primary.try_recv()
+    }
+}
+
+impl TuiTask {
+    pub fn start(&self) {
+        self.handle.send(Event::StartTask {
+            task: self.name.clone(),
+        });
+    }
+
+    pub fn output(&self) -> TaskOutput {
+        TaskOutput {
+            name: self.name.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+
+    /// Lets input typed into the TUI be sent to the task
+    pub fn set_stdin(&self, stdin: Box<dyn Write + Send>) {
+        self.handle.send(Event::SetStdin {
+            task: self.name.clone(),
+            stdin,
+        });
+    }
+
+    pub fn finish(&self, result: TaskResult, log_file: Option<AbsoluteSystemPathBuf>) {
+        self.handle.send(Event::EndTask {
+            task: self.name.clone(),
+            result,
+            log_file,
+        });
+    }
+}
+
+impl Write for TaskOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.handle.send(Event::TaskOutput {
+            task: self.name.clone(),
+            output: buf.to_vec(),
+        });
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
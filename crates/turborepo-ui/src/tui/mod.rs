@@ -0,0 +1,17 @@
+//! A full screen terminal UI for `turbo run`. Tasks are listed in a sidebar
+//! along with their status, and the output of the focused task is shown next
+//! to it.
+mod app;
+mod event;
+mod handle;
+mod task;
+
+pub use app::{run_app, startup, AppTerminal};
+pub use event::TaskResult;
+pub use handle::{AppReceiver, AppSender, TaskOutput, TuiTask};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unable to draw terminal UI: {0}")]
+    Io(#[from] std::io::Error),
+}
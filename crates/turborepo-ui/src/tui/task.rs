@@ -0,0 +1,197 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+use turbopath::AbsoluteSystemPathBuf;
+
+use super::event::TaskResult;
+
+// Output of running tasks is kept in memory, once a task finishes its output
+// is read back from its log file instead
+const MAX_BUFFERED_LINES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TaskStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Cached,
+    Failed,
+    Canceled,
+}
+
+pub(crate) struct Task {
+    name: String,
+    status: TaskStatus,
+    started_at: Option<Instant>,
+    duration: Option<Duration>,
+    output: OutputBuffer,
+    stdin: Option<Box<dyn Write + Send>>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct OutputBuffer {
+    lines: VecDeque<String>,
+    // Output after the last newline
+    partial: Vec<u8>,
+}
+
+impl Task {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            status: TaskStatus::Pending,
+            started_at: None,
+            duration: None,
+            output: OutputBuffer::default(),
+            stdin: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        self.status
+    }
+
+    pub fn output(&self) -> &OutputBuffer {
+        &self.output
+    }
+
+    /// How long the task has been running, or how long it took if it finished
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration.or_else(|| Some(self.started_at?.elapsed()))
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.status == TaskStatus::Running && self.stdin.is_some()
+    }
+
+    pub fn start(&mut self) {
+        self.status = TaskStatus::Running;
+        self.started_at = Some(Instant::now());
+    }
+
+    pub fn push_output(&mut self, output: &[u8]) {
+        self.output.push(output);
+    }
+
+    pub fn set_stdin(&mut self, stdin: Box<dyn Write + Send>) {
+        self.stdin = Some(stdin);
+    }
+
+    /// Sends input to the task, returning false if the task can't receive it
+    pub fn write_stdin(&mut self, input: &[u8]) -> bool {
+        let Some(stdin) = &mut self.stdin else {
+            return false;
+        };
+        if let Err(e) = stdin.write_all(input).and_then(|()| stdin.flush()) {
+            debug!("unable to write to stdin of {}: {e}", self.name);
+            self.stdin = None;
+            return false;
+        }
+        true
+    }
+
+    pub fn finish(&mut self, result: TaskResult, log_file: Option<AbsoluteSystemPathBuf>) {
+        self.status = match result {
+            TaskResult::Success => TaskStatus::Succeeded,
+            TaskResult::CacheHit => TaskStatus::Cached,
+            TaskResult::Failure => TaskStatus::Failed,
+            TaskResult::Canceled => TaskStatus::Canceled,
+        };
+        self.duration = Some(
+            self.started_at
+                .map_or(Duration::ZERO, |started_at| started_at.elapsed()),
+        );
+        self.stdin = None;
+
+        // The log file has the complete output of the task, including any output
+        // that was dropped from the buffer or hidden by `outputLogs`
+        if let Some(log_file) = log_file {
+            match log_file.read() {
+                Ok(logs) => {
+                    let mut output = OutputBuffer::default();
+                    output.push(&logs);
+                    self.output = output;
+                }
+                Err(e) => debug!("unable to read log file {log_file}: {e}"),
+            }
+        }
+    }
+}
+
+impl OutputBuffer {
+    pub fn push(&mut self, output: &[u8]) {
+        self.partial.extend_from_slice(output);
+        while let Some(newline) = self.partial.iter().position(|byte| *byte == b'\n') {
+            let line = self.partial.drain(..=newline).collect::<Vec<_>>();
+            let line = Self::render_line(&line[..line.len() - 1]);
+            self.lines.push_back(line);
+            if self.lines.len() > MAX_BUFFERED_LINES {
+                self.lines.pop_front();
+            }
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty())
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines
+            .iter()
+            .cloned()
+            .chain((!self.partial.is_empty()).then(|| Self::render_line(&self.partial)))
+    }
+
+    // Colors aren't kept, and a carriage return overwrites the line like it
+    // would in a terminal
+    fn render_line(line: &[u8]) -> String {
+        let line = String::from_utf8_lossy(line);
+        let line = console::strip_ansi_codes(&line);
+        let line = line.trim_end_matches('\r');
+        line.rsplit('\r').next().unwrap_or_default().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_output_buffer() {
+        let mut output = OutputBuffer::default();
+        output.push(b"\x1b[32mcompiled\x1b[0m\nprogress 10%");
+        output.push(b"\rprogress 100%\r\ndone");
+        assert_eq!(output.line_count(), 3);
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec!["compiled", "progress 100%", "done"]
+        );
+    }
+
+    #[test]
+    fn test_finish_reads_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_file = AbsoluteSystemPathBuf::try_from(dir.path())
+            .unwrap()
+            .join_component("turbo-build.log");
+        log_file.create_with_contents("first\nsecond\n").unwrap();
+
+        let mut task = Task::new("web#build".into());
+        task.start();
+        task.push_output(b"second\n");
+        task.finish(TaskResult::CacheHit, Some(log_file));
+        assert_eq!(task.status(), TaskStatus::Cached);
+        assert_eq!(
+            task.output().lines().collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+    }
+}
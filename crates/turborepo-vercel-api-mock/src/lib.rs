@@ -12,8 +12,9 @@ use axum::{
 use futures_util::StreamExt;
 use tokio::sync::Mutex;
 use turborepo_vercel_api::{
-    AnalyticsEvent, CachingStatus, CachingStatusResponse, Membership, Role, Space, SpaceRun,
-    SpacesResponse, Team, TeamsResponse, User, UserResponse, VerificationResponse,
+    AnalyticsEvent, ArtifactInfo, ArtifactQueryResult, ArtifactsQueryRequest,
+    ArtifactsQueryResponse, CachingStatus, CachingStatusResponse, Membership, Role, Space,
+    SpaceRun, SpacesResponse, Team, TeamsResponse, User, UserResponse, VerificationResponse,
 };
 
 pub const EXPECTED_TOKEN: &str = "expected_token";
@@ -39,8 +40,12 @@ pub async fn start_test_server(port: u16) -> Result<()> {
     let get_durations_ref = Arc::new(Mutex::new(HashMap::new()));
     let head_durations_ref = get_durations_ref.clone();
     let put_durations_ref = get_durations_ref.clone();
+    let query_durations_ref = get_durations_ref.clone();
+    let get_tags_ref = Arc::new(Mutex::new(HashMap::new()));
+    let put_tags_ref = get_tags_ref.clone();
     let put_tempdir_ref = Arc::new(tempfile::tempdir()?);
     let get_tempdir_ref = put_tempdir_ref.clone();
+    let query_tempdir_ref = put_tempdir_ref.clone();
 
     let get_analytics_events_ref = Arc::new(Mutex::new(Vec::new()));
     let post_analytics_events_ref = get_analytics_events_ref.clone();
@@ -164,6 +169,10 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                     let mut durations_map = put_durations_ref.lock().await;
                     durations_map.insert(hash.clone(), duration);
 
+                    if let Some(tag) = headers.get("x-artifact-tag") {
+                        put_tags_ref.lock().await.insert(hash.clone(), tag.clone());
+                    }
+
                     while let Some(item) = body.next().await {
                         let chunk = item.unwrap();
                         file.write_all(&chunk).unwrap();
@@ -193,6 +202,9 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                     "x-artifact-duration",
                     HeaderValue::from_str(&duration.to_string()).unwrap(),
                 );
+                if let Some(tag) = get_tags_ref.lock().await.get(&hash) {
+                    headers.insert("x-artifact-tag", tag.clone());
+                }
 
                 (StatusCode::FOUND, headers, buffer)
             }),
@@ -214,6 +226,33 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                 (StatusCode::OK, headers)
             }),
         )
+        .route(
+            "/v8/artifacts",
+            post(|Json(request): Json<ArtifactsQueryRequest>| async move {
+                let durations = query_durations_ref.lock().await;
+                let root_path = query_tempdir_ref.path();
+
+                let response: ArtifactsQueryResponse = request
+                    .hashes
+                    .into_iter()
+                    .map(|hash| {
+                        let artifact = durations.get(&hash).map(|duration| {
+                            let size = std::fs::metadata(root_path.join(&hash))
+                                .map(|metadata| metadata.len())
+                                .unwrap_or(0);
+                            ArtifactQueryResult::Found(ArtifactInfo {
+                                size,
+                                task_duration_ms: u64::from(*duration),
+                                tag: None,
+                            })
+                        });
+                        (hash, artifact)
+                    })
+                    .collect();
+
+                Json(response)
+            }),
+        )
         .route(
             "/v8/artifacts/events",
             post(
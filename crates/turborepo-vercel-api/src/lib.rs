@@ -70,6 +70,36 @@ pub struct ArtifactResponse {
     pub body: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactsQueryRequest {
+    pub hashes: Vec<String>,
+}
+
+/// Information about a single artifact, as returned by a batch artifacts query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactInfo {
+    pub size: u64,
+    pub task_duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactQueryError {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ArtifactQueryResult {
+    Found(ArtifactInfo),
+    Error { error: ArtifactQueryError },
+}
+
+/// Maps each queried hash to its artifact, or `None` if it doesn't exist
+pub type ArtifactsQueryResponse = HashMap<String, Option<ArtifactQueryResult>>;
+
 /// Membership is the relationship between the logged-in user and a particular
 /// team
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -247,7 +277,10 @@ mod tests {
     use serde_json::json;
     use test_case::test_case;
 
-    use crate::{AnalyticsEvent, CacheEvent, CacheSource, TokenMetadata, TokenScope};
+    use crate::{
+        AnalyticsEvent, ArtifactInfo, ArtifactQueryError, ArtifactQueryResult,
+        ArtifactsQueryResponse, CacheEvent, CacheSource, TokenMetadata, TokenScope,
+    };
 
     #[test_case(
       AnalyticsEvent {
@@ -374,4 +407,32 @@ mod tests {
             want
         )
     }
+
+    #[test]
+    fn test_deserialize_artifacts_query_response() {
+        let response: ArtifactsQueryResponse = serde_json::from_value(json!({
+            "hit": { "size": 1024, "taskDurationMs": 58, "tag": "signed" },
+            "miss": null,
+            "broken": { "error": { "message": "artifact is corrupted" } },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            response["hit"],
+            Some(ArtifactQueryResult::Found(ArtifactInfo {
+                size: 1024,
+                task_duration_ms: 58,
+                tag: Some("signed".to_string()),
+            }))
+        );
+        assert_eq!(response["miss"], None);
+        assert_eq!(
+            response["broken"],
+            Some(ArtifactQueryResult::Error {
+                error: ArtifactQueryError {
+                    message: "artifact is corrupted".to_string()
+                }
+            })
+        );
+    }
 }
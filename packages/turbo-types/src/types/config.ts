@@ -22,6 +22,16 @@ export interface BaseSchema {
      */
     [script: string]: Pipeline;
   };
+
+  /**
+   * Tags for the workspace, in addition to those in its package.json.
+   * In the root turbo.json they tag the root workspace.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#tags
+   *
+   * @defaultValue []
+   */
+  tags?: Array<string>;
 }
 
 export interface WorkspaceSchema extends BaseSchema {
@@ -111,6 +121,38 @@ export interface RootSchema extends BaseSchema {
    * @defaultValue `{}`
    */
   remoteCache?: RemoteCache;
+
+  /**
+   * The number of slots available for each resource that tasks can require.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#resources
+   *
+   * @defaultValue `{}`
+   */
+  resources?: Record<string, number>;
+
+  /**
+   * Rules for the dependencies of tagged workspaces, keyed by tag.
+   * Checked by `turbo boundaries`.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#boundaries
+   *
+   * @defaultValue `{}`
+   */
+  boundaries?: Record<string, BoundaryRule>;
+}
+
+export interface BoundaryRule {
+  /**
+   * If set, every dependency of a workspace with the tag needs at least
+   * one of these tags.
+   */
+  allow?: Array<string>;
+
+  /**
+   * Workspaces with the tag can't depend on workspaces with any of these tags.
+   */
+  deny?: Array<string>;
 }
 
 export interface Pipeline {
@@ -249,6 +291,58 @@ export interface Pipeline {
    * @defaultValue false
    */
   persistent?: boolean;
+
+  /**
+   * Indicates whether a persistent task can be restarted by `turbo watch`.
+   * Interruptible tasks are stopped and started again whenever a file they
+   * depend on changes. Tasks that are not interruptible are only restarted
+   * when the workspace structure changes.
+   *
+   * Only applies to tasks that are `persistent`.
+   *
+   * @defaultValue false
+   */
+  interruptible?: boolean;
+
+  /**
+   * A probe that tells when a persistent task is ready. Tasks that depend on
+   * the persistent task start once the probe succeeds, and the persistent task
+   * is stopped when the rest of the run finishes.
+   *
+   * Only applies to tasks that are `persistent`.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#readywhen
+   */
+  readyWhen?: ReadyWhen;
+
+  /**
+   * The number of seconds a single run of the task may take before it is
+   * stopped and treated as a failure.
+   *
+   * Can't be used with tasks that are `persistent`.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#timeout
+   */
+  timeout?: number;
+
+  /**
+   * The number of times the task is run again after it fails or times out.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#retries
+   *
+   * @defaultValue 0
+   */
+  retries?: number;
+
+  /**
+   * The slots of each resource that the task holds while it runs. Resources
+   * must be declared in the root turbo.json.
+   *
+   * Documentation: https://turbo.build/repo/docs/reference/configuration#resources-1
+   *
+   * @defaultValue `{}`
+   */
+  resources?: Record<string, number>;
 }
 
 export interface RemoteCache {
@@ -262,6 +356,24 @@ export interface RemoteCache {
    */
   signature?: boolean;
 
+  /**
+   * The algorithm used to sign artifacts when `signature` is `true`. With `"ed25519"`,
+   * artifacts are signed with the private key in `TURBO_REMOTE_CACHE_SIGNATURE_KEY` and
+   * verified with the public keys in `signaturePublicKeys`.
+   * Documentation: https://turbo.build/repo/docs/core-concepts/remote-caching#ed25519-signatures-and-key-rotation
+   *
+   * @defaultValue `"hmac-sha256"`
+   */
+  signatureAlgorithm?: "hmac-sha256" | "ed25519";
+
+  /**
+   * The base64 encoded Ed25519 public keys that artifacts may be signed with, keyed by key id.
+   * Artifacts signed with a key that isn't listed are rejected.
+   *
+   * @defaultValue `{}`
+   */
+  signaturePublicKeys?: Record<string, string>;
+
   /**
    * Indicates if the remote cache is enabled. When `false`, Turborepo will disable
    * all remote cache operations, even if the repo has a valid token. If true, remote caching
@@ -271,6 +383,14 @@ export interface RemoteCache {
    * @defaultValue true
    */
   enabled?: boolean;
+
+  /**
+   * A directory shared between machines, such as an NFS mount, to use as a cache in
+   * addition to the local filesystem cache. Relative paths are resolved from the root
+   * of the repository. Can also be set with the `TURBO_SHARED_CACHE_DIR` environment variable.
+   * Documentation: https://turbo.build/repo/docs/core-concepts/remote-caching#shared-directory-caches
+   */
+  sharedCacheDir?: string;
 }
 
 export type OutputMode =
@@ -280,5 +400,10 @@ export type OutputMode =
   | "errors-only"
   | "none";
 
+export type ReadyWhen =
+  | { output: string }
+  | { port: number }
+  | { url: string };
+
 export type AnchoredUnixPath = string;
 export type EnvWildcard = string;
@@ -9,6 +9,8 @@ Make sure exit code is 2 when no args are passed
   
   Commands:
     bin         Get the path to the Turbo binary
+    boundaries  Check dependencies between workspaces against the boundaries in turbo.json
+    cache       Manage the local filesystem cache
     completion  Generate the autocompletion script for the specified shell
     daemon      Runs the Turborepo background daemon
     generate    Generate a new app / package
@@ -18,6 +20,8 @@ Make sure exit code is 2 when no args are passed
     prune       Prepare a subset of your monorepo
     run         Run tasks across projects in your monorepo
     unlink      Unlink the current directory from your Vercel organization and disable Remote Caching
+    watch       Watch for file changes and re-run the tasks they affect
+    why         Explain why a task or workspace is part of a run
   
   Options:
         --version                         
@@ -55,6 +59,8 @@ Make sure exit code is 2 when no args are passed
             Run turbo in single-package mode
     -F, --filter <FILTER>
             Use the given selector to specify package(s) to act as entry points. The syntax mirrors pnpm's syntax, and additional documentation and examples can be found in turbo's documentation https://turbo.build/repo/docs/reference/command-line-reference/run#--filter
+        --affected
+            Only run packages that changed in this CI build, and their dependents. Changes are compared against the branch a pull request targets, or the previous commit when the CI provider doesn't expose one
         --force [<FORCE>]
             Ignore the existing cache (to force execution) [env: TURBO_FORCE=] [possible values: true, false]
         --framework-inference [<BOOL>]
@@ -65,6 +71,10 @@ Make sure exit code is 2 when no args are passed
             Generate a graph of the task execution and output to a file when a filename is specified (.svg, .png, .jpg, .pdf, .json, .html). Outputs dot graph to stdout when if no filename is provided
         --env-mode [<ENV_MODE>]
             Environment variable mode. Use "loose" to pass the entire existing environment. Use "strict" to use an allowlist specified in turbo.json. Use "infer" to defer to existence of "passThroughEnv" or "globalPassThroughEnv" in turbo.json. (default infer) [default: infer] [possible values: infer, loose, strict]
+        --explain-miss
+            Record the hash inputs of every task and, when a task misses the cache, print which inputs changed since its last cached run
+        --check-outputs [<CHECK_OUTPUTS>]
+            Check that tasks only write files covered by their outputs and that every declared output is produced. Use "warn" to report problems or "error" to also fail the task. (default warn) [possible values: warn, error]
         --ignore <IGNORE>
             Files to ignore when calculating changed files (i.e. --since). Supports globs
         --include-dependencies
@@ -79,6 +89,8 @@ Make sure exit code is 2 when no args are passed
             Set type of process output logging. Use "full" to show all output. Use "hash-only" to show only turbo-computed task hashes. Use "new-only" to show only new output with only hashes for cached tasks. Use "none" to hide process output. (default full) [possible values: full, none, hash-only, new-only, errors-only]
         --log-order <LOG_ORDER>
             Set type of task output order. Use "stream" to show output as soon as it is available. Use "grouped" to show output when a command has finished execution. Use "auto" to let turbo decide based on its own heuristics. (default auto) [env: TURBO_LOG_ORDER=] [default: auto] [possible values: auto, stream, grouped]
+        --ui <UI>
+            Set how the run is displayed. Use "stream" to print task output as it happens. Use "tui" for a full screen interface with a task list and the output of the selected task, which falls back to "stream" when not attached to a terminal. (default stream) [env: TURBO_UI=] [default: stream] [possible values: stream, tui]
         --only
             Only executes the tasks specified, does not execute parent tasks
         --parallel
@@ -96,7 +108,7 @@ Make sure exit code is 2 when no args are passed
         --since <SINCE>
             Limit/Set scope to changed packages since a mergebase. This uses the git diff ${target_branch}... mechanism to identify which packages have changed
         --summarize [<SUMMARIZE>]
-            Generate a summary of the turbo run [env: TURBO_RUN_SUMMARY=] [possible values: true, false]
+            Generate a summary of the turbo run. Use "junit" to write it as JUnit XML or "trace" to write it as a Chrome trace [env: TURBO_RUN_SUMMARY=] [possible values: true, false, junit, trace]
         --log-prefix <LOG_PREFIX>
             Use "none" to remove prefixes from task logs. Use "task" to get task id prefixing. Use "auto" to let turbo decide how to prefix the logs based on the execution environment. In most cases this will be the same as "task". Note that tasks running in parallel interleave their logs, so removing prefixes can make it difficult to associate logs with tasks. Use --log-order=grouped to prevent interleaving. (default auto) [default: auto] [possible values: auto, none, task]
   [1]